@@ -54,7 +54,9 @@ pub enum AnimeActions {
     #[options(help = "display an animated diagonal/pixel-perfect GIF")]
     PixelGif(AnimeGifDiagonal),
     #[options(help = "change which builtin animations are shown")]
-    SetBuiltins(Builtins)
+    SetBuiltins(Builtins),
+    #[options(help = "render an AniMe config to a GIF or PNG, without the display")]
+    Preview(AnimePreview)
 }
 
 #[derive(Options)]
@@ -152,3 +154,23 @@ pub struct AnimeGifDiagonal {
     )]
     pub loops: u32
 }
+
+#[derive(Options)]
+pub struct AnimePreview {
+    #[options(help = "print help message")]
+    pub help: bool,
+    #[options(free, help = "the asusd anime.ron or an asusd-user anime config")]
+    pub config: String,
+    #[options(
+        meta = "",
+        default = "anime-preview.gif",
+        help = "file to write, the first frame only if it ends in .png"
+    )]
+    pub output: String,
+    #[options(
+        meta = "",
+        help = "which actions to show <system, boot, wake, shutdown, anime>, default is anime if \
+                there are any, otherwise system"
+    )]
+    pub actions: Option<String>
+}
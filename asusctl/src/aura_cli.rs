@@ -1,4 +1,6 @@
+use std::path::PathBuf;
 use std::str::FromStr;
+use std::sync::OnceLock;
 
 use gumdrop::Options;
 use rog_aura::error::Error;
@@ -136,7 +138,7 @@ pub struct SingleSpeedDirection {
 pub struct SingleColour {
     #[options(help = "print help message")]
     help: bool,
-    #[options(no_long, meta = "", help = "set the RGB value e.g, ff00ff")]
+    #[options(no_long, meta = "", help = "set the RGB value e.g, ff00ff or magenta")]
     pub colour: Colour,
     #[options(
         no_long,
@@ -150,7 +152,7 @@ pub struct SingleColour {
 pub struct SingleColourSpeed {
     #[options(help = "print help message")]
     help: bool,
-    #[options(no_long, meta = "", help = "set the RGB value e.g, ff00ff")]
+    #[options(no_long, meta = "", help = "set the RGB value e.g, ff00ff or magenta")]
     pub colour: Colour,
     #[options(no_long, meta = "", help = "set the speed: low, med, high")]
     pub speed: Speed,
@@ -166,9 +168,17 @@ pub struct SingleColourSpeed {
 pub struct TwoColourSpeed {
     #[options(help = "print help message")]
     help: bool,
-    #[options(no_long, meta = "", help = "set the first RGB value e.g, ff00ff")]
+    #[options(
+        no_long,
+        meta = "",
+        help = "set the first RGB value e.g, ff00ff or magenta"
+    )]
     pub colour: Colour,
-    #[options(no_long, meta = "", help = "set the second RGB value e.g, ff00ff")]
+    #[options(
+        no_long,
+        meta = "",
+        help = "set the second RGB value e.g, ff00ff or magenta"
+    )]
     pub colour2: Colour,
     #[options(no_long, meta = "", help = "set the speed: low, med, high")]
     pub speed: Speed,
@@ -184,13 +194,29 @@ pub struct TwoColourSpeed {
 pub struct MultiZone {
     #[options(help = "print help message")]
     help: bool,
-    #[options(short = "a", meta = "", help = "set the RGB value e.g, ff00ff")]
+    #[options(
+        short = "a",
+        meta = "",
+        help = "set the RGB value e.g, ff00ff or magenta"
+    )]
     pub colour1: Colour,
-    #[options(short = "b", meta = "", help = "set the RGB value e.g, ff00ff")]
+    #[options(
+        short = "b",
+        meta = "",
+        help = "set the RGB value e.g, ff00ff or magenta"
+    )]
     pub colour2: Colour,
-    #[options(short = "c", meta = "", help = "set the RGB value e.g, ff00ff")]
+    #[options(
+        short = "c",
+        meta = "",
+        help = "set the RGB value e.g, ff00ff or magenta"
+    )]
     pub colour3: Colour,
-    #[options(short = "d", meta = "", help = "set the RGB value e.g, ff00ff")]
+    #[options(
+        short = "d",
+        meta = "",
+        help = "set the RGB value e.g, ff00ff or magenta"
+    )]
     pub colour4: Colour
 }
 
@@ -198,13 +224,29 @@ pub struct MultiZone {
 pub struct MultiColourSpeed {
     #[options(help = "print help message")]
     help: bool,
-    #[options(short = "a", meta = "", help = "set the RGB value e.g, ff00ff")]
+    #[options(
+        short = "a",
+        meta = "",
+        help = "set the RGB value e.g, ff00ff or magenta"
+    )]
     pub colour1: Colour,
-    #[options(short = "b", meta = "", help = "set the RGB value e.g, ff00ff")]
+    #[options(
+        short = "b",
+        meta = "",
+        help = "set the RGB value e.g, ff00ff or magenta"
+    )]
     pub colour2: Colour,
-    #[options(short = "c", meta = "", help = "set the RGB value e.g, ff00ff")]
+    #[options(
+        short = "c",
+        meta = "",
+        help = "set the RGB value e.g, ff00ff or magenta"
+    )]
     pub colour3: Colour,
-    #[options(short = "d", meta = "", help = "set the RGB value e.g, ff00ff")]
+    #[options(
+        short = "d",
+        meta = "",
+        help = "set the RGB value e.g, ff00ff or magenta"
+    )]
     pub colour4: Colour,
     #[options(no_long, meta = "", help = "set the speed: low, med, high")]
     pub speed: Speed
@@ -242,6 +284,158 @@ pub enum SetAuraBuiltin {
     Flash(SingleColour) // 12
 }
 
+/// The `aura` commands that work on the support and layout files rather than
+/// set a mode, so are usable without asusd
+#[derive(Options)]
+pub enum AuraTool {
+    #[options(help = "check, preview, or start a keyboard layout file")]
+    Layout(LayoutCommand),
+    #[options(help = "show which aura_support.ron entry this machine uses, and why")]
+    Support(SupportCommand)
+}
+
+/// The commands under `aura`, either a built-in mode or one of the tools. The
+/// two are listed together so `aura layout` reads the same as `aura static`.
+pub enum AuraCommand {
+    Mode(SetAuraBuiltin),
+    Tool(AuraTool)
+}
+
+impl Options for AuraCommand {
+    fn parse<S: AsRef<str>>(parser: &mut gumdrop::Parser<S>) -> Result<Self, gumdrop::Error> {
+        let arg = parser
+            .next_arg()
+            .ok_or_else(gumdrop::Error::missing_command)?;
+        Self::parse_command(arg, parser)
+    }
+
+    fn command(&self) -> Option<&dyn Options> {
+        match self {
+            AuraCommand::Mode(cmd) => cmd.command(),
+            AuraCommand::Tool(cmd) => cmd.command()
+        }
+    }
+
+    fn command_name(&self) -> Option<&'static str> {
+        match self {
+            AuraCommand::Mode(cmd) => cmd.command_name(),
+            AuraCommand::Tool(cmd) => cmd.command_name()
+        }
+    }
+
+    fn help_requested(&self) -> bool {
+        match self {
+            AuraCommand::Mode(cmd) => cmd.help_requested(),
+            AuraCommand::Tool(cmd) => cmd.help_requested()
+        }
+    }
+
+    fn parse_command<S: AsRef<str>>(
+        name: &str,
+        parser: &mut gumdrop::Parser<S>
+    ) -> Result<Self, gumdrop::Error> {
+        if SetAuraBuiltin::command_usage(name).is_some() {
+            SetAuraBuiltin::parse_command(name, parser).map(AuraCommand::Mode)
+        } else {
+            AuraTool::parse_command(name, parser).map(AuraCommand::Tool)
+        }
+    }
+
+    fn usage() -> &'static str {
+        static USAGE: OnceLock<String> = OnceLock::new();
+        USAGE.get_or_init(|| format!("{}\n{}", SetAuraBuiltin::usage(), AuraTool::usage()))
+    }
+
+    fn self_usage(&self) -> &'static str {
+        match self {
+            AuraCommand::Mode(cmd) => cmd.self_usage(),
+            AuraCommand::Tool(cmd) => cmd.self_usage()
+        }
+    }
+
+    fn command_usage(command: &str) -> Option<&'static str> {
+        SetAuraBuiltin::command_usage(command).or_else(|| AuraTool::command_usage(command))
+    }
+
+    fn command_list() -> Option<&'static str> {
+        Some(Self::usage())
+    }
+
+    fn self_command_list(&self) -> Option<&'static str> {
+        match self {
+            AuraCommand::Mode(cmd) => cmd.self_command_list(),
+            AuraCommand::Tool(cmd) => cmd.self_command_list()
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Options)]
+pub struct LayoutCommand {
+    #[options(help = "print help message")]
+    pub help: bool,
+    #[options(
+        meta = "FILE",
+        help = "check a layout file for unknown or repeated keys, missing shapes, and rows wider \
+                than the chassis"
+    )]
+    pub check: Option<PathBuf>,
+    #[options(
+        no_short,
+        meta = "",
+        help = "with check, rows wider than this many keys are reported"
+    )]
+    pub max_width: Option<f32>,
+    #[options(
+        meta = "DIR",
+        help = "check that every layout named in the aura_support.ron is in this dir"
+    )]
+    pub links: Option<PathBuf>,
+    #[options(
+        no_short,
+        meta = "FILE",
+        help = "the aura_support.ron to use, defaults to the installed one"
+    )]
+    pub support: Option<PathBuf>,
+    #[options(meta = "FILE", help = "print a preview of a layout file")]
+    pub preview: Option<PathBuf>,
+    #[options(no_short, help = "with preview, print an SVG instead of text")]
+    pub svg: bool,
+    #[options(
+        short = "k",
+        meta = "ID",
+        help = "print a layout to start from for a USB product ID, e.g 19b6"
+    )]
+    pub skeleton: Option<String>
+}
+
+#[derive(Debug, Clone, Default, Options)]
+pub struct SupportCommand {
+    #[options(help = "print help message")]
+    pub help: bool,
+    #[options(
+        meta = "NAME",
+        help = "match this board name instead of this machine's"
+    )]
+    pub board_name: Option<String>,
+    #[options(
+        meta = "ID",
+        help = "match this USB product ID instead of the keyboard found, e.g 19b6"
+    )]
+    pub product_id: Option<String>,
+    #[options(
+        no_short,
+        meta = "FILE",
+        help = "the aura_support.ron to use, defaults to the installed one"
+    )]
+    pub support: Option<PathBuf>,
+    #[options(
+        no_short,
+        meta = "DIR",
+        help = "the drop-in dir to use, defaults to /etc/asusd/aura_support.d"
+    )]
+    pub dropins: Option<PathBuf>
+}
+
 impl Default for SetAuraBuiltin {
     fn default() -> Self {
         SetAuraBuiltin::Static(SingleColour::default())
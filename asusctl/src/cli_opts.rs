@@ -2,10 +2,14 @@ use gumdrop::Options;
 use rog_platform::platform::PlatformProfile;
 
 use crate::anime_cli::AnimeCommand;
-use crate::aura_cli::{LedBrightness, LedPowerCommand1, LedPowerCommand2, SetAuraBuiltin};
+use crate::aura_cli::{AuraCommand, LedBrightness, LedPowerCommand1, LedPowerCommand2};
+use crate::config_cli::ConfigCommand;
 use crate::fan_curve_cli::FanCurveCommand;
+use crate::scene_cli::SceneCommand;
 use crate::scsi_cli::ScsiCommand;
 use crate::slash_cli::SlashCommand;
+use crate::status_cli::StatusCommand;
+use crate::telemetry_cli::TelemetryCommand;
 
 #[derive(Default, Options)]
 pub struct CliStart {
@@ -13,6 +17,13 @@ pub struct CliStart {
     pub help: bool,
     #[options(help = "show program version number")]
     pub version: bool,
+    #[options(
+        no_short,
+        help = "print results and errors as JSON, for use in scripts"
+    )]
+    pub json: bool,
+    #[options(no_short, help = "print results and errors as RON")]
+    pub ron: bool,
     #[options(help = "show supported functions of this laptop")]
     pub show_supported: bool,
     #[options(meta = "", help = "<off, low, med, high>")]
@@ -49,6 +60,26 @@ pub enum CliCommand {
     Slash(SlashCommand),
     #[options(name = "scsi", help = "Manage SCSI external drive")]
     Scsi(ScsiCommand),
+    #[options(
+        name = "scene",
+        help = "Save, apply or manage named scenes of all settings"
+    )]
+    Scene(SceneCommand),
+    #[options(
+        name = "config",
+        help = "Export or import all settings as a single file"
+    )]
+    Config(ConfigCommand),
+    #[options(
+        name = "telemetry",
+        help = "Show fan speeds, temperatures, power draw and battery health"
+    )]
+    Telemetry(TelemetryCommand),
+    #[options(
+        name = "status",
+        help = "Show a summary of the platform, fans, lighting and firmware attributes"
+    )]
+    Status(StatusCommand),
     #[options(
         help = "Change platform settings. This is a new interface exposed by the asus-armoury \
                 driver, some of the settings will be the same as the older platform interface"
@@ -83,7 +114,7 @@ pub struct LedModeCommand {
     #[options(help = "switch to previous aura mode")]
     pub prev_mode: bool,
     #[options(command)]
-    pub command: Option<SetAuraBuiltin>
+    pub command: Option<AuraCommand>
 }
 
 #[derive(Options)]
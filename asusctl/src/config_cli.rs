@@ -0,0 +1,33 @@
+use gumdrop::Options;
+
+#[derive(Options)]
+pub struct ConfigCommand {
+    #[options(help = "print help message")]
+    pub help: bool,
+    #[options(command)]
+    pub command: Option<ConfigActions>
+}
+
+#[derive(Options)]
+pub enum ConfigActions {
+    #[options(help = "export all asusd settings to a single file")]
+    Export(ConfigExport),
+    #[options(help = "import settings from a file created by export")]
+    Import(ConfigImport)
+}
+
+#[derive(Options)]
+pub struct ConfigExport {
+    #[options(help = "print help message")]
+    pub help: bool,
+    #[options(meta = "", help = "file to write to, prints to stdout if not given")]
+    pub file: Option<String>
+}
+
+#[derive(Options)]
+pub struct ConfigImport {
+    #[options(help = "print help message")]
+    pub help: bool,
+    #[options(meta = "", help = "file to read the settings from")]
+    pub file: String
+}
@@ -1,6 +1,6 @@
 use gumdrop::Options;
 use rog_platform::platform::PlatformProfile;
-use rog_profiles::fan_curve_set::CurveData;
+use rog_profiles::fan_curve_set::{CurveData, CurvePreset};
 use rog_profiles::FanCurvePU;
 
 #[derive(Debug, Clone, Options)]
@@ -45,5 +45,12 @@ pub struct FanCurveCommand {
         help = "data format = 30c:1%,49c:2%,59c:3%,69c:4%,79c:31%,89c:49%,99c:56%,109c:58%. \
                 `--mod-profile` required. If '%' is omitted the fan range is 0-255"
     )]
-    pub data: Option<CurveData>
+    pub data: Option<CurveData>,
+
+    #[options(
+        meta = "",
+        help = "set a preset curve <silent/linear/aggressive> for the fan. `--mod-profile` \
+                required"
+    )]
+    pub preset: Option<CurvePreset>
 }
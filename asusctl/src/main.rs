@@ -1,106 +1,244 @@
 use std::convert::TryFrom;
 use std::env::args;
-use std::path::Path;
-use std::process::Command;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitCode};
 use std::thread::sleep;
 
-use anime_cli::{AnimeActions, AnimeCommand};
-use aura_cli::{LedPowerCommand1, LedPowerCommand2};
+use anime_cli::{AnimeActions, AnimeCommand, AnimePreview};
+use aura_cli::{
+    AuraCommand, AuraTool, LayoutCommand, LedPowerCommand1, LedPowerCommand2, SetAuraBuiltin,
+    SupportCommand
+};
+use config_cli::{ConfigActions, ConfigCommand};
 use dmi_id::DMIID;
 use fan_curve_cli::FanCurveCommand;
 use gumdrop::{Opt, Options};
 use log::{error, info};
 use rog_anime::usb::get_anime_type;
-use rog_anime::{AnimTime, AnimeDataBuffer, AnimeDiagonal, AnimeGif, AnimeImage, AnimeType, Vec2};
-use rog_aura::keyboard::{AuraPowerState, LaptopAuraPower};
+use rog_anime::{
+    sequence_frames, ActionLoader, AnimTime, AnimeDataBuffer, AnimeDiagonal, AnimeGif, AnimeImage,
+    AnimeRender, AnimeType, Playback, Sequences, Vec2
+};
+use rog_aura::aura_detection::{
+    LedSupportDb, LedSupportFile, MatchReason, ASUS_LED_MODE_CONF, ASUS_LED_MODE_DROPIN_DIR,
+    ASUS_LED_MODE_USER_CONF
+};
+use rog_aura::keyboard::{
+    validate_layout, validate_support_links, AuraPowerState, KeyLayout, LaptopAuraPower,
+    MAX_CHASSIS_WIDTH
+};
 use rog_aura::{self, AuraDeviceType, AuraEffect, PowerZones};
 use rog_dbus::asus_armoury::AsusArmouryProxyBlocking;
 use rog_dbus::list_iface_blocking;
 use rog_dbus::scsi_aura::ScsiAuraProxyBlocking;
 use rog_dbus::zbus_anime::AnimeProxyBlocking;
 use rog_dbus::zbus_aura::AuraProxyBlocking;
+use rog_dbus::zbus_config_bundle::ConfigBundleProxyBlocking;
 use rog_dbus::zbus_fan_curves::FanCurvesProxyBlocking;
 use rog_dbus::zbus_platform::PlatformProxyBlocking;
+use rog_dbus::zbus_scenes::ScenesProxyBlocking;
 use rog_dbus::zbus_slash::SlashProxyBlocking;
+use rog_dbus::zbus_telemetry::{TelemetryProxyBlocking, TelemetrySample};
 use rog_platform::platform::{PlatformProfile, Properties};
 use rog_profiles::error::ProfileError;
+use rog_profiles::fan_curve_set::CurveData;
 use rog_scsi::AuraMode;
-use rog_slash::SlashMode;
+use rog_slash::{mode_frames, SlashMode, SlashRender};
 use ron::ser::PrettyConfig;
+use scene_cli::SceneCommand;
 use scsi_cli::ScsiCommand;
+use serde::Deserialize;
+use status::Status;
+use status_cli::StatusCommand;
+use telemetry_cli::TelemetryCommand;
 use zbus::blocking::proxy::ProxyImpl;
-use zbus::blocking::Connection;
+use zbus::blocking::{Connection, MessageIterator};
+use zbus::fdo::PropertiesChanged;
+use zbus::MatchRule;
 
 use crate::aura_cli::{AuraPowerStates, LedBrightness};
 use crate::cli_opts::*;
+use crate::output::{
+    AttributeInfo, AuraState, AuraSupport, CliError, ErrorKind, OutputFormat, ProfileInfo,
+    SupportMatchInfo, SupportedInfo, VersionInfo
+};
 use crate::slash_cli::SlashCommand;
 
 mod anime_cli;
 mod aura_cli;
 mod cli_opts;
+mod config_cli;
 mod fan_curve_cli;
+mod output;
+mod scene_cli;
 mod scsi_cli;
 mod slash_cli;
+mod status;
+mod status_cli;
+mod telemetry_cli;
 
-fn main() {
+fn main() -> ExitCode {
+    let args: Vec<String> = args().skip(1).collect();
+
+    let missing_argument_k = gumdrop::Error::missing_argument(Opt::Short('k'));
+    let parsed = CliStart::parse_args_default(&args);
+    let output = match &parsed {
+        Ok(p) => OutputFormat::new(p.json, p.ron),
+        Err(_) => OutputFormat::from_raw_args(&args)
+    };
+
+    // Keep stdout clean for the machine readable formats
     let mut logger = env_logger::Builder::new();
     logger
         .parse_default_env()
-        .target(env_logger::Target::Stdout)
+        .target(if output.is_text() {
+            env_logger::Target::Stdout
+        } else {
+            env_logger::Target::Stderr
+        })
         .format_timestamp(None)
         .filter_level(log::LevelFilter::Debug)
         .init();
 
     let self_version = env!("CARGO_PKG_VERSION");
-    println!("Starting version {self_version}");
-    let args: Vec<String> = args().skip(1).collect();
-
-    let missing_argument_k = gumdrop::Error::missing_argument(Opt::Short('k'));
-    let parsed = match CliStart::parse_args_default(&args) {
+    let parsed = match parsed {
         Ok(p) => p,
         Err(err) if err.to_string() == missing_argument_k.to_string() => CliStart {
             kbd_bright: Some(LedBrightness::new(None)),
+            json: output == OutputFormat::Json,
+            ron: output == OutputFormat::Ron,
             ..Default::default()
         },
         Err(err) => {
-            println!("Error: {}", err);
-            return;
+            if output.is_text() {
+                println!("Error: {}", err);
+            }
+            return CliError::new(ErrorKind::Usage, err.to_string()).report(output);
         }
     };
 
-    let conn = Connection::system().unwrap();
-    if let Ok(platform_proxy) = PlatformProxyBlocking::new(&conn).map_err(|e| {
-        check_service("asusd");
-        println!("\nError: {e}\n");
-        print_info();
-    }) {
-        let asusd_version = platform_proxy
-            .version()
-            .map_err(|e| {
+    // The layout and support tools, and AniMe and Slash previews, work on files
+    // only, so are usable without asusd
+    let offline = match &parsed.command {
+        Some(CliCommand::Aura(LedModeCommand {
+            command: Some(AuraCommand::Tool(AuraTool::Layout(cmd))),
+            ..
+        })) => Some(handle_aura_layout(cmd, output)),
+        Some(CliCommand::Aura(LedModeCommand {
+            command: Some(AuraCommand::Tool(AuraTool::Support(cmd))),
+            ..
+        })) => Some(handle_aura_support(cmd, output)),
+        Some(CliCommand::Anime(AnimeCommand {
+            command: Some(AnimeActions::Preview(preview)),
+            override_type,
+            ..
+        })) => Some(anime_preview(preview, *override_type, output)),
+        Some(CliCommand::Slash(cmd)) if cmd.preview.is_some() && !cmd.help => {
+            Some(slash_preview(cmd, output))
+        }
+        _ => None
+    };
+    if let Some(res) = offline {
+        if let Err(err) = res {
+            if output.is_text() {
+                println!("\nError: {err}\n");
+            }
+            return CliError::from_error(&*err).report(output);
+        }
+        return ExitCode::SUCCESS;
+    }
+
+    if output.is_text() {
+        println!("Starting version {self_version}");
+    }
+
+    let conn = match Connection::system() {
+        Ok(conn) => conn,
+        Err(e) => {
+            if output.is_text() {
+                println!("\nError: {e}\n");
+            }
+            return CliError::new(ErrorKind::Daemon, e.to_string()).report(output);
+        }
+    };
+    let platform_proxy = match PlatformProxyBlocking::new(&conn) {
+        Ok(proxy) => proxy,
+        Err(e) => {
+            if output.is_text() {
+                check_service("asusd");
+                println!("\nError: {e}\n");
+                print_info();
+            }
+            return CliError::new(ErrorKind::Daemon, e.to_string()).report(output);
+        }
+    };
+    let asusd_version = match platform_proxy.version() {
+        Ok(version) => version,
+        Err(e) => {
+            if output.is_text() {
                 error!(
                     "Could not get asusd version: {e:?}\nIs asusd.service running? {}",
                     check_service("asusd")
                 );
-            })
-            .unwrap();
-        if asusd_version != self_version {
-            println!("Version mismatch: asusctl = {self_version}, asusd = {asusd_version}");
-            return;
+            }
+            return CliError::new(
+                ErrorKind::Daemon,
+                format!("Could not get asusd version: {e}")
+            )
+            .report(output);
+        }
+    };
+    if asusd_version != self_version {
+        let msg = format!("Version mismatch: asusctl = {self_version}, asusd = {asusd_version}");
+        if output.is_text() {
+            println!("{msg}");
         }
+        return CliError::new(ErrorKind::VersionMismatch, msg).report(output);
+    }
 
-        let supported_properties = platform_proxy.supported_properties().unwrap();
-        let supported_interfaces = list_iface_blocking().unwrap();
+    let supported_properties = match platform_proxy.supported_properties() {
+        Ok(properties) => properties,
+        Err(e) => return CliError::from_error(&e).report(output)
+    };
+    let supported_interfaces = match list_iface_blocking() {
+        Ok(interfaces) => interfaces,
+        Err(e) => return CliError::from_error(&*e).report(output)
+    };
 
-        if parsed.version {
+    if parsed.version {
+        if output.is_text() {
             println!("asusctl v{}", env!("CARGO_PKG_VERSION"));
             println!();
             print_info();
+        } else {
+            let dmi = DMIID::new().unwrap_or_default();
+            let info = VersionInfo {
+                asusctl: self_version,
+                asusd: &asusd_version,
+                product_family: dmi.product_family.trim(),
+                board_name: dmi.board_name.trim()
+            };
+            if let Err(e) = output.print(&info) {
+                return e.report(output);
+            }
         }
+    }
 
-        if let Err(err) = do_parsed(&parsed, &supported_interfaces, &supported_properties, conn) {
+    if let Err(err) = do_parsed(
+        &parsed,
+        output,
+        &supported_interfaces,
+        &supported_properties,
+        conn
+    ) {
+        if output.is_text() {
             print_error_help(&*err, &supported_interfaces, &supported_properties);
         }
+        return CliError::from_error(&*err).report(output);
     }
+    ExitCode::SUCCESS
 }
 
 fn print_error_help(
@@ -149,9 +287,9 @@ fn find_iface<T>(iface_name: &str) -> Result<Vec<T>, Box<dyn std::error::Error>>
 where
     T: ProxyImpl<'static> + From<zbus::Proxy<'static>>
 {
-    let conn = zbus::blocking::Connection::system().unwrap();
-    let f = zbus::blocking::fdo::ObjectManagerProxy::new(&conn, "xyz.ljones.Asusd", "/").unwrap();
-    let interfaces = f.get_managed_objects().unwrap();
+    let conn = zbus::blocking::Connection::system()?;
+    let f = zbus::blocking::fdo::ObjectManagerProxy::new(&conn, "xyz.ljones.Asusd", "/")?;
+    let interfaces = f.get_managed_objects()?;
     let mut paths = Vec::new();
     for v in interfaces.iter() {
         // let o: Vec<zbus::names::OwnedInterfaceName> = v.1.keys().map(|e|
@@ -164,7 +302,7 @@ where
         }
     }
     if paths.len() > 1 {
-        println!("Multiple asusd interfaces devices found");
+        info!("Multiple asusd interfaces devices found");
     }
     if !paths.is_empty() {
         let mut ctrl = Vec::new();
@@ -180,32 +318,38 @@ where
         return Ok(ctrl);
     }
 
-    Err(format!("Did not find {iface_name}").into())
+    Err(CliError::not_supported(iface_name).into())
 }
 
 fn do_parsed(
     parsed: &CliStart,
+    output: OutputFormat,
     supported_interfaces: &[String],
     supported_properties: &[Properties],
     conn: Connection
 ) -> Result<(), Box<dyn std::error::Error>> {
     match &parsed.command {
-        Some(CliCommand::Aura(mode)) => handle_led_mode(mode)?,
-        Some(CliCommand::AuraPowerOld(pow)) => handle_led_power1(pow)?,
-        Some(CliCommand::AuraPower(pow)) => handle_led_power2(pow)?,
+        Some(CliCommand::Aura(mode)) => handle_led_mode(mode, output)?,
+        Some(CliCommand::AuraPowerOld(pow)) => handle_led_power1(pow, output)?,
+        Some(CliCommand::AuraPower(pow)) => handle_led_power2(pow, output)?,
         Some(CliCommand::Profile(cmd)) => {
-            handle_throttle_profile(&conn, supported_properties, cmd)?
+            handle_throttle_profile(&conn, supported_properties, cmd, output)?
         }
         Some(CliCommand::FanCurve(cmd)) => {
-            handle_fan_curve(&conn, cmd)?;
+            handle_fan_curve(&conn, cmd, output)?;
         }
         Some(CliCommand::Graphics(_)) => do_gfx(),
-        Some(CliCommand::Anime(cmd)) => handle_anime(cmd)?,
-        Some(CliCommand::Slash(cmd)) => handle_slash(cmd)?,
-        Some(CliCommand::Scsi(cmd)) => handle_scsi(cmd)?,
-        Some(CliCommand::Armoury(cmd)) => handle_armoury_command(cmd)?,
+        Some(CliCommand::Anime(cmd)) => handle_anime(cmd, output)?,
+        Some(CliCommand::Slash(cmd)) => handle_slash(cmd, output)?,
+        Some(CliCommand::Scsi(cmd)) => handle_scsi(cmd, output)?,
+        Some(CliCommand::Scene(cmd)) => handle_scene(&conn, cmd, output)?,
+        Some(CliCommand::Config(cmd)) => handle_config(&conn, cmd, output)?,
+        Some(CliCommand::Telemetry(cmd)) => handle_telemetry(&conn, cmd, output)?,
+        Some(CliCommand::Status(cmd)) => handle_status(&conn, cmd, supported_interfaces, output)?,
+        Some(CliCommand::Armoury(cmd)) => handle_armoury_command(cmd, output)?,
         None => {
             if (!parsed.show_supported
+                && !parsed.version
                 && parsed.kbd_bright.is_none()
                 && parsed.chg_limit.is_none()
                 && !parsed.next_kbd_bright
@@ -213,6 +357,9 @@ fn do_parsed(
                 && !parsed.one_shot_chg)
                 || parsed.help
             {
+                if !output.is_text() {
+                    return Err(CliError::usage("Missing arg or command").into());
+                }
                 println!("{}", CliStart::usage());
                 println!();
                 if let Some(cmdlist) = CliStart::command_list() {
@@ -288,61 +435,72 @@ fn do_parsed(
     }
 
     if let Some(brightness) = &parsed.kbd_bright {
-        if let Ok(aura) = find_iface::<AuraProxyBlocking>("xyz.ljones.Aura") {
-            for aura in aura.iter() {
-                match brightness.level() {
-                    None => {
-                        let level = aura.brightness()?;
+        let aura = find_iface::<AuraProxyBlocking>("xyz.ljones.Aura")?;
+        for aura in aura.iter() {
+            match brightness.level() {
+                None => {
+                    let level = aura.brightness()?;
+                    if output.is_text() {
                         println!("Current keyboard led brightness: {level:?}");
+                    } else {
+                        output.print(&level)?;
                     }
-                    Some(level) => aura.set_brightness(rog_aura::LedBrightness::from(level))?
                 }
+                Some(level) => aura.set_brightness(rog_aura::LedBrightness::from(level))?
             }
-        } else {
-            println!("No aura interface found");
         }
     }
 
     if parsed.next_kbd_bright {
-        if let Ok(aura) = find_iface::<AuraProxyBlocking>("xyz.ljones.Aura") {
-            for aura in aura.iter() {
-                let brightness = aura.brightness()?;
-                aura.set_brightness(brightness.next())?;
-            }
-        } else {
-            println!("No aura interface found");
+        let aura = find_iface::<AuraProxyBlocking>("xyz.ljones.Aura")?;
+        for aura in aura.iter() {
+            let brightness = aura.brightness()?;
+            aura.set_brightness(brightness.next())?;
         }
     }
 
     if parsed.prev_kbd_bright {
-        if let Ok(aura) = find_iface::<AuraProxyBlocking>("xyz.ljones.Aura") {
-            for aura in aura.iter() {
-                let brightness = aura.brightness()?;
-                aura.set_brightness(brightness.prev())?;
-            }
-        } else {
-            println!("No aura interface found");
+        let aura = find_iface::<AuraProxyBlocking>("xyz.ljones.Aura")?;
+        for aura in aura.iter() {
+            let brightness = aura.brightness()?;
+            aura.set_brightness(brightness.prev())?;
         }
     }
 
     if parsed.show_supported {
-        println!("Supported Core Functions:\n{:#?}", supported_interfaces);
-        println!(
-            "Supported Platform Properties:\n{:#?}",
-            supported_properties
-        );
-        if let Ok(aura) = find_iface::<AuraProxyBlocking>("xyz.ljones.Aura") {
-            // TODO: multiple RGB check
-            let bright = aura.first().unwrap().supported_brightness()?;
-            let modes = aura.first().unwrap().supported_basic_modes()?;
-            let zones = aura.first().unwrap().supported_basic_zones()?;
-            let power = aura.first().unwrap().supported_power_zones()?;
-            println!("Supported Keyboard Brightness:\n{:#?}", bright);
-            println!("Supported Aura Modes:\n{:#?}", modes);
-            println!("Supported Aura Zones:\n{:#?}", zones);
-            println!("Supported Aura Power Zones:\n{:#?}", power);
+        // TODO: multiple RGB check
+        let aura = match find_iface::<AuraProxyBlocking>("xyz.ljones.Aura") {
+            Ok(aura) => {
+                let aura = aura.first().unwrap();
+                Some(AuraSupport {
+                    brightness: aura.supported_brightness()?,
+                    modes: aura.supported_basic_modes()?,
+                    zones: aura.supported_basic_zones()?,
+                    power_zones: aura.supported_power_zones()?
+                })
+            }
+            Err(_) => None
+        };
+        if output.is_text() {
+            println!("Supported Core Functions:\n{:#?}", supported_interfaces);
+            println!(
+                "Supported Platform Properties:\n{:#?}",
+                supported_properties
+            );
+            if let Some(aura) = aura {
+                println!("Supported Keyboard Brightness:\n{:#?}", aura.brightness);
+                println!("Supported Aura Modes:\n{:#?}", aura.modes);
+                println!("Supported Aura Zones:\n{:#?}", aura.zones);
+                println!("Supported Aura Power Zones:\n{:#?}", aura.power_zones);
+            } else {
+                println!("No aura interface found");
+            }
         } else {
-            println!("No aura interface found");
+            output.print(&SupportedInfo {
+                interfaces: supported_interfaces,
+                platform_properties: supported_properties,
+                aura
+            })?;
         }
     }
 
@@ -367,7 +525,10 @@ fn do_gfx() {
     println!("This command will be removed in future");
 }
 
-fn handle_anime(cmd: &AnimeCommand) -> Result<(), Box<dyn std::error::Error>> {
+fn handle_anime(
+    cmd: &AnimeCommand,
+    output: OutputFormat
+) -> Result<(), Box<dyn std::error::Error>> {
     if (cmd.command.is_none()
         && cmd.enable_display.is_none()
         && cmd.enable_powersave_anim.is_none()
@@ -379,6 +540,9 @@ fn handle_anime(cmd: &AnimeCommand) -> Result<(), Box<dyn std::error::Error>> {
         && !cmd.clear)
         || cmd.help
     {
+        if !output.is_text() {
+            return Err(CliError::usage("Missing arg or command").into());
+        }
         println!("Missing arg or command\n\n{}", cmd.self_usage());
         if let Some(lst) = cmd.self_command_list() {
             println!("\n{}", lst);
@@ -409,7 +573,7 @@ fn handle_anime(cmd: &AnimeCommand) -> Result<(), Box<dyn std::error::Error>> {
         if let Some(enable) = cmd.off_when_unplugged {
             proxy.set_off_when_unplugged(enable)?;
         }
-        if cmd.off_with_his_head.is_some() {
+        if cmd.off_with_his_head.is_some() && output.is_text() {
             println!("Did Alice _really_ make it back from Wonderland?");
         }
 
@@ -430,13 +594,16 @@ fn handle_anime(cmd: &AnimeCommand) -> Result<(), Box<dyn std::error::Error>> {
             match action {
                 AnimeActions::Image(image) => {
                     if image.help_requested() || image.path.is_empty() {
+                        if !output.is_text() {
+                            return Err(CliError::usage("Missing arg or command").into());
+                        }
                         println!("Missing arg or command\n\n{}", image.self_usage());
                         if let Some(lst) = image.self_command_list() {
                             println!("\n{}", lst);
                         }
                         return Ok(());
                     }
-                    verify_brightness(image.bright);
+                    verify_brightness(image.bright, output);
 
                     let matrix = AnimeImage::from_png(
                         Path::new(&image.path),
@@ -451,13 +618,16 @@ fn handle_anime(cmd: &AnimeCommand) -> Result<(), Box<dyn std::error::Error>> {
                 }
                 AnimeActions::PixelImage(image) => {
                     if image.help_requested() || image.path.is_empty() {
+                        if !output.is_text() {
+                            return Err(CliError::usage("Missing arg or command").into());
+                        }
                         println!("Missing arg or command\n\n{}", image.self_usage());
                         if let Some(lst) = image.self_command_list() {
                             println!("\n{}", lst);
                         }
                         return Ok(());
                     }
-                    verify_brightness(image.bright);
+                    verify_brightness(image.bright, output);
 
                     let matrix = AnimeDiagonal::from_png(
                         Path::new(&image.path),
@@ -470,13 +640,16 @@ fn handle_anime(cmd: &AnimeCommand) -> Result<(), Box<dyn std::error::Error>> {
                 }
                 AnimeActions::Gif(gif) => {
                     if gif.help_requested() || gif.path.is_empty() {
+                        if !output.is_text() {
+                            return Err(CliError::usage("Missing arg or command").into());
+                        }
                         println!("Missing arg or command\n\n{}", gif.self_usage());
                         if let Some(lst) = gif.self_command_list() {
                             println!("\n{}", lst);
                         }
                         return Ok(());
                     }
-                    verify_brightness(gif.bright);
+                    verify_brightness(gif.bright, output);
 
                     let matrix = AnimeGif::from_gif(
                         Path::new(&gif.path),
@@ -504,13 +677,16 @@ fn handle_anime(cmd: &AnimeCommand) -> Result<(), Box<dyn std::error::Error>> {
                 }
                 AnimeActions::PixelGif(gif) => {
                     if gif.help_requested() || gif.path.is_empty() {
+                        if !output.is_text() {
+                            return Err(CliError::usage("Missing arg or command").into());
+                        }
                         println!("Missing arg or command\n\n{}", gif.self_usage());
                         if let Some(lst) = gif.self_command_list() {
                             println!("\n{}", lst);
                         }
                         return Ok(());
                     }
-                    verify_brightness(gif.bright);
+                    verify_brightness(gif.bright, output);
 
                     let matrix = AnimeGif::from_diagonal_gif(
                         Path::new(&gif.path),
@@ -535,6 +711,9 @@ fn handle_anime(cmd: &AnimeCommand) -> Result<(), Box<dyn std::error::Error>> {
                 }
                 AnimeActions::SetBuiltins(builtins) => {
                     if builtins.help_requested() || builtins.set.is_none() {
+                        if !output.is_text() {
+                            return Err(CliError::usage("Missing arg or command").into());
+                        }
                         println!(
                             "\nAny unspecified args will be set to default (first shown var)\n"
                         );
@@ -552,14 +731,84 @@ fn handle_anime(cmd: &AnimeCommand) -> Result<(), Box<dyn std::error::Error>> {
                         shutdown: builtins.shutdown
                     })?;
                 }
+                // Handled before connecting to asusd
+                AnimeActions::Preview(_) => {}
             }
         }
     }
     Ok(())
 }
 
-fn verify_brightness(brightness: f32) {
-    if !(0.0..=1.0).contains(&brightness) {
+/// The parts of an asusd `anime.ron`, or an asusd-user anime config, that a
+/// preview is made from
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct PreviewConfig {
+    anime: Vec<ActionLoader>,
+    system: Vec<ActionLoader>,
+    boot: Vec<ActionLoader>,
+    wake: Vec<ActionLoader>,
+    shutdown: Vec<ActionLoader>,
+    playback: Playback
+}
+
+fn anime_preview(
+    preview: &AnimePreview,
+    override_type: Option<AnimeType>,
+    output: OutputFormat
+) -> Result<(), Box<dyn std::error::Error>> {
+    if preview.help_requested() || preview.config.is_empty() {
+        if !output.is_text() {
+            return Err(CliError::usage("Missing arg or command").into());
+        }
+        println!("Missing arg or command\n\n{}", preview.self_usage());
+        return Ok(());
+    }
+
+    let anime_type = override_type.unwrap_or_else(get_anime_type);
+    if let AnimeType::Unsupported = anime_type {
+        return Err("No AniMe display found, pick one with --override-type".into());
+    }
+
+    let config: PreviewConfig = ron::from_str(&std::fs::read_to_string(&preview.config)?)?;
+    let actions = match preview.actions.as_deref() {
+        None if !config.anime.is_empty() => &config.anime,
+        None | Some("system") => &config.system,
+        Some("boot") => &config.boot,
+        Some("wake") => &config.wake,
+        Some("shutdown") => &config.shutdown,
+        Some("anime") => &config.anime,
+        Some(other) => {
+            return Err(format!(
+                "Unknown actions {other}, expected system, boot, wake, shutdown or anime"
+            )
+            .into())
+        }
+    };
+
+    let mut sequences = Sequences::new(anime_type);
+    for (idx, action) in actions.iter().enumerate() {
+        sequences.insert(idx, action)?;
+    }
+    let frames = sequence_frames(&sequences, &config.playback)?;
+    if frames.is_empty() {
+        return Err(format!("Nothing to preview in {}", preview.config).into());
+    }
+    let render = AnimeRender::new(anime_type);
+    let file = BufWriter::new(File::create(&preview.output)?);
+    if preview.output.ends_with(".png") {
+        render.write_png(&frames[0].0, file)?;
+    } else {
+        render.write_gif(&frames, file)?;
+    }
+    if output.is_text() {
+        println!("Wrote the {anime_type:?} preview to {}", preview.output);
+    }
+    Ok(())
+}
+
+fn verify_brightness(brightness: f32, output: OutputFormat) {
+    if !(0.0..=1.0).contains(&brightness) && output.is_text() {
         println!(
             "Image and global brightness must be between 0.0 and 1.0 (inclusive), was {}",
             brightness
@@ -567,7 +816,36 @@ fn verify_brightness(brightness: f32) {
     }
 }
 
-fn handle_slash(cmd: &SlashCommand) -> Result<(), Box<dyn std::error::Error>> {
+fn slash_preview(
+    cmd: &SlashCommand,
+    output: OutputFormat
+) -> Result<(), Box<dyn std::error::Error>> {
+    let Some(path) = cmd.preview.as_deref() else {
+        return Ok(());
+    };
+    let mode = cmd.mode.unwrap_or_default();
+    let frames = mode_frames(
+        mode,
+        cmd.interval.unwrap_or(0),
+        cmd.brightness.unwrap_or(255)
+    );
+    let render = SlashRender::new();
+    let file = BufWriter::new(File::create(path)?);
+    if path.ends_with(".png") {
+        render.write_png(&frames[0].0, file)?;
+    } else {
+        render.write_gif(&frames, file)?;
+    }
+    if output.is_text() {
+        println!("Wrote the Slash {mode} preview to {path}");
+    }
+    Ok(())
+}
+
+fn handle_slash(
+    cmd: &SlashCommand,
+    output: OutputFormat
+) -> Result<(), Box<dyn std::error::Error>> {
     if (cmd.brightness.is_none()
         && cmd.interval.is_none()
         && cmd.show_on_boot.is_none()
@@ -581,6 +859,9 @@ fn handle_slash(cmd: &SlashCommand) -> Result<(), Box<dyn std::error::Error>> {
         && !cmd.disable)
         || cmd.help
     {
+        if !output.is_text() {
+            return Err(CliError::usage("Missing arg or command").into());
+        }
         println!("Missing arg or command\n\n{}", cmd.self_usage());
         if let Some(lst) = cmd.self_command_list() {
             println!("\n{}", lst);
@@ -623,18 +904,25 @@ fn handle_slash(cmd: &SlashCommand) -> Result<(), Box<dyn std::error::Error>> {
     }
     if cmd.list {
         let res = SlashMode::list();
-        for p in &res {
-            println!("{:?}", p);
+        if output.is_text() {
+            for p in &res {
+                println!("{:?}", p);
+            }
+        } else {
+            output.print(&res)?;
         }
     }
 
     Ok(())
 }
 
-fn handle_scsi(cmd: &ScsiCommand) -> Result<(), Box<dyn std::error::Error>> {
+fn handle_scsi(cmd: &ScsiCommand, output: OutputFormat) -> Result<(), Box<dyn std::error::Error>> {
     if (!cmd.list && cmd.enable.is_none() && cmd.mode.is_none() && cmd.colours.is_empty())
         || cmd.help
     {
+        if !output.is_text() {
+            return Err(CliError::usage("Missing arg or command").into());
+        }
         println!("Missing arg or command\n\n{}", cmd.self_usage());
         if let Some(lst) = cmd.self_command_list() {
             println!("\n{}", lst);
@@ -643,14 +931,14 @@ fn handle_scsi(cmd: &ScsiCommand) -> Result<(), Box<dyn std::error::Error>> {
 
     let scsis = find_iface::<ScsiAuraProxyBlocking>("xyz.ljones.ScsiAura")?;
 
+    let mut effects = Vec::new();
     for scsi in scsis {
         if let Some(enable) = cmd.enable {
             scsi.set_enabled(enable)?;
         }
 
         if let Some(mode) = cmd.mode {
-            dbg!(mode as u8);
-            scsi.set_led_mode(mode).unwrap();
+            scsi.set_led_mode(mode)?;
         }
 
         let mut mode = scsi.led_mode_data()?;
@@ -689,20 +977,41 @@ fn handle_scsi(cmd: &ScsiCommand) -> Result<(), Box<dyn std::error::Error>> {
 
         // let mode_ret = scsi.led_mode_data()?;
         // assert_eq!(mode, mode_ret);
-        println!("{mode}");
+        if output.is_text() {
+            println!("{mode}");
+        }
+        effects.push(mode);
     }
 
     if cmd.list {
         let res = AuraMode::list();
-        for p in &res {
-            println!("{:?}", p);
+        if output.is_text() {
+            for p in &res {
+                println!("{:?}", p);
+            }
+        } else {
+            output.print(&res)?;
         }
+    } else if !output.is_text() {
+        output.print(&effects)?;
     }
 
     Ok(())
 }
 
-fn handle_led_mode(mode: &LedModeCommand) -> Result<(), Box<dyn std::error::Error>> {
+fn handle_led_mode(
+    mode: &LedModeCommand,
+    output: OutputFormat
+) -> Result<(), Box<dyn std::error::Error>> {
+    if mode.command.is_none() && !mode.prev_mode && !mode.next_mode && !output.is_text() {
+        let mut states = Vec::new();
+        for aura in find_iface::<AuraProxyBlocking>("xyz.ljones.Aura")? {
+            states.push(AuraState::new(&aura)?);
+        }
+        output.print(&states)?;
+        return Ok(());
+    }
+
     if mode.command.is_none() && !mode.prev_mode && !mode.next_mode {
         if !mode.help {
             println!("Missing arg or command\n");
@@ -710,7 +1019,7 @@ fn handle_led_mode(mode: &LedModeCommand) -> Result<(), Box<dyn std::error::Erro
         println!("{}\n", mode.self_usage());
         println!("Commands available");
 
-        if let Some(cmdlist) = LedModeCommand::command_list() {
+        if let Some(cmdlist) = SetAuraBuiltin::command_list() {
             let commands: Vec<String> = cmdlist.lines().map(|s| s.to_owned()).collect();
             // TODO: multiple rgb check
             let aura = find_iface::<AuraProxyBlocking>("xyz.ljones.Aura")?;
@@ -734,14 +1043,16 @@ fn handle_led_mode(mode: &LedModeCommand) -> Result<(), Box<dyn std::error::Erro
                 println!("{}", command);
             }
         }
+        if let Some(cmdlist) = AuraTool::command_list() {
+            println!("{cmdlist}");
+        }
 
         println!("\nHelp can also be requested on modes, e.g: static --help");
         return Ok(());
     }
 
     if mode.next_mode && mode.prev_mode {
-        println!("Please specify either next or previous");
-        return Ok(());
+        return Err(CliError::usage("Please specify either next or previous").into());
     }
     let aura = find_iface::<AuraProxyBlocking>("xyz.ljones.Aura")?;
     if mode.next_mode {
@@ -766,7 +1077,7 @@ fn handle_led_mode(mode: &LedModeCommand) -> Result<(), Box<dyn std::error::Erro
             }
             aura.set_led_mode(modes[pos])?;
         }
-    } else if let Some(mode) = mode.command.as_ref() {
+    } else if let Some(AuraCommand::Mode(mode)) = mode.command.as_ref() {
         if mode.help_requested() {
             println!("{}", mode.self_usage());
             return Ok(());
@@ -779,11 +1090,192 @@ fn handle_led_mode(mode: &LedModeCommand) -> Result<(), Box<dyn std::error::Erro
     Ok(())
 }
 
-fn handle_led_power1(power: &LedPowerCommand1) -> Result<(), Box<dyn std::error::Error>> {
+fn handle_aura_layout(
+    cmd: &LayoutCommand,
+    output: OutputFormat
+) -> Result<(), Box<dyn std::error::Error>> {
+    if (cmd.check.is_none()
+        && cmd.links.is_none()
+        && cmd.preview.is_none()
+        && cmd.skeleton.is_none())
+        || cmd.help
+    {
+        if !output.is_text() {
+            return Err(CliError::usage("Missing arg or command").into());
+        }
+        println!("Missing arg or command\n\n{}", cmd.self_usage());
+        return Ok(());
+    }
+
+    let support = || -> Result<LedSupportFile, Box<dyn std::error::Error>> {
+        let path = cmd
+            .support
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(ASUS_LED_MODE_CONF));
+        let file = std::fs::read_to_string(&path)
+            .map_err(|e| CliError::new(ErrorKind::Io, format!("{}: {e}", path.display())))?;
+        Ok(ron::from_str(&file)?)
+    };
+
+    let mut issues = Vec::new();
+    if let Some(path) = &cmd.check {
+        let file = std::fs::read_to_string(path)
+            .map_err(|e| CliError::new(ErrorKind::Io, format!("{}: {e}", path.display())))?;
+        let max_width = cmd.max_width.unwrap_or(MAX_CHASSIS_WIDTH);
+        issues.append(&mut validate_layout(&file, max_width)?);
+    }
+    if let Some(dir) = &cmd.links {
+        issues.append(&mut validate_support_links(support()?.get(), dir));
+    }
+    if cmd.check.is_some() || cmd.links.is_some() {
+        if output.is_text() {
+            for issue in &issues {
+                let level = if issue.is_error() { "error" } else { "warning" };
+                println!("{level}: {issue}");
+            }
+        } else {
+            output.print(&issues)?;
+        }
+        let errors = issues.iter().filter(|i| i.is_error()).count();
+        if errors > 0 {
+            return Err(CliError::new(
+                ErrorKind::Failed,
+                format!("Layout check found {errors} error(s)")
+            )
+            .into());
+        }
+    }
+
+    if let Some(path) = &cmd.preview {
+        let layout = KeyLayout::from_file(path)?;
+        let preview = if cmd.svg {
+            layout.preview_svg()
+        } else {
+            layout.preview_ascii()
+        };
+        if output.is_text() {
+            print!("{preview}");
+        } else {
+            output.print(&preview)?;
+        }
+    }
+
+    if let Some(id) = &cmd.skeleton {
+        // A missing support file only means the type is guessed from the ID
+        let support = support().unwrap_or_default();
+        let layout = KeyLayout::skeleton(support.get(), id)?;
+        if output.is_text() {
+            println!(
+                "{}",
+                ron::ser::to_string_pretty(&layout, PrettyConfig::new().depth_limit(4))?
+            );
+        } else {
+            output.print(&layout)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// The product ID of the first ASUS laptop keyboard on USB
+fn find_aura_product_id() -> Option<String> {
+    let read = |path: PathBuf| std::fs::read_to_string(path).map(|s| s.trim().to_owned());
+    std::fs::read_dir("/sys/bus/usb/devices")
+        .ok()?
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|dev| read(dev.join("idVendor")).is_ok_and(|v| v == "0b05"))
+        .filter_map(|dev| read(dev.join("idProduct")).ok())
+        .find(|id| {
+            let dev_type = AuraDeviceType::from(id.as_str());
+            dev_type.is_new_laptop() || dev_type.is_old_laptop() || dev_type.is_tuf_laptop()
+        })
+}
+
+fn handle_aura_support(
+    cmd: &SupportCommand,
+    output: OutputFormat
+) -> Result<(), Box<dyn std::error::Error>> {
+    if cmd.help {
+        println!("{}", cmd.self_usage());
+        return Ok(());
+    }
+
+    // Same order as asusd uses to find the board name
+    let board_name = cmd
+        .board_name
+        .clone()
+        .or_else(|| std::env::var("BOARD_NAME").ok())
+        .unwrap_or_else(|| DMIID::new().unwrap_or_default().board_name);
+    let product_id = cmd
+        .product_id
+        .clone()
+        .or_else(find_aura_product_id)
+        .unwrap_or_default();
+
+    let db = LedSupportDb::load(
+        cmd.support
+            .as_deref()
+            .unwrap_or(Path::new(ASUS_LED_MODE_CONF)),
+        Path::new(ASUS_LED_MODE_USER_CONF),
+        cmd.dropins
+            .as_deref()
+            .unwrap_or(Path::new(ASUS_LED_MODE_DROPIN_DIR))
+    );
+    let found = db.match_device(&board_name, &product_id);
+
+    if !output.is_text() {
+        output.print(&SupportMatchInfo {
+            board_name: &board_name,
+            product_id: &product_id,
+            issues: db.issues(),
+            found: &found
+        })?;
+    } else {
+        for issue in db.issues() {
+            let level = if issue.is_error() { "error" } else { "warning" };
+            println!("{level}: {issue}");
+        }
+        println!("Board name: {board_name}");
+        println!("Product ID: {product_id}");
+        if !db.is_loaded() {
+            println!("No support data found, asusd controls keyboard brightness only");
+        } else if found.reason == MatchReason::Default {
+            println!("Matched: nothing, {}", found.reason);
+        } else {
+            println!("Matched: {} ({})", found.data.device_name, found.reason);
+            for (i, source) in found.sources.iter().enumerate() {
+                let how = if i == 0 { "defined in" } else { "changed by" };
+                println!("  {how} {}", source.display());
+            }
+        }
+        for other in &found.passed_over {
+            println!("Not used: {other}");
+        }
+        println!(
+            "{}",
+            ron::ser::to_string_pretty(&found.data, PrettyConfig::new().depth_limit(2))?
+        );
+    }
+
+    if db.issues().iter().any(|i| i.is_error()) {
+        return Err(
+            CliError::new(ErrorKind::Failed, "Some support files could not be loaded").into()
+        );
+    }
+    Ok(())
+}
+
+fn handle_led_power1(
+    power: &LedPowerCommand1,
+    output: OutputFormat
+) -> Result<(), Box<dyn std::error::Error>> {
     let aura = find_iface::<AuraProxyBlocking>("xyz.ljones.Aura")?;
     for aura in aura {
         let dev_type = aura.device_type()?;
         if !dev_type.is_old_laptop() && !dev_type.is_tuf_laptop() {
+            if !output.is_text() {
+                continue;
+            }
             println!("This option applies only to keyboards 2021+");
         }
 
@@ -793,6 +1285,13 @@ fn handle_led_power1(power: &LedPowerCommand1) -> Result<(), Box<dyn std::error:
             && !power.keyboard
             && !power.lightbar
         {
+            if !output.is_text() && !power.help {
+                output.print(&aura.led_power()?)?;
+                return Ok(());
+            }
+            if !output.is_text() {
+                return Err(CliError::usage("Missing arg or command").into());
+            }
             if !power.help {
                 println!("Missing arg or command\n");
             }
@@ -806,6 +1305,11 @@ fn handle_led_power1(power: &LedPowerCommand1) -> Result<(), Box<dyn std::error:
         }
     }
 
+    if !output.is_text() {
+        return Err(
+            CliError::not_supported("aura-power-old without a 0x1866 or TUF keyboard").into()
+        );
+    }
     println!("These options are for keyboards of product ID 0x1866 or TUF only");
     Ok(())
 }
@@ -839,15 +1343,26 @@ fn handle_led_power_1_do_1866(
     Ok(())
 }
 
-fn handle_led_power2(power: &LedPowerCommand2) -> Result<(), Box<dyn std::error::Error>> {
+fn handle_led_power2(
+    power: &LedPowerCommand2,
+    output: OutputFormat
+) -> Result<(), Box<dyn std::error::Error>> {
     let aura = find_iface::<AuraProxyBlocking>("xyz.ljones.Aura")?;
     for aura in aura {
         let dev_type = aura.device_type()?;
         if !dev_type.is_new_laptop() {
+            if !output.is_text() {
+                return Err(CliError::not_supported(format!("aura-power on {dev_type:?}")).into());
+            }
             println!("This option applies only to keyboards 2021+");
             continue;
         }
 
+        if power.command().is_none() && !output.is_text() {
+            output.print(&aura.led_power()?)?;
+            continue;
+        }
+
         if power.command().is_none() {
             if !power.help {
                 println!("Missing arg or command\n");
@@ -906,14 +1421,20 @@ fn handle_led_power2(power: &LedPowerCommand2) -> Result<(), Box<dyn std::error:
 fn handle_throttle_profile(
     conn: &Connection,
     supported: &[Properties],
-    cmd: &ProfileCommand
+    cmd: &ProfileCommand,
+    output: OutputFormat
 ) -> Result<(), Box<dyn std::error::Error>> {
     if !supported.contains(&Properties::ThrottlePolicy) {
-        println!("Profiles not supported by either this kernel or by the laptop.");
+        if output.is_text() {
+            println!("Profiles not supported by either this kernel or by the laptop.");
+        }
         return Err(ProfileError::NotSupported.into());
     }
 
     if !cmd.next && !cmd.list && cmd.profile_set.is_none() && !cmd.profile_get {
+        if !output.is_text() {
+            return Err(CliError::usage("Missing arg or command").into());
+        }
         if !cmd.help {
             println!("Missing arg or command\n");
         }
@@ -934,6 +1455,16 @@ fn handle_throttle_profile(
         proxy.set_platform_profile(profile)?;
     }
 
+    if !output.is_text() {
+        if cmd.list || cmd.profile_get {
+            output.print(&ProfileInfo {
+                active: cmd.profile_get.then_some(current),
+                available: cmd.list.then(|| PlatformProfile::list().to_vec())
+            })?;
+        }
+        return Ok(());
+    }
+
     if cmd.list {
         let res = PlatformProfile::list();
         for p in &res {
@@ -948,17 +1479,257 @@ fn handle_throttle_profile(
     Ok(())
 }
 
+fn handle_scene(
+    conn: &Connection,
+    cmd: &SceneCommand,
+    output: OutputFormat
+) -> Result<(), Box<dyn std::error::Error>> {
+    if (!cmd.list && cmd.save.is_none() && cmd.apply.is_none() && cmd.delete.is_none()) || cmd.help
+    {
+        if !output.is_text() {
+            return Err(CliError::usage("Missing arg or command").into());
+        }
+        println!("Missing arg or command\n\n{}", cmd.self_usage());
+        return Ok(());
+    }
+
+    let proxy = ScenesProxyBlocking::new(conn)?;
+
+    if let Some(name) = &cmd.save {
+        let skipped = proxy.save_scene(name)?;
+        if output.is_text() {
+            println!("Saved scene {name}");
+            for s in skipped {
+                println!("  skipped {s}");
+            }
+        } else {
+            output.print(&skipped)?;
+        }
+    }
+
+    if let Some(name) = &cmd.apply {
+        let skipped = proxy.apply_scene(name)?;
+        if output.is_text() {
+            println!("Applied scene {name}");
+            for s in skipped {
+                println!("  skipped {s}");
+            }
+        } else {
+            output.print(&skipped)?;
+        }
+    }
+
+    if let Some(name) = &cmd.delete {
+        proxy.delete_scene(name)?;
+        if output.is_text() {
+            println!("Deleted scene {name}");
+        }
+    }
+
+    if cmd.list {
+        let scenes = proxy.list_scenes()?;
+        if output.is_text() {
+            for name in scenes {
+                println!("{name}");
+            }
+        } else {
+            output.print(&scenes)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn handle_config(
+    conn: &Connection,
+    cmd: &ConfigCommand,
+    output: OutputFormat
+) -> Result<(), Box<dyn std::error::Error>> {
+    let Some(action) = cmd.command.as_ref().filter(|_| !cmd.help) else {
+        if !output.is_text() {
+            return Err(CliError::usage("Missing arg or command").into());
+        }
+        println!("Missing arg or command\n\n{}", cmd.self_usage());
+        if let Some(lst) = cmd.self_command_list() {
+            println!("\n{}", lst);
+        }
+        return Ok(());
+    };
+
+    let proxy = ConfigBundleProxyBlocking::new(conn)?;
+    match action {
+        ConfigActions::Export(export) => {
+            if export.help {
+                if !output.is_text() {
+                    return Err(CliError::usage("Missing arg or command").into());
+                }
+                println!("{}", export.self_usage());
+                return Ok(());
+            }
+            let bundle = proxy.export_config()?;
+            if let Some(file) = &export.file {
+                std::fs::write(file, bundle)?;
+                if output.is_text() {
+                    println!("Exported settings to {file}");
+                }
+            } else if output.is_text() {
+                println!("{bundle}");
+            } else {
+                output.print(&bundle)?;
+            }
+        }
+        ConfigActions::Import(import) => {
+            if import.help || import.file.is_empty() {
+                if !output.is_text() {
+                    return Err(CliError::usage("Missing arg or command").into());
+                }
+                println!("Missing arg or command\n\n{}", import.self_usage());
+                return Ok(());
+            }
+            let bundle = std::fs::read_to_string(&import.file)?;
+            let skipped = proxy.import_config(&bundle)?;
+            if !output.is_text() {
+                output.print(&skipped)?;
+                return Ok(());
+            }
+            println!("Imported settings from {}", import.file);
+            for s in skipped {
+                println!("  skipped {s}");
+            }
+        }
+    }
+    Ok(())
+}
+
+fn print_telemetry(sample: &TelemetrySample) {
+    println!(
+        "CPU: {:.1}°C, GPU: {:.1}°C",
+        sample.cpu_temp, sample.gpu_temp
+    );
+    for fan in &sample.fans {
+        println!("  {}: {} RPM", fan.label, fan.rpm);
+    }
+    for temp in &sample.temperatures {
+        println!("  {}: {:.1}°C", temp.label, temp.celsius);
+    }
+    println!("Power draw: {:.2} W", sample.power_draw);
+    if let Some(health) = sample.battery_health() {
+        println!(
+            "Battery health: {health:.1}% ({:.1}/{:.1} Wh), {} cycles",
+            sample.energy_full, sample.energy_full_design, sample.cycle_count
+        );
+    }
+}
+
+fn handle_telemetry(
+    conn: &Connection,
+    cmd: &TelemetryCommand,
+    output: OutputFormat
+) -> Result<(), Box<dyn std::error::Error>> {
+    if cmd.help {
+        println!("{}", cmd.self_usage());
+        return Ok(());
+    }
+
+    let proxy = TelemetryProxyBlocking::new(conn)?;
+    if let Some(interval) = cmd.interval {
+        proxy.set_interval(interval)?;
+        if output.is_text() {
+            println!("Telemetry interval set to {interval}ms");
+        }
+        return Ok(());
+    }
+
+    let samples = if cmd.history {
+        proxy.history()?
+    } else {
+        vec![proxy.latest()?]
+    };
+    if !output.is_text() {
+        if cmd.history {
+            output.print(&samples)?;
+        } else {
+            output.print(&samples[0])?;
+        }
+        return Ok(());
+    }
+    for sample in &samples {
+        if cmd.history {
+            println!("Time: {}ms", sample.time);
+        }
+        print_telemetry(sample);
+    }
+    Ok(())
+}
+
+fn handle_status(
+    conn: &Connection,
+    cmd: &StatusCommand,
+    supported_interfaces: &[String],
+    output: OutputFormat
+) -> Result<(), Box<dyn std::error::Error>> {
+    if cmd.help {
+        println!("{}", cmd.self_usage());
+        return Ok(());
+    }
+
+    let draw = || -> Result<(), CliError> {
+        let status = Status::collect(conn, supported_interfaces);
+        if output.is_text() {
+            if cmd.watch {
+                // Clear the terminal and move to the top before redrawing
+                print!("\x1b[2J\x1b[H");
+            }
+            print!("{}", status.render());
+        } else {
+            output.print(&status)?;
+        }
+        Ok(())
+    };
+    draw()?;
+    if !cmd.watch {
+        return Ok(());
+    }
+
+    let rule = MatchRule::builder()
+        .msg_type(zbus::message::Type::Signal)
+        .interface("org.freedesktop.DBus.Properties")?
+        .member("PropertiesChanged")?
+        .path_namespace("/xyz/ljones")?
+        .build();
+    for msg in MessageIterator::for_match_rule(rule, conn, None)? {
+        let Some(changed) = PropertiesChanged::from_message(msg?) else {
+            continue;
+        };
+        // Telemetry changes every few seconds and isn't part of the summary
+        if changed
+            .args()
+            .is_ok_and(|args| args.interface_name().as_str() == "xyz.ljones.Telemetry")
+        {
+            continue;
+        }
+        draw()?;
+    }
+    Ok(())
+}
+
 fn handle_fan_curve(
     conn: &Connection,
-    cmd: &FanCurveCommand
+    cmd: &FanCurveCommand,
+    output: OutputFormat
 ) -> Result<(), Box<dyn std::error::Error>> {
     let Ok(fan_proxy) = FanCurvesProxyBlocking::new(conn).map_err(|e| {
-        println!("Fan-curves not supported by either this kernel or by the laptop: {e:?}");
+        if output.is_text() {
+            println!("Fan-curves not supported by either this kernel or by the laptop: {e:?}");
+        }
     }) else {
         return Err(ProfileError::NotSupported.into());
     };
 
     if !cmd.get_enabled && !cmd.default && cmd.mod_profile.is_none() {
+        if !output.is_text() {
+            return Err(CliError::usage("Missing arg or command").into());
+        }
         if !cmd.help {
             println!("Missing arg or command\n");
         }
@@ -970,22 +1741,29 @@ fn handle_fan_curve(
         return Ok(());
     }
 
-    if (cmd.enable_fan_curves.is_some() || cmd.fan.is_some() || cmd.data.is_some())
+    if (cmd.enable_fan_curves.is_some()
+        || cmd.fan.is_some()
+        || cmd.data.is_some()
+        || cmd.preset.is_some())
         && cmd.mod_profile.is_none()
     {
-        println!(
-            "--enable-fan-curves, --enable-fan-curve, --fan, and --data options require \
+        return Err(CliError::usage(
+            "--enable-fan-curves, --enable-fan-curve, --fan, --data, and --preset options require \
              --mod-profile"
-        );
-        return Ok(());
+        )
+        .into());
     }
 
     let plat_proxy = PlatformProxyBlocking::new(conn)?;
     if cmd.get_enabled {
         let profile = plat_proxy.platform_profile()?;
         let curves = fan_proxy.fan_curve_data(profile)?;
-        for curve in curves.iter() {
-            println!("{}", String::from(curve));
+        if output.is_text() {
+            for curve in curves.iter() {
+                println!("{}", String::from(curve));
+            }
+        } else {
+            output.print(&curves)?;
         }
     }
 
@@ -995,10 +1773,14 @@ fn handle_fan_curve(
     }
 
     if let Some(profile) = cmd.mod_profile {
-        if cmd.enable_fan_curves.is_none() && cmd.data.is_none() {
+        if cmd.enable_fan_curves.is_none() && cmd.data.is_none() && cmd.preset.is_none() {
             let data = fan_proxy.fan_curve_data(profile)?;
-            let ron = ron::ser::to_string_pretty(&data, PrettyConfig::new().depth_limit(4))?;
-            println!("\nFan curves for {:?}\n\n{}", profile, ron);
+            if output.is_text() {
+                let ron = output.serialize(&data)?;
+                println!("\nFan curves for {:?}\n\n{}", profile, ron);
+            } else {
+                output.print(&data)?;
+            }
         }
 
         if let Some(enabled) = cmd.enable_fan_curves {
@@ -1009,16 +1791,22 @@ fn handle_fan_curve(
             if let Some(fan) = cmd.fan {
                 fan_proxy.set_profile_fan_curve_enabled(profile, fan, enabled)?;
             } else {
-                println!(
-                    "--enable-fan-curves, --enable-fan-curve, --fan, and --data options require \
-                     --mod-profile"
-                );
+                return Err(CliError::usage("--enable-fan-curve requires --fan").into());
             }
         }
 
         if let Some(mut curve) = cmd.data.clone() {
             let fan = cmd.fan.unwrap_or_default();
             curve.set_fan(fan);
+            curve.validate()?;
+            fan_proxy.set_fan_curve(profile, curve)?;
+        }
+
+        if let Some(preset) = cmd.preset {
+            let curve = CurveData::from_preset(cmd.fan.unwrap_or_default(), preset);
+            if output.is_text() {
+                println!("Setting {}", String::from(&curve));
+            }
             fan_proxy.set_fan_curve(profile, curve)?;
         }
     }
@@ -1103,11 +1891,21 @@ fn print_firmware_attr(attr: &AsusArmouryProxyBlocking) -> Result<(), Box<dyn st
     Ok(())
 }
 
-fn handle_armoury_command(cmd: &ArmouryCommand) -> Result<(), Box<dyn std::error::Error>> {
+fn handle_armoury_command(
+    cmd: &ArmouryCommand,
+    output: OutputFormat
+) -> Result<(), Box<dyn std::error::Error>> {
     {
         if cmd.free.is_empty() || cmd.free.len() % 2 != 0 || cmd.help {
             const USAGE: &str = "Usage: asusctl platform panel_overdrive 1 nv_dynamic_boost 5";
             if cmd.free.len() % 2 != 0 {
+                if !output.is_text() {
+                    return Err(CliError::usage(
+                        "Incorrect number of args, each attribute label must be paired with a \
+                         setting"
+                    )
+                    .into());
+                }
                 println!(
                     "Incorrect number of args, each attribute label must be paired with a setting:"
                 );
@@ -1115,6 +1913,16 @@ fn handle_armoury_command(cmd: &ArmouryCommand) -> Result<(), Box<dyn std::error
                 return Ok(());
             }
 
+            if !output.is_text() {
+                let attr = find_iface::<AsusArmouryProxyBlocking>("xyz.ljones.AsusArmoury")?;
+                let attrs = attr
+                    .iter()
+                    .map(AttributeInfo::new)
+                    .collect::<Result<Vec<_>, _>>()?;
+                output.print(&attrs)?;
+                return Ok(());
+            }
+
             if let Ok(attr) = find_iface::<AsusArmouryProxyBlocking>("xyz.ljones.AsusArmoury") {
                 println!("\n{USAGE}\n");
                 println!("Available firmware attributes: ");
@@ -1125,22 +1933,30 @@ fn handle_armoury_command(cmd: &ArmouryCommand) -> Result<(), Box<dyn std::error
             return Ok(());
         }
 
-        if let Ok(attr) = find_iface::<AsusArmouryProxyBlocking>("xyz.ljones.AsusArmoury") {
-            for cmd in cmd.free.chunks(2) {
-                for attr in attr.iter() {
-                    let name = attr.name()?;
-                    if <&str>::from(name) == cmd[0] {
-                        let mut value: i32 = cmd[1].parse()?;
-                        if value == -1 {
-                            info!("Setting to default");
-                            value = attr.default_value()?;
-                        }
-                        attr.set_current_value(value)?;
-                        print_firmware_attr(attr)?;
-                    }
-                }
+        let attr = find_iface::<AsusArmouryProxyBlocking>("xyz.ljones.AsusArmoury")?;
+        let mut changed = Vec::new();
+        for cmd in cmd.free.chunks(2) {
+            let Some(attr) = attr
+                .iter()
+                .find(|a| a.name().is_ok_and(|n| <&str>::from(n) == cmd[0]))
+            else {
+                return Err(CliError::not_supported(format!("Attribute {}", cmd[0])).into());
+            };
+            let mut value: i32 = cmd[1].parse()?;
+            if value == -1 {
+                info!("Setting to default");
+                value = attr.default_value()?;
+            }
+            attr.set_current_value(value)?;
+            if output.is_text() {
+                print_firmware_attr(attr)?;
+            } else {
+                changed.push(AttributeInfo::new(attr)?);
             }
         }
+        if !output.is_text() {
+            output.print(&changed)?;
+        }
     }
     Ok(())
 }
@@ -0,0 +1,324 @@
+//! Machine readable output selected with the global `--json` or `--ron`
+//! options. Errors are reported in the same format with a non-zero exit code
+//! so that scripts don't need to scrape the human readable text.
+
+use std::error::Error;
+use std::fmt;
+use std::process::ExitCode;
+
+use rog_aura::aura_detection::{SupportIssue, SupportMatch};
+use rog_aura::keyboard::LaptopAuraPower;
+use rog_aura::{AuraDeviceType, AuraEffect, AuraModeNum, AuraZone, LedBrightness, PowerZones};
+use rog_dbus::asus_armoury::AsusArmouryProxyBlocking;
+use rog_dbus::zbus_aura::AuraProxyBlocking;
+use rog_platform::asus_armoury::FirmwareAttribute;
+use rog_platform::platform::{PlatformProfile, Properties};
+use rog_profiles::error::ProfileError;
+use ron::ser::PrettyConfig;
+use serde::Serialize;
+use zbus::DBusError;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+    Ron
+}
+
+impl OutputFormat {
+    pub fn new(json: bool, ron: bool) -> Self {
+        if json {
+            Self::Json
+        } else if ron {
+            Self::Ron
+        } else {
+            Self::Text
+        }
+    }
+
+    /// Used when the args could not be parsed, so errors are still reported in
+    /// the requested format
+    pub fn from_raw_args(args: &[String]) -> Self {
+        Self::new(
+            args.iter().any(|a| a == "--json"),
+            args.iter().any(|a| a == "--ron")
+        )
+    }
+
+    pub fn is_text(self) -> bool {
+        self == Self::Text
+    }
+
+    pub fn serialize<T: Serialize>(self, value: &T) -> Result<String, CliError> {
+        match self {
+            Self::Json => serde_json::to_string_pretty(value)
+                .map_err(|e| CliError::new(ErrorKind::Failed, e.to_string())),
+            Self::Text | Self::Ron => {
+                ron::ser::to_string_pretty(value, PrettyConfig::new().depth_limit(4))
+                    .map_err(|e| CliError::new(ErrorKind::Failed, e.to_string()))
+            }
+        }
+    }
+
+    /// Print the value in the selected format. Text output is left to the
+    /// caller, so this should be used only when `!is_text()`.
+    pub fn print<T: Serialize>(self, value: &T) -> Result<(), CliError> {
+        println!("{}", self.serialize(value)?);
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct VersionInfo<'a> {
+    pub asusctl: &'a str,
+    pub asusd: &'a str,
+    pub product_family: &'a str,
+    pub board_name: &'a str
+}
+
+#[derive(Debug, Serialize)]
+pub struct AuraSupport {
+    pub brightness: Vec<LedBrightness>,
+    pub modes: Vec<AuraModeNum>,
+    pub zones: Vec<AuraZone>,
+    pub power_zones: Vec<PowerZones>
+}
+
+/// The `aura_support.ron` entry matched for a device
+#[derive(Debug, Serialize)]
+pub struct SupportMatchInfo<'a> {
+    pub board_name: &'a str,
+    pub product_id: &'a str,
+    pub issues: &'a [SupportIssue],
+    pub found: &'a SupportMatch
+}
+
+#[derive(Debug, Serialize)]
+pub struct SupportedInfo<'a> {
+    pub interfaces: &'a [String],
+    pub platform_properties: &'a [Properties],
+    pub aura: Option<AuraSupport>
+}
+
+/// Current state of one aura device
+#[derive(Debug, Serialize)]
+pub struct AuraState {
+    pub device_type: AuraDeviceType,
+    pub brightness: LedBrightness,
+    pub effect: AuraEffect,
+    pub supported_modes: Vec<AuraModeNum>,
+    pub power: LaptopAuraPower
+}
+
+impl AuraState {
+    pub fn new(aura: &AuraProxyBlocking) -> zbus::Result<Self> {
+        Ok(Self {
+            device_type: aura.device_type()?,
+            brightness: aura.brightness()?,
+            effect: aura.led_mode_data()?,
+            supported_modes: aura.supported_basic_modes()?,
+            power: aura.led_power()?
+        })
+    }
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct ProfileInfo {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub active: Option<PlatformProfile>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub available: Option<Vec<PlatformProfile>>
+}
+
+/// The metadata of an armoury attribute, values the driver doesn't expose for
+/// the attribute are `None`
+#[derive(Serialize)]
+pub struct AttributeInfo {
+    pub name: FirmwareAttribute,
+    pub current_value: Option<i32>,
+    pub default_value: Option<i32>,
+    pub min_value: Option<i32>,
+    pub max_value: Option<i32>,
+    pub scalar_increment: Option<i32>,
+    pub possible_values: Option<Vec<i32>>
+}
+
+impl AttributeInfo {
+    pub fn new(attr: &AsusArmouryProxyBlocking) -> zbus::Result<Self> {
+        let attrs = attr.available_attrs()?;
+        let has = |name: &str| attrs.iter().any(|a| a == name);
+        Ok(Self {
+            name: attr.name()?,
+            current_value: has("current_value")
+                .then(|| attr.current_value())
+                .transpose()?,
+            default_value: has("default_value")
+                .then(|| attr.default_value())
+                .transpose()?,
+            min_value: has("min_value").then(|| attr.min_value()).transpose()?,
+            max_value: has("max_value").then(|| attr.max_value()).transpose()?,
+            scalar_increment: has("scalar_increment")
+                .then(|| attr.scalar_increment())
+                .transpose()?,
+            possible_values: has("possible_values")
+                .then(|| attr.possible_values())
+                .transpose()?
+        })
+    }
+}
+
+/// The kind of failure, each has a distinct exit code
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    Failed,
+    /// Args could not be parsed
+    Usage,
+    /// The laptop, kernel or daemon doesn't support the function
+    NotSupported,
+    /// asusd could not be reached
+    Daemon,
+    /// asusctl and asusd versions differ
+    VersionMismatch,
+    InvalidArgs,
+    Io
+}
+
+impl ErrorKind {
+    pub const fn exit_code(self) -> i32 {
+        match self {
+            ErrorKind::Failed => 1,
+            ErrorKind::Usage => 2,
+            ErrorKind::NotSupported => 3,
+            ErrorKind::Daemon => 4,
+            ErrorKind::VersionMismatch => 5,
+            ErrorKind::InvalidArgs => 6,
+            ErrorKind::Io => 7
+        }
+    }
+
+    fn from_dbus_name(name: &str) -> Self {
+        match name.rsplit('.').next().unwrap_or_default() {
+            "NotSupported" | "UnknownInterface" | "UnknownObject" | "UnknownMethod"
+            | "UnknownProperty" => ErrorKind::NotSupported,
+            "InvalidArgs" => ErrorKind::InvalidArgs,
+            "ServiceUnknown" | "NameHasNoOwner" | "NoReply" | "Disconnected" => ErrorKind::Daemon,
+            _ => ErrorKind::Failed
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CliError {
+    pub kind: ErrorKind,
+    pub message: String,
+    pub exit_code: i32
+}
+
+impl CliError {
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+            exit_code: kind.exit_code()
+        }
+    }
+
+    pub fn not_supported(what: impl fmt::Display) -> Self {
+        Self::new(ErrorKind::NotSupported, format!("{what} is not supported"))
+    }
+
+    pub fn usage(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::Usage, message)
+    }
+
+    /// Sort an error from any of the libraries into a kind
+    pub fn from_error(err: &(dyn Error + 'static)) -> Self {
+        let kind = if let Some(e) = err.downcast_ref::<CliError>() {
+            return e.clone();
+        } else if let Some(e) = err.downcast_ref::<zbus::Error>() {
+            match e {
+                zbus::Error::MethodError(name, ..) => ErrorKind::from_dbus_name(name.as_str()),
+                zbus::Error::FDO(e) => ErrorKind::from_dbus_name(e.name().as_str()),
+                zbus::Error::InputOutput(_) | zbus::Error::Address(_) => ErrorKind::Daemon,
+                zbus::Error::InterfaceNotFound | zbus::Error::Unsupported => {
+                    ErrorKind::NotSupported
+                }
+                _ => ErrorKind::Failed
+            }
+        } else if let Some(e) = err.downcast_ref::<zbus::fdo::Error>() {
+            ErrorKind::from_dbus_name(e.name().as_str())
+        } else if let Some(e) = err.downcast_ref::<ProfileError>() {
+            match e {
+                ProfileError::NotSupported | ProfileError::NotFound(_) => ErrorKind::NotSupported,
+                _ => ErrorKind::InvalidArgs
+            }
+        } else if err.is::<gumdrop::Error>() {
+            ErrorKind::Usage
+        } else if err.is::<std::num::ParseIntError>() {
+            ErrorKind::InvalidArgs
+        } else if err.is::<std::io::Error>() {
+            ErrorKind::Io
+        } else {
+            ErrorKind::Failed
+        };
+        Self::new(kind, err.to_string())
+    }
+
+    /// Print the error in the machine readable formats, then return the exit
+    /// code for its kind. Text output is left to the caller.
+    pub fn report(self, format: OutputFormat) -> ExitCode {
+        if !format.is_text() {
+            format.print(&self).ok();
+        }
+        ExitCode::from(self.exit_code as u8)
+    }
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl Error for CliError {}
+
+#[cfg(test)]
+mod tests {
+    use rog_profiles::error::ProfileError;
+
+    use super::{CliError, ErrorKind, OutputFormat};
+
+    #[test]
+    fn classify_errors() {
+        let err: Box<dyn std::error::Error> = Box::new(ProfileError::NotSupported);
+        assert_eq!(CliError::from_error(&*err).kind, ErrorKind::NotSupported);
+
+        let err: Box<dyn std::error::Error> = Box::new(zbus::Error::FDO(Box::new(
+            zbus::fdo::Error::InvalidArgs("bad".to_owned())
+        )));
+        assert_eq!(CliError::from_error(&*err).kind, ErrorKind::InvalidArgs);
+
+        let err: Box<dyn std::error::Error> = "x".parse::<i32>().unwrap_err().into();
+        let err = CliError::from_error(&*err);
+        assert_eq!(err.kind, ErrorKind::InvalidArgs);
+        assert_eq!(err.exit_code, 6);
+
+        let err: Box<dyn std::error::Error> = CliError::not_supported("xyz.ljones.Aura").into();
+        assert_eq!(
+            CliError::from_error(&*err).message,
+            "xyz.ljones.Aura is not supported"
+        );
+    }
+
+    #[test]
+    fn error_as_json() {
+        let err = CliError::new(ErrorKind::Daemon, "asusd is not running");
+        assert_eq!(
+            OutputFormat::Json.serialize(&err).unwrap(),
+            "{\n  \"kind\": \"daemon\",\n  \"message\": \"asusd is not running\",\n  \
+             \"exit_code\": 4\n}"
+        );
+    }
+}
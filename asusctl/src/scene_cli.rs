@@ -0,0 +1,19 @@
+use gumdrop::Options;
+
+#[derive(Options)]
+pub struct SceneCommand {
+    #[options(help = "print help message")]
+    pub help: bool,
+
+    #[options(help = "list all saved scenes")]
+    pub list: bool,
+
+    #[options(meta = "", help = "save the current settings as a named scene")]
+    pub save: Option<String>,
+
+    #[options(meta = "", help = "apply a saved scene")]
+    pub apply: Option<String>,
+
+    #[options(meta = "", help = "delete a saved scene")]
+    pub delete: Option<String>
+}
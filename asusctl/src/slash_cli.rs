@@ -17,6 +17,11 @@ pub struct SlashCommand {
     pub mode: Option<SlashMode>,
     #[options(help = "list available animations")]
     pub list: bool,
+    #[options(
+        meta = "FILE",
+        help = "Write a preview of --mode to a .png or .gif, without changing the Slash"
+    )]
+    pub preview: Option<String>,
 
     #[options(short = "B", meta = "", help = "Show the animation on boot")]
     pub show_on_boot: Option<bool>,
@@ -0,0 +1,354 @@
+//! A summary of every subsystem asusd exposes, used by `asusctl status`
+
+use log::warn;
+use rog_anime::usb::Brightness as AnimeBrightness;
+use rog_anime::Animations;
+use rog_aura::Colour;
+use rog_dbus::asus_armoury::AsusArmouryProxyBlocking;
+use rog_dbus::scsi_aura::ScsiAuraProxyBlocking;
+use rog_dbus::zbus_anime::AnimeProxyBlocking;
+use rog_dbus::zbus_aura::AuraProxyBlocking;
+use rog_dbus::zbus_fan_curves::FanCurvesProxyBlocking;
+use rog_dbus::zbus_platform::PlatformProxyBlocking;
+use rog_dbus::zbus_slash::SlashProxyBlocking;
+use rog_platform::platform::PlatformProfile;
+use rog_profiles::fan_curve_set::CurveData;
+use rog_slash::SlashMode;
+use serde::Serialize;
+use zbus::blocking::Connection;
+
+use crate::find_iface;
+use crate::output::{AttributeInfo, AuraState};
+
+#[derive(Debug, Serialize)]
+pub struct PlatformStatus {
+    pub profile: PlatformProfile,
+    pub profile_on_ac: PlatformProfile,
+    pub profile_on_battery: PlatformProfile,
+    pub charge_limit: u8,
+    pub charge_schedule_enabled: bool,
+    /// `0` if no schedule window or full-by target is active
+    pub scheduled_charge_limit: u8
+}
+
+#[derive(Debug, Serialize)]
+pub struct FanCurveStatus {
+    pub profile: PlatformProfile,
+    pub curves: Vec<CurveData>
+}
+
+#[derive(Debug, Serialize)]
+pub struct AnimeStatus {
+    pub display_enabled: bool,
+    pub brightness: AnimeBrightness,
+    pub builtins_enabled: bool,
+    pub builtin_animations: Animations
+}
+
+#[derive(Debug, Serialize)]
+pub struct SlashStatus {
+    pub enabled: bool,
+    pub brightness: u8,
+    pub interval: u8,
+    pub mode: SlashMode
+}
+
+#[derive(Debug, Serialize)]
+pub struct ScsiStatus {
+    pub enabled: bool,
+    pub effect: rog_scsi::AuraEffect
+}
+
+/// Each section is `None` or empty if the interface isn't available. A failure
+/// to read one section doesn't stop the others, it is recorded in `errors`.
+#[derive(Default, Serialize)]
+pub struct Status {
+    pub platform: Option<PlatformStatus>,
+    pub fan_curves: Option<FanCurveStatus>,
+    pub aura: Vec<AuraState>,
+    pub anime: Vec<AnimeStatus>,
+    pub slash: Vec<SlashStatus>,
+    pub scsi: Vec<ScsiStatus>,
+    pub armoury: Vec<AttributeInfo>,
+    pub errors: Vec<String>
+}
+
+impl Status {
+    pub fn collect(conn: &Connection, interfaces: &[String]) -> Self {
+        let mut status = Self::default();
+        let has = |name: &str| interfaces.iter().any(|i| i == name);
+
+        let record = |section: &str, res: Result<(), Box<dyn std::error::Error>>| {
+            res.err().map(|e| {
+                warn!("Could not read {section} status: {e}");
+                format!("{section}: {e}")
+            })
+        };
+        let mut errors = Vec::new();
+
+        if has("xyz.ljones.Platform") {
+            errors.extend(record(
+                "platform",
+                (|| {
+                    let proxy = PlatformProxyBlocking::new(conn)?;
+                    status.platform = Some(PlatformStatus {
+                        profile: proxy.platform_profile()?,
+                        profile_on_ac: proxy.platform_profile_on_ac()?,
+                        profile_on_battery: proxy.platform_profile_on_battery()?,
+                        charge_limit: proxy.charge_control_end_threshold()?,
+                        charge_schedule_enabled: proxy.charge_schedule_enabled()?,
+                        scheduled_charge_limit: proxy.scheduled_charge_limit()?
+                    });
+                    Ok(())
+                })()
+            ));
+        }
+
+        if has("xyz.ljones.FanCurves") {
+            errors.extend(record(
+                "fan curves",
+                (|| {
+                    let profile = PlatformProxyBlocking::new(conn)?.platform_profile()?;
+                    let curves = FanCurvesProxyBlocking::new(conn)?.fan_curve_data(profile)?;
+                    status.fan_curves = Some(FanCurveStatus { profile, curves });
+                    Ok(())
+                })()
+            ));
+        }
+
+        if has("xyz.ljones.Aura") {
+            errors.extend(record(
+                "aura",
+                (|| {
+                    for aura in find_iface::<AuraProxyBlocking>("xyz.ljones.Aura")? {
+                        status.aura.push(AuraState::new(&aura)?);
+                    }
+                    Ok(())
+                })()
+            ));
+        }
+
+        if has("xyz.ljones.Anime") {
+            errors.extend(record(
+                "anime",
+                (|| {
+                    for anime in find_iface::<AnimeProxyBlocking>("xyz.ljones.Anime")? {
+                        status.anime.push(AnimeStatus {
+                            display_enabled: anime.enable_display()?,
+                            brightness: anime.brightness()?,
+                            builtins_enabled: anime.builtins_enabled()?,
+                            builtin_animations: anime.builtin_animations()?
+                        });
+                    }
+                    Ok(())
+                })()
+            ));
+        }
+
+        if has("xyz.ljones.Slash") {
+            errors.extend(record(
+                "slash",
+                (|| {
+                    for slash in find_iface::<SlashProxyBlocking>("xyz.ljones.Slash")? {
+                        status.slash.push(SlashStatus {
+                            enabled: slash.enabled()?,
+                            brightness: slash.brightness()?,
+                            interval: slash.interval()?,
+                            mode: slash.mode()?
+                        });
+                    }
+                    Ok(())
+                })()
+            ));
+        }
+
+        if has("xyz.ljones.ScsiAura") {
+            errors.extend(record(
+                "scsi",
+                (|| {
+                    for scsi in find_iface::<ScsiAuraProxyBlocking>("xyz.ljones.ScsiAura")? {
+                        status.scsi.push(ScsiStatus {
+                            enabled: scsi.enabled()?,
+                            effect: scsi.led_mode_data()?
+                        });
+                    }
+                    Ok(())
+                })()
+            ));
+        }
+
+        if has("xyz.ljones.AsusArmoury") {
+            errors.extend(record(
+                "armoury",
+                (|| {
+                    for attr in find_iface::<AsusArmouryProxyBlocking>("xyz.ljones.AsusArmoury")? {
+                        status.armoury.push(AttributeInfo::new(&attr)?);
+                    }
+                    Ok(())
+                })()
+            ));
+        }
+
+        status.errors = errors;
+        status
+    }
+
+    /// Render the grouped, human readable summary
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        let mut line = |s: String| {
+            out.push_str(&s);
+            out.push('\n');
+        };
+
+        if let Some(p) = &self.platform {
+            line("Platform".into());
+            line(format!(
+                "  Profile:         {:?} (AC: {:?}, battery: {:?})",
+                p.profile, p.profile_on_ac, p.profile_on_battery
+            ));
+            line(format!("  Charge limit:    {}%", p.charge_limit));
+            if p.charge_schedule_enabled {
+                if p.scheduled_charge_limit == 0 {
+                    line("  Charge schedule: enabled, no window active".into());
+                } else {
+                    line(format!(
+                        "  Charge schedule: enabled, limit {}%",
+                        p.scheduled_charge_limit
+                    ));
+                }
+            }
+        }
+
+        if let Some(f) = &self.fan_curves {
+            line(format!("Fan curves ({:?})", f.profile));
+            for curve in &f.curves {
+                line(format!("  {}", String::from(curve)));
+            }
+        }
+
+        for aura in &self.aura {
+            line(format!("Aura ({:?})", aura.device_type));
+            line(format!("  Brightness: {:?}", aura.brightness));
+            let e = &aura.effect;
+            line(format!(
+                "  Effect:     {:?}, zone {:?}, colours {} {}, speed {:?}, direction {:?}",
+                e.mode,
+                e.zone,
+                hex(&e.colour1),
+                hex(&e.colour2),
+                e.speed,
+                e.direction
+            ));
+            for state in &aura.power.states {
+                line(format!(
+                    "  Power {:?}: boot {}, awake {}, sleep {}, shutdown {}",
+                    state.zone, state.boot, state.awake, state.sleep, state.shutdown
+                ));
+            }
+        }
+
+        for anime in &self.anime {
+            line("AniMe Matrix".into());
+            line(format!(
+                "  Display:    {}, brightness {:?}",
+                on_off(anime.display_enabled),
+                anime.brightness
+            ));
+            let a = &anime.builtin_animations;
+            line(format!(
+                "  Builtins:   {}, boot {:?}, awake {:?}, sleep {:?}, shutdown {:?}",
+                on_off(anime.builtins_enabled),
+                a.boot,
+                a.awake,
+                a.sleep,
+                a.shutdown
+            ));
+        }
+
+        for slash in &self.slash {
+            line("Slash".into());
+            line(format!(
+                "  {}, mode {}, brightness {}, interval {}",
+                on_off(slash.enabled),
+                slash.mode,
+                slash.brightness,
+                slash.interval
+            ));
+        }
+
+        for scsi in &self.scsi {
+            line("SCSI drive".into());
+            line(format!("  {}, {}", on_off(scsi.enabled), scsi.effect));
+        }
+
+        if !self.armoury.is_empty() {
+            line("Armoury".into());
+            for attr in &self.armoury {
+                let name = <&str>::from(attr.name);
+                let value = attr
+                    .current_value
+                    .map_or_else(|| "-".to_owned(), |v| v.to_string());
+                let range = match (attr.min_value, attr.max_value, &attr.possible_values) {
+                    (Some(min), Some(max), _) => format!(" ({min}..{max})"),
+                    (_, _, Some(v)) => format!(" {v:?}"),
+                    _ => String::new()
+                };
+                line(format!("  {name:<30} {value}{range}"));
+            }
+        }
+
+        if !self.errors.is_empty() {
+            line("Errors".into());
+            for e in &self.errors {
+                line(format!("  {e}"));
+            }
+        }
+        out
+    }
+}
+
+fn hex(c: &Colour) -> String {
+    format!("#{:02x}{:02x}{:02x}", c.r, c.g, c.b)
+}
+
+fn on_off(b: bool) -> &'static str {
+    if b {
+        "on"
+    } else {
+        "off"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rog_platform::platform::PlatformProfile;
+
+    use super::{PlatformStatus, Status};
+
+    #[test]
+    fn render_sections() {
+        let status = Status {
+            platform: Some(PlatformStatus {
+                profile: PlatformProfile::Balanced,
+                profile_on_ac: PlatformProfile::Performance,
+                profile_on_battery: PlatformProfile::Quiet,
+                charge_limit: 80,
+                charge_schedule_enabled: true,
+                scheduled_charge_limit: 60
+            }),
+            errors: vec!["aura: xyz.ljones.Aura is not supported".to_owned()],
+            ..Default::default()
+        };
+        assert_eq!(
+            status.render(),
+            "Platform\n  Profile:         Balanced (AC: Performance, battery: Quiet)\n  Charge \
+             limit:    80%\n  Charge schedule: enabled, limit 60%\nErrors\n  aura: \
+             xyz.ljones.Aura is not supported\n"
+        );
+        // Sections that aren't available are still present for scripts
+        let json = serde_json::to_value(&status).unwrap();
+        assert!(json["fan_curves"].is_null());
+        assert_eq!(json["platform"]["charge_limit"], 80);
+    }
+}
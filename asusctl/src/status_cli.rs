@@ -0,0 +1,10 @@
+use gumdrop::Options;
+
+#[derive(Options)]
+pub struct StatusCommand {
+    #[options(help = "print help message")]
+    pub help: bool,
+
+    #[options(help = "keep running and redraw whenever a setting changes")]
+    pub watch: bool
+}
@@ -0,0 +1,13 @@
+use gumdrop::Options;
+
+#[derive(Options)]
+pub struct TelemetryCommand {
+    #[options(help = "print help message")]
+    pub help: bool,
+
+    #[options(help = "print all samples kept by asusd, oldest first")]
+    pub history: bool,
+
+    #[options(meta = "", help = "set the sampling interval in milliseconds")]
+    pub interval: Option<u32>
+}
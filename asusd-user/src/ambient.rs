@@ -0,0 +1,778 @@
+//! Ambient lighting, the keyboard and lightbar follow the colours on screen.
+//! Frames come from a `FrameSource` so the capture method can be swapped, and
+//! are reduced to one colour per vertical strip of the screen. Per-key
+//! keyboards take the strip under each key, zoned keyboards get a static
+//! colour per `AuraZone`. LEDs and zones are only driven if the laptop has
+//! the `PowerZones` they are switched with.
+
+use std::io::Read;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::sync::Arc;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use log::warn;
+use rog_aura::keyboard::{AdvancedAuraType, KeyLayout, LedCode, LedUsbPackets};
+use rog_aura::{AuraEffect, AuraModeNum, AuraZone, Colour, DeviceCorrection, PowerZones};
+use rog_dbus::zbus_aura::AuraProxyBlocking;
+
+use crate::config::{AmbientSource, ConfigAmbient};
+use crate::ctrl_aura::PauseState;
+use crate::error::Error;
+use crate::screencast::PortalCapture;
+
+/// Zoned keyboards are slow to write to, a zone is updated only if its colour
+/// moved at least this far (sum of the channel differences)
+const ZONE_CHANGE_THRESHOLD: u32 = 12;
+
+/// How the bytes of a pixel in a raw capture are ordered
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PixelOrder {
+    Rgb,
+    Bgr,
+    /// The fourth byte, alpha or padding, is ignored
+    Rgbx,
+    /// The fourth byte, alpha or padding, is ignored
+    Bgrx
+}
+
+impl PixelOrder {
+    fn bytes(self) -> usize {
+        match self {
+            Self::Rgb | Self::Bgr => 3,
+            Self::Rgbx | Self::Bgrx => 4
+        }
+    }
+
+    fn rgb(self, px: &[u8]) -> [u8; 3] {
+        match self {
+            Self::Rgb | Self::Rgbx => [
+                px[0], px[1], px[2]
+            ],
+            Self::Bgr | Self::Bgrx => [
+                px[2], px[1], px[0]
+            ]
+        }
+    }
+}
+
+/// An 8bit RGB image, rows top to bottom
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u8>
+}
+
+impl Frame {
+    pub fn new(width: usize, height: usize, pixels: Vec<u8>) -> Result<Self, Error> {
+        if width == 0 || height == 0 || pixels.len() != width * height * 3 {
+            return Err(Error::InvalidFrame(format!(
+                "{width}x{height} frame with {} bytes",
+                pixels.len()
+            )));
+        }
+        Ok(Self {
+            width,
+            height,
+            pixels
+        })
+    }
+
+    /// Parse a binary PPM (`P6`) with a max value of 255, as written by most
+    /// screenshot tools, e.g `grim -t ppm -`
+    pub fn from_ppm(data: &[u8]) -> Result<Self, Error> {
+        let mut pos = 0;
+        let mut fields = Vec::with_capacity(4);
+        while fields.len() < 4 {
+            // Skip whitespace and comments between the header fields
+            while pos < data.len() && (data[pos].is_ascii_whitespace() || data[pos] == b'#') {
+                if data[pos] == b'#' {
+                    while pos < data.len() && data[pos] != b'\n' {
+                        pos += 1;
+                    }
+                }
+                pos += 1;
+            }
+            let start = pos;
+            while pos < data.len() && !data[pos].is_ascii_whitespace() {
+                pos += 1;
+            }
+            if start == pos {
+                return Err(Error::InvalidFrame("PPM header is incomplete".to_owned()));
+            }
+            fields.push(String::from_utf8_lossy(&data[start..pos]).into_owned());
+        }
+        // A single whitespace byte separates the header from the pixels
+        pos += 1;
+
+        if fields[0] != "P6" {
+            return Err(Error::InvalidFrame(format!(
+                "{} is not a binary PPM",
+                fields[0]
+            )));
+        }
+        let parse = |s: &str| {
+            s.parse::<usize>()
+                .map_err(|_| Error::InvalidFrame(format!("{s} is not a number")))
+        };
+        let (width, height) = (parse(&fields[1])?, parse(&fields[2])?);
+        if parse(&fields[3])? != 255 {
+            return Err(Error::InvalidFrame(
+                "Only 8bit PPM images are supported".to_owned()
+            ));
+        }
+        let end = pos + width * height * 3;
+        if end > data.len() {
+            return Err(Error::InvalidFrame("PPM image is truncated".to_owned()));
+        }
+        Self::new(width, height, data[pos..end].to_vec())
+    }
+
+    /// Convert a raw capture with rows `stride` bytes apart. Only strip
+    /// averages are needed so large captures are sampled down to about 256
+    /// pixels on the long side.
+    pub fn from_raw(
+        width: usize,
+        height: usize,
+        stride: usize,
+        data: &[u8],
+        order: PixelOrder
+    ) -> Result<Self, Error> {
+        let bpp = order.bytes();
+        if width == 0
+            || height == 0
+            || stride < width * bpp
+            || data.len() < stride * (height - 1) + width * bpp
+        {
+            return Err(Error::InvalidFrame(format!(
+                "{width}x{height} capture with a stride of {stride} and {} bytes",
+                data.len()
+            )));
+        }
+        let step = (width.max(height) / 256).max(1);
+        let mut pixels = Vec::with_capacity(width.div_ceil(step) * height.div_ceil(step) * 3);
+        for y in (0..height).step_by(step) {
+            let row = &data[y * stride..];
+            for x in (0..width).step_by(step) {
+                pixels.extend_from_slice(&order.rgb(&row[x * bpp..]));
+            }
+        }
+        Self::new(width.div_ceil(step), height.div_ceil(step), pixels)
+    }
+
+    /// Average colour of each of `count` equal width vertical strips, left to
+    /// right. Large frames are sampled sparsely.
+    pub fn column_colours(&self, count: usize) -> Vec<Colour> {
+        let count = count.clamp(1, self.width);
+        let step = (self.width.max(self.height) / 256).max(1);
+        (0..count)
+            .map(|col| {
+                let x0 = col * self.width / count;
+                let x1 = ((col + 1) * self.width / count).max(x0 + 1);
+                let mut sum = [0u64; 3];
+                let mut n = 0;
+                for y in (0..self.height).step_by(step) {
+                    for x in (x0..x1).step_by(step) {
+                        let i = (y * self.width + x) * 3;
+                        sum[0] += self.pixels[i] as u64;
+                        sum[1] += self.pixels[i + 1] as u64;
+                        sum[2] += self.pixels[i + 2] as u64;
+                        n += 1;
+                    }
+                }
+                Colour {
+                    r: (sum[0] / n) as u8,
+                    g: (sum[1] / n) as u8,
+                    b: (sum[2] / n) as u8
+                }
+            })
+            .collect()
+    }
+}
+
+/// Where frames come from. Implementations may block until a frame is ready.
+pub trait FrameSource {
+    fn next_frame(&mut self) -> Result<Frame, Error>;
+}
+
+/// Hue bars scrolling to the right, for testing without a capture backend
+#[derive(Debug, Default)]
+pub struct TestPattern {
+    offset: usize
+}
+
+impl TestPattern {
+    const HEIGHT: usize = 36;
+    const WIDTH: usize = 64;
+}
+
+impl FrameSource for TestPattern {
+    fn next_frame(&mut self) -> Result<Frame, Error> {
+        self.offset = (self.offset + 1) % Self::WIDTH;
+        let mut pixels = Vec::with_capacity(Self::WIDTH * Self::HEIGHT * 3);
+        for _ in 0..Self::HEIGHT {
+            for x in 0..Self::WIDTH {
+                let hue =
+                    ((x + Self::WIDTH - self.offset) % Self::WIDTH) as f32 / Self::WIDTH as f32;
+                let c = hue_colour(hue);
+                pixels.extend_from_slice(&[
+                    c.r, c.g, c.b
+                ]);
+            }
+        }
+        Frame::new(Self::WIDTH, Self::HEIGHT, pixels)
+    }
+}
+
+/// Fully saturated colour for a hue of `0.0..1.0`
+fn hue_colour(hue: f32) -> Colour {
+    let h = hue.rem_euclid(1.0) * 6.0;
+    let x = ((1.0 - (h % 2.0 - 1.0).abs()) * 255.0).round() as u8;
+    let (r, g, b) = match h as u32 {
+        0 => (255, x, 0),
+        1 => (x, 255, 0),
+        2 => (0, 255, x),
+        3 => (0, x, 255),
+        4 => (x, 0, 255),
+        _ => (255, 0, x)
+    };
+    Colour { r, g, b }
+}
+
+/// Reads a PPM file for every frame, so a tool that repeatedly overwrites the
+/// file can feed the lighting
+#[derive(Debug)]
+pub struct PpmFile(pub PathBuf);
+
+impl FrameSource for PpmFile {
+    fn next_frame(&mut self) -> Result<Frame, Error> {
+        Frame::from_ppm(&std::fs::read(&self.0)?)
+    }
+}
+
+/// Runs a capture command for every frame and reads a PPM from its stdout.
+/// This covers the desktop specific capture methods, e.g `grim -t ppm -` on
+/// wlroots or `import -window root ppm:-` on X11. A portal or PipeWire capture
+/// is used through `PortalCapture`.
+#[derive(Debug)]
+pub struct CaptureCommand(pub Vec<String>);
+
+impl FrameSource for CaptureCommand {
+    fn next_frame(&mut self) -> Result<Frame, Error> {
+        let (program, args) = self
+            .0
+            .split_first()
+            .ok_or_else(|| Error::InvalidFrame("Capture command is empty".to_owned()))?;
+        let mut child = Command::new(program)
+            .args(args)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let mut data = Vec::new();
+        if let Some(mut stdout) = child.stdout.take() {
+            stdout.read_to_end(&mut data)?;
+        }
+        child.wait()?;
+        Frame::from_ppm(&data)
+    }
+}
+
+pub fn frame_source(source: &AmbientSource) -> Box<dyn FrameSource + Send> {
+    match source {
+        AmbientSource::TestPattern => Box::<TestPattern>::default(),
+        AmbientSource::File(path) => Box::new(PpmFile(path.clone())),
+        AmbientSource::Command(cmd) => Box::new(CaptureCommand(cmd.clone())),
+        AmbientSource::Portal => Box::<PortalCapture>::default()
+    }
+}
+
+/// Exponential smoothing over time, `factor` is how much of the previous
+/// colour is kept each frame
+#[derive(Debug, Default)]
+pub struct Smoothing {
+    factor: f32,
+    state: Vec<[f32; 3]>
+}
+
+impl Smoothing {
+    pub fn new(factor: f32) -> Self {
+        Self {
+            factor: factor.clamp(0.0, 0.99),
+            state: Vec::new()
+        }
+    }
+
+    pub fn apply(&mut self, colours: &[Colour]) -> Vec<Colour> {
+        if self.state.len() != colours.len() {
+            self.state = colours
+                .iter()
+                .map(|c| {
+                    [
+                        c.r as f32, c.g as f32, c.b as f32
+                    ]
+                })
+                .collect();
+        }
+        self.state
+            .iter_mut()
+            .zip(colours)
+            .map(|(s, c)| {
+                for (s, c) in s.iter_mut().zip([
+                    c.r, c.g, c.b
+                ]) {
+                    *s = *s * self.factor + c as f32 * (1.0 - self.factor);
+                }
+                Colour {
+                    r: s[0].round() as u8,
+                    g: s[1].round() as u8,
+                    b: s[2].round() as u8
+                }
+            })
+            .collect()
+    }
+}
+
+/// The screen strips as a fraction of the width, `0.0..=1.0`
+fn zone_span(zone: AuraZone) -> (f32, f32) {
+    match zone {
+        AuraZone::Key1 => (0.0, 0.25),
+        AuraZone::Key2 => (0.25, 0.5),
+        AuraZone::Key3 => (0.5, 0.75),
+        AuraZone::Key4 => (0.75, 1.0),
+        AuraZone::BarLeft => (0.0, 0.5),
+        AuraZone::BarRight => (0.5, 1.0),
+        AuraZone::None | AuraZone::Logo => (0.0, 1.0)
+    }
+}
+
+/// The power zone that switches the LED on and off
+fn led_power_zone(led: LedCode) -> PowerZones {
+    match led {
+        LedCode::LidLogo => PowerZones::Logo,
+        LedCode::LidLeft | LedCode::LidRight => PowerZones::Lid,
+        led if led.is_lightbar_zone() => PowerZones::Lightbar,
+        _ => PowerZones::Keyboard
+    }
+}
+
+/// The power zone that switches the aura zone on and off
+fn zone_power_zone(zone: AuraZone) -> PowerZones {
+    match zone {
+        AuraZone::BarLeft | AuraZone::BarRight => PowerZones::Lightbar,
+        AuraZone::Logo => PowerZones::Logo,
+        AuraZone::None | AuraZone::Key1 | AuraZone::Key2 | AuraZone::Key3 | AuraZone::Key4 => {
+            PowerZones::Keyboard
+        }
+    }
+}
+
+/// True if the laptop has the power zone. The 0x1866 models switch the
+/// keyboard and lightbar together, and an empty list is a laptop without
+/// power zone data so everything is assumed present.
+fn has_power_zone(power_zones: &[PowerZones], zone: PowerZones) -> bool {
+    power_zones.is_empty()
+        || power_zones.contains(&zone)
+        || (matches!(zone, PowerZones::Keyboard | PowerZones::Lightbar)
+            && power_zones.contains(&PowerZones::KeyboardAndLightbar))
+}
+
+/// The zones that are in `power_zones`
+fn powered_zones(zones: &[AuraZone], power_zones: &[PowerZones]) -> Vec<AuraZone> {
+    zones
+        .iter()
+        .copied()
+        .filter(|zone| has_power_zone(power_zones, zone_power_zone(*zone)))
+        .collect()
+}
+
+/// Which column each output reads from
+#[derive(Debug, Clone, PartialEq)]
+pub enum AmbientTarget {
+    /// Each LED takes the column under its centre
+    PerKey(Vec<(LedCode, usize)>),
+    /// Each zone takes the average of the columns it spans
+    Zoned(Vec<(AuraZone, std::ops::Range<usize>)>)
+}
+
+impl AmbientTarget {
+    /// Per-key if the layout supports it, otherwise the layout's basic zones.
+    /// Only the parts of the keyboard in `power_zones` are used.
+    pub fn new(layout: &KeyLayout, power_zones: &[PowerZones], columns: usize) -> Self {
+        if matches!(layout.advanced_type(), AdvancedAuraType::PerKey) {
+            Self::per_key(layout, power_zones, columns)
+        } else {
+            Self::zoned(&powered_zones(layout.basic_zones(), power_zones), columns)
+        }
+    }
+
+    pub fn per_key(layout: &KeyLayout, power_zones: &[PowerZones], columns: usize) -> Self {
+        let columns = columns.max(1);
+        let width = layout.max_width().max(1.0);
+        Self::PerKey(
+            layout
+                .key_positions()
+                .into_iter()
+                .filter(|(led, _)| {
+                    !led.is_placeholder() && has_power_zone(power_zones, led_power_zone(*led))
+                })
+                .map(|(led, pos)| {
+                    let col = (pos.x / width * columns as f32) as usize;
+                    (led, col.min(columns - 1))
+                })
+                .collect()
+        )
+    }
+
+    pub fn zoned(zones: &[AuraZone], columns: usize) -> Self {
+        let columns = columns.max(1);
+        let mut zones = zones.to_vec();
+        if zones.is_empty() {
+            zones.push(AuraZone::None);
+        }
+        Self::Zoned(
+            zones
+                .into_iter()
+                .map(|zone| {
+                    let (start, end) = zone_span(zone);
+                    let start = (start * columns as f32) as usize;
+                    let end = ((end * columns as f32) as usize).max(start + 1);
+                    (zone, start..end.min(columns))
+                })
+                .collect()
+        )
+    }
+
+    /// One colour per LED or zone, in the same order as the target
+    pub fn colours(&self, columns: &[Colour]) -> Vec<Colour> {
+        match self {
+            AmbientTarget::PerKey(leds) => leds.iter().map(|(_, col)| columns[*col]).collect(),
+            AmbientTarget::Zoned(zones) => zones
+                .iter()
+                .map(|(_, range)| {
+                    let n = range.len().max(1) as u32;
+                    let sum = columns[range.clone()].iter().fold([0u32; 3], |s, c| {
+                        [
+                            s[0] + c.r as u32,
+                            s[1] + c.g as u32,
+                            s[2] + c.b as u32
+                        ]
+                    });
+                    Colour {
+                        r: (sum[0] / n) as u8,
+                        g: (sum[1] / n) as u8,
+                        b: (sum[2] / n) as u8
+                    }
+                })
+                .collect()
+        }
+    }
+}
+
+fn colour_distance(a: &Colour, b: &Colour) -> u32 {
+    a.r.abs_diff(b.r) as u32 + a.g.abs_diff(b.g) as u32 + a.b.abs_diff(b.b) as u32
+}
+
+pub struct AmbientInner<'a> {
+    source: Box<dyn FrameSource + Send>,
+    target: AmbientTarget,
+    columns: usize,
+    frame_rate: u32,
+    smoothing: Smoothing,
+    /// The zone colours last sent, zoned keyboards only
+    sent: Vec<Colour>,
+    client: AuraProxyBlocking<'a>,
+    pause: Arc<PauseState>,
+    /// Per-key only, zone colours are corrected by asusd
+    correction: DeviceCorrection
+}
+
+impl AmbientInner<'static> {
+    pub fn new(
+        config: &ConfigAmbient,
+        layout: &KeyLayout,
+        power_zones: &[PowerZones],
+        client: AuraProxyBlocking<'static>,
+        pause: Arc<PauseState>,
+        correction: DeviceCorrection
+    ) -> Self {
+        Self {
+            source: frame_source(&config.source),
+            target: AmbientTarget::new(layout, power_zones, config.columns),
+            columns: config.columns,
+            frame_rate: config.frame_rate,
+            smoothing: Smoothing::new(config.smoothing),
+            sent: Vec::new(),
+            client,
+            pause,
+            correction
+        }
+    }
+
+    /// Capture, map and send one frame, then sleep for the rest of the frame
+    /// time
+    pub fn run(&mut self) -> Result<(), Error> {
+        let start = Instant::now();
+        if self.pause.is_paused() {
+            sleep(Duration::from_millis(100));
+            return Ok(());
+        }
+
+        let frame = self.source.next_frame()?;
+        let columns = frame.column_colours(self.columns);
+        let colours = self.smoothing.apply(&self.target.colours(&columns));
+
+        match &self.target {
+            AmbientTarget::PerKey(leds) => {
+                let mut packets = LedUsbPackets::new_per_key();
+                for ((led, _), c) in leds.iter().zip(&colours) {
+                    let c = self.correction.for_led(*led).apply(*c);
+                    packets.set(*led, c.r, c.g, c.b);
+                }
+                if let Err(e) = self.client.direct_addressing_raw(packets.into()) {
+                    warn!("Ambient: could not send frame: {e}");
+                }
+            }
+            AmbientTarget::Zoned(zones) => {
+                self.sent.resize(zones.len(), Colour { r: 0, g: 0, b: 0 });
+                for (i, ((zone, _), c)) in zones.iter().zip(&colours).enumerate() {
+                    if colour_distance(&self.sent[i], c) < ZONE_CHANGE_THRESHOLD {
+                        continue;
+                    }
+                    let effect = AuraEffect {
+                        mode: AuraModeNum::Static,
+                        zone: *zone,
+                        colour1: *c,
+                        ..Default::default()
+                    };
+                    // Not stored by asusd, so the config isn't written each frame
+                    match self.client.apply_effect(effect) {
+                        Ok(_) => self.sent[i] = *c,
+                        Err(e) => warn!("Ambient: could not set {zone:?}: {e}")
+                    }
+                }
+            }
+        }
+
+        let frame_time = Duration::from_micros(1_000_000 / self.frame_rate.clamp(1, 60) as u64);
+        sleep(frame_time.saturating_sub(start.elapsed()));
+        Ok(())
+    }
+
+    /// Run on a new thread until the process exits, or the frame source can
+    /// never work. The stored effect is shown again once stopped.
+    pub fn spawn(mut self) {
+        std::thread::spawn(move || loop {
+            match self.run() {
+                Err(e @ Error::ScreenCastUnavailable(_)) => {
+                    warn!("Ambient: {e}, ambient lighting is stopped");
+                    break;
+                }
+                Err(e) => {
+                    warn!("Ambient: {e}");
+                    sleep(Duration::from_secs(1));
+                }
+                Ok(_) => {}
+            }
+        });
+    }
+}
+
+impl Drop for AmbientInner<'_> {
+    /// Show the user's stored effect again in place of the last frame
+    fn drop(&mut self) {
+        self.client
+            .led_mode()
+            .and_then(|mode| self.client.set_led_mode(mode))
+            .map_err(|e| warn!("Ambient: could not restore the aura mode: {e}"))
+            .ok();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use rog_aura::keyboard::{KeyLayout, KeyRow, KeyShape, LedCode};
+    use rog_aura::{AuraZone, Colour, PowerZones};
+
+    use super::{
+        powered_zones, AmbientTarget, Frame, FrameSource, PixelOrder, Smoothing, TestPattern
+    };
+
+    const RED: Colour = Colour { r: 255, g: 0, b: 0 };
+    const BLUE: Colour = Colour { r: 0, g: 0, b: 255 };
+
+    /// Left half red, right half blue
+    fn split_frame() -> Frame {
+        let mut data = b"P6\n# test\n4 2\n255\n".to_vec();
+        for _ in 0..2 {
+            data.extend_from_slice(&[
+                255, 0, 0, 255, 0, 0, 0, 0, 255, 0, 0, 255
+            ]);
+        }
+        Frame::from_ppm(&data).unwrap()
+    }
+
+    #[test]
+    fn parse_ppm_columns() {
+        let frame = split_frame();
+        assert_eq!((frame.width, frame.height), (4, 2));
+        assert_eq!(frame.column_colours(2), vec![RED, BLUE]);
+        assert_eq!(frame.column_colours(1), vec![Colour {
+            r: 127,
+            g: 0,
+            b: 127
+        }]);
+
+        assert!(Frame::from_ppm(b"P3\n1 1\n255\n0 0 0").is_err());
+        assert!(Frame::from_ppm(b"P6\n2 2\n255\n\0\0\0").is_err());
+
+        let frame = TestPattern::default().next_frame().unwrap();
+        assert_eq!(frame.pixels.len(), frame.width * frame.height * 3);
+    }
+
+    #[test]
+    fn convert_raw_captures() {
+        // BGRx rows padded to 12 bytes, the last row unpadded
+        let data = [
+            0, 0, 255, 9, 255, 0, 0, 9, 1, 2, 3, 4, //
+            0, 0, 255, 9, 255, 0, 0, 9
+        ];
+        let frame = Frame::from_raw(2, 2, 12, &data, PixelOrder::Bgrx).unwrap();
+        assert_eq!(frame.column_colours(2), vec![RED, BLUE]);
+        assert!(Frame::from_raw(2, 2, 12, &data[..19], PixelOrder::Bgrx).is_err());
+        assert!(Frame::from_raw(4, 2, 12, &data, PixelOrder::Bgrx).is_err());
+
+        let frame = Frame::from_raw(1, 1, 3, &[1, 2, 3], PixelOrder::Rgb).unwrap();
+        assert_eq!(frame.pixels, vec![1, 2, 3]);
+
+        // Sampled down to 256 wide
+        let data = vec![0; 1024 * 4 * 8];
+        let frame = Frame::from_raw(1024, 8, 1024 * 4, &data, PixelOrder::Rgbx).unwrap();
+        assert_eq!((frame.width, frame.height), (256, 2));
+    }
+
+    #[test]
+    fn map_keys_and_zones() {
+        let layout = KeyLayout::default_layout();
+        let columns = split_frame().column_colours(2);
+
+        let target = AmbientTarget::per_key(&layout, &[], 2);
+        let AmbientTarget::PerKey(leds) = &target else {
+            panic!("The default layout is per-key");
+        };
+        let colours = target.colours(&columns);
+        let colour_of = |led| colours[leds.iter().position(|(l, _)| *l == led).unwrap()];
+        assert_eq!(colour_of(LedCode::Esc), RED);
+        assert_eq!(colour_of(LedCode::Return), BLUE);
+
+        let target = AmbientTarget::zoned(
+            &[
+                AuraZone::Key1,
+                AuraZone::Key4,
+                AuraZone::None
+            ],
+            2
+        );
+        assert_eq!(
+            target,
+            AmbientTarget::Zoned(vec![
+                (AuraZone::Key1, 0..1),
+                (AuraZone::Key4, 1..2),
+                (AuraZone::None, 0..2)
+            ])
+        );
+        assert_eq!(target.colours(&columns), vec![
+            RED,
+            BLUE,
+            Colour {
+                r: 127,
+                g: 0,
+                b: 127
+            }
+        ]);
+    }
+
+    #[test]
+    fn map_power_zones() {
+        let layout = KeyLayout::new(
+            "US",
+            HashMap::from([(
+                "key".to_owned(),
+                KeyShape::new_led(1.0, 1.0, 0.0, 0.0, 0.0, 0.0)
+            )]),
+            vec![
+                KeyRow::new(0.0, 0.0, vec![
+                    (LedCode::Esc, "key".to_owned()),
+                    (LedCode::LightbarLeft, "key".to_owned()),
+                    (LedCode::LidLogo, "key".to_owned()),
+                ]),
+            ]
+        );
+        let leds = |power_zones: &[PowerZones]| {
+            let AmbientTarget::PerKey(leds) = AmbientTarget::per_key(&layout, power_zones, 3)
+            else {
+                panic!("Expected per-key");
+            };
+            leds.into_iter().map(|(led, _)| led).collect::<Vec<_>>()
+        };
+        assert_eq!(leds(&[]), vec![
+            LedCode::Esc,
+            LedCode::LightbarLeft,
+            LedCode::LidLogo
+        ]);
+        assert_eq!(leds(&[PowerZones::Keyboard]), vec![LedCode::Esc]);
+        assert_eq!(leds(&[PowerZones::KeyboardAndLightbar]), vec![
+            LedCode::Esc,
+            LedCode::LightbarLeft
+        ]);
+        assert_eq!(
+            leds(&[
+                PowerZones::Logo,
+                PowerZones::Lightbar
+            ]),
+            vec![
+                LedCode::LightbarLeft,
+                LedCode::LidLogo
+            ]
+        );
+
+        let zones = [
+            AuraZone::Key1,
+            AuraZone::Logo,
+            AuraZone::BarLeft
+        ];
+        assert_eq!(powered_zones(&zones, &[PowerZones::Keyboard]), vec![
+            AuraZone::Key1
+        ]);
+        assert_eq!(
+            powered_zones(&zones, &[
+                PowerZones::Keyboard,
+                PowerZones::Lightbar
+            ]),
+            vec![
+                AuraZone::Key1,
+                AuraZone::BarLeft
+            ]
+        );
+        assert_eq!(powered_zones(&zones, &[]), zones.to_vec());
+    }
+
+    #[test]
+    fn smoothing_follows_slowly() {
+        let mut smooth = Smoothing::new(0.5);
+        assert_eq!(smooth.apply(&[RED]), vec![RED]);
+        assert_eq!(smooth.apply(&[BLUE]), vec![Colour {
+            r: 128,
+            g: 0,
+            b: 128
+        }]);
+        assert_eq!(smooth.apply(&[BLUE]), vec![Colour {
+            r: 64,
+            g: 0,
+            b: 191
+        }]);
+    }
+}
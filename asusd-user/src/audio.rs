@@ -0,0 +1,558 @@
+//! Audio levels for the audio reactive aura effects and the AniMe spectrum.
+//! Samples are read from a `SampleSource`, split in to log spaced frequency
+//! bands with an FFT, and published through `AudioLevels`.
+
+use std::f32::consts::PI;
+use std::io::Read;
+use std::path::Path;
+use std::process::{Child, ChildStdout, Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread::{sleep, JoinHandle};
+use std::time::{Duration, Instant};
+
+use log::{info, warn};
+
+use crate::error::Error;
+
+/// How many bands the levels are split in to, lowest frequency first
+pub const ANALYSER_BANDS: usize = 16;
+/// Samples per analysis, about 23ms at 44.1kHz
+pub const FFT_SIZE: usize = 1024;
+const MIN_FREQ: f32 = 40.0;
+const MAX_FREQ: f32 = 16000.0;
+/// Levels at or below this are shown as silence
+const FLOOR_DB: f32 = -60.0;
+/// How much of the previous level is kept when a band gets quieter, so bars
+/// fall smoothly instead of flickering
+const DECAY: f32 = 0.85;
+const MONITOR_RATE: u32 = 44100;
+/// How long to wait before starting `parec` again after it failed or exited
+const MONITOR_RETRY: Duration = Duration::from_secs(5);
+
+/// Mono samples in the range `-1.0..=1.0`
+pub trait SampleSource {
+    fn sample_rate(&self) -> u32;
+
+    /// Fill `buf`, returning how many samples were read. Only returns `0` if
+    /// no more samples will ever be available.
+    fn read(&mut self, buf: &mut [f32]) -> Result<usize, Error>;
+
+    /// True if `read()` blocks until the samples are played, as with a live
+    /// capture. Otherwise the reader has to keep time itself.
+    fn is_live(&self) -> bool {
+        true
+    }
+}
+
+/// A 16bit PCM wav file, mixed down to mono and looped
+#[derive(Debug)]
+pub struct WavFile {
+    rate: u32,
+    samples: Vec<f32>,
+    pos: usize
+}
+
+impl WavFile {
+    pub fn open(path: &Path) -> Result<Self, Error> {
+        Self::from_bytes(&std::fs::read(path)?)
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Result<Self, Error> {
+        let invalid = |s: &str| Error::InvalidAudio(s.to_owned());
+        if data.len() < 12 || &data[0..4] != b"RIFF" || &data[8..12] != b"WAVE" {
+            return Err(invalid("Not a wav file"));
+        }
+        let u16_at = |i: usize| {
+            u16::from_le_bytes([
+                data[i],
+                data[i + 1]
+            ])
+        };
+        let u32_at = |i: usize| {
+            u32::from_le_bytes([
+                data[i],
+                data[i + 1],
+                data[i + 2],
+                data[i + 3]
+            ])
+        };
+
+        let mut format = None;
+        let mut pos = 12;
+        while pos + 8 <= data.len() {
+            let id = &data[pos..pos + 4];
+            let len = u32_at(pos + 4) as usize;
+            let body = pos + 8;
+            let end = (body + len).min(data.len());
+            if id == b"fmt " {
+                if len < 16 || body + 16 > data.len() {
+                    return Err(invalid("Wav format is truncated"));
+                }
+                // format tag, channels, rate, .., bits per sample
+                format = Some((
+                    u16_at(body),
+                    u16_at(body + 2),
+                    u32_at(body + 4),
+                    u16_at(body + 14)
+                ));
+            } else if id == b"data" {
+                let (tag, channels, rate, bits) =
+                    format.ok_or_else(|| invalid("Wav data before the format"))?;
+                if tag != 1 || bits != 16 || channels == 0 {
+                    return Err(invalid("Only 16bit PCM wav files are supported"));
+                }
+                if rate == 0 {
+                    return Err(invalid("Wav sample rate is zero"));
+                }
+                let samples = data[body..end]
+                    .chunks_exact(2 * channels as usize)
+                    .map(|frame| {
+                        let sum: f32 = frame
+                            .chunks_exact(2)
+                            .map(|s| {
+                                i16::from_le_bytes([
+                                    s[0], s[1]
+                                ]) as f32
+                                    / 32768.0
+                            })
+                            .sum();
+                        sum / channels as f32
+                    })
+                    .collect();
+                return Ok(Self {
+                    rate,
+                    samples,
+                    pos: 0
+                });
+            }
+            // Chunks are padded to an even length
+            pos = body + len + (len & 1);
+        }
+        Err(invalid("Wav file has no data"))
+    }
+}
+
+impl SampleSource for WavFile {
+    fn sample_rate(&self) -> u32 {
+        self.rate
+    }
+
+    fn read(&mut self, buf: &mut [f32]) -> Result<usize, Error> {
+        if self.samples.is_empty() {
+            return Ok(0);
+        }
+        for s in buf.iter_mut() {
+            *s = self.samples[self.pos];
+            self.pos = (self.pos + 1) % self.samples.len();
+        }
+        Ok(buf.len())
+    }
+
+    fn is_live(&self) -> bool {
+        false
+    }
+}
+
+/// Records what is playing from the monitor of the default output. `parec`
+/// works with both PulseAudio and PipeWire (through `pipewire-pulse`).
+#[derive(Debug)]
+pub struct MonitorCapture {
+    child: Child,
+    stdout: ChildStdout
+}
+
+impl MonitorCapture {
+    pub fn new() -> Result<Self, Error> {
+        let mut child = Command::new("parec")
+            .args([
+                "--device=@DEFAULT_MONITOR@",
+                "--format=s16le",
+                "--channels=1",
+                &format!("--rate={MONITOR_RATE}"),
+                "--raw",
+                "--latency-msec=20"
+            ])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let stdout = child
+            .stdout
+            .take()
+            .ok_or_else(|| Error::InvalidAudio("parec has no output".to_owned()))?;
+        Ok(Self { child, stdout })
+    }
+}
+
+impl SampleSource for MonitorCapture {
+    fn sample_rate(&self) -> u32 {
+        MONITOR_RATE
+    }
+
+    fn read(&mut self, buf: &mut [f32]) -> Result<usize, Error> {
+        let mut bytes = vec![0u8; buf.len() * 2];
+        self.stdout.read_exact(&mut bytes)?;
+        for (s, b) in buf.iter_mut().zip(bytes.chunks_exact(2)) {
+            *s = i16::from_le_bytes([
+                b[0], b[1]
+            ]) as f32
+                / 32768.0;
+        }
+        Ok(buf.len())
+    }
+}
+
+impl Drop for MonitorCapture {
+    fn drop(&mut self) {
+        self.child.kill().ok();
+        self.child.wait().ok();
+    }
+}
+
+/// In place radix-2 FFT, the length must be a power of two
+fn fft(re: &mut [f32], im: &mut [f32]) {
+    let n = re.len();
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+
+    let mut len = 2;
+    while len <= n {
+        let angle = -2.0 * PI / len as f32;
+        for start in (0..n).step_by(len) {
+            for k in 0..len / 2 {
+                let (sin, cos) = (angle * k as f32).sin_cos();
+                let (a, b) = (start + k, start + k + len / 2);
+                let tr = re[b] * cos - im[b] * sin;
+                let ti = re[b] * sin + im[b] * cos;
+                re[b] = re[a] - tr;
+                im[b] = im[a] - ti;
+                re[a] += tr;
+                im[a] += ti;
+            }
+        }
+        len <<= 1;
+    }
+}
+
+/// Turns blocks of `FFT_SIZE` samples in to band levels
+#[derive(Debug)]
+pub struct Analyser {
+    window: Vec<f32>,
+    /// The FFT bins in each band
+    bands: Vec<std::ops::Range<usize>>,
+    levels: Vec<f32>
+}
+
+impl Analyser {
+    pub fn new(sample_rate: u32, band_count: usize) -> Self {
+        let window = (0..FFT_SIZE)
+            .map(|i| 0.5 - 0.5 * (2.0 * PI * i as f32 / (FFT_SIZE - 1) as f32).cos())
+            .collect();
+
+        let bin_hz = sample_rate as f32 / FFT_SIZE as f32;
+        let max_freq = MAX_FREQ.min(sample_rate as f32 / 2.0);
+        let ratio = (max_freq / MIN_FREQ).powf(1.0 / band_count as f32);
+        let mut bands = Vec::with_capacity(band_count);
+        let mut start = (MIN_FREQ / bin_hz).round().max(1.0) as usize;
+        for band in 1..=band_count {
+            let freq = MIN_FREQ * ratio.powi(band as i32);
+            let end = ((freq / bin_hz).round() as usize)
+                .max(start + 1)
+                .min(FFT_SIZE / 2);
+            bands.push(start..end);
+            start = end.min(FFT_SIZE / 2 - 1);
+        }
+
+        Self {
+            window,
+            bands,
+            levels: vec![0.0; band_count]
+        }
+    }
+
+    /// Analyse a block of `FFT_SIZE` samples. Levels are `0.0..=1.0`, a full
+    /// scale sine is `1.0`.
+    pub fn analyse(&mut self, samples: &[f32]) -> &[f32] {
+        let mut re: Vec<f32> = samples
+            .iter()
+            .chain(std::iter::repeat(&0.0))
+            .zip(&self.window)
+            .map(|(s, w)| s * w)
+            .collect();
+        let mut im = vec![0.0; FFT_SIZE];
+        fft(&mut re, &mut im);
+
+        for (level, bins) in self.levels.iter_mut().zip(&self.bands) {
+            // Scaled so a full scale sine peaks at 1.0, the Hann window halves
+            // the amplitude
+            let peak = bins
+                .clone()
+                .map(|i| (re[i] * re[i] + im[i] * im[i]).sqrt() * 4.0 / FFT_SIZE as f32)
+                .fold(0.0f32, f32::max);
+            let db = 20.0 * peak.max(1e-6).log10();
+            let new = ((db - FLOOR_DB) / -FLOOR_DB).clamp(0.0, 1.0);
+            *level = new.max(*level * DECAY);
+        }
+        &self.levels
+    }
+}
+
+/// The capture thread started by `AudioLevels::start_monitor()`
+#[derive(Debug, Default)]
+struct Monitor {
+    thread: Option<JoinHandle<()>>,
+    failed: Option<Instant>
+}
+
+/// The latest band levels, shared between the capture thread and the effects
+/// that use them. Clones share the same levels.
+#[derive(Debug, Clone)]
+pub struct AudioLevels {
+    levels: Arc<Mutex<Vec<f32>>>,
+    monitor: Arc<Mutex<Monitor>>
+}
+
+impl Default for AudioLevels {
+    fn default() -> Self {
+        Self {
+            levels: Arc::new(Mutex::new(vec![0.0; ANALYSER_BANDS])),
+            monitor: Arc::new(Mutex::new(Monitor::default()))
+        }
+    }
+}
+
+impl AudioLevels {
+    pub fn get(&self) -> Vec<f32> {
+        self.levels.lock().map(|l| l.clone()).unwrap_or_default()
+    }
+
+    /// Read and analyse `source` on a new thread until it ends, the levels
+    /// then fall to zero
+    pub fn start(&self, mut source: Box<dyn SampleSource + Send>) -> JoinHandle<()> {
+        let levels = self.levels.clone();
+        std::thread::spawn(move || {
+            let mut analyser = Analyser::new(source.sample_rate(), ANALYSER_BANDS);
+            let block_time =
+                Duration::from_secs_f32(FFT_SIZE as f32 / source.sample_rate().max(1) as f32);
+            let mut buf = vec![0.0; FFT_SIZE];
+            loop {
+                match source.read(&mut buf) {
+                    Ok(0) => break,
+                    Ok(_) => {}
+                    Err(e) => {
+                        warn!("Audio capture stopped: {e}");
+                        break;
+                    }
+                }
+                let new = analyser.analyse(&buf);
+                if let Ok(mut levels) = levels.lock() {
+                    levels.clear();
+                    levels.extend_from_slice(new);
+                }
+                if !source.is_live() {
+                    sleep(block_time);
+                }
+            }
+            if let Ok(mut levels) = levels.lock() {
+                levels.fill(0.0);
+            }
+        })
+    }
+
+    /// Start capturing the default output if it isn't running. This is called
+    /// each frame, so `parec` is started again if it exits.
+    pub fn start_monitor(&self) {
+        self.start_monitor_with(MONITOR_RETRY, || {
+            Ok(Box::new(MonitorCapture::new()?) as Box<dyn SampleSource + Send>)
+        });
+    }
+
+    fn start_monitor_with(
+        &self,
+        retry: Duration,
+        open: impl FnOnce() -> Result<Box<dyn SampleSource + Send>, Error>
+    ) {
+        let Ok(mut monitor) = self.monitor.lock() else {
+            return;
+        };
+        if let Some(thread) = &monitor.thread {
+            if !thread.is_finished() {
+                return;
+            }
+            // The capture thread has already warned why it stopped
+            monitor.thread = None;
+            monitor.failed = Some(Instant::now());
+        }
+        if monitor.failed.is_some_and(|t| t.elapsed() < retry) {
+            return;
+        }
+        match open() {
+            Ok(source) => {
+                info!("Capturing audio for the audio effects");
+                monitor.thread = Some(self.start(source));
+                monitor.failed = None;
+            }
+            Err(e) => {
+                if monitor.failed.is_none() {
+                    warn!("Audio effects will not respond to audio: {e}");
+                }
+                monitor.failed = Some(Instant::now());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+    use std::sync::mpsc::{channel, Receiver};
+    use std::thread::sleep;
+    use std::time::{Duration, Instant};
+
+    use super::{Analyser, AudioLevels, SampleSource, WavFile, ANALYSER_BANDS, FFT_SIZE};
+    use crate::error::Error;
+
+    /// A mono 16bit wav of a sine at `freq`
+    fn sine_wav(freq: f32, rate: u32, amplitude: f32) -> Vec<u8> {
+        let samples: Vec<i16> = (0..rate / 10)
+            .map(|i| {
+                ((2.0 * PI * freq * i as f32 / rate as f32).sin() * amplitude * 32767.0) as i16
+            })
+            .collect();
+        let data_len = samples.len() as u32 * 2;
+        let mut wav = b"RIFF".to_vec();
+        wav.extend_from_slice(&(36 + data_len).to_le_bytes());
+        wav.extend_from_slice(b"WAVEfmt ");
+        wav.extend_from_slice(&16u32.to_le_bytes());
+        wav.extend_from_slice(&1u16.to_le_bytes());
+        wav.extend_from_slice(&1u16.to_le_bytes());
+        wav.extend_from_slice(&rate.to_le_bytes());
+        wav.extend_from_slice(&(rate * 2).to_le_bytes());
+        wav.extend_from_slice(&2u16.to_le_bytes());
+        wav.extend_from_slice(&16u16.to_le_bytes());
+        wav.extend_from_slice(b"data");
+        wav.extend_from_slice(&data_len.to_le_bytes());
+        for s in samples {
+            wav.extend_from_slice(&s.to_le_bytes());
+        }
+        wav
+    }
+
+    #[test]
+    fn sine_lands_in_one_band() {
+        let mut wav = WavFile::from_bytes(&sine_wav(1000.0, 44100, 1.0)).unwrap();
+        assert_eq!(wav.sample_rate(), 44100);
+        let mut buf = vec![0.0; FFT_SIZE];
+        assert_eq!(wav.read(&mut buf).unwrap(), FFT_SIZE);
+
+        let mut analyser = Analyser::new(44100, ANALYSER_BANDS);
+        let levels = analyser.analyse(&buf).to_vec();
+        let loudest = levels
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.total_cmp(b.1))
+            .unwrap()
+            .0;
+        // 40Hz to 16kHz in 16 log spaced bands, 1kHz is in the 9th
+        assert_eq!(loudest, 8);
+        assert!(levels[8] > 0.95);
+        assert!(levels[0] < 0.3);
+        assert!(levels[15] < 0.3);
+
+        // Silence falls away smoothly
+        let levels = analyser.analyse(&[0.0; FFT_SIZE]);
+        assert!(levels[8] > 0.8 && levels[8] < 0.9);
+    }
+
+    #[test]
+    fn reject_bad_wav() {
+        assert!(WavFile::from_bytes(b"RIFF\0\0\0\0WAVE").is_err());
+        let mut wav = sine_wav(440.0, 8000, 0.5);
+        // 8bit
+        wav[34] = 8;
+        assert!(WavFile::from_bytes(&wav).is_err());
+        // The format chunk is cut short
+        assert!(WavFile::from_bytes(&wav[..30]).is_err());
+        let mut rate = sine_wav(440.0, 8000, 0.5);
+        rate[24..28].copy_from_slice(&0u32.to_le_bytes());
+        assert!(WavFile::from_bytes(&rate).is_err());
+        let mut short = wav[..20].to_vec();
+        short.extend_from_slice(&[1, 0]);
+        assert!(WavFile::from_bytes(&short).is_err());
+    }
+
+    /// Plays a block of 1kHz for each message, and ends when the sender is
+    /// dropped as `parec` would if it died
+    struct Blocks(Receiver<()>);
+
+    impl SampleSource for Blocks {
+        fn sample_rate(&self) -> u32 {
+            44100
+        }
+
+        fn read(&mut self, buf: &mut [f32]) -> Result<usize, Error> {
+            self.0
+                .recv()
+                .map_err(|_| Error::InvalidAudio("parec exited".to_owned()))?;
+            for (i, s) in buf.iter_mut().enumerate() {
+                *s = (2.0 * PI * 1000.0 * i as f32 / 44100.0).sin();
+            }
+            Ok(buf.len())
+        }
+    }
+
+    fn wait_for(levels: &AudioLevels, check: fn(&[f32]) -> bool) {
+        let start = Instant::now();
+        while !check(&levels.get()) {
+            assert!(start.elapsed() < Duration::from_secs(5));
+            sleep(Duration::from_millis(5));
+        }
+    }
+
+    #[test]
+    fn levels_reset_when_source_ends() {
+        let (send, recv) = channel();
+        let levels = AudioLevels::default();
+        levels.start(Box::new(Blocks(recv)));
+        send.send(()).unwrap();
+        wait_for(&levels, |l| l[8] > 0.9);
+        drop(send);
+        wait_for(&levels, |l| l.iter().all(|l| *l == 0.0));
+    }
+
+    #[test]
+    fn monitor_restarts_after_exit() {
+        let levels = AudioLevels::default();
+        let (send, recv) = channel();
+        levels.start_monitor_with(Duration::ZERO, || Ok(Box::new(Blocks(recv))));
+        send.send(()).unwrap();
+        wait_for(&levels, |l| l[8] > 0.9);
+        // Still running, so nothing new is started
+        levels.start_monitor_with(Duration::ZERO, || panic!("The capture was started twice"));
+
+        drop(send);
+        wait_for(&levels, |l| l.iter().all(|l| *l == 0.0));
+        let (send, recv) = channel();
+        let mut recv = Some(recv);
+        let start = Instant::now();
+        while recv.is_some() {
+            assert!(start.elapsed() < Duration::from_secs(5));
+            levels.start_monitor_with(Duration::ZERO, || {
+                Ok(Box::new(Blocks(recv.take().unwrap())))
+            });
+            sleep(Duration::from_millis(5));
+        }
+        send.send(()).unwrap();
+        wait_for(&levels, |l| l[8] > 0.9);
+    }
+}
@@ -2,15 +2,20 @@ use std::path::PathBuf;
 use std::time::Duration;
 
 use config_traits::{StdConfig, StdConfigLoad};
-use rog_anime::{ActionLoader, AnimTime, AnimeType, Fade, Sequences as AnimeSequences, Vec2};
+use rog_anime::{
+    ActionLoader, AnimTime, AnimeType, Fade, FrameCache, Sequences as AnimeSequences, Vec2
+};
 use rog_aura::effects::{AdvancedEffects as AuraSequences, Breathe, DoomFlicker, Effect, Static};
-use rog_aura::keyboard::LedCode;
+use rog_aura::keyboard::{LayoutLocale, LedCode};
 use rog_aura::{Colour, Speed};
+use rog_slash::SlashMode;
 use serde::{Deserialize, Serialize};
 
 use crate::error::Error;
 
 const ROOT_CONF_DIR: &str = "rog";
+/// Frames per second for the aura effects if a config doesn't set it
+pub const DEFAULT_AURA_FRAME_RATE: u32 = 30;
 
 fn root_conf_dir() -> PathBuf {
     let mut dir = dirs::config_dir().unwrap_or_else(|| PathBuf::from("/tmp"));
@@ -18,6 +23,12 @@ fn root_conf_dir() -> PathBuf {
     dir
 }
 
+/// Where the portal token to restore the ambient screen cast is kept, so the
+/// screen is not asked for on every start
+pub fn portal_token_path() -> PathBuf {
+    root_conf_dir().join("ambient-portal-token")
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct ConfigAnime {
     pub name: String,
@@ -26,7 +37,10 @@ pub struct ConfigAnime {
 
 impl ConfigAnime {
     pub fn create(&self, anime_type: AnimeType) -> Result<AnimeSequences, Error> {
-        let mut seq = AnimeSequences::new(anime_type);
+        let mut cache = dirs::cache_dir().unwrap_or_else(|| PathBuf::from("/tmp"));
+        cache.push(ROOT_CONF_DIR);
+        cache.push("anime");
+        let mut seq = AnimeSequences::new(anime_type).with_cache(FrameCache::new(cache));
 
         for (idx, action) in self.anime.iter().enumerate() {
             seq.insert(idx, action)?;
@@ -118,9 +132,16 @@ impl StdConfig for ConfigAnime {
 
 impl StdConfigLoad for ConfigAnime {}
 
+fn default_frame_rate() -> u32 {
+    DEFAULT_AURA_FRAME_RATE
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct ConfigAura {
     pub name: String,
+    /// Frames per second sent to the keyboard
+    #[serde(default = "default_frame_rate")]
+    pub frame_rate: u32,
     pub aura: AuraSequences
 }
 
@@ -129,6 +150,28 @@ impl ConfigAura {
         self.name = name;
         self
     }
+
+    /// Names of the aura configs in the user config directory. Other configs
+    /// share the directory, so only files that parse as `ConfigAura` are
+    /// listed.
+    pub fn list_names() -> Vec<String> {
+        let Ok(dir) = std::fs::read_dir(Self::config_dir()) else {
+            return Vec::new();
+        };
+        let mut names: Vec<String> = dir
+            .filter_map(|entry| {
+                let path = entry.ok()?.path();
+                if path.extension()? != "ron" {
+                    return None;
+                }
+                let text = std::fs::read_to_string(&path).ok()?;
+                ron::from_str::<ConfigAura>(&text).ok()?;
+                Some(path.file_stem()?.to_string_lossy().into_owned())
+            })
+            .collect();
+        names.sort();
+        names
+    }
 }
 
 impl Default for ConfigAura {
@@ -182,6 +225,7 @@ impl Default for ConfigAura {
 
         Self {
             name: "aura-default".to_owned(),
+            frame_rate: DEFAULT_AURA_FRAME_RATE,
             aura: seq
         }
     }
@@ -203,20 +247,141 @@ impl StdConfig for ConfigAura {
 
 impl StdConfigLoad for ConfigAura {}
 
+/// Where the ambient lighting gets screen frames from
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub enum AmbientSource {
+    /// Scrolling hue bars, for testing
+    TestPattern,
+    /// A binary PPM file, read each frame
+    File(PathBuf),
+    /// A command that writes a binary PPM of the screen to stdout, such as
+    /// `["grim", "-t", "ppm", "-"]`
+    Command(Vec<String>),
+    /// A screen cast through xdg-desktop-portal and PipeWire. The desktop
+    /// asks which screen to share the first time. Needs asusd-user built
+    /// with the `pipewire` feature.
+    Portal
+}
+
+fn default_ambient_frame_rate() -> u32 {
+    10
+}
+
+fn default_ambient_columns() -> usize {
+    16
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ConfigAmbient {
+    pub source: AmbientSource,
+    /// Captures per second, capturing the screen is expensive so keep this
+    /// low
+    #[serde(default = "default_ambient_frame_rate")]
+    pub frame_rate: u32,
+    /// How many vertical strips the screen is averaged in to
+    #[serde(default = "default_ambient_columns")]
+    pub columns: usize,
+    /// `0.0` follows the screen immediately, values up to `0.99` fade between
+    /// colours more slowly
+    #[serde(default)]
+    pub smoothing: f32
+}
+
+/// The urgency a desktop notification is sent with, from the `urgency` hint
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+pub enum Urgency {
+    Low,
+    #[default]
+    Normal,
+    Critical
+}
+
+impl From<u8> for Urgency {
+    fn from(value: u8) -> Self {
+        match value {
+            0 => Self::Low,
+            1 => Self::Normal,
+            _ => Self::Critical
+        }
+    }
+}
+
+fn default_notification_repeats() -> u32 {
+    1
+}
+
+fn default_notification_secs() -> u64 {
+    5
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ConfigNotifications {
+    /// Names of the apps to mirror, case is ignored. If empty every app is
+    /// mirrored.
+    #[serde(default)]
+    pub apps: Vec<String>,
+    /// Notifications less urgent than this are not mirrored
+    #[serde(default)]
+    pub min_urgency: Urgency,
+    /// Scroll the notification summary across the AniMe
+    #[serde(default)]
+    pub anime_text: bool,
+    /// TTF or OTF font for the summary, the built-in font if not set
+    #[serde(default)]
+    pub font: Option<PathBuf>,
+    /// How many times the summary scrolls past
+    #[serde(default = "default_notification_repeats")]
+    pub repeats: u32,
+    /// Greyscale PNGs shown on the AniMe in place of the summary, by app name
+    #[serde(default)]
+    pub icons: Vec<(String, PathBuf)>,
+    /// The Slash mode shown while there is a notification
+    #[serde(default)]
+    pub slash_mode: Option<SlashMode>,
+    /// Seconds an icon or Slash mode is shown for
+    #[serde(default = "default_notification_secs")]
+    pub show_secs: u64
+}
+
+impl ConfigNotifications {
+    /// If a notification from `app` with this `urgency` should be mirrored
+    pub fn wants(&self, app: &str, urgency: Urgency) -> bool {
+        urgency >= self.min_urgency
+            && (self.apps.is_empty() || self.apps.iter().any(|a| a.eq_ignore_ascii_case(app)))
+    }
+
+    pub fn icon(&self, app: &str) -> Option<&PathBuf> {
+        self.icons
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(app))
+            .map(|(_, icon)| icon)
+    }
+}
+
 #[derive(Debug, Default, Deserialize, Serialize)]
 #[serde(default)]
 pub struct ConfigBase {
     /// Name of active anime config file in the user config directory
     pub active_anime: Option<String>,
     /// Name of active aura config file in the user config directory
-    pub active_aura: Option<String>
+    pub active_aura: Option<String>,
+    /// If set the keyboard follows the colours on screen instead of running
+    /// `active_aura`
+    pub ambient: Option<ConfigAmbient>,
+    /// The keyboard locale, if not set it is found from the XKB layout
+    pub keyboard_locale: Option<LayoutLocale>,
+    /// If set desktop notifications are mirrored to the AniMe and Slash
+    pub notifications: Option<ConfigNotifications>
 }
 
 impl StdConfig for ConfigBase {
     fn new() -> Self {
         Self {
             active_anime: Some("anime-default".to_owned()),
-            active_aura: Some("aura-default".to_owned())
+            active_aura: Some("aura-default".to_owned()),
+            ambient: None,
+            keyboard_locale: None,
+            notifications: None
         }
     }
 
@@ -1,21 +1,33 @@
+use std::collections::VecDeque;
 use std::path::Path;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 use std::thread::sleep;
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use config_traits::StdConfig;
 use rog_anime::error::AnimeError;
-use rog_anime::{ActionData, ActionLoader, AnimTime, Fade, Sequences, Vec2};
+use rog_anime::{
+    ActionData, ActionLoader, AnimTime, AnimeDataBuffer, AnimeText, ClockTime, Fade, Sequences,
+    SystemMonitor, Vec2
+};
 use rog_dbus::zbus_anime::AnimeProxyBlocking;
 use ron::ser::PrettyConfig;
 use serde::{Deserialize, Serialize};
 use zbus::interface;
 use zbus::zvariant::{ObjectPath, Type};
 
+use crate::audio::AudioLevels;
 use crate::config::ConfigAnime;
 use crate::error::Error;
 
+/// About 30 frames a second for the audio spectrum
+const SPECTRUM_FRAME_TIME: Duration = Duration::from_millis(33);
+/// The most interrupts kept waiting to be shown. A burst of notifications
+/// would otherwise hold the sequence off for as long as they all take to show,
+/// so the oldest are dropped.
+const MAX_INTERRUPTS: usize = 4;
+
 #[derive(Debug, Clone, Deserialize, Serialize, Type)]
 pub struct Timer {
     type_of: TimeType,
@@ -63,66 +75,185 @@ pub enum TimeType {
 pub struct CtrlAnimeInner<'a> {
     sequences: Sequences,
     client: AnimeProxyBlocking<'a>,
-    do_early_return: Arc<AtomicBool>
+    do_early_return: Arc<AtomicBool>,
+    audio: AudioLevels,
+    /// Shown once each, oldest first, before the sequence carries on
+    interrupts: VecDeque<ActionData>,
+    /// The action the sequence carries on from after being stopped early. It
+    /// starts again from the beginning, so an animation restarts from its
+    /// first frame.
+    next_action: usize,
+    /// The display was turned off, nothing is queued until it is back on
+    display_off: bool
 }
 
 impl CtrlAnimeInner<'static> {
     pub fn new(
         sequences: Sequences,
         client: AnimeProxyBlocking<'static>,
-        do_early_return: Arc<AtomicBool>
+        do_early_return: Arc<AtomicBool>,
+        audio: AudioLevels
     ) -> Result<Self, Error> {
         Ok(Self {
             sequences,
             client,
-            do_early_return
+            do_early_return,
+            audio,
+            interrupts: VecDeque::new(),
+            next_action: 0,
+            display_off: false
         })
     }
 
+    /// Write frames from `render` until `run_time` is up. Returns `false` if
+    /// asked to return early.
+    fn run_generated(
+        &self,
+        run_time: Option<Duration>,
+        frame_time: Duration,
+        mut render: impl FnMut() -> rog_anime::error::Result<AnimeDataBuffer>
+    ) -> Result<bool, Error> {
+        let start = Instant::now();
+        while run_time.is_none_or(|t| start.elapsed() < t) {
+            if self.do_early_return.load(Ordering::SeqCst) {
+                return Ok(false);
+            }
+            let frame_start = Instant::now();
+            self.client.write(render()?).ok();
+            sleep(frame_time.saturating_sub(frame_start.elapsed()));
+        }
+        Ok(true)
+    }
+
     /// To be called on each main loop iteration to pump out commands to the
-    /// anime
-    pub fn run(&self) -> Result<(), Error> {
+    /// anime. Pending interrupts are shown first, then the sequence carries on
+    /// from the start of the action it was stopped in.
+    pub fn run(&mut self) -> Result<(), Error> {
         if self.do_early_return.load(Ordering::SeqCst) {
             return Ok(());
         }
 
-        for action in self.sequences.iter() {
-            match action {
-                ActionData::Animation(frames) => {
-                    rog_anime::run_animation(frames, &|output| {
-                        if self.do_early_return.load(Ordering::Acquire) {
-                            return Ok(true); // Do safe exit
-                        }
-                        self.client
-                            .write(output)
-                            .map_err(|e| AnimeError::Dbus(format!("{}", e)))
-                            .map(|_| false)
-                    });
-                }
-                ActionData::Image(image) => {
-                    self.client.write(image.as_ref().clone()).ok();
-                }
-                ActionData::Pause(duration) => {
-                    let start = Instant::now();
-                    'pause: loop {
-                        if self.do_early_return.load(Ordering::SeqCst) {
-                            return Ok(());
-                        }
-                        if Instant::now().duration_since(start) > *duration {
-                            break 'pause;
-                        }
-                        sleep(Duration::from_millis(1));
-                    }
-                }
-                ActionData::AudioEq
-                | ActionData::SystemInfo
-                | ActionData::TimeDate
-                | ActionData::Matrix => {}
+        while let Some(action) = self.interrupts.front() {
+            if !self.run_action(action)? {
+                return Ok(());
+            }
+            self.interrupts.pop_front();
+        }
+        // Actions may have been removed since it was stopped
+        if self.next_action >= self.sequences.len() {
+            self.next_action = 0;
+        }
+        while let Some(action) = self.sequences.get(self.next_action) {
+            if !self.run_action(action)? {
+                return Ok(());
             }
+            self.next_action += 1;
         }
+        self.next_action = 0;
 
         Ok(())
     }
+
+    /// Queue `actions` to be shown once before the sequence carries on. They
+    /// are dropped if the display is off.
+    fn queue(&mut self, actions: &[ActionLoader]) -> Result<(), Error> {
+        if self.display_off {
+            return Ok(());
+        }
+        let anime_type = self.sequences.anime_type();
+        for action in actions {
+            self.interrupts
+                .push_back(ActionData::from_anime_action(anime_type, action)?);
+        }
+        while self.interrupts.len() > MAX_INTERRUPTS {
+            self.interrupts.pop_front();
+        }
+        Ok(())
+    }
+
+    /// Returns `false` if asked to return early
+    fn run_action(&self, action: &ActionData) -> Result<bool, Error> {
+        let anime_type = self.sequences.anime_type();
+        match action {
+            ActionData::Animation(frames) => {
+                rog_anime::run_animation(frames, &|output| {
+                    if self.do_early_return.load(Ordering::Acquire) {
+                        return Ok(true); // Do safe exit
+                    }
+                    self.client
+                        .write(output)
+                        .map_err(|e| AnimeError::Dbus(format!("{}", e)))
+                        .map(|_| false)
+                });
+            }
+            ActionData::Image(image) => {
+                self.client.write(image.as_ref().clone()).ok();
+            }
+            ActionData::Pause(duration) => {
+                let start = Instant::now();
+                'pause: loop {
+                    if self.do_early_return.load(Ordering::SeqCst) {
+                        return Ok(false);
+                    }
+                    if Instant::now().duration_since(start) > *duration {
+                        break 'pause;
+                    }
+                    sleep(Duration::from_millis(1));
+                }
+            }
+            ActionData::AudioEq(spectrum) => {
+                self.audio.start_monitor();
+                let render = || spectrum.render(anime_type, &self.audio.get());
+                return self.run_generated(spectrum.run_time(), SPECTRUM_FRAME_TIME, render);
+            }
+            ActionData::TimeDate(clock) => {
+                let render = || clock.render(anime_type, &ClockTime::now());
+                return self.run_generated(clock.run_time(), clock.frame_time(), render);
+            }
+            ActionData::SystemInfo(info) => {
+                let mut monitor = SystemMonitor::new();
+                let render = || info.render(anime_type, &monitor.sample());
+                return self.run_generated(info.run_time(), info.frame_time(), render);
+            }
+            ActionData::Matrix(rain) => {
+                let seed = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|d| d.as_nanos() as u64)
+                    .unwrap_or_default();
+                let mut frames = rain.start(anime_type, seed);
+                return self
+                    .run_generated(rain.run_time(), rain.frame_time(), || frames.next_frame());
+            }
+            ActionData::Text(text) => {
+                let start = Instant::now();
+                return self.run_generated(text.run_time(), text.frame_time(), || {
+                    text.frame(start.elapsed())
+                });
+            }
+        }
+        Ok(!self.do_early_return.load(Ordering::SeqCst))
+    }
+}
+
+/// Stop the run loop and queue `actions` to be shown once, after which the
+/// sequence resumes from the start of the action it was stopped in. Nothing is
+/// queued while the display is off. `early_return` must be the same Atomic as
+/// in `inner`.
+pub fn interrupt_anime(
+    inner: &Mutex<CtrlAnimeInner<'static>>,
+    early_return: &AtomicBool,
+    actions: &[ActionLoader]
+) -> Result<(), Error> {
+    // Must make the inner run loop return early, keeping it stopped after if
+    // the display was turned off
+    let stopped = early_return.swap(true, Ordering::SeqCst);
+    let res = match inner.lock() {
+        Ok(mut controller) => controller.queue(actions),
+        Err(_) => Err(Error::AnimeLockFail)
+    };
+    // Release the inner run loop again
+    early_return.store(stopped, Ordering::SeqCst);
+    res
 }
 
 pub struct CtrlAnime<'a> {
@@ -342,15 +473,40 @@ impl CtrlAnime<'static> {
         Err(zbus::fdo::Error::Failed("UserConfig lock fail".into()))
     }
 
+    /// Show `message` scrolling across the display `repeats` times, then go
+    /// back to the sequence. `font` is the path of a TTF or OTF font, or
+    /// empty for the built-in font.
+    pub fn show_message(
+        &mut self,
+        message: &str,
+        font: &str,
+        repeats: u32
+    ) -> zbus::fdo::Result<()> {
+        let text = AnimeText {
+            font: (!font.is_empty()).then(|| font.into()),
+            ..AnimeText::message(message, repeats)
+        };
+        interrupt_anime(&self.inner, &self.inner_early_return, &[text.into()])?;
+        Ok(())
+    }
+
     pub fn set_state(&mut self, on: bool) -> zbus::fdo::Result<()> {
         // Operations here need to be in specific order
         if on {
             self.client.set_enable_display(on).ok();
+            if let Ok(mut controller) = self.inner.lock() {
+                controller.display_off = false;
+            }
             // Let the inner loop run
             self.inner_early_return.store(false, Ordering::SeqCst);
         } else {
             // Must make the inner run loop return early
             self.inner_early_return.store(true, Ordering::SeqCst);
+            if let Ok(mut controller) = self.inner.lock() {
+                // Anything waiting would be stale by the time it is back on
+                controller.display_off = true;
+                controller.interrupts.clear();
+            }
             self.client.set_enable_display(on).ok();
         }
         Ok(())
@@ -0,0 +1,372 @@
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use config_traits::{StdConfig, StdConfigLoad};
+use log::{debug, warn};
+use logind_zbus::manager::ManagerProxy;
+use rog_aura::effects::Effect;
+use rog_aura::keyboard::KeyLayout;
+use rog_aura::DeviceCorrection;
+use rog_dbus::zbus_aura::AuraProxyBlocking;
+use ron::ser::PrettyConfig;
+use smol::stream::StreamExt;
+use zbus::object_server::SignalEmitter;
+use zbus::zvariant::ObjectPath;
+use zbus::{interface, proxy};
+
+use crate::audio::AudioLevels;
+use crate::config::{ConfigAura, ConfigBase};
+use crate::error::Error;
+use crate::input::{EvdevSource, KeySource, INPUT_DEV, INPUT_SYSFS};
+
+/// The screensaver interface implemented by most desktops, `ActiveChanged` is
+/// emitted when the session is locked or unlocked
+#[proxy(
+    interface = "org.freedesktop.ScreenSaver",
+    default_service = "org.freedesktop.ScreenSaver",
+    default_path = "/org/freedesktop/ScreenSaver"
+)]
+trait ScreenSaver {
+    #[zbus(signal)]
+    fn active_changed(&self, active: bool) -> zbus::Result<()>;
+}
+
+/// The reasons the effects may be paused. Frames are sent only while none
+/// are set.
+#[derive(Debug, Default)]
+pub struct PauseState {
+    /// Set with the `SetState` method
+    pub user: AtomicBool,
+    pub locked: AtomicBool,
+    pub sleeping: AtomicBool
+}
+
+impl PauseState {
+    pub fn is_paused(&self) -> bool {
+        self.user.load(Ordering::SeqCst)
+            || self.locked.load(Ordering::SeqCst)
+            || self.sleeping.load(Ordering::SeqCst)
+    }
+}
+
+const AURA_PATH: &str = "/xyz/ljones/Aura";
+/// How long to wait before looking for a keyboard again if none could be
+/// opened
+const INPUT_RETRY: Duration = Duration::from_secs(5);
+
+fn frame_time(frame_rate: u32) -> Duration {
+    Duration::from_micros(1_000_000 / frame_rate.clamp(1, 120) as u64)
+}
+
+/// Runs the effects of the active config, the config is shared with the
+/// `CtrlAura` dbus object which edits it
+pub struct CtrlAuraInner<'a> {
+    config: Arc<Mutex<ConfigAura>>,
+    layout: KeyLayout,
+    client: AuraProxyBlocking<'a>,
+    pause: Arc<PauseState>,
+    keys: Option<EvdevSource>,
+    /// When opening the keyboards last failed
+    keys_failed: Option<Instant>,
+    audio: AudioLevels,
+    correction: DeviceCorrection
+}
+
+impl CtrlAuraInner<'static> {
+    pub fn new(
+        config: Arc<Mutex<ConfigAura>>,
+        layout: KeyLayout,
+        client: AuraProxyBlocking<'static>,
+        pause: Arc<PauseState>,
+        audio: AudioLevels,
+        correction: DeviceCorrection
+    ) -> Self {
+        Self {
+            config,
+            layout,
+            client,
+            pause,
+            keys: None,
+            keys_failed: None,
+            audio,
+            correction
+        }
+    }
+
+    /// Calculate and send one frame, then sleep for the rest of the frame
+    /// time. Sleeps without sending if paused.
+    pub fn run(&mut self) -> Result<(), Error> {
+        let start = Instant::now();
+        if self.pause.is_paused() {
+            sleep(Duration::from_millis(100));
+            return Ok(());
+        }
+
+        let (packets, frame_rate) = {
+            let mut config = self.config.lock().map_err(|_| Error::ConfigLockFail)?;
+            // Only listen to the keyboard if an effect needs it. This is checked
+            // each frame as the effects can be changed over dbus.
+            if config.aura.wants_key_presses() {
+                if self.keys.is_none()
+                    && self.keys_failed.is_none_or(|t| t.elapsed() >= INPUT_RETRY)
+                {
+                    match EvdevSource::new(Path::new(INPUT_SYSFS), Path::new(INPUT_DEV)) {
+                        Ok(keys) => {
+                            self.keys = Some(keys);
+                            self.keys_failed = None;
+                        }
+                        Err(e) => {
+                            if self.keys_failed.is_none() {
+                                warn!("Reactive effects will not respond to typing: {e}");
+                            }
+                            self.keys_failed = Some(Instant::now());
+                        }
+                    }
+                }
+                if let Some(keys) = self.keys.as_mut() {
+                    for led in keys.poll() {
+                        config.aura.key_pressed(led);
+                    }
+                }
+            } else {
+                self.keys = None;
+                self.keys_failed = None;
+            }
+            // Capture is started on first use, and again if parec exits
+            if config.aura.wants_audio() {
+                self.audio.start_monitor();
+                config.aura.audio_levels(&self.audio.get());
+            }
+            config.aura.next_state(&self.layout);
+            (
+                config.aura.create_corrected_packets(&self.correction),
+                config.frame_rate
+            )
+        };
+
+        if let Err(e) = self.client.direct_addressing_raw(packets) {
+            warn!("CtrlAura: could not send frame: {e}");
+        }
+        sleep(frame_time(frame_rate).saturating_sub(start.elapsed()));
+        Ok(())
+    }
+
+    /// Run the effects on a new thread until the process exits
+    pub fn spawn(mut self) {
+        std::thread::spawn(move || loop {
+            if let Err(e) = self.run() {
+                warn!("CtrlAura: {e}");
+                sleep(Duration::from_secs(1));
+            }
+        });
+    }
+}
+
+pub struct CtrlAura {
+    config: Arc<Mutex<ConfigAura>>,
+    pause: Arc<PauseState>
+}
+
+impl CtrlAura {
+    pub fn new(config: Arc<Mutex<ConfigAura>>, pause: Arc<PauseState>) -> Self {
+        Self { config, pause }
+    }
+
+    pub async fn add_to_server(self, server: &mut zbus::Connection) {
+        server
+            .object_server()
+            .at(&ObjectPath::from_str_unchecked(AURA_PATH), self)
+            .await
+            .map_err(|err| {
+                warn!("CtrlAura: add_to_server {}", err);
+                err
+            })
+            .ok();
+    }
+
+    /// Emit `Paused` changed for the object added to `conn`
+    async fn notify_paused(conn: &zbus::Connection) {
+        if let Ok(iface) = conn
+            .object_server()
+            .interface::<_, CtrlAura>(AURA_PATH)
+            .await
+        {
+            let ctrl = iface.get().await;
+            ctrl.paused_changed(iface.signal_emitter()).await.ok();
+        }
+    }
+
+    /// Pause while the system is suspended or the session is locked. Runs
+    /// until the signal streams end. `Paused` changes are emitted on
+    /// `server`, if the `CtrlAura` object was added to it.
+    pub async fn watch_session(pause: Arc<PauseState>, server: Option<zbus::Connection>) {
+        let sleeping = pause.clone();
+        let sleep_server = server.clone();
+        let suspend = async move {
+            let conn = zbus::Connection::system().await?;
+            let manager = ManagerProxy::new(&conn).await?;
+            let mut notif = manager.receive_prepare_for_sleep().await?;
+            while let Some(event) = notif.next().await {
+                if let Ok(args) = event.args() {
+                    debug!("CtrlAura: prepare for sleep {}", args.start);
+                    sleeping.sleeping.store(args.start, Ordering::SeqCst);
+                    if let Some(conn) = &sleep_server {
+                        Self::notify_paused(conn).await;
+                    }
+                }
+            }
+            Ok::<(), zbus::Error>(())
+        };
+
+        let lock = async move {
+            let conn = zbus::Connection::session().await?;
+            let screensaver = ScreenSaverProxy::new(&conn).await?;
+            let mut notif = screensaver.receive_active_changed().await?;
+            while let Some(event) = notif.next().await {
+                if let Ok(args) = event.args() {
+                    debug!("CtrlAura: screen locked {}", args.active);
+                    pause.locked.store(args.active, Ordering::SeqCst);
+                    if let Some(conn) = &server {
+                        Self::notify_paused(conn).await;
+                    }
+                }
+            }
+            Ok::<(), zbus::Error>(())
+        };
+
+        let (suspend, lock) = smol::future::zip(suspend, lock).await;
+        if let Err(e) = suspend {
+            warn!("CtrlAura: will not pause on suspend: {e}");
+        }
+        if let Err(e) = lock {
+            warn!("CtrlAura: will not pause on lock: {e}");
+        }
+    }
+}
+
+fn config_ron(config: &ConfigAura) -> String {
+    ron::ser::to_string_pretty(config, PrettyConfig::new().depth_limit(4))
+        .expect("Parse config to RON failed")
+}
+
+// Unlike `CtrlAnime` the run loop doesn't need to return early, the config
+// lock is held only while a frame is calculated.
+#[interface(name = "xyz.ljones.Asusd")]
+impl CtrlAura {
+    /// Insert an effect, given in RON, to the base layer. Returns the config
+    /// as RON.
+    pub fn insert_effect(&mut self, index: u32, effect: &str) -> zbus::fdo::Result<String> {
+        let effect: Effect =
+            ron::from_str(effect).map_err(|e| zbus::fdo::Error::InvalidArgs(e.to_string()))?;
+        let mut config = self.config.lock().map_err(|_| Error::ConfigLockFail)?;
+        if index as usize > config.aura.effects().len() {
+            return Err(zbus::fdo::Error::InvalidArgs(format!(
+                "Index {index} is past the end of the effects"
+            )));
+        }
+        config.aura.insert(index as usize, effect);
+        config.write();
+        Ok(config_ron(&config))
+    }
+
+    pub fn remove_item(&mut self, index: u32) -> zbus::fdo::Result<String> {
+        let mut config = self.config.lock().map_err(|_| Error::ConfigLockFail)?;
+        if config.aura.remove_item(index as usize).is_none() {
+            return Err(zbus::fdo::Error::InvalidArgs(format!(
+                "No effect at index {index}"
+            )));
+        }
+        config.write();
+        Ok(config_ron(&config))
+    }
+
+    /// Each effect of the base layer as RON
+    pub fn list_effects(&self) -> zbus::fdo::Result<Vec<String>> {
+        let config = self.config.lock().map_err(|_| Error::ConfigLockFail)?;
+        Ok(config
+            .aura
+            .effects()
+            .iter()
+            .filter_map(|e| ron::to_string(e).ok())
+            .collect())
+    }
+
+    /// The names of the aura configs that can be selected
+    pub fn list_configs(&self) -> Vec<String> {
+        ConfigAura::list_names()
+    }
+
+    /// Switch to another config and make it the default for the next start
+    pub fn select_config(&mut self, name: &str) -> zbus::fdo::Result<String> {
+        if !ConfigAura::list_names().iter().any(|n| n == name) {
+            return Err(zbus::fdo::Error::InvalidArgs(format!(
+                "No aura config named {name}"
+            )));
+        }
+        let mut config = self.config.lock().map_err(|_| Error::ConfigLockFail)?;
+        *config = ConfigAura::new().set_name(name.to_owned()).load();
+
+        let mut base = ConfigBase::new().load();
+        base.active_aura = Some(name.to_owned());
+        base.write();
+        Ok(config_ron(&config))
+    }
+
+    /// Pause or resume the effects
+    pub async fn set_state(&mut self, on: bool, #[zbus(signal_emitter)] ctxt: SignalEmitter<'_>) {
+        self.pause.user.store(!on, Ordering::SeqCst);
+        self.paused_changed(&ctxt).await.ok();
+    }
+
+    /// True if paused by `SetState`, a locked session, or suspend
+    #[zbus(property)]
+    pub fn paused(&self) -> bool {
+        self.pause.is_paused()
+    }
+
+    #[zbus(property)]
+    pub fn frame_rate(&self) -> zbus::fdo::Result<u32> {
+        let config = self.config.lock().map_err(|_| Error::ConfigLockFail)?;
+        Ok(config.frame_rate)
+    }
+
+    #[zbus(property)]
+    pub fn set_frame_rate(&mut self, frame_rate: u32) -> zbus::fdo::Result<()> {
+        if !(1..=120).contains(&frame_rate) {
+            return Err(zbus::fdo::Error::InvalidArgs(
+                "Frame rate must be 1 to 120".to_owned()
+            ));
+        }
+        let mut config = self.config.lock().map_err(|_| Error::ConfigLockFail)?;
+        config.frame_rate = frame_rate;
+        config.write();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::Ordering;
+    use std::time::Duration;
+
+    use super::{frame_time, PauseState};
+
+    #[test]
+    fn pause_reasons() {
+        let pause = PauseState::default();
+        assert!(!pause.is_paused());
+        pause.sleeping.store(true, Ordering::SeqCst);
+        pause.locked.store(true, Ordering::SeqCst);
+        pause.sleeping.store(false, Ordering::SeqCst);
+        // Still locked after resume
+        assert!(pause.is_paused());
+        pause.locked.store(false, Ordering::SeqCst);
+        assert!(!pause.is_paused());
+
+        assert_eq!(frame_time(30), Duration::from_micros(33_333));
+        assert_eq!(frame_time(0), Duration::from_secs(1));
+    }
+}
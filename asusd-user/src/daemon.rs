@@ -3,14 +3,20 @@ use std::path::PathBuf;
 use std::sync::atomic::AtomicBool;
 use std::sync::{Arc, Mutex};
 
+use asusd_user::ambient::AmbientInner;
+use asusd_user::audio::AudioLevels;
 use asusd_user::config::*;
 use asusd_user::ctrl_anime::{CtrlAnime, CtrlAnimeInner};
+use asusd_user::ctrl_aura::{CtrlAura, CtrlAuraInner, PauseState};
+use asusd_user::notifications::NotificationMirror;
 use config_traits::{StdConfig, StdConfigLoad};
+use log::error;
 use rog_anime::usb::get_anime_type;
 use rog_aura::aura_detection::LedSupportData;
-use rog_aura::keyboard::KeyLayout;
+use rog_aura::keyboard::{KeyLayout, LayoutLocale};
 use rog_dbus::zbus_anime::AnimeProxyBlocking;
 use rog_dbus::zbus_aura::AuraProxyBlocking;
+use rog_dbus::zbus_slash::SlashProxy;
 use rog_dbus::{list_iface_blocking, DBUS_NAME};
 use smol::Executor;
 use zbus::Connection;
@@ -40,7 +46,18 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     let config = ConfigBase::new().load();
     let executor = Executor::new();
 
+    // Shared by the anime and aura servers so the name is requested once
+    let session = smol::block_on(async {
+        let connection = Connection::session().await?;
+        connection.request_name(DBUS_NAME).await?;
+        Ok::<Connection, zbus::Error>(connection)
+    })?;
+
     let early_return = Arc::new(AtomicBool::new(false));
+    // Audio capture is shared by the anime and aura effects, and only started
+    // if one of them uses it
+    let audio = AudioLevels::default();
+    let mut notifications = config.notifications.map(NotificationMirror::new);
     // Set up the anime data and run loop/thread
     if supported.contains(&"xyz.ljones.Anime".to_string()) {
         if let Some(cfg) = config.active_anime {
@@ -50,63 +67,105 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             let anime_config = Arc::new(Mutex::new(anime_config));
 
             let anime_proxy_blocking = AnimeProxyBlocking::new(&conn).unwrap();
+            // Inner behind mutex required for thread safety
+            let inner = Arc::new(Mutex::new(CtrlAnimeInner::new(
+                anime,
+                anime_proxy_blocking.clone(),
+                early_return.clone(),
+                audio.clone()
+            )?));
+            // Need new client object for dbus control part
+            let anime_control = CtrlAnime::new(
+                anime_config,
+                inner.clone(),
+                anime_proxy_blocking,
+                early_return.clone()
+            )?;
+            notifications = notifications.map(|n| n.with_anime(inner.clone(), early_return));
+            let mut connection = session.clone();
             executor
                 .spawn(async move {
-                    // Create server
-                    let mut connection = Connection::session().await.unwrap();
-                    connection.request_name(DBUS_NAME).await.unwrap();
-
-                    // Inner behind mutex required for thread safety
-                    let inner = Arc::new(Mutex::new(
-                        CtrlAnimeInner::new(
-                            anime,
-                            anime_proxy_blocking.clone(),
-                            early_return.clone()
-                        )
-                        .unwrap()
-                    ));
-                    // Need new client object for dbus control part
-                    let anime_control = CtrlAnime::new(
-                        anime_config,
-                        inner.clone(),
-                        anime_proxy_blocking,
-                        early_return
-                    )
-                    .unwrap();
                     anime_control.add_to_server(&mut connection).await;
-                    loop {
-                        if let Ok(inner) = inner.clone().try_lock() {
-                            inner.run().ok();
-                        }
-                    }
                 })
                 .detach();
+            // The run loop blocks, so it must not share the executor thread
+            std::thread::spawn(move || loop {
+                if let Ok(mut inner) = inner.clone().try_lock() {
+                    inner.run().ok();
+                }
+            });
         }
     }
 
-    // if supported.keyboard_led.per_key_led_mode {
-    if let Some(cfg) = config.active_aura {
-        let mut aura_config = ConfigAura::new().set_name(cfg).load();
-        // let baord_name = std::fs::read_to_string(BOARD_NAME)?;
-
-        let led_support = LedSupportData::get_data("");
+    if let Some(mut mirror) = notifications {
+        if supported.contains(&"xyz.ljones.Slash".to_string()) {
+            let slash =
+                smol::block_on(async { SlashProxy::new(&Connection::system().await?).await })?;
+            mirror = mirror.with_slash(slash, &executor);
+        }
+        executor
+            .spawn(async move {
+                mirror
+                    .listen()
+                    .await
+                    .map_err(|e| error!("Notification mirroring: {e}"))
+                    .ok();
+            })
+            .detach();
+    }
 
-        let layout = KeyLayout::find_layout(led_support, PathBuf::from(DATA_DIR))
+    let locale = config.keyboard_locale.unwrap_or_else(LayoutLocale::detect);
+    // let baord_name = std::fs::read_to_string(BOARD_NAME)?;
+    let led_support = LedSupportData::get_data("");
+    let correction = led_support.colour_correction;
+    let layout = || {
+        KeyLayout::find_layout(led_support.clone(), PathBuf::from(DATA_DIR), locale)
             .map_err(|e| {
                 println!("{BOARD_NAME}, {e}");
             })
-            .unwrap_or_else(|_| KeyLayout::default_layout());
+            .unwrap_or_else(|_| KeyLayout::default_layout())
+    };
 
+    // if supported.keyboard_led.per_key_led_mode {
+    if let Some(ambient) = config.ambient {
+        // Takes over the keyboard, so the aura effects are not run
+        let pause = Arc::new(PauseState::default());
+        AmbientInner::new(
+            &ambient,
+            &layout(),
+            &led_support.power_zones,
+            AuraProxyBlocking::new(&conn)?,
+            pause.clone(),
+            correction
+        )
+        .spawn();
+        executor
+            .spawn(CtrlAura::watch_session(pause, None))
+            .detach();
+    } else if let Some(cfg) = config.active_aura {
+        let aura_config = ConfigAura::new().set_name(cfg).load();
+        let layout = layout();
+
+        let aura_config = Arc::new(Mutex::new(aura_config));
+        let pause = Arc::new(PauseState::default());
         let aura_proxy_blocking = AuraProxyBlocking::new(&conn).unwrap();
+        CtrlAuraInner::new(
+            aura_config.clone(),
+            layout,
+            aura_proxy_blocking,
+            pause.clone(),
+            audio,
+            correction
+        )
+        .spawn();
+
+        let mut connection = session.clone();
         executor
             .spawn(async move {
-                loop {
-                    aura_config.aura.next_state(&layout);
-                    let packets = aura_config.aura.create_packets();
-
-                    aura_proxy_blocking.direct_addressing_raw(packets).unwrap();
-                    std::thread::sleep(std::time::Duration::from_millis(33));
-                }
+                CtrlAura::new(aura_config, pause.clone())
+                    .add_to_server(&mut connection)
+                    .await;
+                CtrlAura::watch_session(pause, Some(connection)).await;
             })
             .detach();
     }
@@ -8,6 +8,14 @@ pub enum Error {
     ConfigLoadFail,
     ConfigLockFail,
     XdgVars,
+    NoInputDevices,
+    InvalidFrame(String),
+    /// The screen cast failed and may be started again
+    ScreenCast(String),
+    /// The screen cast was refused, or can't work in this build
+    ScreenCastUnavailable(String),
+    InvalidAudio(String),
+    AnimeLockFail,
     Anime(AnimeError)
 }
 
@@ -19,6 +27,12 @@ impl fmt::Display for Error {
             Error::ConfigLoadFail => write!(f, "Failed to load user config"),
             Error::ConfigLockFail => write!(f, "Failed to lock user config"),
             Error::XdgVars => write!(f, "XDG environment vars appear unset"),
+            Error::NoInputDevices => write!(f, "No readable keyboard input devices found"),
+            Error::InvalidFrame(err) => write!(f, "Invalid screen frame: {}", err),
+            Error::ScreenCast(err) => write!(f, "Screen cast failed: {}", err),
+            Error::ScreenCastUnavailable(err) => write!(f, "No screen cast: {}", err),
+            Error::InvalidAudio(err) => write!(f, "Invalid audio: {}", err),
+            Error::AnimeLockFail => write!(f, "Failed to lock the anime runner"),
             Error::Anime(err) => write!(f, "Anime error: {}", err)
         }
     }
@@ -40,6 +54,6 @@ impl From<AnimeError> for Error {
 
 impl From<Error> for zbus::fdo::Error {
     fn from(err: Error) -> Self {
-        zbus::fdo::Error::Failed(format!("Anime zbus error: {}", err))
+        zbus::fdo::Error::Failed(format!("asusd-user zbus error: {}", err))
     }
 }
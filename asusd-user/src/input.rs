@@ -0,0 +1,437 @@
+//! Key presses for the reactive aura effects. Keyboards are read through
+//! evdev, which requires the user to be in the `input` group.
+
+use std::collections::VecDeque;
+use std::fs::{self, File};
+use std::io::Read;
+use std::mem::{offset_of, size_of};
+use std::os::raw::c_long;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver};
+
+use log::{info, warn};
+use rog_aura::keyboard::LedCode;
+
+use crate::error::Error;
+
+pub const INPUT_SYSFS: &str = "/sys/class/input";
+pub const INPUT_DEV: &str = "/dev/input";
+
+/// The layout of `struct input_event`, only used for its size and offsets
+#[allow(dead_code)]
+#[repr(C)]
+struct RawInputEvent {
+    time: [c_long; 2],
+    kind: u16,
+    code: u16,
+    value: i32
+}
+
+const EVENT_SIZE: usize = size_of::<RawInputEvent>();
+const KIND: usize = offset_of!(RawInputEvent, kind);
+const CODE: usize = offset_of!(RawInputEvent, code);
+const VALUE: usize = offset_of!(RawInputEvent, value);
+const EV_SYN: u16 = 0x00;
+const EV_KEY: u16 = 0x01;
+const EV_REP_BIT: u64 = 1 << 0x14;
+const EV_KEY_BIT: u64 = 1 << EV_KEY;
+const SYN_REPORT: u16 = 0;
+/// Key event values, repeats (2) are ignored
+const KEY_PRESSED: i32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InputEvent {
+    pub kind: u16,
+    pub code: u16,
+    pub value: i32
+}
+
+impl InputEvent {
+    pub fn key(code: u16, value: i32) -> Self {
+        Self {
+            kind: EV_KEY,
+            code,
+            value
+        }
+    }
+
+    pub fn sync() -> Self {
+        Self {
+            kind: EV_SYN,
+            code: SYN_REPORT,
+            value: 0
+        }
+    }
+
+    fn is_sync(&self) -> bool {
+        self.kind == EV_SYN && self.code == SYN_REPORT
+    }
+
+    /// The LED for a key going down
+    fn pressed_led(&self) -> Option<LedCode> {
+        if self.kind == EV_KEY && self.value == KEY_PRESSED {
+            led_from_keycode(self.code)
+        } else {
+            None
+        }
+    }
+
+    /// The raw bytes as read from an evdev device, used to record streams
+    pub fn to_bytes(&self) -> [u8; EVENT_SIZE] {
+        let mut buf = [0; EVENT_SIZE];
+        buf[KIND..CODE].copy_from_slice(&self.kind.to_ne_bytes());
+        buf[CODE..VALUE].copy_from_slice(&self.code.to_ne_bytes());
+        buf[VALUE..EVENT_SIZE].copy_from_slice(&self.value.to_ne_bytes());
+        buf
+    }
+}
+
+/// Parse raw evdev data, trailing partial events are dropped
+pub fn parse_events(buf: &[u8]) -> Vec<InputEvent> {
+    buf.chunks_exact(EVENT_SIZE)
+        .map(|e| InputEvent {
+            kind: u16::from_ne_bytes([
+                e[KIND],
+                e[KIND + 1]
+            ]),
+            code: u16::from_ne_bytes([
+                e[CODE],
+                e[CODE + 1]
+            ]),
+            value: i32::from_ne_bytes([
+                e[VALUE],
+                e[VALUE + 1],
+                e[VALUE + 2],
+                e[VALUE + 3]
+            ])
+        })
+        .collect()
+}
+
+/// Map a Linux keycode (`input-event-codes.h`) to the keyboard LED
+pub fn led_from_keycode(code: u16) -> Option<LedCode> {
+    Some(match code {
+        1 => LedCode::Esc,
+        2 => LedCode::N1,
+        3 => LedCode::N2,
+        4 => LedCode::N3,
+        5 => LedCode::N4,
+        6 => LedCode::N5,
+        7 => LedCode::N6,
+        8 => LedCode::N7,
+        9 => LedCode::N8,
+        10 => LedCode::N9,
+        11 => LedCode::N0,
+        12 => LedCode::Hyphen,
+        13 => LedCode::Equals,
+        14 => LedCode::Backspace,
+        15 => LedCode::Tab,
+        16 => LedCode::Q,
+        17 => LedCode::W,
+        18 => LedCode::E,
+        19 => LedCode::R,
+        20 => LedCode::T,
+        21 => LedCode::Y,
+        22 => LedCode::U,
+        23 => LedCode::I,
+        24 => LedCode::O,
+        25 => LedCode::P,
+        26 => LedCode::LBracket,
+        27 => LedCode::RBracket,
+        28 => LedCode::Return,
+        29 => LedCode::LCtrl,
+        30 => LedCode::A,
+        31 => LedCode::S,
+        32 => LedCode::D,
+        33 => LedCode::F,
+        34 => LedCode::G,
+        35 => LedCode::H,
+        36 => LedCode::J,
+        37 => LedCode::K,
+        38 => LedCode::L,
+        39 => LedCode::SemiColon,
+        40 => LedCode::Quote,
+        41 => LedCode::Tilde,
+        42 => LedCode::LShift,
+        43 => LedCode::BackSlash,
+        44 => LedCode::Z,
+        45 => LedCode::X,
+        46 => LedCode::C,
+        47 => LedCode::V,
+        48 => LedCode::B,
+        49 => LedCode::N,
+        50 => LedCode::M,
+        51 => LedCode::Comma,
+        52 => LedCode::Period,
+        53 => LedCode::FwdSlash,
+        54 => LedCode::Rshift,
+        55 => LedCode::Star,
+        56 => LedCode::LAlt,
+        57 => LedCode::Spacebar,
+        58 => LedCode::Caps,
+        59 => LedCode::F1,
+        60 => LedCode::F2,
+        61 => LedCode::F3,
+        62 => LedCode::F4,
+        63 => LedCode::F5,
+        64 => LedCode::F6,
+        65 => LedCode::F7,
+        66 => LedCode::F8,
+        67 => LedCode::F9,
+        68 => LedCode::F10,
+        69 => LedCode::NumLock,
+        78 => LedCode::NumPadPlus,
+        83 => LedCode::NumPadDel,
+        86 => LedCode::Iso102,
+        87 => LedCode::F11,
+        88 => LedCode::F12,
+        96 => LedCode::NumPadEnter,
+        97 => LedCode::RCtrl,
+        99 => LedCode::PrtSc,
+        100 => LedCode::RAlt,
+        102 => LedCode::Home,
+        103 => LedCode::Up,
+        104 => LedCode::PgUp,
+        105 => LedCode::Left,
+        106 => LedCode::Right,
+        107 => LedCode::End,
+        108 => LedCode::Down,
+        109 => LedCode::PgDn,
+        111 => LedCode::Del,
+        114 => LedCode::VolDown,
+        115 => LedCode::VolUp,
+        119 => LedCode::Pause,
+        125 => LedCode::Meta,
+        // KEY_PROG1, the ROG key via asus-wmi
+        148 => LedCode::RogApp,
+        163 => LedCode::MediaNext,
+        164 => LedCode::MediaPlay,
+        165 => LedCode::MediaPrev,
+        166 => LedCode::MediaStop,
+        248 => LedCode::MicMute,
+        _ => return None
+    })
+}
+
+/// Somewhere to get key presses from. `poll()` is called once per frame and
+/// must not block.
+pub trait KeySource {
+    /// The keys pressed since the last poll
+    fn poll(&mut self) -> Vec<LedCode>;
+}
+
+/// Reads every keyboard found in `sys_root`, one thread per device
+pub struct EvdevSource {
+    events: Receiver<InputEvent>
+}
+
+impl EvdevSource {
+    pub fn new(sys_root: &Path, dev_root: &Path) -> Result<Self, Error> {
+        let (tx, rx) = channel();
+        let mut opened = 0;
+        for path in find_keyboards(sys_root, dev_root)? {
+            let mut file = match File::open(&path) {
+                Ok(f) => f,
+                Err(e) => {
+                    warn!("Could not open {path:?}, is the user in the input group? {e}");
+                    continue;
+                }
+            };
+            info!("Listening for key presses on {path:?}");
+            let tx = tx.clone();
+            std::thread::spawn(move || {
+                let mut buf = [0u8; EVENT_SIZE * 64];
+                // Ends when the device is removed or the receiver is dropped
+                while let Ok(len @ 1..) = file.read(&mut buf) {
+                    for event in parse_events(&buf[..len]) {
+                        if tx.send(event).is_err() {
+                            return;
+                        }
+                    }
+                }
+            });
+            opened += 1;
+        }
+
+        if opened == 0 {
+            return Err(Error::NoInputDevices);
+        }
+        Ok(Self { events: rx })
+    }
+}
+
+impl KeySource for EvdevSource {
+    fn poll(&mut self) -> Vec<LedCode> {
+        self.events
+            .try_iter()
+            .filter_map(|e| e.pressed_led())
+            .collect()
+    }
+}
+
+/// Devices that report keys with autorepeat, which excludes mice and most
+/// buttons
+fn find_keyboards(sys_root: &Path, dev_root: &Path) -> Result<Vec<PathBuf>, Error> {
+    let mut found = Vec::new();
+    for entry in fs::read_dir(sys_root)?.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if !name.starts_with("event") {
+            continue;
+        }
+        let Ok(caps) = fs::read_to_string(entry.path().join("device/capabilities/ev")) else {
+            continue;
+        };
+        let caps = u64::from_str_radix(caps.trim(), 16).unwrap_or_default();
+        if caps & EV_KEY_BIT != 0 && caps & EV_REP_BIT != 0 {
+            found.push(dev_root.join(name));
+        }
+    }
+    found.sort();
+    Ok(found)
+}
+
+/// Replays a recorded stream, each poll returns the presses up to the next
+/// `SYN_REPORT` as a device would deliver them
+pub struct ReplaySource {
+    events: VecDeque<InputEvent>
+}
+
+impl ReplaySource {
+    pub fn new(events: Vec<InputEvent>) -> Self {
+        Self {
+            events: events.into()
+        }
+    }
+
+    /// Raw bytes as recorded from `/dev/input/eventN`
+    pub fn from_bytes(buf: &[u8]) -> Self {
+        Self::new(parse_events(buf))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+}
+
+impl KeySource for ReplaySource {
+    fn poll(&mut self) -> Vec<LedCode> {
+        let mut pressed = Vec::new();
+        while let Some(event) = self.events.pop_front() {
+            if event.is_sync() {
+                break;
+            }
+            pressed.extend(event.pressed_led());
+        }
+        pressed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::time::{Duration, Instant};
+
+    use rog_aura::effects::{AdvancedEffects, Effect, KeyPress};
+    use rog_aura::keyboard::{KeyLayout, LedCode};
+    use rog_aura::Colour;
+
+    use super::{EvdevSource, InputEvent, KeySource, ReplaySource};
+    use crate::error::Error;
+
+    /// "fg" typed with a held key repeating
+    fn recording() -> Vec<u8> {
+        [
+            InputEvent::key(33, 1),
+            InputEvent::sync(),
+            InputEvent::key(33, 0),
+            InputEvent::sync(),
+            InputEvent::key(34, 1),
+            InputEvent::sync(),
+            InputEvent::key(34, 2),
+            InputEvent::sync(),
+            InputEvent::key(34, 0),
+            InputEvent::sync()
+        ]
+        .iter()
+        .flat_map(InputEvent::to_bytes)
+        .collect()
+    }
+
+    #[test]
+    fn replay_into_effects() {
+        let layout = KeyLayout::default_layout();
+        let mut seq = AdvancedEffects::new(false);
+        seq.push_all_keys(&layout, |led| {
+            Effect::KeyPress(KeyPress::new(
+                led,
+                Colour {
+                    r: 255,
+                    g: 255,
+                    b: 255
+                },
+                Colour { r: 0, g: 0, b: 0 },
+                2
+            ))
+        });
+
+        let mut source = ReplaySource::from_bytes(&recording());
+        let mut frames = Vec::new();
+        while !source.is_empty() {
+            let keys = source.poll();
+            for led in &keys {
+                seq.key_pressed(*led);
+            }
+            seq.next_state(&layout);
+            let packets = seq.create_packets();
+            frames.push((keys, packets[5][33], packets[5][36]));
+        }
+
+        assert_eq!(frames, vec![
+            (vec![LedCode::F], 255, 0),
+            (vec![], 128, 0),
+            (vec![LedCode::G], 0, 255),
+            // Repeats don't count as a press
+            (vec![], 0, 128),
+            (vec![], 0, 0),
+        ]);
+    }
+
+    #[test]
+    fn evdev_finds_keyboards() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        let sys = root.join("sys");
+        let dev = root.join("dev");
+        for (name, caps) in [
+            ("event3", "120013"),
+            ("event5", "17"),
+            ("mouse0", "120013")
+        ] {
+            fs::create_dir_all(sys.join(name).join("device/capabilities")).unwrap();
+            fs::write(sys.join(name).join("device/capabilities/ev"), caps).unwrap();
+        }
+        fs::create_dir_all(&dev).unwrap();
+        fs::write(dev.join("event3"), recording()).unwrap();
+        fs::write(dev.join("event5"), InputEvent::key(30, 1).to_bytes()).unwrap();
+
+        let mut source = EvdevSource::new(&sys, &dev).unwrap();
+        let start = Instant::now();
+        let mut pressed = Vec::new();
+        while pressed.len() < 2 && start.elapsed() < Duration::from_secs(5) {
+            pressed.extend(source.poll());
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        // The event5 device isn't a keyboard so 'A' is never seen
+        assert_eq!(pressed, vec![
+            LedCode::F,
+            LedCode::G
+        ]);
+
+        fs::remove_dir_all(&dev).unwrap();
+        fs::create_dir_all(&dev).unwrap();
+        assert!(matches!(
+            EvdevSource::new(&sys, &dev),
+            Err(Error::NoInputDevices)
+        ));
+    }
+}
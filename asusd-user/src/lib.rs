@@ -6,4 +6,18 @@ pub mod ctrl_anime;
 
 pub mod zbus_anime;
 
+pub mod ctrl_aura;
+
+pub mod zbus_aura;
+
+pub mod input;
+
+pub mod ambient;
+
+pub mod screencast;
+
+pub mod audio;
+
+pub mod notifications;
+
 pub static VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -0,0 +1,297 @@
+use std::collections::HashMap;
+use std::sync::atomic::AtomicBool;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use log::{info, warn};
+use rog_anime::{ActionLoader, AnimTime, AnimeText, Vec2};
+use rog_dbus::zbus_slash::SlashProxy;
+use rog_slash::SlashMode;
+use smol::channel::{unbounded, Receiver, Sender};
+use smol::stream::StreamExt;
+use smol::Timer;
+use zbus::fdo::MonitoringProxy;
+use zbus::message::Type as MessageType;
+use zbus::zvariant::OwnedValue;
+use zbus::{Connection, MatchRule, Message, MessageStream};
+
+use crate::config::{ConfigNotifications, Urgency};
+use crate::ctrl_anime::{interrupt_anime, CtrlAnimeInner};
+
+const NOTIFICATIONS_IFACE: &str = "org.freedesktop.Notifications";
+
+/// App name, replaced ID, icon, summary, body, actions, hints and timeout
+type NotifyArgs = (
+    String,
+    u32,
+    String,
+    String,
+    String,
+    Vec<String>,
+    HashMap<String, OwnedValue>,
+    i32
+);
+
+/// The parts of a `Notify` call that are mirrored
+#[derive(Debug, Clone, PartialEq)]
+pub struct Notification {
+    pub app_name: String,
+    pub summary: String,
+    pub urgency: Urgency
+}
+
+impl Notification {
+    /// Read the arguments of an `org.freedesktop.Notifications.Notify` call,
+    /// `None` if the message is anything else
+    pub fn from_message(msg: &Message) -> Option<Self> {
+        let header = msg.header();
+        if header.interface().map(|i| i.as_str()) != Some(NOTIFICATIONS_IFACE)
+            || header.member().map(|m| m.as_str()) != Some("Notify")
+        {
+            return None;
+        }
+        let (app_name, _, _, summary, _, _, hints, _): NotifyArgs =
+            msg.body().deserialize().ok()?;
+        let urgency = hints
+            .get("urgency")
+            .and_then(|u| u8::try_from(u).ok())
+            .map(Urgency::from)
+            .unwrap_or_default();
+        Some(Self {
+            app_name,
+            summary,
+            urgency
+        })
+    }
+}
+
+/// Shows desktop notifications on the AniMe and Slash. Whatever was running
+/// carries on once the notification has been shown.
+pub struct NotificationMirror {
+    config: ConfigNotifications,
+    /// The AniMe runner and its early return Atomic
+    anime: Option<(Arc<Mutex<CtrlAnimeInner<'static>>>, Arc<AtomicBool>)>,
+    slash: Option<Sender<()>>
+}
+
+impl NotificationMirror {
+    pub fn new(config: ConfigNotifications) -> Self {
+        Self {
+            config,
+            anime: None,
+            slash: None
+        }
+    }
+
+    /// Mirror to the AniMe if there is an icon for the app or `anime_text` is
+    /// set
+    pub fn with_anime(
+        mut self,
+        inner: Arc<Mutex<CtrlAnimeInner<'static>>>,
+        early_return: Arc<AtomicBool>
+    ) -> Self {
+        self.anime = Some((inner, early_return));
+        self
+    }
+
+    /// Mirror to the Slash if `slash_mode` is set. The mode is held while
+    /// notifications keep arriving, and put back after.
+    pub fn with_slash(mut self, proxy: SlashProxy<'static>, executor: &smol::Executor<'_>) -> Self {
+        if let Some(mode) = self.config.slash_mode {
+            let (tx, rx) = unbounded();
+            let show_for = Duration::from_secs(self.config.show_secs);
+            executor
+                .spawn(Self::mirror_slash(proxy, mode, show_for, rx))
+                .detach();
+            self.slash = Some(tx);
+        }
+        self
+    }
+
+    /// What to show on the AniMe for a notification, if anything
+    pub fn anime_actions(&self, notification: &Notification) -> Vec<ActionLoader> {
+        if let Some(icon) = self.config.icon(&notification.app_name) {
+            return vec![
+                ActionLoader::Image {
+                    file: icon.clone(),
+                    scale: 1.0,
+                    angle: 0.0,
+                    translation: Vec2::default(),
+                    time: AnimTime::Infinite,
+                    brightness: 1.0
+                },
+                ActionLoader::Pause(Duration::from_secs(self.config.show_secs)),
+            ];
+        }
+        if self.config.anime_text && !notification.summary.is_empty() {
+            let text = AnimeText {
+                font: self.config.font.clone(),
+                ..AnimeText::message(&notification.summary, self.config.repeats)
+            };
+            return vec![text.into()];
+        }
+        Vec::new()
+    }
+
+    /// Listen for notifications on the session bus until the connection is
+    /// closed. This needs a connection of its own as a monitoring connection
+    /// can not be used for anything else.
+    pub async fn listen(self) -> zbus::Result<()> {
+        let connection = Connection::session().await?;
+        let rule = MatchRule::builder()
+            .msg_type(MessageType::MethodCall)
+            .interface(NOTIFICATIONS_IFACE)?
+            .member("Notify")?
+            .build();
+        MonitoringProxy::new(&connection)
+            .await?
+            .become_monitor(&[rule], 0)
+            .await?;
+        info!("Mirroring desktop notifications");
+
+        let mut stream = MessageStream::from(connection);
+        while let Some(msg) = stream.next().await {
+            let Some(notification) = msg.ok().as_ref().and_then(Notification::from_message) else {
+                continue;
+            };
+            if self
+                .config
+                .wants(&notification.app_name, notification.urgency)
+            {
+                self.show(notification).await;
+            }
+        }
+        Ok(())
+    }
+
+    async fn show(&self, notification: Notification) {
+        if let Some(slash) = &self.slash {
+            slash.send(()).await.ok();
+        }
+        let actions = self.anime_actions(&notification);
+        if let (Some((inner, early_return)), false) = (&self.anime, actions.is_empty()) {
+            let inner = inner.clone();
+            let early_return = early_return.clone();
+            // Waits for the runner to stop, so is kept off the executor
+            smol::unblock(move || interrupt_anime(&inner, &early_return, &actions))
+                .await
+                .map_err(|e| warn!("Notification {}: {e}", notification.app_name))
+                .ok();
+        }
+    }
+
+    async fn mirror_slash(
+        proxy: SlashProxy<'static>,
+        mode: SlashMode,
+        show_for: Duration,
+        notified: Receiver<()>
+    ) {
+        while notified.recv().await.is_ok() {
+            let Ok(previous) = proxy.mode().await else {
+                continue;
+            };
+            proxy.set_mode(mode).await.ok();
+            // Each notification that arrives while shown restarts the time
+            loop {
+                let next = async { notified.recv().await.ok() };
+                let timeout = async {
+                    Timer::after(show_for).await;
+                    None
+                };
+                if smol::future::or(next, timeout).await.is_none() {
+                    break;
+                }
+            }
+            proxy.set_mode(previous).await.ok();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn notify(app: &str, summary: &str, urgency: Option<u8>) -> Message {
+        let mut hints = HashMap::new();
+        if let Some(urgency) = urgency {
+            hints.insert("urgency".to_owned(), OwnedValue::from(urgency));
+        }
+        Message::method_call("/org/freedesktop/Notifications", "Notify")
+            .unwrap()
+            .interface(NOTIFICATIONS_IFACE)
+            .unwrap()
+            .build(&(
+                app,
+                0u32,
+                "",
+                summary,
+                "body",
+                Vec::<String>::new(),
+                hints,
+                -1i32
+            ))
+            .unwrap()
+    }
+
+    fn config() -> ConfigNotifications {
+        ron::from_str("(apps: [\"Firefox\"], min_urgency: Normal, anime_text: true)").unwrap()
+    }
+
+    #[test]
+    fn parse_notify() {
+        let msg = notify("firefox", "Download done", Some(2));
+        let notification = Notification::from_message(&msg).unwrap();
+        assert_eq!(notification.app_name, "firefox");
+        assert_eq!(notification.summary, "Download done");
+        assert_eq!(notification.urgency, Urgency::Critical);
+
+        let msg = notify("firefox", "Download done", None);
+        let notification = Notification::from_message(&msg).unwrap();
+        assert_eq!(notification.urgency, Urgency::Normal);
+
+        let msg = Message::method_call("/org/freedesktop/Notifications", "CloseNotification")
+            .unwrap()
+            .interface(NOTIFICATIONS_IFACE)
+            .unwrap()
+            .build(&(1u32,))
+            .unwrap();
+        assert!(Notification::from_message(&msg).is_none());
+    }
+
+    #[test]
+    fn filter_by_app_and_urgency() {
+        let mut config = config();
+        assert_eq!(config.repeats, 1);
+        assert!(config.wants("firefox", Urgency::Normal));
+        assert!(!config.wants("firefox", Urgency::Low));
+        assert!(!config.wants("discord", Urgency::Critical));
+        config.apps.clear();
+        assert!(config.wants("discord", Urgency::Normal));
+    }
+
+    #[test]
+    fn icon_or_text() {
+        let mut config = config();
+        let notification = Notification {
+            app_name: "Firefox".to_owned(),
+            summary: "Download done".to_owned(),
+            urgency: Urgency::Normal
+        };
+        let actions = NotificationMirror::new(config.clone()).anime_actions(&notification);
+        assert!(matches!(actions[..], [ActionLoader::Text { .. }]));
+
+        config
+            .icons
+            .push(("firefox".to_owned(), "/tmp/firefox.png".into()));
+        let actions = NotificationMirror::new(config.clone()).anime_actions(&notification);
+        assert!(matches!(actions[..], [
+            ActionLoader::Image { .. },
+            ActionLoader::Pause(_)
+        ]));
+
+        config.icons.clear();
+        config.anime_text = false;
+        let actions = NotificationMirror::new(config).anime_actions(&notification);
+        assert!(actions.is_empty());
+    }
+}
@@ -0,0 +1,502 @@
+//! Screen frames for the ambient lighting from the xdg-desktop-portal
+//! `ScreenCast` interface. The portal asks the user which screen to share and
+//! hands over a PipeWire remote with the screen as a video stream. The stream
+//! is read on its own thread as PipeWire objects must stay on the thread of
+//! their loop, and needs the `pipewire` feature.
+
+use std::collections::HashMap;
+use std::os::fd::OwnedFd;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::mpsc::RecvTimeoutError;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use log::{info, warn};
+use zbus::blocking::Connection;
+use zbus::proxy;
+use zbus::zvariant::{ObjectPath, OwnedObjectPath, OwnedValue, Value};
+
+use crate::ambient::{Frame, FrameSource};
+use crate::config::portal_token_path;
+use crate::error::Error;
+
+/// `SelectSources` source types, monitors only
+const SOURCE_MONITOR: u32 = 1;
+/// `SelectSources` persist mode, until the user revokes it
+const PERSIST_PERMANENT: u32 = 2;
+/// The `Response` code of a request the user cancelled
+const RESPONSE_CANCELLED: u32 = 1;
+/// How long to wait before starting the screen cast again after it failed
+const RETRY_DELAY: Duration = Duration::from_secs(10);
+/// How long to wait for a new frame before the last is reused. Frames are
+/// only sent when the screen changes.
+const FRAME_WAIT: Duration = Duration::from_millis(500);
+
+#[proxy(
+    interface = "org.freedesktop.portal.ScreenCast",
+    default_service = "org.freedesktop.portal.Desktop",
+    default_path = "/org/freedesktop/portal/desktop"
+)]
+trait ScreenCast {
+    fn create_session(&self, options: HashMap<&str, Value<'_>>) -> zbus::Result<OwnedObjectPath>;
+
+    fn select_sources(
+        &self,
+        session_handle: &ObjectPath<'_>,
+        options: HashMap<&str, Value<'_>>
+    ) -> zbus::Result<OwnedObjectPath>;
+
+    fn start(
+        &self,
+        session_handle: &ObjectPath<'_>,
+        parent_window: &str,
+        options: HashMap<&str, Value<'_>>
+    ) -> zbus::Result<OwnedObjectPath>;
+
+    #[zbus(name = "OpenPipeWireRemote")]
+    fn open_pipe_wire_remote(
+        &self,
+        session_handle: &ObjectPath<'_>,
+        options: HashMap<&str, Value<'_>>
+    ) -> zbus::Result<zbus::zvariant::OwnedFd>;
+}
+
+#[proxy(
+    interface = "org.freedesktop.portal.Request",
+    default_service = "org.freedesktop.portal.Desktop"
+)]
+trait Request {
+    #[zbus(signal)]
+    fn response(&self, response: u32, results: HashMap<String, OwnedValue>) -> zbus::Result<()>;
+}
+
+#[proxy(
+    interface = "org.freedesktop.portal.Session",
+    default_service = "org.freedesktop.portal.Desktop"
+)]
+trait Session {
+    fn close(&self) -> zbus::Result<()>;
+}
+
+fn portal_error(err: zbus::Error) -> Error {
+    Error::ScreenCast(err.to_string())
+}
+
+/// A unique token for a request or session handle
+fn handle_token() -> String {
+    static COUNT: AtomicU32 = AtomicU32::new(0);
+    format!("asusd_user_{}", COUNT.fetch_add(1, Ordering::Relaxed))
+}
+
+/// Call a portal method that returns a `Request` and wait for its results.
+/// The response is listened for before the call so it can't be missed.
+fn request(
+    conn: &Connection,
+    call: impl FnOnce(HashMap<&str, Value<'_>>) -> zbus::Result<OwnedObjectPath>,
+    mut options: HashMap<&str, Value<'_>>
+) -> Result<HashMap<String, OwnedValue>, Error> {
+    let token = handle_token();
+    let sender = conn
+        .unique_name()
+        .map(|n| n.as_str().trim_start_matches(':').replace('.', "_"))
+        .unwrap_or_default();
+    let path = format!("/org/freedesktop/portal/desktop/request/{sender}/{token}");
+    let proxy = RequestProxyBlocking::builder(conn)
+        .path(path)
+        .map_err(portal_error)?
+        .build()
+        .map_err(portal_error)?;
+    let mut responses = proxy.receive_response().map_err(portal_error)?;
+
+    options.insert("handle_token", Value::from(token));
+    call(options).map_err(portal_error)?;
+    let response = responses.next().ok_or_else(|| {
+        Error::ScreenCast("The portal request ended without a response".to_owned())
+    })?;
+    let args = response.args().map_err(portal_error)?;
+    match args.response {
+        0 => Ok(args.results),
+        RESPONSE_CANCELLED => Err(Error::ScreenCastUnavailable(
+            "The screen cast was cancelled".to_owned()
+        )),
+        _ => Err(Error::ScreenCast("The portal request failed".to_owned()))
+    }
+}
+
+fn result<T: TryFrom<OwnedValue>>(results: &HashMap<String, OwnedValue>, key: &str) -> Option<T> {
+    T::try_from(results.get(key)?.try_clone().ok()?).ok()
+}
+
+/// A portal screen cast session, closed on drop
+struct PortalSession {
+    conn: Connection,
+    handle: OwnedObjectPath
+}
+
+impl PortalSession {
+    /// Ask for a monitor and start casting it. The choice is remembered by
+    /// the portal and used again for the next start, if the desktop supports
+    /// it. Returns the PipeWire remote and the node of the screen.
+    fn start() -> Result<(Self, OwnedFd, u32), Error> {
+        let conn = Connection::session().map_err(portal_error)?;
+        let portal = ScreenCastProxyBlocking::new(&conn).map_err(portal_error)?;
+
+        let results = request(
+            &conn,
+            |opts| portal.create_session(opts),
+            HashMap::from([("session_handle_token", Value::from(handle_token()))])
+        )?;
+        let handle = result::<String>(&results, "session_handle")
+            .and_then(|h| OwnedObjectPath::try_from(h).ok())
+            .ok_or_else(|| Error::ScreenCast("The portal returned no session".to_owned()))?;
+        let session = Self { conn, handle };
+
+        let mut options = HashMap::from([
+            ("types", Value::from(SOURCE_MONITOR)),
+            ("multiple", Value::from(false)),
+            ("persist_mode", Value::from(PERSIST_PERMANENT))
+        ]);
+        if let Some(token) = std::fs::read_to_string(portal_token_path())
+            .ok()
+            .map(|t| t.trim().to_owned())
+            .filter(|t| !t.is_empty())
+        {
+            options.insert("restore_token", Value::from(token));
+        }
+        request(
+            &session.conn,
+            |opts| portal.select_sources(&session.handle, opts),
+            options
+        )?;
+
+        let results = request(
+            &session.conn,
+            |opts| portal.start(&session.handle, "", opts),
+            HashMap::new()
+        )?;
+        let node = result::<Vec<(u32, HashMap<String, OwnedValue>)>>(&results, "streams")
+            .and_then(|streams| streams.first().map(|s| s.0))
+            .ok_or_else(|| Error::ScreenCast("The portal started no streams".to_owned()))?;
+        if let Some(token) = result::<String>(&results, "restore_token") {
+            if let Err(e) = std::fs::write(portal_token_path(), token) {
+                warn!("Ambient: could not save the screen cast choice: {e}");
+            }
+        }
+
+        let fd = portal
+            .open_pipe_wire_remote(&session.handle, HashMap::new())
+            .map_err(portal_error)?;
+        Ok((session, fd.into(), node))
+    }
+}
+
+impl Drop for PortalSession {
+    fn drop(&mut self) {
+        if let Ok(session) = SessionProxyBlocking::builder(&self.conn)
+            .path(&self.handle)
+            .and_then(|b| b.build())
+        {
+            session.close().ok();
+        }
+    }
+}
+
+/// Frames of a screen picked through xdg-desktop-portal. The screen cast is
+/// started on the first frame, and again if it stops.
+#[derive(Default)]
+pub struct PortalCapture {
+    cast: Option<(PortalSession, video::VideoStream)>,
+    last: Option<Frame>,
+    failed: Option<Instant>
+}
+
+impl PortalCapture {
+    fn start(&mut self) -> Result<(), Error> {
+        if let Some(failed) = self.failed {
+            sleep(RETRY_DELAY.saturating_sub(failed.elapsed()));
+        }
+        let (session, fd, node) = PortalSession::start()?;
+        let stream = video::VideoStream::new(fd, node)?;
+        info!("Ambient: screen cast started");
+        self.cast = Some((session, stream));
+        self.failed = None;
+        Ok(())
+    }
+}
+
+impl FrameSource for PortalCapture {
+    fn next_frame(&mut self) -> Result<Frame, Error> {
+        if !cfg!(feature = "pipewire") {
+            return Err(Error::ScreenCastUnavailable(
+                "asusd-user was built without the pipewire feature".to_owned()
+            ));
+        }
+        if self.cast.is_none() {
+            if let Err(e) = self.start() {
+                self.failed = Some(Instant::now());
+                return Err(e);
+            }
+        }
+        let Some((_, stream)) = &self.cast else {
+            return Err(Error::ScreenCast(
+                "The screen cast is not running".to_owned()
+            ));
+        };
+
+        match stream.frames.recv_timeout(FRAME_WAIT) {
+            Ok(frame) => {
+                self.last = Some(frame.clone());
+                Ok(frame)
+            }
+            Err(RecvTimeoutError::Timeout) => self
+                .last
+                .clone()
+                .ok_or_else(|| Error::ScreenCast("No frames received yet".to_owned())),
+            Err(RecvTimeoutError::Disconnected) => {
+                self.cast = None;
+                self.last = None;
+                self.failed = Some(Instant::now());
+                Err(Error::ScreenCast("The screen cast stopped".to_owned()))
+            }
+        }
+    }
+}
+
+#[cfg(feature = "pipewire")]
+mod video {
+    use std::os::fd::OwnedFd;
+    use std::sync::mpsc::{sync_channel, Receiver, SyncSender};
+
+    use log::warn;
+    use pipewire as pw;
+    use pw::spa;
+    use pw::spa::param::video::{VideoFormat, VideoInfoRaw};
+    use pw::spa::pod::Pod;
+
+    use crate::ambient::{Frame, PixelOrder};
+    use crate::error::Error;
+
+    pub struct VideoStream {
+        /// Holds at most one frame, newer frames are dropped until it is taken
+        pub frames: Receiver<Frame>,
+        quit: pw::channel::Sender<()>
+    }
+
+    impl VideoStream {
+        pub fn new(fd: OwnedFd, node: u32) -> Result<Self, Error> {
+            let (send, frames) = sync_channel(1);
+            let (quit, quit_recv) = pw::channel::channel();
+            std::thread::spawn(move || {
+                if let Err(e) = run(fd, node, send, quit_recv) {
+                    warn!("Ambient: PipeWire stream failed: {e}");
+                }
+            });
+            Ok(Self { frames, quit })
+        }
+    }
+
+    impl Drop for VideoStream {
+        fn drop(&mut self) {
+            self.quit.send(()).ok();
+        }
+    }
+
+    fn pixel_order(format: VideoFormat) -> Option<PixelOrder> {
+        match format {
+            VideoFormat::RGB => Some(PixelOrder::Rgb),
+            VideoFormat::BGR => Some(PixelOrder::Bgr),
+            VideoFormat::RGBx | VideoFormat::RGBA => Some(PixelOrder::Rgbx),
+            VideoFormat::BGRx | VideoFormat::BGRA => Some(PixelOrder::Bgrx),
+            _ => None
+        }
+    }
+
+    /// The raw video formats a frame can be made from, screen casts are
+    /// usually BGRx
+    fn format_params() -> Result<Vec<u8>, Error> {
+        let obj = spa::pod::object!(
+            spa::utils::SpaTypes::ObjectParamFormat,
+            spa::param::ParamType::EnumFormat,
+            spa::pod::property!(
+                spa::param::format::FormatProperties::MediaType,
+                Id,
+                spa::param::format::MediaType::Video
+            ),
+            spa::pod::property!(
+                spa::param::format::FormatProperties::MediaSubtype,
+                Id,
+                spa::param::format::MediaSubtype::Raw
+            ),
+            spa::pod::property!(
+                spa::param::format::FormatProperties::VideoFormat,
+                Choice,
+                Enum,
+                Id,
+                VideoFormat::BGRx,
+                VideoFormat::BGRx,
+                VideoFormat::BGRA,
+                VideoFormat::RGBx,
+                VideoFormat::RGBA,
+                VideoFormat::RGB,
+                VideoFormat::BGR
+            ),
+            spa::pod::property!(
+                spa::param::format::FormatProperties::VideoSize,
+                Choice,
+                Range,
+                Rectangle,
+                spa::utils::Rectangle {
+                    width: 1920,
+                    height: 1080
+                },
+                spa::utils::Rectangle {
+                    width: 1,
+                    height: 1
+                },
+                spa::utils::Rectangle {
+                    width: 8192,
+                    height: 8192
+                }
+            ),
+            spa::pod::property!(
+                spa::param::format::FormatProperties::VideoFramerate,
+                Choice,
+                Range,
+                Fraction,
+                spa::utils::Fraction { num: 30, denom: 1 },
+                spa::utils::Fraction { num: 0, denom: 1 },
+                spa::utils::Fraction {
+                    num: 1000,
+                    denom: 1
+                }
+            ),
+        );
+        spa::pod::serialize::PodSerializer::serialize(
+            std::io::Cursor::new(Vec::new()),
+            &spa::pod::Value::Object(obj)
+        )
+        .map(|(cursor, _)| cursor.into_inner())
+        .map_err(|e| Error::ScreenCast(format!("{e:?}")))
+    }
+
+    /// Read frames until the stream ends or `quit` is sent to
+    fn run(
+        fd: OwnedFd,
+        node: u32,
+        frames: SyncSender<Frame>,
+        quit: pw::channel::Receiver<()>
+    ) -> Result<(), Error> {
+        let pw_error = |e: pw::Error| Error::ScreenCast(e.to_string());
+        pw::init();
+        let mainloop = pw::main_loop::MainLoop::new(None).map_err(pw_error)?;
+        let context = pw::context::Context::new(&mainloop).map_err(pw_error)?;
+        let core = context.connect_fd(fd, None).map_err(pw_error)?;
+        let _quit = quit.attach(mainloop.loop_(), {
+            let mainloop = mainloop.clone();
+            move |_| mainloop.quit()
+        });
+
+        let stream =
+            pw::stream::Stream::new(&core, "asusd-user-ambient", pw::properties::properties! {
+                *pw::keys::MEDIA_TYPE => "Video",
+                *pw::keys::MEDIA_CATEGORY => "Capture",
+                *pw::keys::MEDIA_ROLE => "Screen",
+            })
+            .map_err(pw_error)?;
+        let _listener = stream
+            .add_local_listener_with_user_data(VideoInfoRaw::default())
+            .state_changed({
+                let mainloop = mainloop.clone();
+                move |_, _, _, state| match state {
+                    pw::stream::StreamState::Error(e) => {
+                        warn!("Ambient: PipeWire stream error: {e}");
+                        mainloop.quit();
+                    }
+                    pw::stream::StreamState::Unconnected => mainloop.quit(),
+                    _ => {}
+                }
+            })
+            .param_changed(|_, format, id, param| {
+                if let Some(param) = param {
+                    if id == spa::param::ParamType::Format.as_raw() && format.parse(param).is_err()
+                    {
+                        warn!("Ambient: could not parse the screen cast format");
+                    }
+                }
+            })
+            .process(move |stream, format| {
+                let Some(mut buffer) = stream.dequeue_buffer() else {
+                    return;
+                };
+                let Some(order) = pixel_order(format.format()) else {
+                    return;
+                };
+                let Some(data) = buffer.datas_mut().first_mut() else {
+                    return;
+                };
+                let chunk = data.chunk();
+                let (offset, size, stride) = (
+                    chunk.offset() as usize,
+                    chunk.size() as usize,
+                    chunk.stride()
+                );
+                // A negative stride is a bottom up image, not used by screen casts
+                let Ok(stride) = usize::try_from(stride) else {
+                    return;
+                };
+                let Some(bytes) = data.data().and_then(|d| d.get(offset..offset + size)) else {
+                    return;
+                };
+                let (width, height) = (format.size().width as usize, format.size().height as usize);
+                match Frame::from_raw(width, height, stride, bytes, order) {
+                    // If the last frame hasn't been taken this one is dropped
+                    Ok(frame) => {
+                        frames.try_send(frame).ok();
+                    }
+                    Err(e) => warn!("Ambient: {e}")
+                }
+            })
+            .register()
+            .map_err(pw_error)?;
+
+        let params = format_params()?;
+        let mut params = [
+            Pod::from_bytes(&params)
+                .ok_or_else(|| Error::ScreenCast("Invalid format params".to_owned()))?
+        ];
+        stream
+            .connect(
+                spa::utils::Direction::Input,
+                Some(node),
+                pw::stream::StreamFlags::AUTOCONNECT | pw::stream::StreamFlags::MAP_BUFFERS,
+                &mut params
+            )
+            .map_err(pw_error)?;
+        mainloop.run();
+        Ok(())
+    }
+}
+
+/// Without PipeWire there is never a stream, `PortalCapture` stops before
+/// the portal is asked
+#[cfg(not(feature = "pipewire"))]
+mod video {
+    use std::os::fd::OwnedFd;
+    use std::sync::mpsc::Receiver;
+
+    use crate::ambient::Frame;
+    use crate::error::Error;
+
+    #[allow(dead_code)]
+    pub struct VideoStream {
+        pub frames: Receiver<Frame>
+    }
+
+    impl VideoStream {
+        pub fn new(_: OwnedFd, _: u32) -> Result<Self, Error> {
+            Err(Error::ScreenCastUnavailable(
+                "asusd-user was built without the pipewire feature".to_owned()
+            ))
+        }
+    }
+}
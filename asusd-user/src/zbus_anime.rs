@@ -65,6 +65,9 @@ trait Daemon {
     /// RemoveItem method
     fn remove_item(&self, index: u32) -> zbus::Result<String>;
 
+    /// ShowMessage method
+    fn show_message(&self, message: &str, font: &str, repeats: u32) -> zbus::Result<()>;
+
     /// SetState method
     fn set_state(&self, on: bool) -> zbus::Result<()>;
 }
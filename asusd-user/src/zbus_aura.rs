@@ -0,0 +1,45 @@
+//! # `DBus` interface proxy for: `xyz.ljones.Asusd`
+//!
+//! The user daemon's per-key effect controller at `/xyz/ljones/Aura` on the
+//! session bus.
+//!
+//! This `DBus` object implements
+//! [standard `DBus` interfaces](https://dbus.freedesktop.org/doc/dbus-specification.html),
+//! (`org.freedesktop.DBus.*`) for which the following zbus proxies can be used:
+//!
+//! * [`zbus::fdo::PeerProxy`]
+//! * [`zbus::fdo::IntrospectableProxy`]
+//! * [`zbus::fdo::PropertiesProxy`]
+
+use zbus::proxy;
+
+#[proxy(interface = "xyz.ljones.Asusd", default_path = "/xyz/ljones/Aura")]
+trait Daemon {
+    /// InsertEffect method, `effect` is an `Effect` as RON
+    fn insert_effect(&self, index: u32, effect: &str) -> zbus::Result<String>;
+
+    /// RemoveItem method
+    fn remove_item(&self, index: u32) -> zbus::Result<String>;
+
+    /// ListEffects method
+    fn list_effects(&self) -> zbus::Result<Vec<String>>;
+
+    /// ListConfigs method
+    fn list_configs(&self) -> zbus::Result<Vec<String>>;
+
+    /// SelectConfig method
+    fn select_config(&self, name: &str) -> zbus::Result<String>;
+
+    /// SetState method
+    fn set_state(&self, on: bool) -> zbus::Result<()>;
+
+    /// Paused property
+    #[zbus(property)]
+    fn paused(&self) -> zbus::Result<bool>;
+
+    /// FrameRate property
+    #[zbus(property)]
+    fn frame_rate(&self) -> zbus::Result<u32>;
+    #[zbus(property)]
+    fn set_frame_rate(&self, value: u32) -> zbus::Result<()>;
+}
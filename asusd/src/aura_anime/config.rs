@@ -4,11 +4,14 @@ use config_traits::{StdConfig, StdConfigLoad};
 use rog_anime::error::AnimeError;
 use rog_anime::usb::Brightness;
 use rog_anime::{
-    ActionData, ActionLoader, AnimTime, Animations, AnimeType, DeviceState, Fade, Vec2
+    ActionData, ActionLoader, AnimTime, Animations, AnimeType, DeviceState, Fade, FrameCache,
+    Playback, Vec2
 };
 use serde::{Deserialize, Serialize};
 
 const CONFIG_FILE: &str = "anime.ron";
+/// Converted gif frames are kept here between starts
+const FRAME_CACHE_DIR: &str = "/var/cache/asusd/anime";
 
 #[derive(Debug, Clone, Deserialize, Serialize, Default)]
 pub struct AniMeConfigCached {
@@ -24,27 +27,28 @@ impl AniMeConfigCached {
         config: &AniMeConfig,
         anime_type: AnimeType
     ) -> Result<(), AnimeError> {
+        let cache = FrameCache::new(FRAME_CACHE_DIR);
         let mut sys = Vec::with_capacity(config.system.len());
         for ani in &config.system {
-            sys.push(ActionData::from_anime_action(anime_type, ani)?);
+            sys.push(cache.action_data(anime_type, ani)?);
         }
         self.system = sys;
 
         let mut boot = Vec::with_capacity(config.boot.len());
         for ani in &config.boot {
-            boot.push(ActionData::from_anime_action(anime_type, ani)?);
+            boot.push(cache.action_data(anime_type, ani)?);
         }
         self.boot = boot;
 
         let mut wake = Vec::with_capacity(config.wake.len());
         for ani in &config.wake {
-            wake.push(ActionData::from_anime_action(anime_type, ani)?);
+            wake.push(cache.action_data(anime_type, ani)?);
         }
         self.wake = wake;
 
         let mut shutdown = Vec::with_capacity(config.shutdown.len());
         for ani in &config.shutdown {
-            shutdown.push(ActionData::from_anime_action(anime_type, ani)?);
+            shutdown.push(cache.action_data(anime_type, ani)?);
         }
         self.shutdown = shutdown;
         Ok(())
@@ -68,7 +72,10 @@ pub struct AniMeConfig {
     pub off_when_suspended: bool,
     pub off_when_lid_closed: bool,
     pub brightness_on_battery: Brightness,
-    pub builtin_anims: Animations
+    pub builtin_anims: Animations,
+    /// Rate, loops and cross-fade for playing the actions
+    #[serde(default)]
+    pub playback: Playback
 }
 
 impl Default for AniMeConfig {
@@ -87,7 +94,8 @@ impl Default for AniMeConfig {
             off_when_suspended: true,
             off_when_lid_closed: true,
             brightness_on_battery: Brightness::Low,
-            builtin_anims: Animations::default()
+            builtin_anims: Animations::default(),
+            playback: Playback::default()
         }
     }
 }
@@ -5,7 +5,7 @@ pub mod trait_impls;
 use std::convert::TryFrom;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use std::thread::sleep;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use config_traits::StdConfig;
 use log::{debug, error, info, warn};
@@ -13,14 +13,20 @@ use rog_anime::usb::{
     pkt_flush, pkt_set_brightness, pkt_set_enable_display, pkt_set_enable_powersave_anim,
     pkts_for_init, Brightness
 };
-use rog_anime::{ActionData, AnimeDataBuffer, AnimePacketType};
+use rog_anime::{
+    ActionData, AnimeDataBuffer, AnimePacketType, AnimePlayer, ClockTime, Crossfade, SystemMonitor
+};
 use rog_platform::hid_raw::HidRaw;
 use rog_platform::usb_raw::USBRaw;
 use tokio::sync::Mutex;
+use tokio::time::sleep;
 
 use self::config::{AniMeConfig, AniMeConfigCached};
 use crate::error::RogError;
 
+/// How often frames are written while blending in to the next action
+const CROSSFADE_FRAME_TIME: Duration = Duration::from_millis(33);
+
 #[derive(Debug, Clone)]
 pub struct AniMe {
     hid: Option<Arc<Mutex<HidRaw>>>,
@@ -103,6 +109,71 @@ impl AniMe {
         self.write_bytes(&pkt_flush()).await
     }
 
+    /// Write frames from `render` until `run_time` is up. Returns `false` if
+    /// the thread was asked to exit first.
+    async fn run_generated(
+        &self,
+        thread_exit: &AtomicBool,
+        fade: &mut Crossfade,
+        run_time: Option<Duration>,
+        frame_time: Duration,
+        mut render: impl FnMut() -> rog_anime::error::Result<AnimeDataBuffer>
+    ) -> bool {
+        let start = Instant::now();
+        while run_time.is_none_or(|t| start.elapsed() < t) {
+            if thread_exit.load(Ordering::SeqCst) {
+                return false;
+            }
+            let frame_start = Instant::now();
+            match render() {
+                Ok(frame) => {
+                    self.write_data_buffer(fade.apply(frame, start.elapsed()))
+                        .await
+                        .map_err(|e| error!("{}", e))
+                        .ok();
+                }
+                Err(e) => {
+                    error!("AniMe generated frame failed: {e}");
+                    break;
+                }
+            }
+            sleep(frame_time.saturating_sub(frame_start.elapsed())).await;
+        }
+        true
+    }
+
+    /// Write each frame of `player` in order as it falls due, skipping frames
+    /// if running late. Returns `false` if the thread was asked to exit first.
+    async fn play_animation(
+        &self,
+        thread_exit: &AtomicBool,
+        fade: &mut Crossfade,
+        player: &AnimePlayer<'_>
+    ) -> bool {
+        let start = Instant::now();
+        let mut shown = None;
+        while let Some(number) = player.frame_at(start.elapsed()) {
+            if thread_exit.load(Ordering::SeqCst) {
+                return false;
+            }
+            let blending = fade.blending(start.elapsed());
+            if shown != Some(number) || blending {
+                let frame = fade.apply(player.frame(number, start.elapsed()), start.elapsed());
+                self.write_data_buffer(frame)
+                    .await
+                    .map_err(|err| warn!("rog_anime::run_animation:callback {}", err))
+                    .ok();
+                shown = Some(number);
+            }
+            let mut next = player.next_due(number);
+            if blending {
+                next = next.min(start.elapsed() + CROSSFADE_FRAME_TIME);
+            }
+            sleep(next.saturating_sub(start.elapsed())).await;
+        }
+        true
+    }
+
     pub async fn set_builtins_enabled(
         &self,
         enabled: bool,
@@ -136,7 +207,10 @@ impl AniMe {
 
         let thread_exit = self.thread_exit.clone();
         let thread_running = self.thread_running.clone();
-        let anime_type = self.config.lock().await.anime_type;
+        let (anime_type, playback) = {
+            let config = self.config.lock().await;
+            (config.anime_type, config.playback)
+        };
         let inner = self.clone();
 
         // Loop rules:
@@ -162,49 +236,113 @@ impl AniMe {
             info!("AniMe no previous system thread running (now)");
             thread_exit.store(false, Ordering::SeqCst);
             thread_running.store(true, Ordering::SeqCst);
+            let mut fade = Crossfade::new(playback.crossfade);
             'main: loop {
                 for action in &actions {
                     if thread_exit.load(Ordering::SeqCst) {
                         break 'main;
                     }
+                    fade.next_action();
                     match action {
                         ActionData::Animation(frames) => {
-                            // TODO: sort all this out
-                            rog_anime::run_animation(frames, &|frame| {
-                                if thread_exit.load(Ordering::Acquire) {
-                                    info!("rog-anime: animation sub-loop was asked to exit");
-                                    return Ok(true); // Do safe exit
-                                }
-                                let inner = inner.clone();
-                                tokio::task::spawn_local(async move {
-                                    inner
-                                        .write_data_buffer(frame)
-                                        .await
-                                        .map_err(|err| {
-                                            warn!("rog_anime::run_animation:callback {}", err);
-                                        })
-                                        .ok();
-                                });
-                                Ok(false) // Don't exit yet
-                            });
-                            if thread_exit.load(Ordering::Acquire) {
+                            let player = AnimePlayer::new(frames, &playback);
+                            if !inner.play_animation(&thread_exit, &mut fade, &player).await {
                                 info!("rog-anime: sub-loop exited and main loop exiting now");
                                 break 'main;
                             }
                         }
                         ActionData::Image(image) => {
                             once = false;
+                            // Only written more than once if blending from the action before
+                            if fade.blending(Duration::ZERO)
+                                && !inner
+                                    .run_generated(
+                                        &thread_exit,
+                                        &mut fade,
+                                        Some(playback.crossfade),
+                                        CROSSFADE_FRAME_TIME,
+                                        || Ok(image.as_ref().clone())
+                                    )
+                                    .await
+                            {
+                                break 'main;
+                            }
                             inner
-                                .write_data_buffer(image.as_ref().clone())
+                                .write_data_buffer(
+                                    fade.apply(image.as_ref().clone(), playback.crossfade)
+                                )
                                 .await
                                 .map_err(|e| error!("{}", e))
                                 .ok();
                         }
-                        ActionData::Pause(duration) => sleep(*duration),
-                        ActionData::AudioEq
-                        | ActionData::SystemInfo
-                        | ActionData::TimeDate
-                        | ActionData::Matrix => {}
+                        ActionData::Pause(duration) => sleep(*duration).await,
+                        ActionData::TimeDate(clock) => {
+                            let render = || clock.render(anime_type, &ClockTime::now());
+                            if !inner
+                                .run_generated(
+                                    &thread_exit,
+                                    &mut fade,
+                                    clock.run_time(),
+                                    clock.frame_time(),
+                                    render
+                                )
+                                .await
+                            {
+                                break 'main;
+                            }
+                        }
+                        ActionData::SystemInfo(info) => {
+                            let mut monitor = SystemMonitor::new();
+                            let render = || info.render(anime_type, &monitor.sample());
+                            if !inner
+                                .run_generated(
+                                    &thread_exit,
+                                    &mut fade,
+                                    info.run_time(),
+                                    info.frame_time(),
+                                    render
+                                )
+                                .await
+                            {
+                                break 'main;
+                            }
+                        }
+                        ActionData::Matrix(rain) => {
+                            let seed = SystemTime::now()
+                                .duration_since(UNIX_EPOCH)
+                                .map(|d| d.as_nanos() as u64)
+                                .unwrap_or_default();
+                            let mut frames = rain.start(anime_type, seed);
+                            if !inner
+                                .run_generated(
+                                    &thread_exit,
+                                    &mut fade,
+                                    rain.run_time(),
+                                    rain.frame_time(),
+                                    || frames.next_frame()
+                                )
+                                .await
+                            {
+                                break 'main;
+                            }
+                        }
+                        ActionData::Text(text) => {
+                            let start = Instant::now();
+                            if !inner
+                                .run_generated(
+                                    &thread_exit,
+                                    &mut fade,
+                                    text.run_time(),
+                                    text.frame_time(),
+                                    || text.frame(start.elapsed())
+                                )
+                                .await
+                            {
+                                break 'main;
+                            }
+                        }
+                        // Audio is only captured in the user session by asusd-user
+                        ActionData::AudioEq(_) => {}
                     }
                 }
                 if thread_exit.load(Ordering::SeqCst) {
@@ -13,7 +13,7 @@ use zbus::proxy::CacheProperties;
 use zbus::zvariant::OwnedObjectPath;
 use zbus::{interface, Connection};
 
-use super::config::AniMeConfig;
+use super::config::{AniMeConfig, AniMeConfigCached};
 use super::AniMe;
 use crate::error::RogError;
 use crate::Reloadable;
@@ -38,6 +38,21 @@ impl AniMeZbus {
         Self(anime)
     }
 
+    /// Replace the stored config, then apply it to the device. Fails without
+    /// changing anything if the animations in the config can't be loaded.
+    pub async fn replace_config(&mut self, mut new: AniMeConfig) -> Result<(), RogError> {
+        {
+            let mut config = self.0.config.lock().await;
+            new.anime_type = config.anime_type;
+            let mut cache = AniMeConfigCached::default();
+            cache.init_from_config(&new, new.anime_type)?;
+            *config = new;
+            config.write();
+            self.0.cache = cache;
+        }
+        self.reload().await
+    }
+
     pub async fn start_tasks(
         mut self,
         connection: &Connection,
@@ -5,7 +5,7 @@ use log::{debug, info, warn};
 use rog_aura::aura_detection::LedSupportData;
 use rog_aura::keyboard::LaptopAuraPower;
 use rog_aura::{
-    AuraDeviceType, AuraEffect, AuraModeNum, AuraZone, Direction, LedBrightness, Speed, GRADIENT
+    AuraDeviceType, AuraEffect, AuraModeNum, AuraZone, Direction, Gradient, LedBrightness, Speed
 };
 use serde::{Deserialize, Serialize};
 
@@ -85,12 +85,15 @@ impl AuraConfig {
 
             if !config.support_data.basic_zones.is_empty() {
                 let mut default = vec![];
+                let rainbow = Gradient::rainbow();
+                let zones = config.support_data.basic_zones.len() as f32;
                 for (i, tmp) in config.support_data.basic_zones.iter().enumerate() {
+                    let pos = i as f32 / zones;
                     default.push(AuraEffect {
                         mode: *n,
                         zone: *tmp,
-                        colour1: *GRADIENT.get(i).unwrap_or(&GRADIENT[0]),
-                        colour2: *GRADIENT.get(GRADIENT.len() - i).unwrap_or(&GRADIENT[6]),
+                        colour1: rainbow.sample(pos),
+                        colour2: rainbow.sample(1.0 - pos),
                         speed: Speed::Med,
                         direction: Direction::Left
                     });
@@ -149,12 +152,15 @@ impl AuraConfig {
     /// exists.
     pub fn create_multizone_default(&mut self) -> Result<(), RogError> {
         let mut default = vec![];
+        let rainbow = Gradient::rainbow();
+        let zones = self.support_data.basic_zones.len() as f32;
         for (i, tmp) in self.support_data.basic_zones.iter().enumerate() {
+            let pos = i as f32 / zones;
             default.push(AuraEffect {
                 mode: self.current_mode,
                 zone: *tmp,
-                colour1: *GRADIENT.get(i).unwrap_or(&GRADIENT[0]),
-                colour2: *GRADIENT.get(GRADIENT.len() - i).unwrap_or(&GRADIENT[6]),
+                colour1: rainbow.sample(pos),
+                colour2: rainbow.sample(1.0 - pos),
                 speed: Speed::Med,
                 direction: Direction::Left
             });
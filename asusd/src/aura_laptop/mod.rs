@@ -5,7 +5,10 @@ use config_traits::StdConfig;
 use log::info;
 use rog_aura::keyboard::{AuraLaptopUsbPackets, LedUsbPackets};
 use rog_aura::usb::{AURA_LAPTOP_LED_APPLY, AURA_LAPTOP_LED_SET};
-use rog_aura::{AuraDeviceType, AuraEffect, LedBrightness, PowerZones, AURA_LAPTOP_LED_MSG_LEN};
+use rog_aura::{
+    AuraDeviceType, AuraEffect, DeviceCorrection, LedBrightness, PowerZones,
+    AURA_LAPTOP_LED_MSG_LEN
+};
 use rog_platform::hid_raw::HidRaw;
 use rog_platform::keyboard_led::KeyboardBacklight;
 use tokio::sync::{Mutex, MutexGuard};
@@ -69,30 +72,47 @@ impl Aura {
             if let Some(multizones) = config.multizone.as_mut() {
                 if let Some(set) = multizones.get(&mode) {
                     for mode in set.clone() {
-                        self.write_effect_and_apply(config.led_type, &mode).await?;
+                        self.write_effect_and_apply(
+                            config.led_type,
+                            &config.support_data.colour_correction,
+                            &mode
+                        )
+                        .await?;
                     }
                 }
             }
         } else {
             let mode = config.current_mode;
             if let Some(effect) = config.builtins.get(&mode).cloned() {
-                self.write_effect_and_apply(config.led_type, &effect)
-                    .await?;
+                self.write_effect_and_apply(
+                    config.led_type,
+                    &config.support_data.colour_correction,
+                    &effect
+                )
+                .await?;
             }
         }
 
         Ok(())
     }
 
-    /// Write the AuraEffect to the device. Will lock `backlight` or `hid`.
+    /// Write the AuraEffect to the device, with the colours corrected for the
+    /// zone. Will lock `backlight` or `hid`.
     ///
     /// If per-key or software-mode is active it must be marked as disabled in
     /// config.
     pub async fn write_effect_and_apply(
         &self,
         dev_type: AuraDeviceType,
+        correction: &DeviceCorrection,
         mode: &AuraEffect
     ) -> Result<(), RogError> {
+        let correction = correction.for_zone(mode.zone);
+        let mode = &AuraEffect {
+            colour1: correction.apply(mode.colour1),
+            colour2: correction.apply(mode.colour2),
+            ..mode.clone()
+        };
         if matches!(dev_type, AuraDeviceType::LaptopKeyboardTuf) {
             if let Some(platform) = &self.backlight {
                 let buf = [
@@ -9,6 +9,7 @@ use zbus::object_server::SignalEmitter;
 use zbus::zvariant::OwnedObjectPath;
 use zbus::{interface, Connection};
 
+use super::config::AuraConfig;
 use super::Aura;
 use crate::error::RogError;
 use crate::{CtrlTask, Reloadable};
@@ -19,11 +20,42 @@ pub const AURA_ZBUS_PATH: &str = "/xyz/ljones";
 #[derive(Clone)]
 pub struct AuraZbus(Aura);
 
+fn check_effect_supported(config: &AuraConfig, effect: &AuraEffect) -> Result<(), ZbErr> {
+    if !config.support_data.basic_modes.contains(&effect.mode)
+        || effect.zone != AuraZone::None && !config.support_data.basic_zones.contains(&effect.zone)
+    {
+        return Err(ZbErr::NotSupported(format!(
+            "The Aura effect is not supported: {effect:?}"
+        )));
+    }
+    Ok(())
+}
+
 impl AuraZbus {
     pub fn new(aura: Aura) -> Self {
         Self(aura)
     }
 
+    /// A copy of the current config, including the detected device data
+    pub async fn config(&self) -> AuraConfig {
+        self.0.lock_config().await.clone()
+    }
+
+    /// Replace the stored config while keeping the detected device data, then
+    /// apply it to the device
+    pub async fn replace_config(&mut self, mut new: AuraConfig) -> Result<(), RogError> {
+        {
+            let mut config = self.0.lock_config().await;
+            new.led_type = config.led_type;
+            new.support_data = config.support_data.clone();
+            new.config_name = config.config_name.clone();
+            new.per_key_mode_active = false;
+            *config = new;
+            config.write();
+        }
+        self.reload().await
+    }
+
     pub async fn start_tasks(
         mut self,
         connection: &Connection,
@@ -158,17 +190,14 @@ impl AuraZbus {
     #[zbus(property)]
     async fn set_led_mode_data(&mut self, effect: AuraEffect) -> Result<(), ZbErr> {
         let mut config = self.0.config.lock().await;
-        if !config.support_data.basic_modes.contains(&effect.mode)
-            || effect.zone != AuraZone::None
-                && !config.support_data.basic_zones.contains(&effect.zone)
-        {
-            return Err(ZbErr::NotSupported(format!(
-                "The Aura effect is not supported: {effect:?}"
-            )));
-        }
+        check_effect_supported(&config, &effect)?;
 
         self.0
-            .write_effect_and_apply(config.led_type, &effect)
+            .write_effect_and_apply(
+                config.led_type,
+                &config.support_data.colour_correction,
+                &effect
+            )
             .await?;
         if config.brightness == LedBrightness::Off {
             config.brightness = LedBrightness::Med;
@@ -180,6 +209,22 @@ impl AuraZbus {
         Ok(())
     }
 
+    /// Show an Aura effect without storing it. This is for effects a client
+    /// updates many times a second, the stored effect is left as it was and
+    /// is shown again by setting `LedMode`.
+    async fn apply_effect(&self, effect: AuraEffect) -> Result<(), ZbErr> {
+        let config = self.0.config.lock().await;
+        check_effect_supported(&config, &effect)?;
+        self.0
+            .write_effect_and_apply(
+                config.led_type,
+                &config.support_data.colour_correction,
+                &effect
+            )
+            .await?;
+        Ok(())
+    }
+
     /// Get the data set for every mode available
     async fn all_mode_data(&self) -> BTreeMap<AuraModeNum, AuraEffect> {
         let config = self.0.config.lock().await;
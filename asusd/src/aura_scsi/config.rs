@@ -1,20 +1,23 @@
 use std::collections::BTreeMap;
 
 use config_traits::{StdConfig, StdConfigLoad};
-use rog_aura::AuraDeviceType;
+use rog_aura::{AuraDeviceType, ColourCorrection};
 use rog_scsi::{AuraEffect, AuraMode};
 use serde::{Deserialize, Serialize};
 
 const CONFIG_FILE: &str = "scsi.ron";
 
 /// Config for base system actions for the anime display
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct ScsiConfig {
     #[serde(skip)]
     pub dev_type: AuraDeviceType,
     pub enabled: bool,
     pub current_mode: AuraMode,
-    pub modes: BTreeMap<AuraMode, AuraEffect>
+    pub modes: BTreeMap<AuraMode, AuraEffect>,
+    /// Gamma and white balance so colours match the laptop keyboard
+    #[serde(default)]
+    pub colour_correction: ColourCorrection
 }
 
 impl ScsiConfig {
@@ -92,7 +95,8 @@ impl Default for ScsiConfig {
                     AuraMode::DoubleFade,
                     AuraEffect::default_with_mode(AuraMode::DoubleFade)
                 )
-            ])
+            ]),
+            colour_correction: ColourCorrection::default()
         }
     }
 }
@@ -1,6 +1,7 @@
 use std::sync::Arc;
 
 use config::ScsiConfig;
+use rog_aura::ColourCorrection;
 use rog_scsi::{AuraEffect, Device, Task};
 use tokio::sync::{Mutex, MutexGuard};
 
@@ -24,8 +25,20 @@ impl ScsiAura {
         self.config.lock().await
     }
 
-    pub async fn write_effect(&self, effect: &AuraEffect) -> Result<(), RogError> {
-        let tasks: Vec<Task> = effect.into();
+    /// Write the effect with the colours corrected for the disk
+    pub async fn write_effect(
+        &self,
+        correction: &ColourCorrection,
+        effect: &AuraEffect
+    ) -> Result<(), RogError> {
+        let effect = AuraEffect {
+            colour1: correction.apply(effect.colour1),
+            colour2: correction.apply(effect.colour2),
+            colour3: correction.apply(effect.colour3),
+            colour4: correction.apply(effect.colour4),
+            ..effect.clone()
+        };
+        let tasks: Vec<Task> = (&effect).into();
         for task in &tasks {
             self.device.lock().await.perform(task).ok();
         }
@@ -38,7 +51,7 @@ impl ScsiAura {
         let config = self.config.lock().await;
         let mode = config.current_mode;
         if let Some(effect) = config.modes.get(&mode) {
-            self.write_effect(effect).await?;
+            self.write_effect(&config.colour_correction, effect).await?;
         }
         Ok(())
     }
@@ -8,6 +8,7 @@ use zbus::fdo::Error as ZbErr;
 use zbus::zvariant::OwnedObjectPath;
 use zbus::{interface, Connection};
 
+use super::config::ScsiConfig;
 use super::ScsiAura;
 use crate::error::RogError;
 
@@ -19,6 +20,17 @@ impl ScsiZbus {
         Self(scsi)
     }
 
+    /// Replace the stored config, then apply it to the device
+    pub async fn replace_config(&mut self, mut new: ScsiConfig) -> Result<(), RogError> {
+        {
+            let mut config = self.0.lock_config().await;
+            new.dev_type = config.dev_type;
+            *config = new;
+            config.write();
+        }
+        self.0.do_initialization().await
+    }
+
     pub async fn start_tasks(
         self,
         connection: &Connection,
@@ -66,9 +78,10 @@ impl ScsiZbus {
     #[zbus(property)]
     async fn set_led_mode(&self, mode: AuraMode) -> Result<(), zbus::Error> {
         let mut config = self.0.lock_config().await;
+        let correction = config.colour_correction;
         if let Some(effect) = config.get_effect(mode) {
             self.0
-                .write_effect(effect)
+                .write_effect(&correction, effect)
                 .await
                 .map_err(|e| zbus::Error::Failure(format!("{e:?}")))?;
         } else {
@@ -100,7 +113,8 @@ impl ScsiZbus {
     /// the effect is stored and config written to disk.
     #[zbus(property)]
     async fn set_led_mode_data(&mut self, effect: AuraEffect) -> Result<(), ZbErr> {
-        self.0.write_effect(&effect).await?;
+        let correction = self.0.config.lock().await.colour_correction;
+        self.0.write_effect(&correction, &effect).await?;
 
         let mut config = self.0.config.lock().await;
         config.save_effect(effect);
@@ -5,7 +5,7 @@ use serde::{Deserialize, Serialize};
 const CONFIG_FILE: &str = "slash.ron";
 
 /// Config for base system actions for the anime display
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct SlashConfig {
     #[serde(skip)]
     pub slash_type: SlashType,
@@ -8,6 +8,7 @@ use rog_slash::{DeviceState, SlashMode};
 use zbus::zvariant::OwnedObjectPath;
 use zbus::{interface, Connection};
 
+use super::config::SlashConfig;
 use super::Slash;
 use crate::error::RogError;
 use crate::Reloadable;
@@ -20,6 +21,17 @@ impl SlashZbus {
         Self(slash)
     }
 
+    /// Replace the stored config, then apply it to the device
+    pub async fn replace_config(&mut self, mut new: SlashConfig) -> Result<(), RogError> {
+        {
+            let mut config = self.0.lock_config().await;
+            new.slash_type = config.slash_type;
+            *config = new;
+            config.write();
+        }
+        self.reload().await
+    }
+
     pub async fn start_tasks(
         mut self,
         connection: &Connection,
@@ -130,9 +142,9 @@ impl SlashZbus {
     }
 
     #[zbus(property)]
-    async fn mode(&self) -> zbus::fdo::Result<u8> {
+    async fn mode(&self) -> zbus::fdo::Result<SlashMode> {
         let config = self.0.lock_config().await;
-        Ok(config.display_interval)
+        Ok(config.display_mode)
     }
 
     /// Set interval between slash animations (0-255)
@@ -0,0 +1,509 @@
+//! Time based battery charge limits. A schedule can raise the charge limit
+//! during weekly windows, or release it just in time for the battery to be
+//! full at a set time. The limit the user set is restored afterwards.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use chrono::{Datelike, Duration, NaiveDateTime, NaiveTime};
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+
+use crate::error::RogError;
+use crate::time_of_day::TimeOfDay;
+
+pub const POWER_SUPPLY_ROOT: &str = "/sys/class/power_supply";
+/// Used until a charge rate has been observed, percent per hour
+pub const DEFAULT_CHARGE_RATE: f32 = 30.0;
+/// Extra time allowed when releasing for a full-by target, as charging
+/// slows down towards 100%
+const FULL_BY_MARGIN_MINUTES: i64 = 20;
+/// The charge level must rise by this much before a rate is calculated
+const RATE_SAMPLE_PERCENT: u8 = 5;
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Day {
+    Mon,
+    Tue,
+    Wed,
+    Thu,
+    Fri,
+    Sat,
+    Sun
+}
+
+impl From<chrono::Weekday> for Day {
+    fn from(day: chrono::Weekday) -> Self {
+        match day {
+            chrono::Weekday::Mon => Day::Mon,
+            chrono::Weekday::Tue => Day::Tue,
+            chrono::Weekday::Wed => Day::Wed,
+            chrono::Weekday::Thu => Day::Thu,
+            chrono::Weekday::Fri => Day::Fri,
+            chrono::Weekday::Sat => Day::Sat,
+            chrono::Weekday::Sun => Day::Sun
+        }
+    }
+}
+
+fn on_day(days: &[Day], day: chrono::Weekday) -> bool {
+    days.is_empty() || days.contains(&day.into())
+}
+
+/// Raise the charge limit between two times on the given days
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct ChargeWindow {
+    /// Days the window starts on, empty for every day
+    pub days: Vec<Day>,
+    pub start: TimeOfDay,
+    /// If earlier than `start` the window ends the following day
+    pub end: TimeOfDay,
+    /// Charge limit in percent, 20-100
+    pub limit: u8
+}
+
+impl ChargeWindow {
+    pub fn contains(&self, now: NaiveDateTime) -> bool {
+        let time = TimeOfDay::from(now.time()).minutes();
+        let (start, end) = (self.start.minutes(), self.end.minutes());
+        if start <= end {
+            on_day(&self.days, now.weekday()) && start <= time && time < end
+        } else {
+            (on_day(&self.days, now.weekday()) && time >= start)
+                || (on_day(&self.days, now.weekday().pred()) && time < end)
+        }
+    }
+}
+
+/// Have the battery full by a time on the given days. The limit is held until
+/// there is just enough time to charge, then released to 100%.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct FullBy {
+    /// Empty for every day
+    pub days: Vec<Day>,
+    pub time: TimeOfDay
+}
+
+impl FullBy {
+    /// The next time after `now` that the battery should be full
+    pub fn next_target(&self, now: NaiveDateTime) -> Option<NaiveDateTime> {
+        let time = NaiveTime::from_hms_opt(self.time.hour as u32, self.time.minute as u32, 0)?;
+        (0..=7)
+            .map(|d| (now.date() + Duration::days(d)).and_time(time))
+            .find(|t| *t > now && on_day(&self.days, t.weekday()))
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct ChargeSchedule {
+    pub enabled: bool,
+    pub windows: Vec<ChargeWindow>,
+    pub full_by: Vec<FullBy>,
+    /// Learned while charging, percent per hour
+    pub charge_rate: f32,
+    /// The schedule has raised the limit and must restore it. Saved so that
+    /// the limit is still restored if asusd restarts while it is raised.
+    pub raised: bool
+}
+
+impl Default for ChargeSchedule {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            windows: Vec::new(),
+            full_by: Vec::new(),
+            charge_rate: DEFAULT_CHARGE_RATE,
+            raised: false
+        }
+    }
+}
+
+impl ChargeSchedule {
+    /// The user's own limit: `base` while the schedule has raised the limit,
+    /// otherwise the current `limit`
+    pub fn user_limit(&self, limit: u8, base: u8) -> u8 {
+        if self.raised {
+            base
+        } else {
+            limit
+        }
+    }
+
+    /// The highest limit of all windows active at `now`
+    pub fn window_limit(&self, now: NaiveDateTime) -> Option<u8> {
+        self.windows
+            .iter()
+            .filter(|w| w.contains(now))
+            .map(|w| w.limit)
+            .max()
+    }
+
+    /// If the limit must be released now to be full in time, returns the
+    /// target it must be held released until
+    pub fn full_by_release(&self, now: NaiveDateTime, capacity: u8) -> Option<NaiveDateTime> {
+        let rate = if self.charge_rate > 0.0 {
+            self.charge_rate
+        } else {
+            DEFAULT_CHARGE_RATE
+        };
+        let needed =
+            (100.0 - capacity.min(100) as f32) / rate * 60.0 + FULL_BY_MARGIN_MINUTES as f32;
+        self.full_by
+            .iter()
+            .filter_map(|f| f.next_target(now))
+            .filter(|target| (*target - now).num_minutes() as f32 <= needed)
+            .min()
+    }
+}
+
+/// Charging state read from the `power_supply` class
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct ChargeState {
+    pub online: bool,
+    pub capacity: u8
+}
+
+pub type Clock = Arc<dyn Fn() -> NaiveDateTime + Send + Sync>;
+
+pub fn local_clock() -> Clock {
+    Arc::new(|| chrono::Local::now().naive_local())
+}
+
+/// Tracks the schedule state that doesn't need persisting and decides when
+/// the charge limit changes. The `power_supply` root and clock are injectable
+/// for testing.
+#[derive(Clone)]
+pub struct ChargeScheduler {
+    power_supply: PathBuf,
+    clock: Clock,
+    /// A full-by target the limit is released until
+    released_until: Option<NaiveDateTime>,
+    /// Start of the current charge rate measurement
+    rate_sample: Option<(NaiveDateTime, u8)>
+}
+
+impl ChargeScheduler {
+    pub fn new(power_supply: &Path, clock: Clock) -> Self {
+        Self {
+            power_supply: power_supply.to_path_buf(),
+            clock,
+            released_until: None,
+            rate_sample: None
+        }
+    }
+
+    pub fn read_state(&self) -> Result<ChargeState, RogError> {
+        let mut state = ChargeState::default();
+        let mut found_battery = false;
+        for entry in fs::read_dir(&self.power_supply)
+            .map_err(|e| RogError::Read(self.power_supply.to_string_lossy().to_string(), e))?
+        {
+            let path = entry?.path();
+            let read = |attr: &str| {
+                fs::read_to_string(path.join(attr))
+                    .map(|s| s.trim().to_owned())
+                    .unwrap_or_default()
+            };
+            match read("type").as_str() {
+                "Mains" => state.online |= read("online") == "1",
+                "Battery" if !found_battery => {
+                    found_battery = true;
+                    state.capacity = read("capacity").parse().map_err(|_| {
+                        RogError::DoTask(format!("invalid capacity in {}", path.display()))
+                    })?;
+                }
+                _ => {}
+            }
+        }
+        if !found_battery {
+            return Err(RogError::NotFound("battery".to_owned()));
+        }
+        Ok(state)
+    }
+
+    /// Update the charge rate from a rise in capacity while charging
+    fn learn_rate(
+        &mut self,
+        schedule: &mut ChargeSchedule,
+        now: NaiveDateTime,
+        state: ChargeState,
+        limit: u8
+    ) {
+        if !state.online || state.capacity >= limit {
+            self.rate_sample = None;
+            return;
+        }
+        match self.rate_sample {
+            Some((_, capacity)) if state.capacity < capacity => {
+                self.rate_sample = Some((now, state.capacity));
+            }
+            Some((start, capacity)) if state.capacity - capacity >= RATE_SAMPLE_PERCENT => {
+                let hours = (now - start).num_seconds() as f32 / 3600.0;
+                if hours > 0.0 {
+                    let rate = (state.capacity - capacity) as f32 / hours;
+                    schedule.charge_rate = schedule.charge_rate * 0.7 + rate * 0.3;
+                    debug!(
+                        "Charge rate measured {rate:.1}%/h, now {:.1}",
+                        schedule.charge_rate
+                    );
+                }
+                self.rate_sample = Some((now, state.capacity));
+            }
+            Some(_) => {}
+            None => self.rate_sample = Some((now, state.capacity))
+        }
+    }
+
+    /// The limit the schedule wants right now, `None` if it does not raise it
+    pub fn scheduled_limit(&self, schedule: &ChargeSchedule) -> Option<u8> {
+        let now = (self.clock)();
+        if !schedule.enabled {
+            return None;
+        }
+        if self.released_until.is_some_and(|t| now < t) {
+            return Some(100);
+        }
+        schedule.window_limit(now)
+    }
+
+    /// Run one step of the schedule given the current limit. When the limit
+    /// is first raised it is saved to `base`, the same as
+    /// `base_charge_control_end_threshold` is for a one shot charge, and it is
+    /// restored from there afterwards. Returns the new limit if it should be
+    /// changed.
+    pub fn tick(
+        &mut self,
+        schedule: &mut ChargeSchedule,
+        limit: u8,
+        base: &mut u8
+    ) -> Result<Option<u8>, RogError> {
+        let now = (self.clock)();
+        let state = self.read_state()?;
+        self.learn_rate(schedule, now, state, limit);
+
+        if self.released_until.is_some_and(|t| now >= t) {
+            self.released_until = None;
+        }
+        if schedule.enabled && self.released_until.is_none() {
+            self.released_until = schedule.full_by_release(now, state.capacity);
+            if let Some(target) = self.released_until {
+                info!("Releasing charge limit to be full by {target}");
+            }
+        }
+
+        let user = schedule.user_limit(limit, *base);
+        match self.scheduled_limit(schedule).filter(|l| *l > user) {
+            Some(raised) => {
+                if !schedule.raised {
+                    *base = limit;
+                    schedule.raised = true;
+                }
+                Ok((limit != raised).then_some(raised))
+            }
+            None if std::mem::take(&mut schedule.raised) => Ok((*base != limit).then_some(*base)),
+            None => Ok(None)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+    use std::sync::{Arc, Mutex};
+
+    use chrono::{NaiveDate, NaiveDateTime};
+    use tempfile::TempDir;
+
+    use super::{ChargeSchedule, ChargeScheduler, ChargeWindow, Day, FullBy};
+    use crate::time_of_day::TimeOfDay;
+
+    fn fake_power_supply() -> TempDir {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        fs::create_dir_all(root.join("AC0")).unwrap();
+        fs::create_dir_all(root.join("BAT0")).unwrap();
+        fs::write(root.join("AC0/type"), "Mains\n").unwrap();
+        fs::write(root.join("BAT0/type"), "Battery\n").unwrap();
+        set_state(root, true, 60);
+        tmp
+    }
+
+    fn set_state(root: &Path, online: bool, capacity: u8) {
+        fs::write(root.join("AC0/online"), if online { "1\n" } else { "0\n" }).unwrap();
+        fs::write(root.join("BAT0/capacity"), format!("{capacity}\n")).unwrap();
+    }
+
+    // 2024-06-03 is a Monday
+    fn at(day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, 6, day)
+            .unwrap()
+            .and_hms_opt(hour, minute, 0)
+            .unwrap()
+    }
+
+    fn scheduler(root: &Path, now: &Arc<Mutex<NaiveDateTime>>) -> ChargeScheduler {
+        let now = now.clone();
+        ChargeScheduler::new(root, Arc::new(move || *now.lock().unwrap()))
+    }
+
+    #[test]
+    fn windows() {
+        let window = ChargeWindow {
+            days: vec![Day::Fri],
+            start: TimeOfDay::new(22, 0),
+            end: TimeOfDay::new(6, 0),
+            limit: 100
+        };
+        assert!(window.contains(at(7, 23, 0)));
+        assert!(window.contains(at(8, 5, 59)));
+        assert!(!window.contains(at(8, 6, 0)));
+        // Starts on Friday only
+        assert!(!window.contains(at(8, 23, 0)));
+        assert!(!window.contains(at(7, 5, 0)));
+    }
+
+    #[test]
+    fn full_by_targets() {
+        let full_by = FullBy {
+            days: vec![
+                Day::Mon,
+                Day::Tue,
+            ],
+            time: TimeOfDay::new(7, 30)
+        };
+        assert_eq!(full_by.next_target(at(3, 6, 0)), Some(at(3, 7, 30)));
+        assert_eq!(full_by.next_target(at(3, 7, 30)), Some(at(4, 7, 30)));
+        assert_eq!(full_by.next_target(at(4, 8, 0)), Some(at(10, 7, 30)));
+
+        let schedule = ChargeSchedule {
+            enabled: true,
+            full_by: vec![full_by],
+            charge_rate: 40.0,
+            ..Default::default()
+        };
+        // 20% at 40%/h is 30 minutes, plus the margin
+        assert_eq!(schedule.full_by_release(at(3, 6, 30), 80), None);
+        assert_eq!(
+            schedule.full_by_release(at(3, 6, 40), 80),
+            Some(at(3, 7, 30))
+        );
+    }
+
+    #[test]
+    fn raise_and_restore_window() {
+        let tmp = fake_power_supply();
+        let root = tmp.path();
+        let now = Arc::new(Mutex::new(at(3, 12, 0)));
+        let mut sched = scheduler(root, &now);
+        let mut schedule = ChargeSchedule {
+            enabled: true,
+            windows: vec![
+                ChargeWindow {
+                    days: Vec::new(),
+                    start: TimeOfDay::new(13, 0),
+                    end: TimeOfDay::new(14, 0),
+                    limit: 90
+                },
+            ],
+            ..Default::default()
+        };
+
+        let mut base = 0;
+        assert_eq!(sched.tick(&mut schedule, 60, &mut base).unwrap(), None);
+        *now.lock().unwrap() = at(3, 13, 0);
+        assert_eq!(sched.tick(&mut schedule, 60, &mut base).unwrap(), Some(90));
+        assert_eq!(base, 60);
+        assert_eq!(sched.tick(&mut schedule, 90, &mut base).unwrap(), None);
+        // Something else lowered it, raise again
+        assert_eq!(sched.tick(&mut schedule, 80, &mut base).unwrap(), Some(90));
+        // The user changed their limit while raised
+        base = 70;
+
+        *now.lock().unwrap() = at(3, 14, 0);
+        assert_eq!(sched.tick(&mut schedule, 90, &mut base).unwrap(), Some(70));
+        assert_eq!(sched.tick(&mut schedule, 70, &mut base).unwrap(), None);
+
+        // Never lowers below the user's limit
+        *now.lock().unwrap() = at(4, 13, 30);
+        assert_eq!(sched.tick(&mut schedule, 95, &mut base).unwrap(), None);
+        // A one shot charge is left alone
+        base = 60;
+        *now.lock().unwrap() = at(4, 13, 45);
+        assert_eq!(sched.tick(&mut schedule, 100, &mut base).unwrap(), None);
+
+        schedule.enabled = false;
+        *now.lock().unwrap() = at(5, 13, 30);
+        assert_eq!(sched.tick(&mut schedule, 60, &mut base).unwrap(), None);
+    }
+
+    #[test]
+    fn restore_after_restart() {
+        let tmp = fake_power_supply();
+        let root = tmp.path();
+        let now = Arc::new(Mutex::new(at(3, 13, 0)));
+        let mut schedule = ChargeSchedule {
+            enabled: true,
+            windows: vec![
+                ChargeWindow {
+                    days: Vec::new(),
+                    start: TimeOfDay::new(13, 0),
+                    end: TimeOfDay::new(14, 0),
+                    limit: 90
+                },
+            ],
+            ..Default::default()
+        };
+        let mut base = 0;
+        let mut sched = scheduler(root, &now);
+        assert_eq!(sched.tick(&mut schedule, 60, &mut base).unwrap(), Some(90));
+        assert!(schedule.raised);
+
+        // A new scheduler, as on restart, still restores the limit
+        let mut sched = scheduler(root, &now);
+        *now.lock().unwrap() = at(3, 14, 0);
+        assert_eq!(sched.tick(&mut schedule, 90, &mut base).unwrap(), Some(60));
+        assert!(!schedule.raised);
+    }
+
+    #[test]
+    fn full_by_release_and_learn_rate() {
+        let tmp = fake_power_supply();
+        let root = tmp.path();
+        let now = Arc::new(Mutex::new(at(3, 5, 0)));
+        let mut sched = scheduler(root, &now);
+        let mut base = 80;
+        let mut schedule = ChargeSchedule {
+            enabled: true,
+            full_by: vec![FullBy {
+                days: Vec::new(),
+                time: TimeOfDay::new(7, 30)
+            }],
+            ..Default::default()
+        };
+
+        // Charging 60 -> 70 in half an hour, 20%/h, blended with the default
+        assert_eq!(sched.tick(&mut schedule, 80, &mut base).unwrap(), None);
+        *now.lock().unwrap() = at(3, 5, 30);
+        set_state(root, true, 70);
+        assert_eq!(sched.tick(&mut schedule, 80, &mut base).unwrap(), None);
+        assert!((schedule.charge_rate - 27.0).abs() < 0.01);
+
+        // Held at the limit, 20% at 27%/h needs ~44min + margin
+        set_state(root, true, 80);
+        *now.lock().unwrap() = at(3, 6, 20);
+        assert_eq!(sched.tick(&mut schedule, 80, &mut base).unwrap(), None);
+        *now.lock().unwrap() = at(3, 6, 30);
+        assert_eq!(sched.tick(&mut schedule, 80, &mut base).unwrap(), Some(100));
+
+        // Stays released as the battery fills
+        set_state(root, true, 97);
+        *now.lock().unwrap() = at(3, 7, 20);
+        assert_eq!(sched.tick(&mut schedule, 100, &mut base).unwrap(), None);
+
+        *now.lock().unwrap() = at(3, 7, 30);
+        assert_eq!(sched.tick(&mut schedule, 100, &mut base).unwrap(), Some(80));
+    }
+}
@@ -6,6 +6,8 @@ use rog_platform::cpu::CPUEPP;
 use rog_platform::platform::PlatformProfile;
 use serde::{Deserialize, Serialize};
 
+use crate::charge_schedule::ChargeSchedule;
+
 const CONFIG_FILE: &str = "asusd.ron";
 
 #[derive(Default, Clone, Deserialize, Serialize, PartialEq)]
@@ -47,6 +49,9 @@ pub struct Config {
     pub ac_profile_tunings: Tunings,
     pub dc_profile_tunings: Tunings,
     pub armoury_settings: HashMap<FirmwareAttribute, i32>,
+    /// Time based changes to the charge limit
+    #[serde(default)]
+    pub charge_schedule: ChargeSchedule,
     /// Temporary state for AC/Batt
     #[serde(skip)]
     pub last_power_plugged: u8
@@ -82,6 +87,7 @@ impl Default for Config {
             ac_profile_tunings: HashMap::default(),
             dc_profile_tunings: HashMap::default(),
             armoury_settings: HashMap::default(),
+            charge_schedule: ChargeSchedule::default(),
             last_power_plugged: Default::default()
         }
     }
@@ -170,7 +176,8 @@ impl From<Config601> for Config {
             last_power_plugged: c.last_power_plugged,
             ac_profile_tunings: HashMap::default(),
             dc_profile_tunings: HashMap::default(),
-            armoury_settings: HashMap::default()
+            armoury_settings: HashMap::default(),
+            charge_schedule: ChargeSchedule::default()
         }
     }
 }
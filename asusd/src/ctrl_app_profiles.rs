@@ -0,0 +1,519 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use config_traits::{StdConfig, StdConfigLoad};
+use log::{debug, error, info, warn};
+use rog_aura::AuraEffect;
+use rog_platform::platform::PlatformProfile;
+use rog_profiles::fan_curve_set::CurveData;
+use rog_profiles::FanCurvePU;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+use zbus::object_server::SignalEmitter;
+use zbus::{interface, Connection};
+
+use crate::ctrl_fancurves::FAN_CURVE_ZBUS_PATH;
+use crate::error::RogError;
+use crate::loopback::Loopback;
+use crate::{CtrlTask, CONFIG_PATH_BASE};
+
+pub const APP_PROFILES_ZBUS_PATH: &str = "/xyz/ljones";
+const CONFIG_FILE: &str = "app_profiles.ron";
+const PROC_ROOT: &str = "/proc";
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+/// The kernel truncates `comm` to this many bytes
+const COMM_LEN: usize = 15;
+
+const PLATFORM_IFACE: &str = "xyz.ljones.Platform";
+const FAN_CURVES_IFACE: &str = "xyz.ljones.FanCurves";
+const AURA_IFACE: &str = "xyz.ljones.Aura";
+
+/// The parts of a running process that can be matched against
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ProcessInfo {
+    pub pid: u32,
+    /// From `/proc/<pid>/comm`
+    pub comm: String,
+    /// File name of the `/proc/<pid>/exe` link, which can only be read for
+    /// processes we have permission for
+    pub exe: Option<String>,
+    /// `/proc/<pid>/cmdline` with the arguments joined by spaces
+    pub cmdline: String
+}
+
+/// Reads running processes from a procfs mount. The root is configurable so
+/// that a fake tree can be used for testing.
+#[derive(Debug, Clone)]
+pub struct ProcessWatcher {
+    root: PathBuf
+}
+
+impl Default for ProcessWatcher {
+    fn default() -> Self {
+        Self::new(PROC_ROOT)
+    }
+}
+
+impl ProcessWatcher {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    fn read_process(path: &Path, pid: u32) -> Option<ProcessInfo> {
+        // A process may exit between listing and reading, so skip on failure
+        let comm = fs::read_to_string(path.join("comm")).ok()?;
+        let cmdline = fs::read(path.join("cmdline")).unwrap_or_default();
+        let cmdline = cmdline
+            .split(|b| *b == 0)
+            .filter(|s| !s.is_empty())
+            .map(|s| String::from_utf8_lossy(s))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let exe = fs::read_link(path.join("exe"))
+            .ok()
+            .and_then(|p| p.file_name().map(|n| n.to_string_lossy().to_string()));
+
+        Some(ProcessInfo {
+            pid,
+            comm: comm.trim_end().to_owned(),
+            exe,
+            cmdline
+        })
+    }
+
+    /// List all readable processes
+    pub fn processes(&self) -> Result<Vec<ProcessInfo>, RogError> {
+        let dir = fs::read_dir(&self.root)
+            .map_err(|e| RogError::Read(self.root.to_string_lossy().to_string(), e))?;
+        Ok(dir
+            .filter_map(|entry| {
+                let entry = entry.ok()?;
+                let pid = entry.file_name().to_str()?.parse::<u32>().ok()?;
+                Self::read_process(&entry.path(), pid)
+            })
+            .collect())
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub enum ProcessMatch {
+    /// Exact executable name, e.g. `cargo`. Checked against the `exe` link
+    /// and the (possibly truncated) `comm`
+    Exe(String),
+    /// Matches if the full command line contains this string
+    Cmdline(String)
+}
+
+impl ProcessMatch {
+    pub fn matches(&self, process: &ProcessInfo) -> bool {
+        match self {
+            ProcessMatch::Exe(name) => {
+                process.exe.as_deref() == Some(name.as_str())
+                    || (!process.comm.is_empty()
+                        && name.get(..COMM_LEN).unwrap_or(name) == process.comm)
+            }
+            ProcessMatch::Cmdline(pattern) => process.cmdline.contains(pattern.as_str())
+        }
+    }
+}
+
+/// A bundle of settings to apply while a matching process is running
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct AppProfile {
+    pub name: String,
+    pub enabled: bool,
+    /// The profile is active if any process matches any of these
+    pub matches: Vec<ProcessMatch>,
+    pub platform_profile: Option<PlatformProfile>,
+    /// Enable the custom fan curves for `platform_profile`, or for the
+    /// current profile if that is not set
+    pub enable_fan_curves: bool,
+    /// Applied to all Aura devices
+    pub aura_effect: Option<AuraEffect>
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct AppProfilesConfig {
+    pub enabled: bool,
+    /// Checked in order, the first with a running process wins
+    pub profiles: Vec<AppProfile>
+}
+
+impl Default for AppProfilesConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            profiles: Vec::new()
+        }
+    }
+}
+
+impl AppProfilesConfig {
+    /// Find the first enabled profile that matches one of the processes
+    pub fn find_match(&self, processes: &[ProcessInfo]) -> Option<&AppProfile> {
+        if !self.enabled {
+            return None;
+        }
+        self.profiles.iter().filter(|p| p.enabled).find(|profile| {
+            processes
+                .iter()
+                .any(|proc| profile.matches.iter().any(|m| m.matches(proc)))
+        })
+    }
+}
+
+impl StdConfig for AppProfilesConfig {
+    fn new() -> Self {
+        Self::default()
+    }
+
+    fn file_name(&self) -> String {
+        CONFIG_FILE.to_owned()
+    }
+
+    fn config_dir() -> std::path::PathBuf {
+        PathBuf::from(CONFIG_PATH_BASE)
+    }
+}
+
+impl StdConfigLoad for AppProfilesConfig {}
+
+/// The settings as they were before an `AppProfile` was applied
+#[derive(Debug, Default, Clone)]
+struct Snapshot {
+    platform_profile: Option<PlatformProfile>,
+    /// Only the curves whose enabled state was changed
+    fan_curves: Vec<(PlatformProfile, FanCurvePU, bool)>,
+    /// dbus path and effect of each Aura device
+    aura: Vec<(String, AuraEffect)>
+}
+
+#[derive(Clone)]
+pub struct CtrlAppProfiles {
+    config: Arc<Mutex<AppProfilesConfig>>,
+    /// Name of the applied profile and the state to restore on exit
+    active: Arc<Mutex<Option<(String, Snapshot)>>>,
+    watcher: ProcessWatcher,
+    loopback: Loopback
+}
+
+impl CtrlAppProfiles {
+    pub fn new(connection: Connection) -> Self {
+        Self {
+            config: Arc::new(Mutex::new(AppProfilesConfig::new().load())),
+            active: Default::default(),
+            watcher: ProcessWatcher::default(),
+            loopback: Loopback::new(connection)
+        }
+    }
+
+    /// Apply the profile, recording the current state of everything it
+    /// changes
+    async fn apply(&self, profile: &AppProfile) -> Snapshot {
+        let mut snapshot = Snapshot::default();
+
+        let current: Option<PlatformProfile> = self
+            .loopback
+            .get_property(APP_PROFILES_ZBUS_PATH, PLATFORM_IFACE, "PlatformProfile")
+            .await
+            .map_err(|e| warn!("AppProfiles: {e}"))
+            .ok();
+
+        if let Some(platform_profile) = profile.platform_profile {
+            snapshot.platform_profile = current;
+            self.loopback
+                .set_property(
+                    APP_PROFILES_ZBUS_PATH,
+                    PLATFORM_IFACE,
+                    "PlatformProfile",
+                    platform_profile
+                )
+                .await
+                .map_err(|e| error!("AppProfiles: {e}"))
+                .ok();
+        }
+
+        if profile.enable_fan_curves {
+            if let Some(target) = profile.platform_profile.or(current) {
+                match self
+                    .loopback
+                    .call::<_, Vec<CurveData>>(
+                        FAN_CURVE_ZBUS_PATH,
+                        FAN_CURVES_IFACE,
+                        "FanCurveData",
+                        &(target,)
+                    )
+                    .await
+                {
+                    Ok(curves) => {
+                        for curve in curves.iter().filter(|c| !c.enabled) {
+                            snapshot.fan_curves.push((target, curve.fan, false));
+                            self.set_fan_curve_enabled(target, curve.fan, true).await;
+                        }
+                    }
+                    Err(e) => error!("AppProfiles: {e}")
+                }
+            }
+        }
+
+        if let Some(effect) = &profile.aura_effect {
+            for path in self
+                .loopback
+                .find_paths(AURA_IFACE)
+                .await
+                .unwrap_or_default()
+            {
+                match self
+                    .loopback
+                    .get_property::<AuraEffect>(&path, AURA_IFACE, "LedModeData")
+                    .await
+                {
+                    Ok(previous) => {
+                        self.loopback
+                            .set_property(&path, AURA_IFACE, "LedModeData", effect.clone())
+                            .await
+                            .map_err(|e| error!("AppProfiles: {e}"))
+                            .ok();
+                        snapshot.aura.push((path, previous));
+                    }
+                    Err(e) => warn!("AppProfiles: {e}")
+                }
+            }
+        }
+
+        snapshot
+    }
+
+    async fn set_fan_curve_enabled(&self, profile: PlatformProfile, fan: FanCurvePU, on: bool) {
+        self.loopback
+            .call::<_, ()>(
+                FAN_CURVE_ZBUS_PATH,
+                FAN_CURVES_IFACE,
+                "SetProfileFanCurveEnabled",
+                &(profile, fan, on)
+            )
+            .await
+            .map_err(|e| error!("AppProfiles: {e}"))
+            .ok();
+    }
+
+    async fn restore(&self, snapshot: Snapshot) {
+        if let Some(platform_profile) = snapshot.platform_profile {
+            self.loopback
+                .set_property(
+                    APP_PROFILES_ZBUS_PATH,
+                    PLATFORM_IFACE,
+                    "PlatformProfile",
+                    platform_profile
+                )
+                .await
+                .map_err(|e| error!("AppProfiles: {e}"))
+                .ok();
+        }
+        for (profile, fan, enabled) in snapshot.fan_curves {
+            self.set_fan_curve_enabled(profile, fan, enabled).await;
+        }
+        for (path, effect) in snapshot.aura {
+            self.loopback
+                .set_property(&path, AURA_IFACE, "LedModeData", effect)
+                .await
+                .map_err(|e| error!("AppProfiles: {e}"))
+                .ok();
+        }
+    }
+
+    /// Check running processes and switch profiles if required
+    async fn update(&self) -> Result<(), RogError> {
+        let processes = self.watcher.processes()?;
+        let wanted = self.config.lock().await.find_match(&processes).cloned();
+
+        let mut active = self.active.lock().await;
+        if active.as_ref().map(|(name, _)| name) == wanted.as_ref().map(|p| &p.name) {
+            return Ok(());
+        }
+
+        // Always go back to the original state first so that switching
+        // directly between two profiles doesn't leave anything behind
+        if let Some((name, snapshot)) = active.take() {
+            info!("AppProfiles: {name} no longer running, restoring previous settings");
+            self.restore(snapshot).await;
+        }
+        if let Some(profile) = wanted {
+            info!("AppProfiles: applying {}", profile.name);
+            let snapshot = self.apply(&profile).await;
+            *active = Some((profile.name, snapshot));
+        }
+        Ok(())
+    }
+}
+
+#[interface(name = "xyz.ljones.AppProfiles")]
+impl CtrlAppProfiles {
+    /// Name of the currently applied profile, empty if none
+    #[zbus(property)]
+    async fn active_profile(&self) -> String {
+        self.active
+            .lock()
+            .await
+            .as_ref()
+            .map(|(name, _)| name.clone())
+            .unwrap_or_default()
+    }
+
+    #[zbus(property)]
+    async fn enabled(&self) -> bool {
+        self.config.lock().await.enabled
+    }
+
+    #[zbus(property)]
+    async fn set_enabled(&mut self, enabled: bool) {
+        let mut config = self.config.lock().await;
+        config.enabled = enabled;
+        config.write();
+    }
+}
+
+impl crate::ZbusRun for CtrlAppProfiles {
+    async fn add_to_server(self, server: &mut Connection) {
+        Self::add_to_server_helper(self, APP_PROFILES_ZBUS_PATH, server).await;
+    }
+}
+
+impl crate::Reloadable for CtrlAppProfiles {
+    /// Nothing to restore, processes are checked once the tasks start
+    async fn reload(&mut self) -> Result<(), RogError> {
+        Ok(())
+    }
+}
+
+impl CtrlTask for CtrlAppProfiles {
+    fn zbus_path() -> &'static str {
+        APP_PROFILES_ZBUS_PATH
+    }
+
+    async fn create_tasks(&self, signal_ctxt: SignalEmitter<'static>) -> Result<(), RogError> {
+        let ctrl = self.clone();
+        tokio::spawn(async move {
+            let config_path = ctrl.config.lock().await.file_path();
+            let modified = |path: &Path| fs::metadata(path).and_then(|m| m.modified()).ok();
+            let mut last_modified = modified(&config_path);
+            let mut last_active = String::new();
+            loop {
+                // Pick up edits to the config file
+                let modified = modified(&config_path);
+                if modified != last_modified {
+                    last_modified = modified;
+                    let res = ctrl.config.lock().await.read_new();
+                    if let Some(config) = res {
+                        debug!("AppProfiles: {CONFIG_FILE} changed, reloading");
+                        *ctrl.config.lock().await = config;
+                    }
+                }
+
+                if let Err(e) = ctrl.update().await {
+                    warn!("AppProfiles: {e}");
+                }
+
+                let active = ctrl.active_profile().await;
+                if active != last_active {
+                    ctrl.active_profile_changed(&signal_ctxt).await.ok();
+                    last_active = active;
+                }
+
+                tokio::time::sleep(POLL_INTERVAL).await;
+            }
+        });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use tempfile::TempDir;
+
+    use super::{AppProfile, AppProfilesConfig, ProcessMatch, ProcessWatcher};
+
+    fn fake_proc() -> TempDir {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+
+        let add = |pid: u32, comm: &str, cmdline: &[&str]| {
+            let dir = root.join(pid.to_string());
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(dir.join("comm"), format!("{comm}\n")).unwrap();
+            let mut buf = Vec::new();
+            for arg in cmdline {
+                buf.extend_from_slice(arg.as_bytes());
+                buf.push(0);
+            }
+            fs::write(dir.join("cmdline"), buf).unwrap();
+        };
+        add(1, "systemd", &[
+            "/sbin/init", "splash"
+        ]);
+        add(420, "cargo", &[
+            "cargo", "build", "--release"
+        ]);
+        add(1337, "wine64-preloade", &["Z:\\games\\eldenring.exe"]);
+        // Not a process
+        fs::create_dir_all(root.join("sys")).unwrap();
+        tmp
+    }
+
+    fn profile(name: &str, matches: Vec<ProcessMatch>) -> AppProfile {
+        AppProfile {
+            name: name.to_owned(),
+            enabled: true,
+            matches,
+            platform_profile: None,
+            enable_fan_curves: false,
+            aura_effect: None
+        }
+    }
+
+    #[test]
+    fn read_fake_proc() {
+        let root = fake_proc();
+        let mut procs = ProcessWatcher::new(root.path()).processes().unwrap();
+        procs.sort_by_key(|p| p.pid);
+
+        assert_eq!(procs.len(), 3);
+        assert_eq!(procs[0].comm, "systemd");
+        assert_eq!(procs[0].cmdline, "/sbin/init splash");
+        assert_eq!(procs[1].pid, 420);
+        assert_eq!(procs[1].exe, None);
+        assert_eq!(procs[2].cmdline, "Z:\\games\\eldenring.exe");
+    }
+
+    #[test]
+    fn match_profiles() {
+        let root = fake_proc();
+        let procs = ProcessWatcher::new(root.path()).processes().unwrap();
+
+        let mut config = AppProfilesConfig {
+            enabled: true,
+            profiles: vec![
+                profile("firefox", vec![ProcessMatch::Exe("firefox".to_owned())]),
+                profile("games", vec![
+                    ProcessMatch::Cmdline("eldenring.exe".to_owned()),
+                ]),
+                profile("build", vec![ProcessMatch::Exe("cargo".to_owned())]),
+            ]
+        };
+        assert_eq!(config.find_match(&procs).unwrap().name, "games");
+
+        config.profiles[1].enabled = false;
+        assert_eq!(config.find_match(&procs).unwrap().name, "build");
+
+        // comm is truncated by the kernel
+        config.profiles[2].matches = vec![ProcessMatch::Exe("wine64-preloader".to_owned())];
+        assert_eq!(config.find_match(&procs).unwrap().name, "build");
+
+        config.enabled = false;
+        assert!(config.find_match(&procs).is_none());
+    }
+}
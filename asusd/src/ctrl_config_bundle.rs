@@ -0,0 +1,669 @@
+use std::collections::HashMap;
+use std::fs;
+
+use config_traits::ron::ser::PrettyConfig;
+use config_traits::{ron, StdConfig};
+use dmi_id::DMIID;
+use log::{info, warn};
+use rog_aura::keyboard::LaptopAuraPower;
+use rog_aura::{AuraModeNum, PowerZones};
+use rog_platform::asus_armoury::{AttrValue, FirmwareAttribute, FirmwareAttributes};
+use rog_profiles::{FanCurvePU, FanCurveProfiles};
+use serde::{Deserialize, Serialize};
+use zbus::fdo::Error as FdoErr;
+use zbus::object_server::{Interface, InterfaceRef, SignalEmitter};
+use zbus::{interface, Connection};
+
+use crate::aura_anime::config::AniMeConfig;
+use crate::aura_anime::trait_impls::AniMeZbus;
+use crate::aura_laptop::config::AuraConfig;
+use crate::aura_laptop::trait_impls::AuraZbus;
+use crate::aura_scsi::config::ScsiConfig;
+use crate::aura_scsi::trait_impls::ScsiZbus;
+use crate::aura_slash::config::SlashConfig;
+use crate::aura_slash::trait_impls::SlashZbus;
+use crate::config::Config;
+use crate::ctrl_fancurves::{
+    validate_profiles, CtrlFanCurveZbus, FanCurveConfig, FAN_CURVE_ZBUS_PATH
+};
+use crate::error::RogError;
+use crate::loopback::Loopback;
+use crate::{CtrlTask, CONFIG_PATH_BASE};
+
+pub const CONFIG_BUNDLE_ZBUS_PATH: &str = "/xyz/ljones";
+
+/// Bump this if a change is made to the bundle, or to any config it contains,
+/// that an older asusd could not read
+pub const BUNDLE_VERSION: u32 = 1;
+
+/// Every asusd config in one document, for moving settings between machines.
+/// Sections that were missing on export are `None` or empty.
+#[derive(Deserialize, Serialize)]
+pub struct ConfigBundle {
+    pub version: u32,
+    /// The board the bundle was exported from, for information only
+    #[serde(default)]
+    pub board_name: String,
+    #[serde(default)]
+    pub asusd: Option<Config>,
+    #[serde(default)]
+    pub fan_curves: Option<FanCurveProfiles>,
+    /// One for each product ID, identified by `config_name`
+    #[serde(default)]
+    pub aura: Vec<AuraConfig>,
+    #[serde(default)]
+    pub anime: Option<AniMeConfig>,
+    #[serde(default)]
+    pub slash: Option<SlashConfig>,
+    #[serde(default)]
+    pub scsi: Option<ScsiConfig>
+}
+
+/// Used to check the version before trying to parse the whole bundle, so a
+/// newer bundle gets a useful error rather than a parse failure
+#[derive(Deserialize)]
+struct BundleHeader {
+    version: u32
+}
+
+impl ConfigBundle {
+    /// Collect the configs as they are stored on disk
+    pub fn from_disk() -> Self {
+        let mut aura = Vec::new();
+        if let Ok(dir) = fs::read_dir(CONFIG_PATH_BASE) {
+            for entry in dir.flatten() {
+                let name = entry.file_name().to_string_lossy().to_string();
+                if name.starts_with("aura_") && name.ends_with(".ron") {
+                    let config = AuraConfig {
+                        config_name: name,
+                        ..Default::default()
+                    };
+                    aura.extend(config.read_new());
+                }
+            }
+        }
+        aura.sort_by(|a, b| a.config_name.cmp(&b.config_name));
+
+        Self {
+            version: BUNDLE_VERSION,
+            board_name: DMIID::new().unwrap_or_default().board_name,
+            asusd: Config::new().read_new(),
+            fan_curves: FanCurveConfig::new().read_new().map(|c| c.profiles),
+            aura,
+            anime: AniMeConfig::new().read_new(),
+            slash: SlashConfig::new().read_new(),
+            scsi: ScsiConfig::new().read_new()
+        }
+    }
+
+    pub fn to_ron(&self) -> Result<String, RogError> {
+        ron::ser::to_string_pretty(self, PrettyConfig::new().depth_limit(4))
+            .map_err(RogError::ParseRon)
+    }
+
+    pub fn from_ron(data: &str) -> Result<Self, String> {
+        let header: BundleHeader =
+            ron::from_str(data).map_err(|e| format!("not a config bundle: {e}"))?;
+        if header.version == 0 || header.version > BUNDLE_VERSION {
+            return Err(format!(
+                "bundle version {} is not supported, this asusd supports up to version \
+                 {BUNDLE_VERSION}",
+                header.version
+            ));
+        }
+        ron::from_str(data).map_err(|e| format!("could not parse config bundle: {e}"))
+    }
+}
+
+/// The product ID from an aura config name such as `aura_19b6.ron`
+fn aura_product_id(config_name: &str) -> Option<&str> {
+    config_name
+        .strip_prefix("aura_")
+        .and_then(|n| n.strip_suffix(".ron"))
+}
+
+/// The firmware attributes this machine has, with the range of each that has
+/// one
+type AttrLimits = HashMap<FirmwareAttribute, Option<(i32, i32)>>;
+
+fn attr_limits(attributes: &FirmwareAttributes) -> AttrLimits {
+    attributes
+        .attributes()
+        .iter()
+        .map(|attr| {
+            let range = match (attr.min_value(), attr.max_value()) {
+                (AttrValue::Integer(min), AttrValue::Integer(max)) => Some((*min, *max)),
+                _ => None
+            };
+            (attr.name().into(), range)
+        })
+        .collect()
+}
+
+/// Check the asusd config is safe to use on this machine. Charge limits must
+/// be valid and firmware attributes within this machine's range, otherwise an
+/// error is returned. Settings for attributes this machine doesn't have are
+/// removed. Returns a description of each thing removed.
+fn check_asusd(config: &mut Config, limits: &AttrLimits) -> Result<Vec<String>, String> {
+    let charge_limits = config
+        .charge_schedule
+        .windows
+        .iter()
+        .map(|w| w.limit)
+        .chain([config.charge_control_end_threshold]);
+    for limit in charge_limits {
+        if !(20..=100).contains(&limit) {
+            return Err(RogError::ChargeLimit(limit).to_string());
+        }
+    }
+
+    let mut removed = Vec::new();
+    let settings = config
+        .ac_profile_tunings
+        .values_mut()
+        .chain(config.dc_profile_tunings.values_mut())
+        .map(|t| &mut t.group)
+        .chain([&mut config.armoury_settings]);
+    for group in settings {
+        for (attr, value) in group.iter() {
+            if let Some(Some((min, max))) = limits.get(attr) {
+                if !(min..=max).contains(&value) {
+                    return Err(format!(
+                        "{} of {value} is outside this machine's range of {min} to {max}",
+                        <&str>::from(*attr)
+                    ));
+                }
+            }
+        }
+        group.retain(|attr, _| {
+            let keep = limits.contains_key(attr);
+            let name = format!("{} not supported", <&str>::from(*attr));
+            if !keep && !removed.contains(&name) {
+                removed.push(name);
+            }
+            keep
+        });
+    }
+    Ok(removed)
+}
+
+/// Remove any curves for fans this machine doesn't have. Returns the fans that
+/// were removed.
+fn retain_supported_fans(
+    profiles: &mut FanCurveProfiles,
+    supported: &[FanCurvePU]
+) -> Vec<FanCurvePU> {
+    let mut removed = Vec::new();
+    for curves in [
+        &mut profiles.balanced,
+        &mut profiles.performance,
+        &mut profiles.quiet
+    ] {
+        curves.retain(|c| {
+            let keep = supported.contains(&c.fan);
+            if !keep && !removed.contains(&c.fan) {
+                removed.push(c.fan);
+            }
+            keep
+        });
+    }
+    removed
+}
+
+/// Remove any modes, zones, and power zones the device doesn't support. Power
+/// states fall back to the current ones if none are left. Returns a
+/// description of each thing removed.
+fn retain_supported_aura(config: &mut AuraConfig, current: &AuraConfig) -> Vec<String> {
+    let support = &current.support_data;
+    let modes = if support.basic_modes.is_empty() {
+        vec![AuraModeNum::Static]
+    } else {
+        support.basic_modes.clone()
+    };
+    let power_zones = if support.power_zones.is_empty() {
+        vec![PowerZones::Keyboard]
+    } else {
+        support.power_zones.clone()
+    };
+    let mut removed = Vec::new();
+
+    config.builtins.retain(|mode, _| {
+        let keep = modes.contains(mode);
+        if !keep {
+            removed.push(format!("mode {mode}"));
+        }
+        keep
+    });
+    if !modes.contains(&config.current_mode) {
+        config.current_mode = *config
+            .builtins
+            .keys()
+            .next()
+            .unwrap_or(&AuraModeNum::Static);
+    }
+
+    if let Some(multizone) = config.multizone.as_mut() {
+        multizone.retain(|mode, effects| {
+            effects.retain(|e| support.basic_zones.contains(&e.zone));
+            modes.contains(mode) && !effects.is_empty()
+        });
+        if multizone.is_empty() {
+            if !support.basic_zones.is_empty() || config.multizone_on {
+                removed.push("multizone".to_owned());
+            }
+            config.multizone = None;
+            config.multizone_on = false;
+        }
+    }
+
+    config.enabled.states.retain(|s| {
+        let keep = power_zones.contains(&s.zone);
+        if !keep {
+            removed.push(format!("power zone {:?}", s.zone));
+        }
+        keep
+    });
+    if config.enabled.states.is_empty() {
+        config.enabled = LaptopAuraPower {
+            states: current.enabled.states.clone()
+        };
+    }
+
+    removed
+}
+
+#[derive(Clone)]
+pub struct CtrlConfigBundle {
+    attributes: FirmwareAttributes,
+    loopback: Loopback
+}
+
+impl CtrlConfigBundle {
+    pub fn new(attributes: FirmwareAttributes, connection: Connection) -> Self {
+        Self {
+            attributes,
+            loopback: Loopback::new(connection)
+        }
+    }
+
+    /// Get every controller of the type that is exported on the interface
+    async fn find_ifaces<I: Interface>(&self, iface: &str) -> Vec<(String, InterfaceRef<I>)> {
+        let paths = self
+            .loopback
+            .find_paths(iface)
+            .await
+            .map_err(|e| warn!("ConfigBundle: {e}"))
+            .unwrap_or_default();
+        let server = self.loopback.connection().object_server();
+        let mut ifaces = Vec::new();
+        for path in paths {
+            if let Ok(i) = server.interface::<_, I>(path.as_str()).await {
+                ifaces.push((path, i));
+            }
+        }
+        ifaces
+    }
+
+    async fn import_fan_curves(&self, mut profiles: FanCurveProfiles, skipped: &mut Vec<String>) {
+        let Ok(iface) = self
+            .loopback
+            .connection()
+            .object_server()
+            .interface::<_, CtrlFanCurveZbus>(FAN_CURVE_ZBUS_PATH)
+            .await
+        else {
+            skipped.push("fan curves: not supported on this machine".to_owned());
+            return;
+        };
+        let mut ctrl = iface.get_mut().await;
+        let supported = ctrl.supported_fans().await;
+        for fan in retain_supported_fans(&mut profiles, &supported) {
+            skipped.push(format!("fan curves: {fan:?} fan not present"));
+        }
+        if let Err(e) = ctrl.replace_profiles(profiles).await {
+            skipped.push(format!("fan curves: {e}"));
+        }
+    }
+
+    async fn import_aura(&self, configs: Vec<AuraConfig>, skipped: &mut Vec<String>) {
+        let devices = self.find_ifaces::<AuraZbus>("xyz.ljones.Aura").await;
+        for mut config in configs {
+            let name = config.config_name.clone();
+            if aura_product_id(&name).is_none() {
+                skipped.push(format!("aura {name}: invalid config name"));
+                continue;
+            }
+            let mut found = false;
+            for (path, iface) in &devices {
+                let mut device = iface.get_mut().await;
+                let current = device.config().await;
+                if current.config_name != name {
+                    continue;
+                }
+                found = true;
+                for removed in retain_supported_aura(&mut config, &current) {
+                    skipped.push(format!("aura {name}: {removed} not supported"));
+                }
+                if let Err(e) = device.replace_config(config.clone()).await {
+                    skipped.push(format!("aura {path}: {e}"));
+                }
+            }
+            if !found {
+                skipped.push(format!("aura {name}: device not present"));
+            }
+        }
+    }
+
+    async fn import_anime(&self, config: AniMeConfig, skipped: &mut Vec<String>) {
+        let devices = self.find_ifaces::<AniMeZbus>("xyz.ljones.Anime").await;
+        if devices.is_empty() {
+            skipped.push("anime: device not present".to_owned());
+        }
+        for (_, iface) in devices {
+            if let Err(e) = iface.get_mut().await.replace_config(config.clone()).await {
+                skipped.push(format!("anime: {e}"));
+            }
+        }
+    }
+
+    async fn import_slash(&self, config: SlashConfig, skipped: &mut Vec<String>) {
+        let devices = self.find_ifaces::<SlashZbus>("xyz.ljones.Slash").await;
+        if devices.is_empty() {
+            skipped.push("slash: device not present".to_owned());
+        }
+        for (_, iface) in devices {
+            if let Err(e) = iface.get_mut().await.replace_config(config.clone()).await {
+                skipped.push(format!("slash: {e}"));
+            }
+        }
+    }
+
+    async fn import_scsi(&self, config: ScsiConfig, skipped: &mut Vec<String>) {
+        let devices = self.find_ifaces::<ScsiZbus>("xyz.ljones.ScsiAura").await;
+        if devices.is_empty() {
+            skipped.push("scsi: device not present".to_owned());
+        }
+        for (_, iface) in devices {
+            if let Err(e) = iface.get_mut().await.replace_config(config.clone()).await {
+                skipped.push(format!("scsi: {e}"));
+            }
+        }
+    }
+
+    /// Check everything in the bundle that could be unsafe on this machine,
+    /// before any of it is applied. Returns a description of everything
+    /// removed from the bundle as unsupported.
+    fn check(&self, bundle: &mut ConfigBundle) -> Result<Vec<String>, String> {
+        let mut skipped = Vec::new();
+        if let Some(config) = bundle.asusd.as_mut() {
+            let removed = check_asusd(config, &attr_limits(&self.attributes))
+                .map_err(|e| format!("asusd: {e}"))?;
+            skipped.extend(removed.into_iter().map(|r| format!("asusd: {r}")));
+        }
+        if let Some(profiles) = &bundle.fan_curves {
+            validate_profiles(profiles).map_err(|e| format!("fan curves: {e}"))?;
+        }
+        Ok(skipped)
+    }
+
+    /// Apply each section of the bundle. Returns a description of everything
+    /// skipped.
+    async fn import(&self, bundle: ConfigBundle, mut skipped: Vec<String>) -> Vec<String> {
+        if let Some(config) = bundle.asusd {
+            // The platform controller watches this file and applies changes
+            config.write();
+        }
+        if let Some(profiles) = bundle.fan_curves {
+            self.import_fan_curves(profiles, &mut skipped).await;
+        }
+        self.import_aura(bundle.aura, &mut skipped).await;
+        if let Some(config) = bundle.anime {
+            self.import_anime(config, &mut skipped).await;
+        }
+        if let Some(config) = bundle.slash {
+            self.import_slash(config, &mut skipped).await;
+        }
+        if let Some(config) = bundle.scsi {
+            self.import_scsi(config, &mut skipped).await;
+        }
+        skipped
+    }
+}
+
+#[interface(name = "xyz.ljones.ConfigBundle")]
+impl CtrlConfigBundle {
+    /// Export all stored configs as a single versioned RON document
+    async fn export_config(&self) -> zbus::fdo::Result<String> {
+        Ok(ConfigBundle::from_disk().to_ron()?)
+    }
+
+    /// Import a bundle created by `ExportConfig`. Sections for devices that
+    /// are not present, and anything they contain that this machine doesn't
+    /// support, are skipped. A bundle with unsafe fan curves or settings out
+    /// of this machine's range is rejected with nothing changed. Returns a
+    /// description of each skipped part.
+    async fn import_config(&self, bundle: String) -> zbus::fdo::Result<Vec<String>> {
+        let mut bundle = ConfigBundle::from_ron(&bundle)
+            .map_err(|e| FdoErr::InvalidArgs(format!("ConfigBundle: {e}")))?;
+        info!(
+            "ConfigBundle: importing version {} bundle from {}",
+            bundle.version, bundle.board_name
+        );
+        let skipped = self.check(&mut bundle).map_err(|e| {
+            warn!("ConfigBundle: rejected: {e}");
+            FdoErr::InvalidArgs(format!("ConfigBundle: {e}"))
+        })?;
+        let skipped = self.import(bundle, skipped).await;
+        if !skipped.is_empty() {
+            warn!("ConfigBundle: skipped {skipped:?}");
+        }
+        Ok(skipped)
+    }
+}
+
+impl crate::ZbusRun for CtrlConfigBundle {
+    async fn add_to_server(self, server: &mut Connection) {
+        Self::add_to_server_helper(self, CONFIG_BUNDLE_ZBUS_PATH, server).await;
+    }
+}
+
+impl crate::Reloadable for CtrlConfigBundle {
+    async fn reload(&mut self) -> Result<(), RogError> {
+        Ok(())
+    }
+}
+
+impl CtrlTask for CtrlConfigBundle {
+    fn zbus_path() -> &'static str {
+        CONFIG_BUNDLE_ZBUS_PATH
+    }
+
+    async fn create_tasks(&self, _: SignalEmitter<'static>) -> Result<(), RogError> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use rog_aura::keyboard::{AuraPowerState, LaptopAuraPower};
+    use rog_aura::{AuraEffect, AuraModeNum, AuraZone, PowerZones};
+    use rog_platform::platform::PlatformProfile;
+    use rog_profiles::fan_curve_set::CurveData;
+    use rog_profiles::{FanCurvePU, FanCurveProfiles};
+
+    use super::*;
+    use crate::soft_fan::default_curves;
+
+    fn power(zone: PowerZones) -> AuraPowerState {
+        AuraPowerState {
+            zone,
+            boot: true,
+            awake: true,
+            sleep: false,
+            shutdown: false
+        }
+    }
+
+    #[test]
+    fn version_checked() {
+        assert!(ConfigBundle::from_ron("(version: 1)").is_ok());
+        assert!(ConfigBundle::from_ron("(version: 0)").is_err());
+        let err = ConfigBundle::from_ron(&format!("(version: {})", BUNDLE_VERSION + 1)).err();
+        assert!(err.unwrap().contains("not supported"));
+        assert!(ConfigBundle::from_ron("not ron").is_err());
+    }
+
+    #[test]
+    fn fans_removed() {
+        let curve = |fan| CurveData {
+            fan,
+            ..Default::default()
+        };
+        let mut profiles = FanCurveProfiles {
+            balanced: vec![
+                curve(FanCurvePU::CPU),
+                curve(FanCurvePU::GPU),
+            ],
+            performance: vec![
+                curve(FanCurvePU::CPU),
+                curve(FanCurvePU::MID),
+            ],
+            quiet: vec![curve(
+                FanCurvePU::GPU
+            )]
+        };
+        let removed = retain_supported_fans(&mut profiles, &[FanCurvePU::CPU]);
+        assert_eq!(removed, vec![
+            FanCurvePU::GPU,
+            FanCurvePU::MID
+        ]);
+        assert_eq!(profiles.balanced.len(), 1);
+        assert_eq!(profiles.performance.len(), 1);
+        assert!(profiles.quiet.is_empty());
+    }
+
+    #[test]
+    fn aura_unsupported_removed() {
+        let mut current = AuraConfig::default();
+        current.support_data.basic_modes = vec![
+            AuraModeNum::Static,
+            AuraModeNum::Breathe,
+        ];
+        current.support_data.power_zones = vec![PowerZones::Keyboard];
+        current.enabled = LaptopAuraPower {
+            states: vec![power(
+                PowerZones::Keyboard
+            )]
+        };
+
+        let mut builtins = BTreeMap::new();
+        for mode in [
+            AuraModeNum::Static,
+            AuraModeNum::Breathe,
+            AuraModeNum::RainbowCycle
+        ] {
+            builtins.insert(mode, AuraEffect::default_with_mode(mode));
+        }
+        let mut multizone = BTreeMap::new();
+        multizone.insert(AuraModeNum::Static, vec![
+            AuraEffect {
+                zone: AuraZone::Key1,
+                ..Default::default()
+            },
+        ]);
+        let mut config = AuraConfig {
+            config_name: "aura_19b6.ron".to_owned(),
+            current_mode: AuraModeNum::RainbowCycle,
+            builtins,
+            multizone: Some(multizone),
+            multizone_on: true,
+            enabled: LaptopAuraPower {
+                states: vec![power(
+                    PowerZones::Lightbar
+                )]
+            },
+            ..Default::default()
+        };
+
+        let removed = retain_supported_aura(&mut config, &current);
+        assert_eq!(removed.len(), 3);
+        assert_eq!(config.builtins.len(), 2);
+        assert_eq!(config.current_mode, AuraModeNum::Static);
+        assert!(config.multizone.is_none());
+        assert!(!config.multizone_on);
+        assert_eq!(config.enabled.states, current.enabled.states);
+    }
+
+    #[test]
+    fn unsafe_curves_rejected() {
+        let mut profiles = FanCurveProfiles {
+            balanced: default_curves(PlatformProfile::Balanced, &[FanCurvePU::CPU]),
+            performance: default_curves(PlatformProfile::Performance, &[FanCurvePU::CPU]),
+            quiet: default_curves(PlatformProfile::Quiet, &[FanCurvePU::CPU])
+        };
+        assert!(validate_profiles(&profiles).is_ok());
+        profiles.quiet[0].pwm = [0; 8];
+        // Disabled curves are never run
+        assert!(validate_profiles(&profiles).is_ok());
+        profiles.quiet[0].enabled = true;
+        let err = validate_profiles(&profiles).unwrap_err();
+        assert!(matches!(
+            err,
+            RogError::FanCurve(PlatformProfile::Quiet, FanCurvePU::CPU, _)
+        ));
+    }
+
+    #[test]
+    fn asusd_checked() {
+        let mut limits = AttrLimits::new();
+        limits.insert(FirmwareAttribute::PptPl1Spl, Some((15, 80)));
+        limits.insert(FirmwareAttribute::PanelOverdrive, None);
+
+        let mut config = Config::new();
+        config
+            .ac_profile_tunings
+            .entry(PlatformProfile::Performance)
+            .or_default()
+            .group
+            .insert(FirmwareAttribute::PptPl1Spl, 80);
+        config
+            .dc_profile_tunings
+            .entry(PlatformProfile::Quiet)
+            .or_default()
+            .group
+            .insert(FirmwareAttribute::NvTempTarget, 87);
+        config
+            .armoury_settings
+            .insert(FirmwareAttribute::PanelOverdrive, 1);
+        config
+            .armoury_settings
+            .insert(FirmwareAttribute::NvTempTarget, 87);
+        let removed = check_asusd(&mut config, &limits).unwrap();
+        assert_eq!(removed, ["nv_temp_target not supported"]);
+        assert!(config.dc_profile_tunings[&PlatformProfile::Quiet]
+            .group
+            .is_empty());
+        assert_eq!(config.armoury_settings.len(), 1);
+
+        config
+            .ac_profile_tunings
+            .get_mut(&PlatformProfile::Performance)
+            .unwrap()
+            .group
+            .insert(FirmwareAttribute::PptPl1Spl, 120);
+        let err = check_asusd(&mut config, &limits).unwrap_err();
+        assert!(err.contains("ppt_pl1_spl of 120"));
+
+        let mut config = Config::new();
+        config.charge_control_end_threshold = 5;
+        assert!(check_asusd(&mut config, &limits).is_err());
+    }
+
+    #[test]
+    fn aura_product_ids() {
+        assert_eq!(aura_product_id("aura_19b6.ron"), Some("19b6"));
+        assert_eq!(aura_product_id("asusd.ron"), None);
+    }
+}
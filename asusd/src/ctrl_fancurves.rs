@@ -1,4 +1,5 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 
 use config_traits::{StdConfig, StdConfigLoad};
@@ -14,7 +15,8 @@ use zbus::object_server::SignalEmitter;
 use zbus::{interface, Connection};
 
 use crate::error::RogError;
-use crate::{CtrlTask, CONFIG_PATH_BASE};
+use crate::soft_fan::{default_curves, retry_delay, SoftFanControl, HWMON_ROOT};
+use crate::{CtrlTask, Reloadable, CONFIG_PATH_BASE};
 
 pub const FAN_CURVE_ZBUS_NAME: &str = "FanCurves";
 pub const FAN_CURVE_ZBUS_PATH: &str = "/xyz/ljones";
@@ -22,10 +24,87 @@ pub const FAN_CURVE_ZBUS_PATH: &str = "/xyz/ljones";
 #[derive(Deserialize, Serialize, Debug, Default)]
 pub struct FanCurveConfig {
     pub profiles: FanCurveProfiles,
+    /// Run the curves from asusd if the firmware has no fan curve support
+    #[serde(default)]
+    pub software_fallback: bool,
     #[serde(skip)]
     pub current: PlatformProfile
 }
 
+fn all_curves(profiles: &FanCurveProfiles) -> impl Iterator<Item = (PlatformProfile, &CurveData)> {
+    [
+        (PlatformProfile::Balanced, &profiles.balanced),
+        (PlatformProfile::Performance, &profiles.performance),
+        (PlatformProfile::Quiet, &profiles.quiet)
+    ]
+    .into_iter()
+    .flat_map(|(profile, curves)| curves.iter().map(move |curve| (profile, curve)))
+}
+
+/// Check every enabled curve of every profile with `CurveData::validate`.
+/// Disabled curves are never run so are left as they are.
+pub fn validate_profiles(profiles: &FanCurveProfiles) -> Result<(), RogError> {
+    for (profile, curve) in all_curves(profiles).filter(|(_, c)| c.enabled) {
+        curve
+            .validate()
+            .map_err(|e| RogError::FanCurve(profile, curve.fan, e))?;
+    }
+    Ok(())
+}
+
+/// Firmware defaults are stored as read, but note those which can't be
+/// enabled without changing them first
+fn log_unsafe_defaults(profiles: &FanCurveProfiles) {
+    for (profile, curve) in all_curves(profiles) {
+        if let Err(e) = curve.validate() {
+            warn!(
+                "Default {profile:?} {:?} fan curve is not safe to enable: {e}",
+                curve.fan
+            );
+        }
+    }
+}
+
+/// Run one update of the software fan control, returning the number of
+/// failed updates in a row
+async fn soft_fan_pass(
+    platform: &RogPlatform,
+    config: &Mutex<FanCurveConfig>,
+    control: &mut SoftFanControl,
+    failures: u32
+) -> u32 {
+    let res = match platform.get_platform_profile() {
+        Ok(profile) => {
+            let profile: PlatformProfile = profile.into();
+            let curves = config
+                .lock()
+                .await
+                .profiles
+                .get_fan_curves_for(profile)
+                .to_vec();
+            control.update(&curves)
+        }
+        Err(e) => Err(e.into())
+    };
+    match res {
+        Ok(()) => {
+            if failures > 0 {
+                info!("Software fan control resumed");
+            }
+            0
+        }
+        Err(e) => {
+            if failures == 0 {
+                error!("Software fan control failed, fans returned to auto until it recovers: {e}");
+            } else {
+                debug!("Software fan control failed again: {e}");
+            }
+            control.restore();
+            failures + 1
+        }
+    }
+}
+
 impl StdConfig for FanCurveConfig {
     /// Create a new config. The defaults are zeroed so the device must be read
     /// to get the actual device defaults.
@@ -47,7 +126,10 @@ impl StdConfigLoad for FanCurveConfig {}
 #[derive(Debug, Clone)]
 pub struct CtrlFanCurveZbus {
     config: Arc<Mutex<FanCurveConfig>>,
-    platform: RogPlatform
+    platform: RogPlatform,
+    /// The hwmon root used for userspace control when the firmware has no
+    /// fan curves
+    software: Option<PathBuf>
 }
 
 // Non-zbus-derive impl
@@ -56,10 +138,16 @@ impl CtrlFanCurveZbus {
         let platform = RogPlatform::new()?;
         if platform.has_platform_profile() {
             info!("Device has profile control available");
-            find_fan_curve_node()?;
+            if let Err(e) = find_fan_curve_node() {
+                return Self::new_software(platform, Path::new(HWMON_ROOT), e);
+            }
             info!("Device has fan curves available");
             let mut config = FanCurveConfig::new().load();
             let mut fan_curves = FanCurveProfiles::default();
+            if let Err(e) = validate_profiles(&config.profiles) {
+                warn!("Stored fan curves are not safe, fetching the defaults: {e}");
+                config.profiles = FanCurveProfiles::default();
+            }
 
             // Only do defaults if the config doesn't already exist\
             if config.profiles.balanced.is_empty() || !config.file_path().exists() {
@@ -84,64 +172,156 @@ impl CtrlFanCurveZbus {
                     }
                 }
                 platform.set_platform_profile(current.as_str())?;
+                log_unsafe_defaults(&fan_curves);
                 config.profiles = fan_curves;
                 config.write();
             } else {
-                info!("Fan curves previously stored, loading...");
-                config = config.load();
+                info!("Fan curves previously stored, loaded");
             }
 
             return Ok(Self {
                 config: Arc::new(Mutex::new(config)),
-                platform
+                platform,
+                software: None
             });
         }
 
         Err(ProfileError::NotSupported.into())
     }
+
+    /// Fall back to driving the fans from asusd, only if enabled in the config
+    fn new_software(
+        platform: RogPlatform,
+        hwmon_root: &Path,
+        err: ProfileError
+    ) -> Result<Self, RogError> {
+        // Read without creating the file, which only exists once the
+        // fallback has been enabled
+        let mut config = FanCurveConfig::new().read_new().unwrap_or_default();
+        if !config.software_fallback {
+            return Err(err.into());
+        }
+        let fans = SoftFanControl::new(hwmon_root)?.fans();
+        info!("Device has no firmware fan curves, using software control of {fans:?}");
+
+        if let Err(e) = validate_profiles(&config.profiles) {
+            warn!("Stored fan curves are not safe, using the defaults: {e}");
+            config.profiles = FanCurveProfiles::default();
+        }
+        if config.profiles.balanced.is_empty() {
+            config.profiles = FanCurveProfiles {
+                balanced: default_curves(PlatformProfile::Balanced, &fans),
+                performance: default_curves(PlatformProfile::Performance, &fans),
+                quiet: default_curves(PlatformProfile::Quiet, &fans)
+            };
+            config.write();
+        }
+
+        Ok(Self {
+            config: Arc::new(Mutex::new(config)),
+            platform,
+            software: Some(hwmon_root.to_path_buf())
+        })
+    }
+
+    /// Write the curves of a profile to the platform. With software control
+    /// the loop picks up the stored curves on its next pass instead.
+    async fn write_profile_curve(&self, profile: PlatformProfile) -> Result<(), RogError> {
+        if self.software.is_none() {
+            self.config
+                .lock()
+                .await
+                .profiles
+                .write_profile_curve_to_platform(profile, &mut find_fan_curve_node()?)?;
+        }
+        Ok(())
+    }
+
+    /// Reset the stored curves of a profile to the defaults. The firmware
+    /// defaults can be read only for the active profile so it is switched to
+    /// and back.
+    async fn curves_to_defaults(&self, profile: PlatformProfile) -> Result<(), RogError> {
+        if self.software.is_some() {
+            let fans = self.supported_fans().await;
+            let mut config = self.config.lock().await;
+            for curve in default_curves(profile, &fans) {
+                config.profiles.save_fan_curve(curve, profile)?;
+            }
+            config.write();
+            return Ok(());
+        }
+
+        let active = self.platform.get_platform_profile()?;
+        self.platform.set_platform_profile(profile.into())?;
+        self.config
+            .lock()
+            .await
+            .profiles
+            .set_active_curve_to_defaults(profile, &mut find_fan_curve_node()?)?;
+        self.platform.set_platform_profile(active.as_str())?;
+        self.config.lock().await.write();
+        Ok(())
+    }
+
+    /// The fans this machine has curves for
+    pub async fn supported_fans(&self) -> Vec<FanCurvePU> {
+        let config = self.config.lock().await;
+        config.profiles.balanced.iter().map(|c| c.fan).collect()
+    }
+
+    /// Replace all stored curves, then write those of the active profile to
+    /// the platform. Nothing is replaced if any curve fails
+    /// `CurveData::validate`.
+    pub async fn replace_profiles(&mut self, profiles: FanCurveProfiles) -> Result<(), RogError> {
+        validate_profiles(&profiles)?;
+        {
+            let mut config = self.config.lock().await;
+            config.profiles = profiles;
+            config.write();
+        }
+        self.reload().await
+    }
 }
 
 #[interface(name = "xyz.ljones.FanCurves")]
 impl CtrlFanCurveZbus {
     /// Set all fan curves for a profile to enabled status. Will also activate a
-    /// fan curve if in the same profile mode
+    /// fan curve if in the same profile mode. Curves which fail
+    /// `CurveData::validate` can't be enabled.
     async fn set_fan_curves_enabled(
         &mut self,
         profile: PlatformProfile,
         enabled: bool
     ) -> zbus::fdo::Result<()> {
-        self.config
-            .lock()
-            .await
-            .profiles
-            .set_profile_curves_enabled(profile, enabled);
-        self.config
-            .lock()
-            .await
-            .profiles
-            .write_profile_curve_to_platform(profile, &mut find_fan_curve_node()?)?;
+        {
+            let mut config = self.config.lock().await;
+            let mut profiles = config.profiles.clone();
+            profiles.set_profile_curves_enabled(profile, enabled);
+            validate_profiles(&profiles)?;
+            config.profiles = profiles;
+        }
+        self.write_profile_curve(profile).await?;
         self.config.lock().await.write();
         Ok(())
     }
 
     /// Set a single fan curve for a profile to enabled status. Will also
-    /// activate a fan curve if in the same profile mode
+    /// activate a fan curve if in the same profile mode. Curves which fail
+    /// `CurveData::validate` can't be enabled.
     async fn set_profile_fan_curve_enabled(
         &mut self,
         profile: PlatformProfile,
         fan: FanCurvePU,
         enabled: bool
     ) -> zbus::fdo::Result<()> {
-        self.config
-            .lock()
-            .await
-            .profiles
-            .set_profile_fan_curve_enabled(profile, fan, enabled);
-        self.config
-            .lock()
-            .await
-            .profiles
-            .write_profile_curve_to_platform(profile, &mut find_fan_curve_node()?)?;
+        {
+            let mut config = self.config.lock().await;
+            let mut profiles = config.profiles.clone();
+            profiles.set_profile_fan_curve_enabled(profile, fan, enabled);
+            validate_profiles(&profiles)?;
+            config.profiles = profiles;
+        }
+        self.write_profile_curve(profile).await?;
         self.config.lock().await.write();
         Ok(())
     }
@@ -163,11 +343,14 @@ impl CtrlFanCurveZbus {
 
     /// Set the fan curve for the specified profile.
     /// Will also activate the fan curve if the user is in the same mode.
+    ///
+    /// Curves which fail `CurveData::validate` are rejected.
     async fn set_fan_curve(
         &mut self,
         profile: PlatformProfile,
         curve: CurveData
     ) -> zbus::fdo::Result<()> {
+        curve.validate()?;
         self.config
             .lock()
             .await
@@ -175,11 +358,7 @@ impl CtrlFanCurveZbus {
             .save_fan_curve(curve, profile)?;
         let active: PlatformProfile = self.platform.get_platform_profile()?.into();
         if active == profile {
-            self.config
-                .lock()
-                .await
-                .profiles
-                .write_profile_curve_to_platform(profile, &mut find_fan_curve_node()?)?;
+            self.write_profile_curve(profile).await?;
         }
         self.config.lock().await.write();
         Ok(())
@@ -191,15 +370,7 @@ impl CtrlFanCurveZbus {
     /// Each platform_profile has a different default and the default can be
     /// read only for the currently active profile.
     async fn set_curves_to_defaults(&mut self, profile: PlatformProfile) -> zbus::fdo::Result<()> {
-        let active = self.platform.get_platform_profile()?;
-        self.platform.set_platform_profile(profile.into())?;
-        self.config
-            .lock()
-            .await
-            .profiles
-            .set_active_curve_to_defaults(profile, &mut find_fan_curve_node()?)?;
-        self.platform.set_platform_profile(active.as_str())?;
-        self.config.lock().await.write();
+        self.curves_to_defaults(profile).await?;
         Ok(())
     }
 
@@ -209,6 +380,10 @@ impl CtrlFanCurveZbus {
     /// Each platform_profile has a different default and the defualt can be
     /// read only for the currently active profile.
     async fn reset_profile_curves(&self, profile: PlatformProfile) -> zbus::fdo::Result<()> {
+        if self.software.is_some() {
+            self.curves_to_defaults(profile).await?;
+            return Ok(());
+        }
         let active = self.platform.get_platform_profile()?;
 
         self.platform.set_platform_profile(profile.into())?;
@@ -236,6 +411,52 @@ impl CtrlTask for CtrlFanCurveZbus {
     }
 
     async fn create_tasks(&self, _signal_ctxt: SignalEmitter<'static>) -> Result<(), RogError> {
+        if let Some(hwmon_root) = &self.software {
+            // The active profile and curves are read on each pass so there is
+            // nothing to watch
+            let control = Arc::new(Mutex::new(SoftFanControl::new(hwmon_root)?));
+            // Set while shutting down, the fans are handed back to the
+            // firmware and left there
+            let stopped = Arc::new(AtomicBool::new(false));
+            let platform = self.platform.clone();
+            let config = self.config.clone();
+            let control1 = control.clone();
+            let stopped1 = stopped.clone();
+            tokio::spawn(async move {
+                let mut failures = 0;
+                loop {
+                    let mut control = control1.lock().await;
+                    // Checked with the lock held so an update can't take the
+                    // fans back once the shutdown task has restored them
+                    if !stopped1.load(Ordering::Acquire) {
+                        failures = soft_fan_pass(&platform, &config, &mut control, failures).await;
+                    }
+                    drop(control);
+                    tokio::time::sleep(retry_delay(failures)).await;
+                }
+            });
+
+            self.create_sys_event_tasks(
+                move |_sleeping| async move {},
+                move |shutting_down| {
+                    let control = control.clone();
+                    let stopped = stopped.clone();
+                    async move {
+                        let mut control = control.lock().await;
+                        stopped.store(shutting_down, Ordering::Release);
+                        if shutting_down {
+                            info!("Software fan control stopped, fans returned to auto");
+                            control.restore();
+                        }
+                    }
+                },
+                move |_lid_closed| async move {},
+                move |_power_plugged| async move {}
+            )
+            .await;
+            return Ok(());
+        }
+
         let watch_platform_profile = self.platform.monitor_platform_profile()?;
         let platform = self.platform.clone();
         let config = self.config.clone();
@@ -276,11 +497,20 @@ impl CtrlTask for CtrlFanCurveZbus {
     }
 }
 
-impl crate::Reloadable for CtrlFanCurveZbus {
-    /// Fetch the active profile and use that to set all related components up
+impl Reloadable for CtrlFanCurveZbus {
+    /// Fetch the active profile and use that to set all related components
+    /// up. The curves are not written if any enabled curve fails
+    /// `CurveData::validate`.
     async fn reload(&mut self) -> Result<(), RogError> {
+        if self.software.is_some() {
+            return Ok(());
+        }
         let active = self.platform.get_platform_profile()?.into();
         let mut config = self.config.lock().await;
+        if let Err(e) = validate_profiles(&config.profiles) {
+            warn!("Not writing the stored fan curves: {e}");
+            return Ok(());
+        }
         if let Ok(mut device) = find_fan_curve_node() {
             config
                 .profiles
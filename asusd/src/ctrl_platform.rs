@@ -1,24 +1,29 @@
 use std::path::Path;
 use std::process::Command;
 use std::sync::Arc;
+use std::time::Duration;
 
-use config_traits::StdConfig;
+use config_traits::{ron, StdConfig};
 use log::{debug, error, info, warn};
 use rog_platform::asus_armoury::{AttrValue, FirmwareAttribute, FirmwareAttributes};
 use rog_platform::cpu::{CPUControl, CPUGovernor, CPUEPP};
 use rog_platform::platform::{PlatformProfile, Properties, RogPlatform};
 use rog_platform::power::AsusPower;
+use tokio::sync::Notify;
 use zbus::export::futures_util::lock::Mutex;
 use zbus::fdo::Error as FdoErr;
 use zbus::object_server::SignalEmitter;
 use zbus::{interface, Connection};
 
 use crate::asus_armoury::set_config_or_default;
+use crate::charge_schedule::{local_clock, ChargeSchedule, ChargeScheduler, POWER_SUPPLY_ROOT};
 use crate::config::Config;
 use crate::error::RogError;
 use crate::{task_watch_item, CtrlTask, ReloadAndNotify};
 
 const PLATFORM_ZBUS_PATH: &str = "/xyz/ljones";
+/// How often the charge schedule is checked
+const CHARGE_SCHEDULE_INTERVAL: Duration = Duration::from_secs(60);
 
 macro_rules! platform_get_value {
     ($self:ident, $property:tt, $prop_name:literal) => {
@@ -45,7 +50,10 @@ pub struct CtrlPlatform {
     platform: RogPlatform,
     attributes: FirmwareAttributes,
     cpu_control: Option<CPUControl>,
-    config: Arc<Mutex<Config>>
+    config: Arc<Mutex<Config>>,
+    charge_scheduler: Arc<Mutex<ChargeScheduler>>,
+    /// Wakes the charge schedule task early
+    charge_schedule_updated: Arc<Notify>
 }
 
 impl CtrlPlatform {
@@ -67,7 +75,12 @@ impl CtrlPlatform {
             config,
             cpu_control: CPUControl::new()
                 .map_err(|e| error!("Couldn't get CPU control sysfs: {e}"))
-                .ok()
+                .ok(),
+            charge_scheduler: Arc::new(Mutex::new(ChargeScheduler::new(
+                Path::new(POWER_SUPPLY_ROOT),
+                local_clock()
+            ))),
+            charge_schedule_updated: Arc::new(Notify::new())
         };
         let mut inotify_self = ret_self.clone();
 
@@ -141,6 +154,52 @@ impl CtrlPlatform {
         }
     }
 
+    /// Step the charge schedule and apply any change to the charge limit
+    async fn run_charge_schedule(&self, signal_ctxt: &SignalEmitter<'_>) {
+        if !self.power.has_charge_control_end_threshold() {
+            return;
+        }
+        let mut guard = self.config.lock().await;
+        let config = &mut *guard;
+        let before = config.charge_schedule.clone();
+        let limit = config.charge_control_end_threshold;
+        let res = self.charge_scheduler.lock().await.tick(
+            &mut config.charge_schedule,
+            limit,
+            &mut config.base_charge_control_end_threshold
+        );
+        let mut changed = false;
+        match res {
+            Ok(Some(limit)) => {
+                info!("Charge schedule setting charge_control_end_threshold to {limit}");
+                match self.power.set_charge_control_end_threshold(limit) {
+                    Ok(_) => {
+                        config.charge_control_end_threshold = limit;
+                        changed = true;
+                    }
+                    Err(e) => error!("Charge schedule couldn't set charge limit: {e}")
+                }
+            }
+            Ok(None) => {}
+            Err(e) => warn!("Charge schedule: {e}")
+        }
+        let rate_changed = config.charge_schedule.charge_rate != before.charge_rate;
+        if changed || config.charge_schedule != before {
+            config.write();
+        }
+        drop(guard);
+
+        if changed {
+            self.charge_control_end_threshold_changed(signal_ctxt)
+                .await
+                .ok();
+            self.scheduled_charge_limit_changed(signal_ctxt).await.ok();
+        }
+        if rate_changed {
+            self.charge_rate_changed(signal_ctxt).await.ok();
+        }
+    }
+
     async fn run_ac_or_bat_cmd(&self, power_plugged: bool) {
         let prog: Vec<String> = if power_plugged {
             // AC ONLINE
@@ -296,12 +355,80 @@ impl CtrlPlatform {
             return Err(RogError::ChargeLimit(limit))?;
         }
         self.power.set_charge_control_end_threshold(limit)?;
-        self.config.lock().await.charge_control_end_threshold = limit;
-        self.config.lock().await.base_charge_control_end_threshold = limit;
-        self.config.lock().await.write();
+        let mut config = self.config.lock().await;
+        config.charge_control_end_threshold = limit;
+        config.base_charge_control_end_threshold = limit;
+        config.write();
+        Ok(())
+    }
+
+    /// Enable the time based charge limit schedule
+    #[zbus(property)]
+    async fn charge_schedule_enabled(&self) -> bool {
+        self.config.lock().await.charge_schedule.enabled
+    }
+
+    #[zbus(property)]
+    async fn set_charge_schedule_enabled(&mut self, enabled: bool) {
+        let mut config = self.config.lock().await;
+        config.charge_schedule.enabled = enabled;
+        config.write();
+        self.charge_schedule_updated.notify_one();
+    }
+
+    /// The windows and full-by targets of the charge schedule as a RON
+    /// string, see `asusd::charge_schedule::ChargeSchedule`. The learned
+    /// charge rate and raised state are not changed when set.
+    #[zbus(property)]
+    async fn charge_schedule(&self) -> Result<String, FdoErr> {
+        let config = self.config.lock().await;
+        ron::ser::to_string_pretty(&config.charge_schedule, ron::ser::PrettyConfig::new())
+            .map_err(|e| FdoErr::Failed(format!("ChargeSchedule: {e}")))
+    }
+
+    #[zbus(property)]
+    async fn set_charge_schedule(&mut self, schedule: String) -> Result<(), FdoErr> {
+        let schedule: ChargeSchedule = ron::from_str(&schedule)
+            .map_err(|e| FdoErr::InvalidArgs(format!("ChargeSchedule: {e}")))?;
+        if let Some(window) = schedule
+            .windows
+            .iter()
+            .find(|w| !(20..=100).contains(&w.limit))
+        {
+            return Err(RogError::ChargeLimit(window.limit))?;
+        }
+        let mut config = self.config.lock().await;
+        let current = &mut config.charge_schedule;
+        current.enabled = schedule.enabled;
+        current.windows = schedule.windows;
+        current.full_by = schedule.full_by;
+        config.write();
+        self.charge_schedule_updated.notify_one();
         Ok(())
     }
 
+    /// The limit the charge schedule is currently applying, 0 if none
+    #[zbus(property)]
+    async fn scheduled_charge_limit(&self) -> u8 {
+        let config = self.config.lock().await;
+        let user = config.charge_schedule.user_limit(
+            config.charge_control_end_threshold,
+            config.base_charge_control_end_threshold
+        );
+        self.charge_scheduler
+            .lock()
+            .await
+            .scheduled_limit(&config.charge_schedule)
+            .filter(|limit| *limit > user)
+            .unwrap_or_default()
+    }
+
+    /// The observed charge rate in percent per hour
+    #[zbus(property)]
+    async fn charge_rate(&self) -> f64 {
+        self.config.lock().await.charge_schedule.charge_rate as f64
+    }
+
     async fn one_shot_full_charge(&self) -> Result<(), FdoErr> {
         let base_limit = std::mem::replace(
             &mut self.config.lock().await.charge_control_end_threshold,
@@ -791,6 +918,18 @@ impl CtrlTask for CtrlPlatform {
         self.watch_charge_control_end_threshold(signal_ctxt.clone())
             .await?;
 
+        let ctrl = self.clone();
+        let ctxt = signal_ctxt.clone();
+        tokio::spawn(async move {
+            loop {
+                ctrl.run_charge_schedule(&ctxt).await;
+                tokio::select! {
+                    _ = tokio::time::sleep(CHARGE_SCHEDULE_INTERVAL) => {}
+                    _ = ctrl.charge_schedule_updated.notified() => {}
+                }
+            }
+        });
+
         let watch_platform_profile = self.platform.monitor_platform_profile()?;
         let ctrl = self.clone();
 
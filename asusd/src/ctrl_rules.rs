@@ -0,0 +1,620 @@
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use config_traits::{ron, StdConfig, StdConfigLoad};
+use log::{debug, error, info, warn};
+use logind_zbus::manager::ManagerProxy;
+use rog_aura::AuraModeNum;
+use rog_platform::platform::{PlatformProfile, RogPlatform};
+use rog_platform::power::AsusPower;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+use zbus::fdo::Error as FdoErr;
+use zbus::object_server::SignalEmitter;
+use zbus::proxy::CacheProperties;
+use zbus::{interface, Connection};
+
+use crate::error::RogError;
+use crate::loopback::Loopback;
+use crate::time_of_day::TimeOfDay;
+use crate::{CtrlTask, ASUS_ZBUS_PATH, CONFIG_PATH_BASE};
+
+pub const RULES_ZBUS_PATH: &str = "/xyz/ljones";
+const CONFIG_FILE: &str = "rules.ron";
+/// How often the rules are re-evaluated when no other event occurs. This is
+/// what drives time of day and battery level conditions.
+const EVALUATE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The state of the system that conditions are checked against. Built fresh
+/// for every evaluation.
+#[derive(Deserialize, Serialize, Debug, Default, Clone, Copy, PartialEq)]
+pub struct RuleState {
+    pub power_plugged: bool,
+    pub lid_closed: bool,
+    /// `None` if the battery level could not be read
+    pub battery_percent: Option<u8>,
+    /// `None` if the laptop has no `platform_profile` support
+    pub platform_profile: Option<PlatformProfile>,
+    pub time: TimeOfDay
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub enum RuleCondition {
+    /// `true` to match when on AC power, `false` for battery
+    PowerPlugged(bool),
+    LidClosed(bool),
+    /// Battery level is strictly below the percentage
+    BatteryBelow(u8),
+    /// Battery level is strictly above the percentage
+    BatteryAbove(u8),
+    PlatformProfile(PlatformProfile),
+    /// Local time is within `start..end`. If `end` is earlier than `start`
+    /// then the range wraps past midnight.
+    TimeBetween {
+        start: TimeOfDay,
+        end: TimeOfDay
+    }
+}
+
+impl RuleCondition {
+    pub fn matches(&self, state: &RuleState) -> bool {
+        match self {
+            RuleCondition::PowerPlugged(plugged) => state.power_plugged == *plugged,
+            RuleCondition::LidClosed(closed) => state.lid_closed == *closed,
+            RuleCondition::BatteryBelow(pc) => state.battery_percent.is_some_and(|b| b < *pc),
+            RuleCondition::BatteryAbove(pc) => state.battery_percent.is_some_and(|b| b > *pc),
+            RuleCondition::PlatformProfile(profile) => state.platform_profile == Some(*profile),
+            RuleCondition::TimeBetween { start, end } => {
+                let now = state.time.minutes();
+                let (start, end) = (start.minutes(), end.minutes());
+                if start <= end {
+                    start <= now && now < end
+                } else {
+                    now >= start || now < end
+                }
+            }
+        }
+    }
+}
+
+/// Each action maps directly to a property on one of the daemon's own dbus
+/// interfaces, this way the controllers apply their usual side effects and
+/// emit the changed signals.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub enum RuleAction {
+    PlatformProfile(PlatformProfile),
+    /// Battery charge limit in percent, 20-100
+    ChargeLimit(u8),
+    /// Applied to all Aura devices that support the mode
+    AuraMode(AuraModeNum),
+    AnimeDisplay(bool),
+    SlashEnabled(bool),
+    /// An `asus-armoury` firmware attribute by sysfs name, e.g. `ppt_pl1_spl`
+    Armoury {
+        name: String,
+        value: i32
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct Rule {
+    /// Unique name used to identify the rule over dbus
+    pub name: String,
+    pub enabled: bool,
+    /// All conditions must match for the rule to trigger. A rule with no
+    /// conditions always matches.
+    pub conditions: Vec<RuleCondition>,
+    /// Run in order when the rule goes from not matching to matching
+    pub actions: Vec<RuleAction>
+}
+
+impl Rule {
+    pub fn matches(&self, state: &RuleState) -> bool {
+        self.conditions.iter().all(|c| c.matches(state))
+    }
+
+    /// Check the action values are in range before the rule is stored
+    pub fn validate(&self) -> Result<(), RogError> {
+        for action in &self.actions {
+            if let RuleAction::ChargeLimit(limit) = action {
+                if !(20..=100).contains(limit) {
+                    return Err(RogError::ChargeLimit(*limit));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The result of checking a single rule, returned by the dry-run evaluation
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct RuleEvaluation {
+    pub name: String,
+    pub enabled: bool,
+    pub matched: bool,
+    /// Would the actions be run on this evaluation. This is `false` if the
+    /// rule already matched on a previous evaluation.
+    pub would_trigger: bool,
+    pub actions: Vec<RuleAction>
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct RulesConfig {
+    /// Global switch for the rules engine
+    pub enabled: bool,
+    pub rules: Vec<Rule>
+}
+
+impl Default for RulesConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            rules: Vec::new()
+        }
+    }
+}
+
+impl RulesConfig {
+    /// Check all rules against the state. `active` holds the names of rules
+    /// that matched on the last evaluation so that actions run only on the
+    /// transition to matching.
+    pub fn evaluate(&self, state: &RuleState, active: &HashSet<String>) -> Vec<RuleEvaluation> {
+        self.rules
+            .iter()
+            .map(|rule| {
+                let matched = rule.enabled && rule.matches(state);
+                RuleEvaluation {
+                    name: rule.name.clone(),
+                    enabled: rule.enabled,
+                    matched,
+                    would_trigger: self.enabled && matched && !active.contains(&rule.name),
+                    actions: rule.actions.clone()
+                }
+            })
+            .collect()
+    }
+}
+
+impl StdConfig for RulesConfig {
+    fn new() -> Self {
+        Self::default()
+    }
+
+    fn file_name(&self) -> String {
+        CONFIG_FILE.to_owned()
+    }
+
+    fn config_dir() -> std::path::PathBuf {
+        PathBuf::from(CONFIG_PATH_BASE)
+    }
+}
+
+impl StdConfigLoad for RulesConfig {}
+
+#[derive(Clone)]
+pub struct CtrlRules {
+    config: Arc<Mutex<RulesConfig>>,
+    /// Names of the rules that matched on the last evaluation
+    active: Arc<Mutex<HashSet<String>>>,
+    platform: RogPlatform,
+    power: AsusPower,
+    loopback: Loopback
+}
+
+impl CtrlRules {
+    pub fn new(platform: RogPlatform, power: AsusPower, connection: Connection) -> Self {
+        Self {
+            config: Arc::new(Mutex::new(RulesConfig::new().load())),
+            active: Default::default(),
+            platform,
+            power,
+            loopback: Loopback::new(connection)
+        }
+    }
+
+    async fn current_state(&self) -> RuleState {
+        let lid_closed = match ManagerProxy::builder(self.loopback.connection())
+            .cache_properties(CacheProperties::No)
+            .build()
+            .await
+        {
+            Ok(manager) => manager.lid_closed().await.unwrap_or_default(),
+            Err(e) => {
+                warn!("Rules: could not create ManagerProxy: {e}");
+                false
+            }
+        };
+
+        RuleState {
+            power_plugged: self.power.get_online().map(|p| p == 1).unwrap_or_default(),
+            lid_closed,
+            battery_percent: self.power.get_capacity().ok(),
+            platform_profile: self
+                .platform
+                .has_platform_profile()
+                .then(|| self.platform.get_platform_profile().ok())
+                .flatten()
+                .map(|p| p.into()),
+            time: TimeOfDay::now()
+        }
+    }
+
+    /// Evaluate all rules against the current system state and run the
+    /// actions of any rules which have newly matched.
+    async fn evaluate_and_apply(&self) {
+        let state = self.current_state().await;
+        let mut triggered = Vec::new();
+        {
+            let config = self.config.lock().await;
+            if !config.enabled {
+                return;
+            }
+            let mut active = self.active.lock().await;
+            for eval in config.evaluate(&state, &active) {
+                if eval.matched {
+                    active.insert(eval.name.clone());
+                } else {
+                    active.remove(&eval.name);
+                }
+                if eval.would_trigger {
+                    triggered.push(eval);
+                }
+            }
+        }
+
+        // Locks are released here as the actions may cause further events
+        for eval in triggered {
+            info!("Rules: '{}' matched, applying actions", eval.name);
+            for action in &eval.actions {
+                if let Err(e) = self.apply_action(action).await {
+                    error!("Rules: '{}' action {action:?} failed: {e}", eval.name);
+                }
+            }
+        }
+    }
+
+    async fn apply_action(&self, action: &RuleAction) -> Result<(), RogError> {
+        debug!("Rules: applying {action:?}");
+        match action {
+            RuleAction::PlatformProfile(profile) => {
+                self.loopback
+                    .set_property(
+                        RULES_ZBUS_PATH,
+                        "xyz.ljones.Platform",
+                        "PlatformProfile",
+                        *profile
+                    )
+                    .await
+            }
+            RuleAction::ChargeLimit(limit) => {
+                self.loopback
+                    .set_property(
+                        RULES_ZBUS_PATH,
+                        "xyz.ljones.Platform",
+                        "ChargeControlEndThreshold",
+                        *limit
+                    )
+                    .await
+            }
+            RuleAction::AuraMode(mode) => {
+                self.loopback
+                    .set_property_on_all("xyz.ljones.Aura", "LedMode", *mode)
+                    .await
+            }
+            RuleAction::AnimeDisplay(enabled) => {
+                self.loopback
+                    .set_property_on_all("xyz.ljones.Anime", "EnableDisplay", *enabled)
+                    .await
+            }
+            RuleAction::SlashEnabled(enabled) => {
+                self.loopback
+                    .set_property_on_all("xyz.ljones.Slash", "Enabled", *enabled)
+                    .await
+            }
+            RuleAction::Armoury { name, value } => {
+                self.loopback
+                    .set_property(
+                        &format!("{ASUS_ZBUS_PATH}/asus_armoury/{name}"),
+                        "xyz.ljones.AsusArmoury",
+                        "CurrentValue",
+                        *value
+                    )
+                    .await
+            }
+        }
+    }
+}
+
+#[interface(name = "xyz.ljones.Rules")]
+impl CtrlRules {
+    /// Enable or disable the rules engine as a whole
+    #[zbus(property)]
+    async fn enabled(&self) -> bool {
+        self.config.lock().await.enabled
+    }
+
+    #[zbus(property)]
+    async fn set_enabled(&mut self, enabled: bool) {
+        let mut config = self.config.lock().await;
+        config.enabled = enabled;
+        config.write();
+    }
+
+    /// Return all rules as a RON string
+    async fn list_rules(&self) -> zbus::fdo::Result<String> {
+        let config = self.config.lock().await;
+        ron::ser::to_string_pretty(&config.rules, ron::ser::PrettyConfig::new().depth_limit(4))
+            .map_err(|e| FdoErr::Failed(format!("Rules: {e}")))
+    }
+
+    /// Add a rule from a RON string. A rule with the same name is replaced.
+    async fn add_rule(&mut self, rule: String) -> zbus::fdo::Result<()> {
+        let rule: Rule = ron::from_str(&rule)
+            .map_err(|e| FdoErr::InvalidArgs(format!("Rules: could not parse rule: {e}")))?;
+        rule.validate()
+            .map_err(|e| FdoErr::InvalidArgs(format!("Rules: {e}")))?;
+        let mut config = self.config.lock().await;
+        if let Some(existing) = config.rules.iter_mut().find(|r| r.name == rule.name) {
+            *existing = rule;
+        } else {
+            config.rules.push(rule);
+        }
+        config.write();
+        Ok(())
+    }
+
+    async fn remove_rule(&mut self, name: String) -> zbus::fdo::Result<()> {
+        let mut config = self.config.lock().await;
+        let len = config.rules.len();
+        config.rules.retain(|r| r.name != name);
+        if config.rules.len() == len {
+            return Err(FdoErr::Failed(format!("Rules: no rule named {name}")));
+        }
+        config.write();
+        self.active.lock().await.remove(&name);
+        Ok(())
+    }
+
+    async fn set_rule_enabled(&mut self, name: String, enabled: bool) -> zbus::fdo::Result<()> {
+        let mut config = self.config.lock().await;
+        let rule = config
+            .rules
+            .iter_mut()
+            .find(|r| r.name == name)
+            .ok_or_else(|| FdoErr::Failed(format!("Rules: no rule named {name}")))?;
+        rule.enabled = enabled;
+        config.write();
+        if !enabled {
+            self.active.lock().await.remove(&name);
+        }
+        Ok(())
+    }
+
+    /// Check all rules against the current system state without running any
+    /// actions. Returns the results as a RON string.
+    async fn dry_run(&self) -> zbus::fdo::Result<String> {
+        let state = self.current_state().await;
+        let evaluations = self
+            .config
+            .lock()
+            .await
+            .evaluate(&state, &*self.active.lock().await);
+        ron::ser::to_string_pretty(
+            &(state, evaluations),
+            ron::ser::PrettyConfig::new().depth_limit(4)
+        )
+        .map_err(|e| FdoErr::Failed(format!("Rules: {e}")))
+    }
+}
+
+impl crate::ZbusRun for CtrlRules {
+    async fn add_to_server(self, server: &mut Connection) {
+        Self::add_to_server_helper(self, RULES_ZBUS_PATH, server).await;
+    }
+}
+
+impl crate::Reloadable for CtrlRules {
+    /// Nothing to restore, the rules are evaluated once the tasks start
+    async fn reload(&mut self) -> Result<(), RogError> {
+        Ok(())
+    }
+}
+
+impl CtrlTask for CtrlRules {
+    fn zbus_path() -> &'static str {
+        RULES_ZBUS_PATH
+    }
+
+    async fn create_tasks(&self, _: SignalEmitter<'static>) -> Result<(), RogError> {
+        let rules1 = self.clone();
+        let rules2 = self.clone();
+        let rules3 = self.clone();
+        self.create_sys_event_tasks(
+            move |sleeping| {
+                let rules1 = rules1.clone();
+                async move {
+                    if !sleeping {
+                        // Hardware state may have been reset over suspend so
+                        // allow all matching rules to run again
+                        rules1.active.lock().await.clear();
+                        rules1.evaluate_and_apply().await;
+                    }
+                }
+            },
+            move |_| async move {},
+            move |_| {
+                let rules2 = rules2.clone();
+                async move {
+                    rules2.evaluate_and_apply().await;
+                }
+            },
+            move |_| {
+                let rules3 = rules3.clone();
+                async move {
+                    rules3.evaluate_and_apply().await;
+                }
+            }
+        )
+        .await;
+
+        if let Ok(watch_platform_profile) = self.platform.monitor_platform_profile() {
+            let rules = self.clone();
+            tokio::spawn(async move {
+                use futures_lite::StreamExt;
+                let mut buffer = [0; 32];
+                if let Ok(mut stream) = watch_platform_profile.into_event_stream(&mut buffer) {
+                    while (stream.next().await).is_some() {
+                        debug!("Rules: platform_profile changed");
+                        rules.evaluate_and_apply().await;
+                    }
+                }
+            });
+        }
+
+        let rules = self.clone();
+        tokio::spawn(async move {
+            let mut timer = tokio::time::interval(EVALUATE_INTERVAL);
+            loop {
+                // First tick completes immediately which gives the initial
+                // evaluation on startup
+                timer.tick().await;
+                rules.evaluate_and_apply().await;
+            }
+        });
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use config_traits::ron;
+    use rog_platform::platform::PlatformProfile;
+
+    use super::{Rule, RuleAction, RuleCondition, RuleState, RulesConfig, TimeOfDay};
+
+    fn battery_rule() -> Rule {
+        Rule {
+            name: "low battery".to_owned(),
+            enabled: true,
+            conditions: vec![
+                RuleCondition::PowerPlugged(false),
+                RuleCondition::BatteryBelow(30),
+            ],
+            actions: vec![RuleAction::PlatformProfile(PlatformProfile::Quiet)]
+        }
+    }
+
+    #[test]
+    fn time_between_wraps_midnight() {
+        let cond = RuleCondition::TimeBetween {
+            start: TimeOfDay::new(22, 0),
+            end: TimeOfDay::new(7, 30)
+        };
+        let mut state = RuleState {
+            time: TimeOfDay::new(23, 15),
+            ..Default::default()
+        };
+        assert!(cond.matches(&state));
+        state.time = TimeOfDay::new(3, 0);
+        assert!(cond.matches(&state));
+        state.time = TimeOfDay::new(7, 30);
+        assert!(!cond.matches(&state));
+        state.time = TimeOfDay::new(12, 0);
+        assert!(!cond.matches(&state));
+    }
+
+    #[test]
+    fn battery_unknown_never_matches() {
+        let state = RuleState::default();
+        assert!(!RuleCondition::BatteryBelow(30).matches(&state));
+        assert!(!RuleCondition::BatteryAbove(30).matches(&state));
+    }
+
+    #[test]
+    fn evaluate_triggers_once() {
+        let config = RulesConfig {
+            enabled: true,
+            rules: vec![battery_rule()]
+        };
+        let state = RuleState {
+            power_plugged: false,
+            battery_percent: Some(20),
+            ..Default::default()
+        };
+
+        let mut active = HashSet::new();
+        let eval = config.evaluate(&state, &active);
+        assert!(eval[0].matched);
+        assert!(eval[0].would_trigger);
+
+        active.insert(eval[0].name.clone());
+        let eval = config.evaluate(&state, &active);
+        assert!(eval[0].matched);
+        assert!(!eval[0].would_trigger);
+    }
+
+    #[test]
+    fn evaluate_disabled() {
+        let mut config = RulesConfig {
+            enabled: true,
+            rules: vec![battery_rule()]
+        };
+        config.rules[0].enabled = false;
+        let state = RuleState {
+            power_plugged: false,
+            battery_percent: Some(20),
+            ..Default::default()
+        };
+        let eval = config.evaluate(&state, &HashSet::new());
+        assert!(!eval[0].matched);
+        assert!(!eval[0].would_trigger);
+
+        config.rules[0].enabled = true;
+        config.enabled = false;
+        let eval = config.evaluate(&state, &HashSet::new());
+        assert!(eval[0].matched);
+        assert!(!eval[0].would_trigger);
+    }
+
+    #[test]
+    fn charge_limit_out_of_range() {
+        let mut rule = battery_rule();
+        rule.actions.push(RuleAction::ChargeLimit(80));
+        assert!(rule.validate().is_ok());
+        for limit in [
+            0, 19, 101, 255
+        ] {
+            rule.actions = vec![RuleAction::ChargeLimit(limit)];
+            assert!(rule.validate().is_err());
+        }
+    }
+
+    #[test]
+    fn rule_ron_round_trip() {
+        let rule = Rule {
+            name: "night".to_owned(),
+            enabled: true,
+            conditions: vec![
+                RuleCondition::TimeBetween {
+                    start: TimeOfDay::new(22, 0),
+                    end: TimeOfDay::new(7, 0)
+                },
+            ],
+            actions: vec![
+                RuleAction::ChargeLimit(80),
+                RuleAction::Armoury {
+                    name: "ppt_pl1_spl".to_owned(),
+                    value: 25
+                },
+            ]
+        };
+        let s = ron::to_string(&rule).unwrap();
+        let back: Rule = ron::from_str(&s).unwrap();
+        assert_eq!(rule, back);
+    }
+}
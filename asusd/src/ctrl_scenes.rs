@@ -0,0 +1,904 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use config_traits::{StdConfig, StdConfigLoad};
+use log::{info, warn};
+use rog_anime::usb::Brightness;
+use rog_anime::Animations;
+use rog_aura::keyboard::LaptopAuraPower;
+use rog_aura::{AuraEffect, LedBrightness};
+use rog_platform::asus_armoury::FirmwareAttributes;
+use rog_platform::cpu::CPUEPP;
+use rog_platform::platform::PlatformProfile;
+use rog_platform::power::AsusPower;
+use rog_profiles::fan_curve_set::CurveData;
+use rog_profiles::FanCurveProfiles;
+use rog_slash::SlashMode;
+use serde::{Deserialize, Serialize};
+use zbus::export::futures_util::lock::Mutex;
+use zbus::fdo::Error as FdoErr;
+use zbus::object_server::SignalEmitter;
+use zbus::{interface, Connection};
+
+use crate::asus_armoury::set_config_or_default;
+use crate::config::{Config, Tuning};
+use crate::ctrl_fancurves::FAN_CURVE_ZBUS_PATH;
+use crate::error::RogError;
+use crate::loopback::Loopback;
+use crate::{CtrlTask, CONFIG_PATH_BASE};
+
+pub const SCENES_ZBUS_PATH: &str = "/xyz/ljones";
+const CONFIG_FILE: &str = "scenes.ron";
+
+const PLATFORM_PATH: &str = "/xyz/ljones";
+const PLATFORM_IFACE: &str = "xyz.ljones.Platform";
+const FAN_CURVES_IFACE: &str = "xyz.ljones.FanCurves";
+const AURA_IFACE: &str = "xyz.ljones.Aura";
+const ANIME_IFACE: &str = "xyz.ljones.Anime";
+const SLASH_IFACE: &str = "xyz.ljones.Slash";
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct PlatformScene {
+    pub platform_profile: PlatformProfile,
+    pub platform_profile_linked_epp: bool,
+    pub profile_quiet_epp: CPUEPP,
+    pub profile_balanced_epp: CPUEPP,
+    pub profile_performance_epp: CPUEPP,
+    pub ac_profile_tunings: HashMap<PlatformProfile, Tuning>,
+    pub dc_profile_tunings: HashMap<PlatformProfile, Tuning>
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct AuraScene {
+    /// The USB product ID (or `tuf`) of the device, taken from its dbus path.
+    /// Used to find the same device again as the rest of the path may change
+    /// between boots.
+    pub device: String,
+    pub brightness: LedBrightness,
+    pub effect: AuraEffect,
+    pub power: LaptopAuraPower
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct AnimeScene {
+    pub enable_display: bool,
+    pub brightness: Brightness,
+    pub builtins_enabled: bool,
+    pub builtin_animations: Animations,
+    pub off_when_unplugged: bool,
+    pub off_when_suspended: bool,
+    pub off_when_lid_closed: bool
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct SlashScene {
+    pub enabled: bool,
+    pub brightness: u8,
+    pub interval: u8,
+    pub mode: SlashMode,
+    pub show_on_boot: bool,
+    pub show_on_sleep: bool,
+    pub show_on_shutdown: bool,
+    pub show_on_battery: bool,
+    pub show_battery_warning: bool
+}
+
+/// A named snapshot of the machine state. Any part that was not available
+/// when the scene was saved is left empty and skipped on apply.
+#[derive(Deserialize, Serialize, Clone)]
+pub struct Scene {
+    pub name: String,
+    pub platform: Option<PlatformScene>,
+    pub fan_curves: Option<FanCurveProfiles>,
+    pub aura: Vec<AuraScene>,
+    pub anime: Option<AnimeScene>,
+    pub slash: Option<SlashScene>
+}
+
+#[derive(Deserialize, Serialize, Default)]
+pub struct ScenesConfig {
+    pub scenes: Vec<Scene>
+}
+
+impl StdConfig for ScenesConfig {
+    fn new() -> Self {
+        Self::default()
+    }
+
+    fn file_name(&self) -> String {
+        CONFIG_FILE.to_owned()
+    }
+
+    fn config_dir() -> std::path::PathBuf {
+        PathBuf::from(CONFIG_PATH_BASE)
+    }
+}
+
+impl StdConfigLoad for ScenesConfig {}
+
+/// Get the device identifier from an Aura dbus path such as
+/// `/xyz/ljones/aura/19b6_3_1`
+fn aura_device_id(path: &str) -> String {
+    let name = path.rsplit('/').next().unwrap_or_default();
+    name.split('_').next().unwrap_or(name).to_owned()
+}
+
+/// Which part of the machine a `ScenePart` is for. Devices are by dbus path.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum PartId {
+    Platform,
+    FanCurves,
+    Aura(String),
+    Anime(String),
+    Slash(String)
+}
+
+/// The settings of one part of the machine, as read back or to be set
+#[derive(Clone)]
+pub(crate) enum ScenePart {
+    Platform(PlatformScene),
+    FanCurves(FanCurveProfiles),
+    Aura(String, AuraScene),
+    Anime(String, AnimeScene),
+    Slash(String, SlashScene)
+}
+
+impl PartId {
+    fn label(&self) -> String {
+        match self {
+            PartId::Platform => "platform".to_owned(),
+            PartId::FanCurves => "fan curves".to_owned(),
+            PartId::Aura(path) => format!("aura {path}"),
+            PartId::Anime(_) => "anime".to_owned(),
+            PartId::Slash(_) => "slash".to_owned()
+        }
+    }
+}
+
+impl ScenePart {
+    fn id(&self) -> PartId {
+        match self {
+            ScenePart::Platform(_) => PartId::Platform,
+            ScenePart::FanCurves(_) => PartId::FanCurves,
+            ScenePart::Aura(path, _) => PartId::Aura(path.clone()),
+            ScenePart::Anime(path, _) => PartId::Anime(path.clone()),
+            ScenePart::Slash(path, _) => PartId::Slash(path.clone())
+        }
+    }
+}
+
+/// Reads and sets the parts of the machine a scene covers. `CtrlScenes` does
+/// this through the daemon's own dbus interfaces.
+pub(crate) trait SceneParts {
+    /// Paths of the devices that have `iface`
+    async fn paths(&self, iface: &str) -> Vec<String>;
+
+    /// Read the current settings of a part
+    async fn read(&self, id: &PartId) -> Result<ScenePart, RogError>;
+
+    async fn write(&self, part: &ScenePart) -> Result<(), RogError>;
+}
+
+/// Capture everything currently available. Parts that could not be read
+/// are described in the returned `Vec<String>`.
+async fn capture_all(parts: &impl SceneParts, name: String) -> (Scene, Vec<String>) {
+    let mut skipped = Vec::new();
+    let mut scene = Scene {
+        name,
+        platform: None,
+        fan_curves: None,
+        aura: Vec::new(),
+        anime: None,
+        slash: None
+    };
+
+    let mut ids = vec![PartId::Platform];
+    if parts.paths(FAN_CURVES_IFACE).await.is_empty() {
+        skipped.push("fan curves: not supported".to_owned());
+    } else {
+        ids.push(PartId::FanCurves);
+    }
+    ids.extend(parts.paths(AURA_IFACE).await.into_iter().map(PartId::Aura));
+    ids.extend(
+        parts
+            .paths(ANIME_IFACE)
+            .await
+            .into_iter()
+            .take(1)
+            .map(PartId::Anime)
+    );
+    ids.extend(
+        parts
+            .paths(SLASH_IFACE)
+            .await
+            .into_iter()
+            .take(1)
+            .map(PartId::Slash)
+    );
+
+    for id in ids {
+        match parts.read(&id).await {
+            Ok(ScenePart::Platform(platform)) => scene.platform = Some(platform),
+            Ok(ScenePart::FanCurves(curves)) => scene.fan_curves = Some(curves),
+            Ok(ScenePart::Aura(_, aura)) => scene.aura.push(aura),
+            Ok(ScenePart::Anime(_, anime)) => scene.anime = Some(anime),
+            Ok(ScenePart::Slash(_, slash)) => scene.slash = Some(slash),
+            Err(e) => skipped.push(format!("{}: {e}", id.label()))
+        }
+    }
+    (scene, skipped)
+}
+
+/// Match the parts of `scene` to the devices present. Parts whose device is
+/// absent are described in the returned `Vec<String>`.
+async fn scene_targets(parts: &impl SceneParts, scene: &Scene) -> (Vec<ScenePart>, Vec<String>) {
+    let mut targets = Vec::new();
+    let mut absent = Vec::new();
+
+    if let Some(platform) = &scene.platform {
+        targets.push(ScenePart::Platform(platform.clone()));
+    }
+
+    if let Some(fan_curves) = &scene.fan_curves {
+        if parts.paths(FAN_CURVES_IFACE).await.is_empty() {
+            absent.push("fan curves: not supported".to_owned());
+        } else {
+            targets.push(ScenePart::FanCurves(fan_curves.clone()));
+        }
+    }
+
+    let aura_paths = parts.paths(AURA_IFACE).await;
+    for aura in &scene.aura {
+        let len = targets.len();
+        targets.extend(
+            aura_paths
+                .iter()
+                .filter(|p| aura_device_id(p) == aura.device)
+                .map(|p| ScenePart::Aura(p.clone(), aura.clone()))
+        );
+        if targets.len() == len {
+            absent.push(format!("aura {}: device not present", aura.device));
+        }
+    }
+
+    if let Some(anime) = &scene.anime {
+        match parts.paths(ANIME_IFACE).await.into_iter().next() {
+            Some(path) => targets.push(ScenePart::Anime(path, anime.clone())),
+            None => absent.push("anime: device not present".to_owned())
+        }
+    }
+
+    if let Some(slash) = &scene.slash {
+        match parts.paths(SLASH_IFACE).await.into_iter().next() {
+            Some(path) => targets.push(ScenePart::Slash(path, slash.clone())),
+            None => absent.push("slash: device not present".to_owned())
+        }
+    }
+
+    (targets, absent)
+}
+
+/// Apply all of `scene` that has a device present, or none of it. The
+/// current settings of each part are read first, and if any part fails to
+/// apply every part already set is put back. Parts skipped because the device
+/// is absent are described in the returned `Vec<String>`.
+async fn apply_all(parts: &impl SceneParts, scene: &Scene) -> Result<Vec<String>, RogError> {
+    let (targets, absent) = scene_targets(parts, scene).await;
+
+    let mut previous = Vec::with_capacity(targets.len());
+    for target in &targets {
+        let part = parts.read(&target.id()).await.map_err(|e| {
+            RogError::DoTask(format!(
+                "{}: could not read the current settings, nothing was changed: {e}",
+                target.id().label()
+            ))
+        })?;
+        previous.push(part);
+    }
+
+    for (idx, target) in targets.iter().enumerate() {
+        if let Err(e) = parts.write(target).await {
+            // The failed part may be half set so is put back too, though as
+            // it just failed that likely fails again and is only logged
+            if let Err(e) = parts.write(&previous[idx]).await {
+                warn!("Scenes: could not restore {}: {e}", target.id().label());
+            }
+            let mut not_restored = Vec::new();
+            for part in previous[..idx].iter().rev() {
+                if let Err(e) = parts.write(part).await {
+                    not_restored.push(format!("{}: {e}", part.id().label()));
+                }
+            }
+            let msg = if not_restored.is_empty() {
+                format!(
+                    "{}: {e}, the previous settings were restored",
+                    target.id().label()
+                )
+            } else {
+                format!(
+                    "{}: {e}, and could not restore {}",
+                    target.id().label(),
+                    not_restored.join(", ")
+                )
+            };
+            return Err(RogError::DoTask(msg));
+        }
+    }
+    Ok(absent)
+}
+
+#[derive(Clone)]
+pub struct CtrlScenes {
+    scenes: Arc<Mutex<ScenesConfig>>,
+    /// Held while a scene is applied so two can not be interleaved
+    applying: Arc<Mutex<()>>,
+    config: Arc<Mutex<Config>>,
+    attributes: FirmwareAttributes,
+    power: AsusPower,
+    loopback: Loopback
+}
+
+impl CtrlScenes {
+    pub fn new(
+        config: Arc<Mutex<Config>>,
+        attributes: FirmwareAttributes,
+        power: AsusPower,
+        connection: Connection
+    ) -> Self {
+        Self {
+            scenes: Arc::new(Mutex::new(ScenesConfig::new().load())),
+            applying: Arc::new(Mutex::new(())),
+            config,
+            attributes,
+            power,
+            loopback: Loopback::new(connection)
+        }
+    }
+
+    async fn capture_platform(&self) -> Result<PlatformScene, RogError> {
+        let platform_profile = self
+            .loopback
+            .get_property(PLATFORM_PATH, PLATFORM_IFACE, "PlatformProfile")
+            .await?;
+        let config = self.config.lock().await;
+        Ok(PlatformScene {
+            platform_profile,
+            platform_profile_linked_epp: config.platform_profile_linked_epp,
+            profile_quiet_epp: config.profile_quiet_epp,
+            profile_balanced_epp: config.profile_balanced_epp,
+            profile_performance_epp: config.profile_performance_epp,
+            ac_profile_tunings: config.ac_profile_tunings.clone(),
+            dc_profile_tunings: config.dc_profile_tunings.clone()
+        })
+    }
+
+    async fn apply_platform(&self, scene: &PlatformScene) -> Result<(), RogError> {
+        let lb = &self.loopback;
+        for (prop, epp) in [
+            ("ProfileQuietEpp", scene.profile_quiet_epp),
+            ("ProfileBalancedEpp", scene.profile_balanced_epp),
+            ("ProfilePerformanceEpp", scene.profile_performance_epp)
+        ] {
+            lb.set_property(PLATFORM_PATH, PLATFORM_IFACE, prop, epp)
+                .await?;
+        }
+        lb.set_property(
+            PLATFORM_PATH,
+            PLATFORM_IFACE,
+            "PlatformProfileLinkedEpp",
+            scene.platform_profile_linked_epp
+        )
+        .await?;
+        lb.set_property(
+            PLATFORM_PATH,
+            PLATFORM_IFACE,
+            "PlatformProfile",
+            scene.platform_profile
+        )
+        .await?;
+
+        // Setting the profile disables the active tuning group, so the
+        // tunings must be restored after
+        let power_plugged = self.power.get_online().map(|p| p == 1).unwrap_or_default();
+        let mut config = self.config.lock().await;
+        config.ac_profile_tunings = scene.ac_profile_tunings.clone();
+        config.dc_profile_tunings = scene.dc_profile_tunings.clone();
+        config.write();
+        set_config_or_default(
+            &self.attributes,
+            &mut config,
+            power_plugged,
+            scene.platform_profile
+        )
+        .await;
+        Ok(())
+    }
+
+    async fn capture_fan_curves(&self) -> Result<FanCurveProfiles, RogError> {
+        let mut profiles = FanCurveProfiles::default();
+        for profile in PlatformProfile::list() {
+            let curves: Vec<CurveData> = self
+                .loopback
+                .call(
+                    FAN_CURVE_ZBUS_PATH,
+                    FAN_CURVES_IFACE,
+                    "FanCurveData",
+                    &(profile,)
+                )
+                .await?;
+            match profile {
+                PlatformProfile::Balanced => profiles.balanced = curves,
+                PlatformProfile::Performance => profiles.performance = curves,
+                PlatformProfile::Quiet => profiles.quiet = curves
+            }
+        }
+        Ok(profiles)
+    }
+
+    async fn apply_fan_curves(&self, profiles: &FanCurveProfiles) -> Result<(), RogError> {
+        for profile in PlatformProfile::list() {
+            for curve in profiles.get_fan_curves_for(profile) {
+                self.loopback
+                    .call::<_, ()>(
+                        FAN_CURVE_ZBUS_PATH,
+                        FAN_CURVES_IFACE,
+                        "SetFanCurve",
+                        &(profile, curve)
+                    )
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn capture_aura(&self, path: &str) -> Result<AuraScene, RogError> {
+        let lb = &self.loopback;
+        Ok(AuraScene {
+            device: aura_device_id(path),
+            brightness: lb.get_property(path, AURA_IFACE, "Brightness").await?,
+            effect: lb.get_property(path, AURA_IFACE, "LedModeData").await?,
+            power: lb.get_property(path, AURA_IFACE, "LedPower").await?
+        })
+    }
+
+    async fn apply_aura(&self, path: &str, scene: &AuraScene) -> Result<(), RogError> {
+        let lb = &self.loopback;
+        lb.set_property(path, AURA_IFACE, "Brightness", scene.brightness)
+            .await?;
+        lb.set_property(path, AURA_IFACE, "LedModeData", scene.effect.clone())
+            .await?;
+        lb.set_property(path, AURA_IFACE, "LedPower", scene.power.clone())
+            .await
+    }
+
+    async fn capture_anime(&self, path: &str) -> Result<AnimeScene, RogError> {
+        let lb = &self.loopback;
+        Ok(AnimeScene {
+            enable_display: lb.get_property(path, ANIME_IFACE, "EnableDisplay").await?,
+            brightness: lb.get_property(path, ANIME_IFACE, "Brightness").await?,
+            builtins_enabled: lb
+                .get_property(path, ANIME_IFACE, "BuiltinsEnabled")
+                .await?,
+            builtin_animations: lb
+                .get_property(path, ANIME_IFACE, "BuiltinAnimations")
+                .await?,
+            off_when_unplugged: lb
+                .get_property(path, ANIME_IFACE, "OffWhenUnplugged")
+                .await?,
+            off_when_suspended: lb
+                .get_property(path, ANIME_IFACE, "OffWhenSuspended")
+                .await?,
+            off_when_lid_closed: lb
+                .get_property(path, ANIME_IFACE, "OffWhenLidClosed")
+                .await?
+        })
+    }
+
+    async fn apply_anime(&self, path: &str, scene: &AnimeScene) -> Result<(), RogError> {
+        let lb = &self.loopback;
+        lb.set_property(path, ANIME_IFACE, "Brightness", scene.brightness)
+            .await?;
+        lb.set_property(
+            path,
+            ANIME_IFACE,
+            "BuiltinAnimations",
+            scene.builtin_animations
+        )
+        .await?;
+        lb.set_property(path, ANIME_IFACE, "BuiltinsEnabled", scene.builtins_enabled)
+            .await?;
+        lb.set_property(
+            path,
+            ANIME_IFACE,
+            "OffWhenUnplugged",
+            scene.off_when_unplugged
+        )
+        .await?;
+        lb.set_property(
+            path,
+            ANIME_IFACE,
+            "OffWhenSuspended",
+            scene.off_when_suspended
+        )
+        .await?;
+        lb.set_property(
+            path,
+            ANIME_IFACE,
+            "OffWhenLidClosed",
+            scene.off_when_lid_closed
+        )
+        .await?;
+        lb.set_property(path, ANIME_IFACE, "EnableDisplay", scene.enable_display)
+            .await
+    }
+
+    async fn capture_slash(&self, path: &str) -> Result<SlashScene, RogError> {
+        let lb = &self.loopback;
+        Ok(SlashScene {
+            enabled: lb.get_property(path, SLASH_IFACE, "Enabled").await?,
+            brightness: lb.get_property(path, SLASH_IFACE, "Brightness").await?,
+            interval: lb.get_property(path, SLASH_IFACE, "Interval").await?,
+            mode: lb.get_property(path, SLASH_IFACE, "Mode").await?,
+            show_on_boot: lb.get_property(path, SLASH_IFACE, "ShowOnBoot").await?,
+            show_on_sleep: lb.get_property(path, SLASH_IFACE, "ShowOnSleep").await?,
+            show_on_shutdown: lb.get_property(path, SLASH_IFACE, "ShowOnShutdown").await?,
+            show_on_battery: lb.get_property(path, SLASH_IFACE, "ShowOnBattery").await?,
+            show_battery_warning: lb
+                .get_property(path, SLASH_IFACE, "ShowBatteryWarning")
+                .await?
+        })
+    }
+
+    async fn apply_slash(&self, path: &str, scene: &SlashScene) -> Result<(), RogError> {
+        let lb = &self.loopback;
+        lb.set_property(path, SLASH_IFACE, "Enabled", scene.enabled)
+            .await?;
+        lb.set_property(path, SLASH_IFACE, "Brightness", scene.brightness)
+            .await?;
+        lb.set_property(path, SLASH_IFACE, "Interval", scene.interval)
+            .await?;
+        lb.set_property(path, SLASH_IFACE, "Mode", scene.mode)
+            .await?;
+        for (prop, enabled) in [
+            ("ShowOnBoot", scene.show_on_boot),
+            ("ShowOnSleep", scene.show_on_sleep),
+            ("ShowOnShutdown", scene.show_on_shutdown),
+            ("ShowOnBattery", scene.show_on_battery),
+            ("ShowBatteryWarning", scene.show_battery_warning)
+        ] {
+            lb.set_property(path, SLASH_IFACE, prop, enabled).await?;
+        }
+        Ok(())
+    }
+}
+
+impl SceneParts for CtrlScenes {
+    async fn paths(&self, iface: &str) -> Vec<String> {
+        self.loopback
+            .find_paths(iface)
+            .await
+            .map_err(|e| warn!("Scenes: {e}"))
+            .unwrap_or_default()
+    }
+
+    async fn read(&self, id: &PartId) -> Result<ScenePart, RogError> {
+        Ok(match id {
+            PartId::Platform => ScenePart::Platform(self.capture_platform().await?),
+            PartId::FanCurves => ScenePart::FanCurves(self.capture_fan_curves().await?),
+            PartId::Aura(path) => ScenePart::Aura(path.clone(), self.capture_aura(path).await?),
+            PartId::Anime(path) => ScenePart::Anime(path.clone(), self.capture_anime(path).await?),
+            PartId::Slash(path) => ScenePart::Slash(path.clone(), self.capture_slash(path).await?)
+        })
+    }
+
+    async fn write(&self, part: &ScenePart) -> Result<(), RogError> {
+        match part {
+            ScenePart::Platform(platform) => self.apply_platform(platform).await,
+            ScenePart::FanCurves(curves) => self.apply_fan_curves(curves).await,
+            ScenePart::Aura(path, aura) => self.apply_aura(path, aura).await,
+            ScenePart::Anime(path, anime) => self.apply_anime(path, anime).await,
+            ScenePart::Slash(path, slash) => self.apply_slash(path, slash).await
+        }
+    }
+}
+
+#[interface(name = "xyz.ljones.Scenes")]
+impl CtrlScenes {
+    /// Save the current state under the name, replacing any scene of the
+    /// same name. Returns a list of the parts that could not be saved.
+    async fn save_scene(&mut self, name: String) -> zbus::fdo::Result<Vec<String>> {
+        if name.is_empty() {
+            return Err(FdoErr::InvalidArgs("Scenes: name is empty".to_owned()));
+        }
+        let (scene, skipped) = capture_all(self, name.clone()).await;
+        let mut scenes = self.scenes.lock().await;
+        scenes.scenes.retain(|s| s.name != name);
+        scenes.scenes.push(scene);
+        scenes.write();
+        info!("Scenes: saved {name}, skipped: {skipped:?}");
+        Ok(skipped)
+    }
+
+    /// Apply a saved scene. Either every part with a device present is
+    /// applied, or on any error the previous settings are restored and an
+    /// error returned. Returns a list of the parts skipped because the device
+    /// is not present.
+    async fn apply_scene(&self, name: String) -> zbus::fdo::Result<Vec<String>> {
+        // Copied out so the lock is not held over the dbus calls
+        let scene = self
+            .scenes
+            .lock()
+            .await
+            .scenes
+            .iter()
+            .find(|s| s.name == name)
+            .cloned()
+            .ok_or_else(|| FdoErr::Failed(format!("Scenes: no scene named {name}")))?;
+        let _applying = self.applying.lock().await;
+        let skipped = apply_all(self, &scene).await.map_err(|e| {
+            warn!("Scenes: could not apply {name}: {e}");
+            FdoErr::Failed(format!("Scenes: could not apply {name}: {e}"))
+        })?;
+        info!("Scenes: applied {name}, skipped: {skipped:?}");
+        Ok(skipped)
+    }
+
+    async fn list_scenes(&self) -> Vec<String> {
+        self.scenes
+            .lock()
+            .await
+            .scenes
+            .iter()
+            .map(|s| s.name.clone())
+            .collect()
+    }
+
+    async fn delete_scene(&mut self, name: String) -> zbus::fdo::Result<()> {
+        let mut scenes = self.scenes.lock().await;
+        let len = scenes.scenes.len();
+        scenes.scenes.retain(|s| s.name != name);
+        if scenes.scenes.len() == len {
+            return Err(FdoErr::Failed(format!("Scenes: no scene named {name}")));
+        }
+        scenes.write();
+        Ok(())
+    }
+}
+
+impl crate::ZbusRun for CtrlScenes {
+    async fn add_to_server(self, server: &mut Connection) {
+        Self::add_to_server_helper(self, SCENES_ZBUS_PATH, server).await;
+    }
+}
+
+impl crate::Reloadable for CtrlScenes {
+    /// Scenes are only applied on request
+    async fn reload(&mut self) -> Result<(), RogError> {
+        Ok(())
+    }
+}
+
+impl CtrlTask for CtrlScenes {
+    fn zbus_path() -> &'static str {
+        SCENES_ZBUS_PATH
+    }
+
+    async fn create_tasks(&self, _: SignalEmitter<'static>) -> Result<(), RogError> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    use futures_lite::future::block_on;
+    use rog_anime::usb::Brightness;
+    use rog_aura::keyboard::LaptopAuraPower;
+    use rog_aura::{AuraEffect, LedBrightness};
+
+    use super::{
+        apply_all, aura_device_id, capture_all, AnimeScene, AuraScene, PartId, Scene, ScenePart,
+        SceneParts, ScenesConfig, ANIME_IFACE, AURA_IFACE
+    };
+    use crate::error::RogError;
+
+    /// Aura devices and an AniMe held in memory, with no platform or fan
+    /// curves
+    #[derive(Default)]
+    struct FakeParts {
+        aura: Mutex<HashMap<String, AuraScene>>,
+        anime: Mutex<Option<AnimeScene>>,
+        fail_write: Option<PartId>
+    }
+
+    impl SceneParts for FakeParts {
+        async fn paths(&self, iface: &str) -> Vec<String> {
+            match iface {
+                AURA_IFACE => {
+                    let mut paths: Vec<_> = self.aura.lock().unwrap().keys().cloned().collect();
+                    paths.sort();
+                    paths
+                }
+                ANIME_IFACE if self.anime.lock().unwrap().is_some() => {
+                    vec!["/xyz/ljones/anime".to_owned()]
+                }
+                _ => Vec::new()
+            }
+        }
+
+        async fn read(&self, id: &PartId) -> Result<ScenePart, RogError> {
+            match id {
+                PartId::Aura(path) => self
+                    .aura
+                    .lock()
+                    .unwrap()
+                    .get(path)
+                    .map(|aura| ScenePart::Aura(path.clone(), aura.clone()))
+                    .ok_or_else(|| RogError::NotFound(path.clone())),
+                PartId::Anime(path) => self
+                    .anime
+                    .lock()
+                    .unwrap()
+                    .clone()
+                    .map(|anime| ScenePart::Anime(path.clone(), anime))
+                    .ok_or_else(|| RogError::NotFound(path.clone())),
+                _ => Err(RogError::NotFound(id.label()))
+            }
+        }
+
+        async fn write(&self, part: &ScenePart) -> Result<(), RogError> {
+            if self.fail_write.as_ref() == Some(&part.id()) {
+                return Err(RogError::DoTask("write failed".to_owned()));
+            }
+            match part {
+                ScenePart::Aura(path, aura) => {
+                    self.aura.lock().unwrap().insert(path.clone(), aura.clone());
+                }
+                ScenePart::Anime(_, anime) => *self.anime.lock().unwrap() = Some(anime.clone()),
+                _ => return Err(RogError::NotFound(part.id().label()))
+            }
+            Ok(())
+        }
+    }
+
+    fn aura(device: &str, brightness: LedBrightness) -> AuraScene {
+        AuraScene {
+            device: device.to_owned(),
+            brightness,
+            effect: AuraEffect::default(),
+            power: LaptopAuraPower::default()
+        }
+    }
+
+    fn anime(enable_display: bool) -> AnimeScene {
+        AnimeScene {
+            enable_display,
+            brightness: Brightness::default(),
+            builtins_enabled: false,
+            builtin_animations: Default::default(),
+            off_when_unplugged: false,
+            off_when_suspended: false,
+            off_when_lid_closed: false
+        }
+    }
+
+    fn fake(fail_write: Option<PartId>) -> FakeParts {
+        let parts = FakeParts {
+            fail_write,
+            ..Default::default()
+        };
+        for (path, device) in [
+            ("/xyz/ljones/aura/19b6_3_1", "19b6"),
+            ("/xyz/ljones/aura/tuf", "tuf")
+        ] {
+            parts
+                .aura
+                .lock()
+                .unwrap()
+                .insert(path.to_owned(), aura(device, LedBrightness::Low));
+        }
+        *parts.anime.lock().unwrap() = Some(anime(false));
+        parts
+    }
+
+    fn scene() -> Scene {
+        Scene {
+            name: "night".to_owned(),
+            platform: None,
+            fan_curves: None,
+            aura: vec![
+                aura("19b6", LedBrightness::High),
+                aura("tuf", LedBrightness::Off),
+            ],
+            anime: Some(anime(true)),
+            slash: None
+        }
+    }
+
+    fn brightness(parts: &FakeParts, path: &str) -> LedBrightness {
+        parts.aura.lock().unwrap()[path].brightness
+    }
+
+    #[test]
+    fn capture() {
+        let parts = fake(None);
+        let (scene, skipped) = block_on(capture_all(&parts, "now".to_owned()));
+        assert_eq!(scene.name, "now");
+        let devices: Vec<_> = scene.aura.iter().map(|a| a.device.as_str()).collect();
+        assert_eq!(devices, ["19b6", "tuf"]);
+        assert!(scene.anime.is_some_and(|a| !a.enable_display));
+        assert!(scene.platform.is_none() && scene.fan_curves.is_none());
+        assert_eq!(skipped.len(), 2);
+        assert_eq!(skipped[0], "fan curves: not supported");
+        assert!(skipped[1].starts_with("platform: "));
+    }
+
+    #[test]
+    fn apply() {
+        let parts = fake(None);
+        let skipped = block_on(apply_all(&parts, &scene())).unwrap();
+        assert!(skipped.is_empty());
+        assert_eq!(
+            brightness(&parts, "/xyz/ljones/aura/19b6_3_1"),
+            LedBrightness::High
+        );
+        assert_eq!(
+            brightness(&parts, "/xyz/ljones/aura/tuf"),
+            LedBrightness::Off
+        );
+        assert!(parts.anime.lock().unwrap().as_ref().unwrap().enable_display);
+    }
+
+    #[test]
+    fn failed_apply_restores() {
+        // The anime is applied last, after both aura
+        let parts = fake(Some(PartId::Anime("/xyz/ljones/anime".to_owned())));
+        let err = block_on(apply_all(&parts, &scene())).unwrap_err();
+        assert!(err.to_string().contains("previous settings were restored"));
+        for path in [
+            "/xyz/ljones/aura/19b6_3_1",
+            "/xyz/ljones/aura/tuf"
+        ] {
+            assert_eq!(brightness(&parts, path), LedBrightness::Low);
+        }
+        assert!(!parts.anime.lock().unwrap().as_ref().unwrap().enable_display);
+    }
+
+    #[test]
+    fn absent_device_skipped() {
+        let parts = fake(None);
+        let mut scene = scene();
+        scene.aura.push(aura("1866", LedBrightness::High));
+        let skipped = block_on(apply_all(&parts, &scene)).unwrap();
+        assert_eq!(skipped, ["aura 1866: device not present"]);
+        assert_eq!(
+            brightness(&parts, "/xyz/ljones/aura/19b6_3_1"),
+            LedBrightness::High
+        );
+    }
+
+    #[test]
+    fn scenes_round_trip() {
+        let config = ScenesConfig {
+            scenes: vec![scene()]
+        };
+        let text = ron::ser::to_string_pretty(&config, ron::ser::PrettyConfig::default()).unwrap();
+        let back: ScenesConfig = ron::from_str(&text).unwrap();
+        assert_eq!(back.scenes.len(), 1);
+        assert_eq!(back.scenes[0].name, "night");
+        assert_eq!(
+            ron::ser::to_string_pretty(&back, ron::ser::PrettyConfig::default()).unwrap(),
+            text
+        );
+    }
+
+    #[test]
+    fn aura_device_ids() {
+        assert_eq!(aura_device_id("/xyz/ljones/aura/19b6_3_1"), "19b6");
+        assert_eq!(aura_device_id("/xyz/ljones/aura/tuf"), "tuf");
+        assert_eq!(aura_device_id("/xyz/ljones/aura/1866_2"), "1866");
+    }
+}
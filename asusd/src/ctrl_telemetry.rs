@@ -0,0 +1,456 @@
+use std::collections::VecDeque;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use config_traits::{StdConfig, StdConfigLoad};
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+use zbus::object_server::SignalEmitter;
+use zbus::zvariant::{OwnedValue, Type, Value};
+use zbus::{interface, Connection};
+
+use crate::error::RogError;
+use crate::{CtrlTask, CONFIG_PATH_BASE};
+
+pub const TELEMETRY_ZBUS_PATH: &str = "/xyz/ljones";
+const CONFIG_FILE: &str = "telemetry.ron";
+pub const SYS_CLASS_ROOT: &str = "/sys/class";
+/// Sampling faster than this is more load than the readings are worth
+pub const MIN_INTERVAL_MS: u32 = 250;
+/// hwmon names of CPU temperature sensors
+const CPU_SENSORS: [&str; 3] = [
+    "k10temp", "coretemp", "zenpower"
+];
+/// hwmon names of GPU temperature sensors
+const GPU_SENSORS: [&str; 3] = [
+    "amdgpu", "nouveau", "nvidia"
+];
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct TelemetryConfig {
+    /// Time between samples in milliseconds
+    pub interval_ms: u32,
+    /// Number of samples kept in the history
+    pub history_length: u32
+}
+
+impl Default for TelemetryConfig {
+    fn default() -> Self {
+        Self {
+            interval_ms: 2000,
+            history_length: 150
+        }
+    }
+}
+
+impl StdConfig for TelemetryConfig {
+    fn new() -> Self {
+        Self::default()
+    }
+
+    fn file_name(&self) -> String {
+        CONFIG_FILE.to_owned()
+    }
+
+    fn config_dir() -> std::path::PathBuf {
+        PathBuf::from(CONFIG_PATH_BASE)
+    }
+}
+
+impl StdConfigLoad for TelemetryConfig {}
+
+#[derive(Deserialize, Serialize, Type, Value, OwnedValue, Debug, Default, Clone, PartialEq)]
+pub struct FanReading {
+    /// `<hwmon name>/<fan label>`
+    pub label: String,
+    pub rpm: u32
+}
+
+#[derive(Deserialize, Serialize, Type, Value, OwnedValue, Debug, Default, Clone, PartialEq)]
+pub struct TempReading {
+    /// `<hwmon name>/<temp label>`
+    pub label: String,
+    pub celsius: f64
+}
+
+/// A single reading of all sensors. Values which could not be read are `0`.
+#[derive(Deserialize, Serialize, Type, Value, OwnedValue, Debug, Default, Clone, PartialEq)]
+pub struct TelemetrySample {
+    /// Milliseconds since the unix epoch
+    pub time: u64,
+    pub fans: Vec<FanReading>,
+    pub temperatures: Vec<TempReading>,
+    /// Hottest CPU sensor
+    pub cpu_temp: f64,
+    /// Hottest GPU sensor
+    pub gpu_temp: f64,
+    /// Battery charge or discharge rate in watts
+    pub power_draw: f64,
+    /// Watt hours
+    pub energy_full: f64,
+    /// Watt hours
+    pub energy_full_design: f64,
+    pub cycle_count: u32
+}
+
+impl TelemetrySample {
+    /// Remaining battery capacity as a percentage of the design capacity
+    pub fn battery_health(&self) -> Option<f64> {
+        (self.energy_full_design > 0.0).then(|| self.energy_full / self.energy_full_design * 100.0)
+    }
+}
+
+fn read_trimmed(path: &Path) -> Option<String> {
+    fs::read_to_string(path).ok().map(|s| s.trim().to_owned())
+}
+
+fn read_num(path: &Path) -> Option<f64> {
+    read_trimmed(path)?.parse().ok()
+}
+
+fn sorted_entries(dir: &Path) -> Vec<PathBuf> {
+    let mut entries: Vec<PathBuf> = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .collect();
+    entries.sort();
+    entries
+}
+
+/// Reads the sensors from a sysfs class root, normally `SYS_CLASS_ROOT`. The
+/// devices are rescanned on every sample as some, such as a dGPU, come and
+/// go.
+#[derive(Debug, Clone)]
+pub struct Sensors {
+    root: PathBuf
+}
+
+impl Sensors {
+    pub fn new(root: &Path) -> Self {
+        Self {
+            root: root.to_path_buf()
+        }
+    }
+
+    /// Read every `<prefix>N_input` of an hwmon dir, paired with its label
+    fn hwmon_inputs(dir: &Path, prefix: &str) -> Vec<(String, f64)> {
+        sorted_entries(dir)
+            .into_iter()
+            .filter_map(|path| {
+                let file = path.file_name()?.to_str()?;
+                let index = file.strip_prefix(prefix)?.strip_suffix("_input")?;
+                let value = read_num(&path)?;
+                let label = read_trimmed(&dir.join(format!("{prefix}{index}_label")))
+                    .unwrap_or_else(|| format!("{prefix}{index}"));
+                Some((label, value))
+            })
+            .collect()
+    }
+
+    fn read_hwmon(&self, sample: &mut TelemetrySample) {
+        for dir in sorted_entries(&self.root.join("hwmon")) {
+            let name = read_trimmed(&dir.join("name")).unwrap_or_default();
+            for (label, rpm) in Self::hwmon_inputs(&dir, "fan") {
+                sample.fans.push(FanReading {
+                    label: format!("{name}/{label}"),
+                    rpm: rpm as u32
+                });
+            }
+            for (label, milli) in Self::hwmon_inputs(&dir, "temp") {
+                let celsius = milli / 1000.0;
+                if CPU_SENSORS.contains(&name.as_str()) {
+                    sample.cpu_temp = sample.cpu_temp.max(celsius);
+                } else if GPU_SENSORS.contains(&name.as_str()) {
+                    sample.gpu_temp = sample.gpu_temp.max(celsius);
+                }
+                sample.temperatures.push(TempReading {
+                    label: format!("{name}/{label}"),
+                    celsius
+                });
+            }
+        }
+    }
+
+    fn read_battery(&self, sample: &mut TelemetrySample) {
+        let Some(battery) = sorted_entries(&self.root.join("power_supply"))
+            .into_iter()
+            .find(|p| read_trimmed(&p.join("type")).is_some_and(|t| t == "Battery"))
+        else {
+            return;
+        };
+        let read = |attr: &str| read_num(&battery.join(attr));
+
+        // Values are in micro units. Some batteries report only current and
+        // charge, these are converted using the voltage.
+        let voltage = read("voltage_now").unwrap_or_default() / 1e6;
+        sample.power_draw = read("power_now")
+            .map(|p| p / 1e6)
+            .or_else(|| read("current_now").map(|c| c / 1e6 * voltage))
+            .unwrap_or_default();
+
+        let design_voltage = read("voltage_min_design").unwrap_or(voltage * 1e6) / 1e6;
+        let energy = |energy: &str, charge: &str| {
+            read(energy)
+                .map(|e| e / 1e6)
+                .or_else(|| read(charge).map(|c| c / 1e6 * design_voltage))
+                .unwrap_or_default()
+        };
+        sample.energy_full = energy("energy_full", "charge_full");
+        sample.energy_full_design = energy("energy_full_design", "charge_full_design");
+        sample.cycle_count = read("cycle_count").unwrap_or_default() as u32;
+    }
+
+    pub fn sample(&self) -> TelemetrySample {
+        let mut sample = TelemetrySample {
+            time: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or_default(),
+            ..Default::default()
+        };
+        self.read_hwmon(&mut sample);
+        self.read_battery(&mut sample);
+        sample
+    }
+}
+
+#[derive(Clone)]
+pub struct CtrlTelemetry {
+    config: Arc<Mutex<TelemetryConfig>>,
+    history: Arc<Mutex<VecDeque<TelemetrySample>>>,
+    sensors: Sensors
+}
+
+impl CtrlTelemetry {
+    pub fn new() -> Self {
+        Self {
+            config: Arc::new(Mutex::new(TelemetryConfig::new().load())),
+            history: Default::default(),
+            sensors: Sensors::new(Path::new(SYS_CLASS_ROOT))
+        }
+    }
+
+    async fn push_sample(&self, sample: TelemetrySample) {
+        let length = self.config.lock().await.history_length.max(1) as usize;
+        let mut history = self.history.lock().await;
+        while history.len() >= length {
+            history.pop_front();
+        }
+        history.push_back(sample);
+    }
+}
+
+impl Default for CtrlTelemetry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[interface(name = "xyz.ljones.Telemetry")]
+impl CtrlTelemetry {
+    /// The most recent sample
+    #[zbus(property)]
+    async fn latest(&self) -> TelemetrySample {
+        self.history
+            .lock()
+            .await
+            .back()
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// All samples kept, oldest first
+    async fn history(&self) -> Vec<TelemetrySample> {
+        self.history.lock().await.iter().cloned().collect()
+    }
+
+    /// Time between samples in milliseconds
+    #[zbus(property)]
+    async fn interval(&self) -> u32 {
+        self.config.lock().await.interval_ms
+    }
+
+    #[zbus(property)]
+    async fn set_interval(&mut self, interval: u32) -> zbus::fdo::Result<()> {
+        if interval < MIN_INTERVAL_MS {
+            return Err(zbus::fdo::Error::InvalidArgs(format!(
+                "Telemetry: interval must be at least {MIN_INTERVAL_MS}ms"
+            )));
+        }
+        let mut config = self.config.lock().await;
+        config.interval_ms = interval;
+        config.write();
+        Ok(())
+    }
+
+    /// Number of samples kept in the history
+    #[zbus(property)]
+    async fn history_length(&self) -> u32 {
+        self.config.lock().await.history_length
+    }
+
+    #[zbus(property)]
+    async fn set_history_length(&mut self, length: u32) -> zbus::fdo::Result<()> {
+        if length == 0 {
+            return Err(zbus::fdo::Error::InvalidArgs(
+                "Telemetry: history length must be at least 1".to_owned()
+            ));
+        }
+        let mut config = self.config.lock().await;
+        config.history_length = length;
+        config.write();
+        let mut history = self.history.lock().await;
+        while history.len() > length as usize {
+            history.pop_front();
+        }
+        Ok(())
+    }
+}
+
+impl crate::ZbusRun for CtrlTelemetry {
+    async fn add_to_server(self, server: &mut Connection) {
+        Self::add_to_server_helper(self, TELEMETRY_ZBUS_PATH, server).await;
+    }
+}
+
+impl crate::Reloadable for CtrlTelemetry {
+    /// Nothing to restore, sampling starts with the tasks
+    async fn reload(&mut self) -> Result<(), RogError> {
+        Ok(())
+    }
+}
+
+impl CtrlTask for CtrlTelemetry {
+    fn zbus_path() -> &'static str {
+        TELEMETRY_ZBUS_PATH
+    }
+
+    async fn create_tasks(&self, signal_ctxt: SignalEmitter<'static>) -> Result<(), RogError> {
+        let ctrl = self.clone();
+        tokio::spawn(async move {
+            loop {
+                let sample = ctrl.sensors.sample();
+                debug!("Telemetry: {sample:?}");
+                ctrl.push_sample(sample).await;
+                ctrl.latest_changed(&signal_ctxt)
+                    .await
+                    .map_err(|e| warn!("Telemetry: could not emit change: {e}"))
+                    .ok();
+
+                let interval = ctrl.config.lock().await.interval_ms.max(MIN_INTERVAL_MS);
+                tokio::time::sleep(Duration::from_millis(interval as u64)).await;
+            }
+        });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use tempfile::TempDir;
+
+    use super::Sensors;
+
+    fn fake_sys() -> TempDir {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+
+        let add = |dir: &str, files: &[(&str, &str)]| {
+            let dir = root.join(dir);
+            fs::create_dir_all(&dir).unwrap();
+            for (file, value) in files {
+                fs::write(dir.join(file), value).unwrap();
+            }
+        };
+        add("hwmon/hwmon0", &[
+            ("name", "asus\n"),
+            ("fan1_input", "2600\n"),
+            ("fan1_label", "cpu_fan\n"),
+            ("fan2_input", "0\n")
+        ]);
+        add("hwmon/hwmon1", &[
+            ("name", "k10temp\n"),
+            ("temp1_input", "61250\n"),
+            ("temp1_label", "Tctl\n"),
+            ("temp3_input", "55000\n")
+        ]);
+        add("hwmon/hwmon2", &[
+            ("name", "amdgpu\n"),
+            ("temp1_input", "48000\n")
+        ]);
+        add("power_supply/AC0", &[
+            ("type", "Mains\n"),
+            ("online", "0\n")
+        ]);
+        tmp
+    }
+
+    #[test]
+    fn sample_hwmon() {
+        let tmp = fake_sys();
+        let root = tmp.path();
+        let sample = Sensors::new(root).sample();
+
+        assert_eq!(sample.fans.len(), 2);
+        assert_eq!(sample.fans[0].label, "asus/cpu_fan");
+        assert_eq!(sample.fans[0].rpm, 2600);
+        assert_eq!(sample.fans[1].label, "asus/fan2");
+        assert_eq!(sample.temperatures.len(), 3);
+        assert_eq!(sample.temperatures[0].label, "k10temp/Tctl");
+        assert_eq!(sample.cpu_temp, 61.25);
+        assert_eq!(sample.gpu_temp, 48.0);
+        // No battery
+        assert_eq!(sample.power_draw, 0.0);
+        assert_eq!(sample.battery_health(), None);
+    }
+
+    #[test]
+    fn sample_battery() {
+        let tmp = fake_sys();
+        let root = tmp.path();
+        let bat = root.join("power_supply/BAT0");
+        fs::create_dir_all(&bat).unwrap();
+        for (file, value) in [
+            ("type", "Battery\n"),
+            ("power_now", "12500000\n"),
+            ("energy_full", "72000000\n"),
+            ("energy_full_design", "90000000\n"),
+            ("cycle_count", "143\n")
+        ] {
+            fs::write(bat.join(file), value).unwrap();
+        }
+
+        let sample = Sensors::new(root).sample();
+        assert_eq!(sample.power_draw, 12.5);
+        assert_eq!(sample.energy_full, 72.0);
+        assert_eq!(sample.cycle_count, 143);
+        assert_eq!(sample.battery_health(), Some(80.0));
+
+        // Charge based reporting
+        for file in [
+            "power_now", "energy_full", "energy_full_design"
+        ] {
+            fs::remove_file(bat.join(file)).unwrap();
+        }
+        for (file, value) in [
+            ("current_now", "1000000\n"),
+            ("voltage_now", "16000000\n"),
+            ("voltage_min_design", "15000000\n"),
+            ("charge_full", "4000000\n"),
+            ("charge_full_design", "5000000\n")
+        ] {
+            fs::write(bat.join(file), value).unwrap();
+        }
+        let sample = Sensors::new(root).sample();
+        assert_eq!(sample.power_draw, 16.0);
+        assert_eq!(sample.energy_full, 60.0);
+        assert_eq!(sample.energy_full_design, 75.0);
+    }
+}
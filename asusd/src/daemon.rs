@@ -7,8 +7,13 @@ use ::zbus::Connection;
 use asusd::asus_armoury::start_attributes_zbus;
 use asusd::aura_manager::DeviceManager;
 use asusd::config::Config;
+use asusd::ctrl_app_profiles::CtrlAppProfiles;
+use asusd::ctrl_config_bundle::CtrlConfigBundle;
 use asusd::ctrl_fancurves::CtrlFanCurveZbus;
 use asusd::ctrl_platform::CtrlPlatform;
+use asusd::ctrl_rules::CtrlRules;
+use asusd::ctrl_scenes::CtrlScenes;
+use asusd::ctrl_telemetry::CtrlTelemetry;
 use asusd::{print_board_info, start_tasks, CtrlTask, DBUS_NAME};
 use config_traits::{StdConfig, StdConfigLoad1};
 use log::{error, info};
@@ -91,9 +96,9 @@ async fn start_daemon() -> Result<(), Box<dyn Error>> {
     }
 
     match CtrlPlatform::new(
-        platform,
-        power,
-        attributes,
+        platform.clone(),
+        power.clone(),
+        attributes.clone(),
         config.clone(),
         &cfg_path,
         CtrlPlatform::signal_context(&server)?
@@ -107,8 +112,29 @@ async fn start_daemon() -> Result<(), Box<dyn Error>> {
         }
     }
 
+    let telemetry = CtrlTelemetry::new();
+    let sig_ctx = CtrlTelemetry::signal_context(&server)?;
+    start_tasks(telemetry, &mut server, sig_ctx).await?;
+
     let _ = DeviceManager::new(server.clone()).await?;
 
+    // These act on the other controllers so must be started after them
+    let bundle = CtrlConfigBundle::new(attributes.clone(), server.clone());
+    let sig_ctx = CtrlConfigBundle::signal_context(&server)?;
+    start_tasks(bundle, &mut server, sig_ctx).await?;
+
+    let scenes = CtrlScenes::new(config.clone(), attributes, power.clone(), server.clone());
+    let sig_ctx = CtrlScenes::signal_context(&server)?;
+    start_tasks(scenes, &mut server, sig_ctx).await?;
+
+    let rules = CtrlRules::new(platform, power, server.clone());
+    let sig_ctx = CtrlRules::signal_context(&server)?;
+    start_tasks(rules, &mut server, sig_ctx).await?;
+
+    let app_profiles = CtrlAppProfiles::new(server.clone());
+    let sig_ctx = CtrlAppProfiles::signal_context(&server)?;
+    start_tasks(app_profiles, &mut server, sig_ctx).await?;
+
     // Request dbus name after finishing initalizing all functions
     server.request_name(DBUS_NAME).await?;
 
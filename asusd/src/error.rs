@@ -4,7 +4,9 @@ use std::fmt;
 use config_traits::ron;
 use rog_anime::error::AnimeError;
 use rog_platform::error::PlatformError;
+use rog_platform::platform::PlatformProfile;
 use rog_profiles::error::ProfileError;
+use rog_profiles::FanCurvePU;
 use rog_slash::error::SlashError;
 
 #[derive(Debug)]
@@ -23,6 +25,8 @@ pub enum RogError {
     MissingLedBrightNode(String, std::io::Error),
     ReloadFail(String),
     Profiles(ProfileError),
+    /// A stored or imported curve failed `CurveData::validate`
+    FanCurve(PlatformProfile, FanCurvePU, ProfileError),
     Initramfs(String),
     Modprobe(String),
     Io(std::io::Error),
@@ -63,6 +67,9 @@ impl fmt::Display for RogError {
             ),
             RogError::ReloadFail(deets) => write!(f, "Reload error: {}", deets),
             RogError::Profiles(deets) => write!(f, "Profile error: {}", deets),
+            RogError::FanCurve(profile, fan, deets) => {
+                write!(f, "{:?} fan curve for {:?}: {}", fan, profile, deets)
+            }
             RogError::Initramfs(detail) => write!(f, "Initiramfs error: {}", detail),
             RogError::Modprobe(detail) => write!(f, "Modprobe error: {}", detail),
             RogError::Io(detail) => write!(f, "std::io error: {}", detail),
@@ -139,7 +146,10 @@ impl From<ron::Error> for RogError {
 impl From<RogError> for zbus::fdo::Error {
     #[inline]
     fn from(err: RogError) -> Self {
-        zbus::fdo::Error::Failed(format!("{}", err))
+        match err {
+            RogError::FanCurve(..) => zbus::fdo::Error::InvalidArgs(format!("{}", err)),
+            _ => zbus::fdo::Error::Failed(format!("{}", err))
+        }
     }
 }
 
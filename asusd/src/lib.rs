@@ -1,10 +1,22 @@
 #![deny(unused_must_use)]
+/// Time based battery charge limits
+pub mod charge_schedule;
 /// Configuration loading, saving
 pub mod config;
+/// Apply settings bundles while matching processes are running
+pub mod ctrl_app_profiles;
+/// Export and import all configs as a single versioned bundle
+pub mod ctrl_config_bundle;
 /// Control platform profiles + fan-curves if available
 pub mod ctrl_fancurves;
 /// Control ASUS bios function such as boot sound, Optimus/Dedicated gfx mode
 pub mod ctrl_platform;
+/// User defined rules that react to power, lid, sleep and time events
+pub mod ctrl_rules;
+/// Save and restore named snapshots of all settings
+pub mod ctrl_scenes;
+/// Fan, temperature and battery readings
+pub mod ctrl_telemetry;
 
 pub mod asus_armoury;
 pub mod aura_anime;
@@ -14,6 +26,12 @@ pub mod aura_scsi;
 pub mod aura_slash;
 pub mod aura_types;
 pub mod error;
+/// Call the daemon's own dbus interfaces from within the daemon
+pub mod loopback;
+/// Userspace fan-curve control for machines without firmware curves
+pub mod soft_fan;
+/// Local time of day used by rules and the charge schedule
+pub mod time_of_day;
 
 use std::future::Future;
 use std::time::Duration;
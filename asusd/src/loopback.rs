@@ -0,0 +1,132 @@
+//! Helpers for calling the daemon's own dbus interfaces from within the
+//! daemon. Going through dbus means the controllers apply all of their usual
+//! side effects, config writes and changed signals, exactly as if a client
+//! had made the call.
+
+use serde::Serialize;
+use zbus::fdo::ObjectManagerProxy;
+use zbus::zvariant::{DynamicType, OwnedValue, Value};
+use zbus::{Connection, Proxy};
+
+use crate::error::RogError;
+use crate::DBUS_NAME;
+
+#[derive(Debug, Clone)]
+pub struct Loopback {
+    connection: Connection
+}
+
+impl Loopback {
+    pub fn new(connection: Connection) -> Self {
+        Self { connection }
+    }
+
+    pub fn connection(&self) -> &Connection {
+        &self.connection
+    }
+
+    /// The well-known name may not have been requested yet, so prefer the
+    /// connection's unique name to talk to our own interfaces
+    fn destination(&self) -> String {
+        self.connection
+            .unique_name()
+            .map(|n| n.to_string())
+            .unwrap_or_else(|| DBUS_NAME.to_owned())
+    }
+
+    async fn proxy(&self, path: &str, iface: &str) -> Result<Proxy<'static>, RogError> {
+        Ok(Proxy::new(
+            &self.connection,
+            self.destination(),
+            path.to_owned(),
+            iface.to_owned()
+        )
+        .await?)
+    }
+
+    pub async fn get_property<T>(
+        &self,
+        path: &str,
+        iface: &str,
+        property: &str
+    ) -> Result<T, RogError>
+    where
+        T: TryFrom<OwnedValue>,
+        T::Error: Into<zbus::Error>
+    {
+        self.proxy(path, iface)
+            .await?
+            .get_property(property)
+            .await
+            .map_err(|e| RogError::DoTask(format!("get {iface}.{property} at {path}: {e}")))
+    }
+
+    pub async fn set_property<'a>(
+        &self,
+        path: &str,
+        iface: &str,
+        property: &str,
+        value: impl Into<Value<'a>> + 'a
+    ) -> Result<(), RogError> {
+        self.proxy(path, iface)
+            .await?
+            .set_property(property, value)
+            .await
+            .map_err(|e| RogError::DoTask(format!("set {iface}.{property} at {path}: {e}")))
+    }
+
+    pub async fn call<B, R>(
+        &self,
+        path: &str,
+        iface: &str,
+        method: &str,
+        body: &B
+    ) -> Result<R, RogError>
+    where
+        B: Serialize + DynamicType,
+        R: for<'d> zbus::zvariant::DynamicDeserialize<'d>
+    {
+        self.proxy(path, iface)
+            .await?
+            .call(method, body)
+            .await
+            .map_err(|e| RogError::DoTask(format!("{iface}.{method} at {path}: {e}")))
+    }
+
+    /// Find every object the daemon exports that implements the interface
+    pub async fn find_paths(&self, iface: &str) -> Result<Vec<String>, RogError> {
+        let manager = ObjectManagerProxy::builder(&self.connection)
+            .destination(self.destination())?
+            .path("/")?
+            .build()
+            .await?;
+        let objects = manager
+            .get_managed_objects()
+            .await
+            .map_err(|e| RogError::DoTask(format!("get_managed_objects: {e}")))?;
+        Ok(objects
+            .into_iter()
+            .filter(|(_, ifaces)| ifaces.keys().any(|i| i.as_str() == iface))
+            .map(|(path, _)| path.to_string())
+            .collect())
+    }
+
+    /// Set the property on every object implementing the interface. Errors if
+    /// there are no such objects.
+    pub async fn set_property_on_all<'a>(
+        &self,
+        iface: &str,
+        property: &str,
+        value: impl Into<Value<'a>> + Clone + 'a
+    ) -> Result<(), RogError> {
+        let paths = self.find_paths(iface).await?;
+        if paths.is_empty() {
+            return Err(RogError::NotFound(iface.to_owned()));
+        }
+        for path in paths {
+            self.set_property(&path, iface, property, value.clone())
+                .await?;
+        }
+        Ok(())
+    }
+}
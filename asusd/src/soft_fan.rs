@@ -0,0 +1,417 @@
+//! Userspace fan-curve control for machines where the firmware has no
+//! `asus_custom_fan_curve` hwmon node. A temperature is read from the hwmon
+//! `temp*_input` files, run through the configured `CurveData`, and the
+//! result is written to `pwm*` with `pwm*_enable` set to manual.
+//!
+//! Dropping the controller hands the fans back to the firmware's automatic
+//! mode, as does any failure until an update succeeds again.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use log::{debug, warn};
+use rog_platform::platform::PlatformProfile;
+use rog_profiles::fan_curve_set::{CurveData, CurvePreset};
+use rog_profiles::FanCurvePU;
+
+use crate::error::RogError;
+
+pub const HWMON_ROOT: &str = "/sys/class/hwmon";
+/// How often the temperature is sampled and the fans updated
+pub const SOFT_FAN_INTERVAL: Duration = Duration::from_secs(2);
+/// The longest wait between attempts to take control again after a failure
+pub const SOFT_FAN_MAX_RETRY: Duration = Duration::from_secs(60);
+/// A falling temperature must drop this many degrees before the fan speed is
+/// lowered, this stops the fans hunting around a curve point
+pub const HYSTERESIS: u8 = 3;
+
+/// The hwmon `name` of the asus fans, other hwmons such as `amdgpu` may have
+/// `pwm*` files for fans which aren't ours to drive
+const FAN_HWMON_NAMES: [&str; 2] = [
+    "asus",
+    "asus_custom_fan_curve"
+];
+const PWM_ENABLE_MANUAL: &str = "1";
+const PWM_ENABLE_AUTO: &str = "2";
+/// Temperature sensors to prefer, in order. If none are found the first hwmon
+/// with any `temp*_input` is used.
+const PREFERRED_SENSORS: [&str; 4] = [
+    "k10temp", "coretemp", "zenpower", "acpitz"
+];
+
+/// How long to wait before trying again after `failures` failed updates in a
+/// row. Doubles from `SOFT_FAN_INTERVAL` up to `SOFT_FAN_MAX_RETRY`.
+pub fn retry_delay(failures: u32) -> Duration {
+    SOFT_FAN_INTERVAL
+        .saturating_mul(2u32.saturating_pow(failures))
+        .min(SOFT_FAN_MAX_RETRY)
+}
+
+/// The default curves used when nothing is stored yet
+pub fn default_curves(profile: PlatformProfile, fans: &[FanCurvePU]) -> Vec<CurveData> {
+    let preset = match profile {
+        PlatformProfile::Balanced => CurvePreset::Linear,
+        PlatformProfile::Performance => CurvePreset::Aggressive,
+        PlatformProfile::Quiet => CurvePreset::Silent
+    };
+    fans.iter()
+        .map(|fan| CurveData::from_preset(*fan, preset))
+        .collect()
+}
+
+fn read_trimmed(path: &Path) -> Result<String, RogError> {
+    fs::read_to_string(path)
+        .map(|s| s.trim().to_owned())
+        .map_err(|e| RogError::Read(path.to_string_lossy().to_string(), e))
+}
+
+fn write_value(path: &Path, value: &str) -> Result<(), RogError> {
+    fs::write(path, value).map_err(|e| RogError::Write(path.to_string_lossy().to_string(), e))
+}
+
+/// All `hwmonN` dirs under the root, sorted so that discovery is stable
+fn hwmon_dirs(root: &Path) -> Result<Vec<PathBuf>, RogError> {
+    let mut dirs: Vec<PathBuf> = fs::read_dir(root)
+        .map_err(|e| RogError::Read(root.to_string_lossy().to_string(), e))?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.is_dir())
+        .collect();
+    dirs.sort();
+    Ok(dirs)
+}
+
+fn temp_inputs(dir: &Path) -> Vec<PathBuf> {
+    let mut inputs: Vec<PathBuf> = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| {
+            p.file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| n.starts_with("temp") && n.ends_with("_input"))
+        })
+        .collect();
+    inputs.sort();
+    inputs
+}
+
+#[derive(Debug)]
+struct SoftFan {
+    fan: FanCurvePU,
+    pwm: PathBuf,
+    enable: PathBuf,
+    /// Set once the fan has been switched to manual control
+    manual: bool,
+    /// The temperature the current speed was calculated for
+    last_temp: Option<u8>,
+    last_pwm: Option<u8>
+}
+
+impl SoftFan {
+    fn set_auto(&mut self) -> Result<(), RogError> {
+        if self.manual {
+            write_value(&self.enable, PWM_ENABLE_AUTO)?;
+            self.manual = false;
+            self.last_temp = None;
+            self.last_pwm = None;
+        }
+        Ok(())
+    }
+
+    fn set_curve(&mut self, curve: &CurveData, temp: u8) -> Result<(), RogError> {
+        // Rising temperatures are acted on immediately, falling ones only once
+        // they have dropped far enough
+        let temp = match self.last_temp {
+            Some(last) if temp < last && last - temp < HYSTERESIS => last,
+            _ => temp
+        };
+        self.last_temp = Some(temp);
+
+        if !self.manual {
+            write_value(&self.enable, PWM_ENABLE_MANUAL)?;
+            self.manual = true;
+        }
+        let pwm = curve.pwm_at(temp);
+        if self.last_pwm != Some(pwm) {
+            debug!("{:?} fan: {temp}c, pwm {pwm}", self.fan);
+            write_value(&self.pwm, &pwm.to_string())?;
+            self.last_pwm = Some(pwm);
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct SoftFanControl {
+    fans: Vec<SoftFan>,
+    sensors: Vec<PathBuf>
+}
+
+impl SoftFanControl {
+    /// Find the asus hwmon with `pwmN` + `pwmN_enable` pairs, and a
+    /// temperature sensor. `hwmon_root` is normally `HWMON_ROOT`.
+    pub fn new(hwmon_root: &Path) -> Result<Self, RogError> {
+        let dirs = hwmon_dirs(hwmon_root)?;
+
+        let mut fans = Vec::new();
+        for dir in dirs.iter().filter(|d| {
+            read_trimmed(&d.join("name")).is_ok_and(|n| FAN_HWMON_NAMES.contains(&n.as_str()))
+        }) {
+            for fan in [
+                FanCurvePU::CPU,
+                FanCurvePU::GPU,
+                FanCurvePU::MID
+            ] {
+                let num = char::from(fan);
+                let pwm = dir.join(format!("pwm{num}"));
+                let enable = dir.join(format!("pwm{num}_enable"));
+                if pwm.exists() && enable.exists() {
+                    fans.push(SoftFan {
+                        fan,
+                        pwm,
+                        enable,
+                        manual: false,
+                        last_temp: None,
+                        last_pwm: None
+                    });
+                }
+            }
+            if !fans.is_empty() {
+                break;
+            }
+        }
+        if fans.is_empty() {
+            return Err(RogError::NotFound(format!(
+                "asus pwm fan control in {}",
+                hwmon_root.display()
+            )));
+        }
+
+        let mut sensors = Vec::new();
+        for name in PREFERRED_SENSORS {
+            if let Some(dir) = dirs
+                .iter()
+                .find(|d| read_trimmed(&d.join("name")).is_ok_and(|n| n == name))
+            {
+                sensors = temp_inputs(dir);
+                if !sensors.is_empty() {
+                    break;
+                }
+            }
+        }
+        if sensors.is_empty() {
+            sensors = dirs
+                .iter()
+                .map(|d| temp_inputs(d))
+                .find(|t| !t.is_empty())
+                .unwrap_or_default();
+        }
+        if sensors.is_empty() {
+            return Err(RogError::NotFound(format!(
+                "temperature sensor in {}",
+                hwmon_root.display()
+            )));
+        }
+
+        Ok(Self { fans, sensors })
+    }
+
+    /// The fans which can be controlled
+    pub fn fans(&self) -> Vec<FanCurvePU> {
+        self.fans.iter().map(|f| f.fan).collect()
+    }
+
+    /// The hottest of the sensors in degrees C
+    pub fn read_temp(&self) -> Result<u8, RogError> {
+        let mut hottest = 0;
+        for sensor in &self.sensors {
+            let milli: i64 = read_trimmed(sensor)?.parse().map_err(|_| {
+                RogError::DoTask(format!("invalid temperature in {}", sensor.display()))
+            })?;
+            hottest = hottest.max((milli / 1000).clamp(0, u8::MAX as i64) as u8);
+        }
+        Ok(hottest)
+    }
+
+    /// Sample the temperature and set each fan from its curve. Fans with no
+    /// enabled curve are left to, or returned to, firmware control.
+    pub fn update(&mut self, curves: &[CurveData]) -> Result<(), RogError> {
+        let temp = self.read_temp()?;
+        for fan in self.fans.iter_mut() {
+            match curves.iter().find(|c| c.fan == fan.fan && c.enabled) {
+                Some(curve) => fan.set_curve(curve, temp)?,
+                None => fan.set_auto()?
+            }
+        }
+        Ok(())
+    }
+
+    /// Hand all fans back to the firmware's automatic mode
+    pub fn restore(&mut self) {
+        for fan in self.fans.iter_mut() {
+            // Always try, the state may not be known after a failed write
+            fan.manual = true;
+            fan.set_auto()
+                .map_err(|e| warn!("Could not return {:?} fan to auto: {e}", fan.fan))
+                .ok();
+        }
+    }
+}
+
+impl Drop for SoftFanControl {
+    fn drop(&mut self) {
+        self.restore();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+
+    use rog_platform::platform::PlatformProfile;
+    use rog_profiles::FanCurvePU;
+    use tempfile::TempDir;
+
+    use super::{
+        default_curves, retry_delay, SoftFanControl, SOFT_FAN_INTERVAL, SOFT_FAN_MAX_RETRY
+    };
+
+    fn fake_hwmon() -> TempDir {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+
+        let add = |dir: &str, files: &[(&str, &str)]| {
+            let dir = root.join(dir);
+            fs::create_dir_all(&dir).unwrap();
+            for (file, value) in files {
+                fs::write(dir.join(file), value).unwrap();
+            }
+        };
+        add("hwmon0", &[
+            ("name", "amdgpu\n"),
+            ("pwm1", "0\n"),
+            ("pwm1_enable", "2\n"),
+            ("temp1_input", "38850\n")
+        ]);
+        add("hwmon1", &[
+            ("name", "asus\n"),
+            ("pwm1", "0\n"),
+            ("pwm1_enable", "2\n"),
+            ("pwm2", "0\n"),
+            ("pwm2_enable", "2\n")
+        ]);
+        add("hwmon2", &[
+            ("name", "k10temp\n"),
+            ("temp1_input", "45000\n"),
+            ("temp3_input", "51500\n")
+        ]);
+        tmp
+    }
+
+    fn read(root: &Path, file: &str) -> String {
+        fs::read_to_string(root.join("hwmon1").join(file))
+            .unwrap()
+            .trim()
+            .to_owned()
+    }
+
+    fn set_temp(root: &Path, temp: u32) {
+        fs::write(
+            root.join("hwmon2/temp3_input"),
+            format!("{}\n", temp * 1000)
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn find_fans_and_sensor() {
+        let tmp = fake_hwmon();
+        let root = tmp.path();
+        let control = SoftFanControl::new(root).unwrap();
+        assert_eq!(control.fans(), vec![
+            FanCurvePU::CPU,
+            FanCurvePU::GPU
+        ]);
+        // k10temp is preferred over the gpu, and the hottest is used
+        assert_eq!(control.read_temp().unwrap(), 51);
+
+        assert!(SoftFanControl::new(&root.join("hwmon0")).is_err());
+        // The gpu fan is never picked, even with no asus hwmon
+        fs::write(root.join("hwmon1/name"), "nct6775\n").unwrap();
+        assert!(SoftFanControl::new(root).is_err());
+    }
+
+    #[test]
+    fn drive_fans_with_hysteresis() {
+        let tmp = fake_hwmon();
+        let root = tmp.path();
+        let mut curves = default_curves(PlatformProfile::Balanced, &[FanCurvePU::CPU]);
+        curves[0].enabled = true;
+        let curve = curves[0].clone();
+
+        let mut control = SoftFanControl::new(root).unwrap();
+        set_temp(root, 60);
+        control.update(&curves).unwrap();
+        assert_eq!(read(root, "pwm1_enable"), "1");
+        assert_eq!(read(root, "pwm1"), curve.pwm_at(60).to_string());
+        // No curve for the GPU so it is left alone
+        assert_eq!(read(root, "pwm2_enable"), "2");
+
+        // Small drops are ignored
+        set_temp(root, 58);
+        control.update(&curves).unwrap();
+        assert_eq!(read(root, "pwm1"), curve.pwm_at(60).to_string());
+        set_temp(root, 55);
+        control.update(&curves).unwrap();
+        assert_eq!(read(root, "pwm1"), curve.pwm_at(55).to_string());
+        // Rises are not
+        set_temp(root, 56);
+        control.update(&curves).unwrap();
+        assert_eq!(read(root, "pwm1"), curve.pwm_at(56).to_string());
+
+        // Disabling the curve returns the fan to the firmware
+        curves[0].enabled = false;
+        control.update(&curves).unwrap();
+        assert_eq!(read(root, "pwm1_enable"), "2");
+
+        curves[0].enabled = true;
+        control.update(&curves).unwrap();
+        assert_eq!(read(root, "pwm1_enable"), "1");
+        drop(control);
+        assert_eq!(read(root, "pwm1_enable"), "2");
+    }
+
+    #[test]
+    fn failsafe_on_sensor_error() {
+        let tmp = fake_hwmon();
+        let root = tmp.path();
+        let mut curves = default_curves(PlatformProfile::Performance, &[
+            FanCurvePU::CPU,
+            FanCurvePU::GPU
+        ]);
+        curves.iter_mut().for_each(|c| c.enabled = true);
+
+        let mut control = SoftFanControl::new(root).unwrap();
+        control.update(&curves).unwrap();
+        assert_eq!(read(root, "pwm2_enable"), "1");
+
+        fs::write(root.join("hwmon2/temp3_input"), "garbage").unwrap();
+        assert!(control.update(&curves).is_err());
+        control.restore();
+        assert_eq!(read(root, "pwm1_enable"), "2");
+        assert_eq!(read(root, "pwm2_enable"), "2");
+    }
+
+    #[test]
+    fn retry_backs_off() {
+        assert_eq!(retry_delay(0), SOFT_FAN_INTERVAL);
+        assert_eq!(retry_delay(1), SOFT_FAN_INTERVAL * 2);
+        assert_eq!(retry_delay(3), SOFT_FAN_INTERVAL * 8);
+        assert_eq!(retry_delay(10), SOFT_FAN_MAX_RETRY);
+        assert_eq!(retry_delay(u32::MAX), SOFT_FAN_MAX_RETRY);
+    }
+}
@@ -0,0 +1,30 @@
+use chrono::{NaiveTime, Timelike};
+use serde::{Deserialize, Serialize};
+
+/// A local time of day with minute resolution
+#[derive(Deserialize, Serialize, Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct TimeOfDay {
+    pub hour: u8,
+    pub minute: u8
+}
+
+impl TimeOfDay {
+    pub fn new(hour: u8, minute: u8) -> Self {
+        Self { hour, minute }
+    }
+
+    pub fn now() -> Self {
+        chrono::Local::now().time().into()
+    }
+
+    /// Minutes since midnight
+    pub fn minutes(&self) -> u16 {
+        self.hour as u16 * 60 + self.minute as u16
+    }
+}
+
+impl From<NaiveTime> for TimeOfDay {
+    fn from(time: NaiveTime) -> Self {
+        Self::new(time.hour() as u8, time.minute() as u8)
+    }
+}
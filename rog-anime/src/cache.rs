@@ -0,0 +1,262 @@
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use log::{debug, warn};
+
+use crate::error::Result;
+use crate::{ActionData, ActionLoader, AnimeDataBuffer, AnimeFrame, AnimeGif, AnimeType};
+
+/// Start of every cache file, changed if the layout changes
+const CACHE_MAGIC: &[u8; 8] = b"ANIMEFC1";
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0100_0000_01b3;
+/// The default size the cache is kept under, in bytes
+const MAX_CACHE_BYTES: u64 = 64 * 1024 * 1024;
+
+/// FNV-1a, which unlike the std hasher is the same from one build to the next
+#[derive(Debug, Clone, Copy)]
+struct Fnv(u64);
+
+impl Fnv {
+    fn new() -> Self {
+        Self(FNV_OFFSET)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 = (self.0 ^ *byte as u64).wrapping_mul(FNV_PRIME);
+        }
+    }
+}
+
+/// Gif frames already converted to `AnimeDataBuffer`s, saved in `dir` so large
+/// gifs are not decoded again on every start. Entries are keyed by a hash of
+/// the gif and the settings it was converted with, so an edited file or
+/// config makes a new entry. The entries used least recently are removed to
+/// keep the cache under a size, so those left behind by edits don't build up.
+/// Anything going wrong with the cache only means the gif is converted again.
+#[derive(Debug, Clone)]
+pub struct FrameCache {
+    dir: PathBuf,
+    max_bytes: u64
+}
+
+impl FrameCache {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            max_bytes: MAX_CACHE_BYTES
+        }
+    }
+
+    /// Keep the cache under `max_bytes` instead of the default 64MiB. The
+    /// newest entry is always kept, even if it alone is larger.
+    pub fn with_max_bytes(mut self, max_bytes: u64) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    /// Convert `action` the same as `ActionData::from_anime_action`, using the
+    /// cached frames for gifs if there are any
+    pub fn action_data(&self, anime_type: AnimeType, action: &ActionLoader) -> Result<ActionData> {
+        let (file, kind, params, time) = match action {
+            ActionLoader::AsusAnimation {
+                file,
+                time,
+                brightness
+            } => (file, "diagonal", vec![*brightness], *time),
+            ActionLoader::ImageAnimation {
+                file,
+                scale,
+                angle,
+                translation,
+                time,
+                brightness
+            } if file.extension().is_some_and(|e| e == "gif") => (
+                file,
+                "image",
+                vec![
+                    *scale, *angle, translation.x, translation.y, *brightness,
+                ],
+                *time
+            ),
+            _ => return ActionData::from_anime_action(anime_type, action)
+        };
+
+        let path = match Self::key(file, kind, anime_type, &params) {
+            Ok(key) => self.dir.join(format!("{key:016x}.frames")),
+            Err(e) => {
+                warn!("Could not hash {file:?} for the frame cache: {e}");
+                return ActionData::from_anime_action(anime_type, action);
+            }
+        };
+        if let Ok(frames) = Self::read(&path, anime_type) {
+            debug!("Frames for {file:?} loaded from {path:?}");
+            // The modified time is when it was last used, for pruning
+            File::options()
+                .append(true)
+                .open(&path)
+                .and_then(|f| f.set_modified(SystemTime::now()))
+                .ok();
+            return Ok(ActionData::Animation(AnimeGif::from_frames(frames, time)));
+        }
+
+        let data = ActionData::from_anime_action(anime_type, action)?;
+        if let ActionData::Animation(gif) = &data {
+            self.write(&path, gif)
+                .and_then(|_| self.prune(&path))
+                .map_err(|e| warn!("Could not write frame cache {path:?}: {e}"))
+                .ok();
+        }
+        Ok(data)
+    }
+
+    /// Remove the least recently used entries, other than `keep`, until the
+    /// cache is under `max_bytes`
+    fn prune(&self, keep: &Path) -> std::io::Result<()> {
+        let mut entries = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().is_none_or(|e| e != "frames") {
+                continue;
+            }
+            let meta = fs::metadata(&path)?;
+            entries.push((meta.modified()?, meta.len(), path));
+        }
+
+        let mut total: u64 = entries.iter().map(|(_, len, _)| len).sum();
+        entries.sort();
+        for (_, len, path) in entries {
+            if total <= self.max_bytes {
+                break;
+            }
+            if path != keep {
+                debug!("Removing {path:?} from the frame cache");
+                fs::remove_file(&path)?;
+                total -= len;
+            }
+        }
+        Ok(())
+    }
+
+    fn key(file: &Path, kind: &str, anime_type: AnimeType, params: &[f32]) -> std::io::Result<u64> {
+        let mut hash = Fnv::new();
+        let mut reader = BufReader::new(File::open(file)?);
+        let mut buf = [0u8; 8192];
+        loop {
+            let len = reader.read(&mut buf)?;
+            if len == 0 {
+                break;
+            }
+            hash.write(&buf[..len]);
+        }
+        hash.write(kind.as_bytes());
+        hash.write(format!("{anime_type:?}").as_bytes());
+        for param in params {
+            hash.write(&param.to_le_bytes());
+        }
+        Ok(hash.0)
+    }
+
+    fn read(path: &Path, anime_type: AnimeType) -> std::io::Result<Vec<AnimeFrame>> {
+        let mut bytes = Vec::new();
+        File::open(path)?.read_to_end(&mut bytes)?;
+        let invalid = || std::io::Error::new(std::io::ErrorKind::InvalidData, "bad frame cache");
+
+        let body = bytes.strip_prefix(CACHE_MAGIC).ok_or_else(invalid)?;
+        let len = anime_type.data_length();
+        let (count, body) = body.split_first_chunk::<4>().ok_or_else(invalid)?;
+        let count = u32::from_le_bytes(*count) as usize;
+        if body.len() != count * (len + 4) {
+            return Err(invalid());
+        }
+        body.chunks(len + 4)
+            .map(|chunk| {
+                let (delay, data) = chunk.split_at(4);
+                let delay = u32::from_le_bytes(delay.try_into().map_err(|_| invalid())?);
+                let data =
+                    AnimeDataBuffer::from_vec(anime_type, data.to_vec()).map_err(|_| invalid())?;
+                Ok(AnimeFrame::new(data, Duration::from_millis(delay as u64)))
+            })
+            .collect()
+    }
+
+    fn write(&self, path: &Path, gif: &AnimeGif) -> std::io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        // Written aside then moved in place so a reader never sees half a file
+        let part = path.with_extension("part");
+        let mut writer = BufWriter::new(File::create(&part)?);
+        writer.write_all(CACHE_MAGIC)?;
+        writer.write_all(&(gif.frame_count() as u32).to_le_bytes())?;
+        for frame in gif.frames() {
+            writer.write_all(&(frame.delay().as_millis() as u32).to_le_bytes())?;
+            writer.write_all(frame.frame().data())?;
+        }
+        writer.flush()?;
+        drop(writer);
+        fs::rename(part, path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::FrameCache;
+    use crate::{ActionData, ActionLoader, AnimTime, AnimeType};
+
+    #[test]
+    fn cached_frames_match() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path().join("cache");
+        let mut file = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        file.push("tests/data/ga402-diagonal.gif");
+        let action = |brightness| ActionLoader::AsusAnimation {
+            file: file.clone(),
+            time: AnimTime::Count(2),
+            brightness
+        };
+
+        let cache = FrameCache::new(&dir);
+        let ActionData::Animation(converted) =
+            cache.action_data(AnimeType::GA402, &action(0.5)).unwrap()
+        else {
+            panic!("Not an animation");
+        };
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+        let ActionData::Animation(cached) =
+            cache.action_data(AnimeType::GA402, &action(0.5)).unwrap()
+        else {
+            panic!("Not an animation");
+        };
+        assert!(matches!(cached.duration(), AnimTime::Count(2)));
+        assert_eq!(cached.frame_count(), converted.frame_count());
+        for (a, b) in cached.frames().iter().zip(converted.frames()) {
+            assert_eq!(a.delay(), b.delay());
+            assert_eq!(a.frame().data(), b.frame().data());
+        }
+
+        // Different settings are a different entry
+        cache.action_data(AnimeType::GA402, &action(1.0)).unwrap();
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 2);
+
+        // Too small for two entries, so the older ones are removed and the newest
+        // kept even though it is over
+        let cache = FrameCache::new(&dir).with_max_bytes(1);
+        cache.action_data(AnimeType::GA402, &action(0.1)).unwrap();
+        let left: Vec<_> = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|e| e.unwrap().path())
+            .collect();
+        assert_eq!(left.len(), 1);
+        // The entry left is the newest, at 0.1 brightness
+        let frames = FrameCache::read(&left[0], AnimeType::GA402).unwrap();
+        assert_eq!(frames.len(), converted.frame_count());
+        assert_ne!(
+            frames[0].frame().data(),
+            converted.frames()[0].frame().data()
+        );
+    }
+}
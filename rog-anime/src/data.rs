@@ -1,7 +1,5 @@
 use std::convert::TryFrom;
 use std::str::FromStr;
-use std::thread::sleep;
-use std::time::{Duration, Instant};
 
 use dmi_id::DMIID;
 use log::info;
@@ -11,7 +9,7 @@ use zbus::zvariant::{OwnedValue, Type, Value};
 
 use crate::error::{AnimeError, Result};
 use crate::usb::{AnimAwake, AnimBooting, AnimShutdown, AnimSleeping, Brightness};
-use crate::{AnimTime, AnimeGif};
+use crate::{AnimeGif, AnimePlayer, Playback};
 
 /// The first 7 bytes of a USB packet are accounted for by `USB_PREFIX1` and
 /// `USB_PREFIX2`
@@ -200,91 +198,12 @@ impl TryFrom<AnimeDataBuffer> for AnimePacketType {
 }
 
 /// This runs the animations as a blocking loop by using the `callback` to write
-/// data
+/// data. Frames are played by an `AnimePlayer`.
 ///
 /// If `callback` is `Ok(true)` then `run_animation` will exit the animation
 /// loop early.
 pub fn run_animation(frames: &AnimeGif, callback: &dyn Fn(AnimeDataBuffer) -> Result<bool>) {
-    let mut count = 0;
-    let start = Instant::now();
-
-    let mut timed = false;
-    let mut run_time = frames.total_frame_time();
-    if let AnimTime::Fade(time) = frames.duration() {
-        if let Some(middle) = time.show_for() {
-            run_time = middle + time.total_fade_time();
-        }
-        // add a small buffer
-        run_time += Duration::from_millis(250);
-        timed = true;
-    } else if let AnimTime::Time(time) = frames.duration() {
-        run_time = time;
-        timed = true;
-    }
-
-    // After setting up all the data
-    let mut fade_in = Duration::from_millis(0);
-    let mut fade_out = Duration::from_millis(0);
-    let mut fade_in_step = 0.0;
-    let mut fade_in_accum = 0.0;
-    let mut fade_out_step = 0.0;
-    let mut fade_out_accum;
-    if let AnimTime::Fade(time) = frames.duration() {
-        fade_in = time.fade_in();
-        fade_out = time.fade_out();
-        fade_in_step = 1.0 / fade_in.as_secs_f32();
-        fade_out_step = 1.0 / fade_out.as_secs_f32();
-
-        if time.total_fade_time() > run_time {
-            println!("Total fade in/out time larger than gif run time. Setting fades to half");
-            fade_in = run_time / 2;
-            fade_in_step = 1.0 / (run_time / 2).as_secs_f32();
-
-            fade_out = run_time / 2;
-            fade_out_step = 1.0 / (run_time / 2).as_secs_f32();
-        }
-    }
-
-    'animation: loop {
-        for frame in frames.frames() {
-            let frame_start = Instant::now();
-            let mut output = frame.frame().clone();
-
-            if let AnimTime::Fade(_) = frames.duration() {
-                if frame_start <= start + fade_in {
-                    for pixel in output.data_mut() {
-                        *pixel = (*pixel as f32 * fade_in_accum) as u8;
-                    }
-                    fade_in_accum = fade_in_step * (frame_start - start).as_secs_f32();
-                } else if frame_start > (start + run_time) - fade_out {
-                    if run_time > (frame_start - start) {
-                        fade_out_accum =
-                            fade_out_step * (run_time - (frame_start - start)).as_secs_f32();
-                    } else {
-                        fade_out_accum = 0.0;
-                    }
-                    for pixel in output.data_mut() {
-                        *pixel = (*pixel as f32 * fade_out_accum) as u8;
-                    }
-                }
-            }
-
-            // TODO: Log this error
-            if matches!(callback(output), Ok(true)) {
-                info!("rog-anime: animation frame-loop callback asked to exit early");
-                return;
-            }
-
-            if timed && Instant::now().duration_since(start) > run_time {
-                break 'animation;
-            }
-            sleep(frame.delay());
-        }
-        if let AnimTime::Count(times) = frames.duration() {
-            count += 1;
-            if count >= times {
-                break 'animation;
-            }
-        }
+    if !AnimePlayer::new(frames, &Playback::default()).play(callback) {
+        info!("rog-anime: animation frame-loop callback asked to exit early");
     }
 }
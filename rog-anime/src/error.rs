@@ -1,8 +1,9 @@
 use std::error::Error;
 use std::fmt;
 
-use gif::DecodingError;
+use gif::{DecodingError, EncodingError};
 use png_pong::decode::Error as PngError;
+use png_pong::encode::Error as PngEncodeError;
 
 pub type Result<T> = std::result::Result<T, AnimeError>;
 
@@ -12,6 +13,8 @@ pub enum AnimeError {
     Io(std::io::Error),
     Png(PngError),
     Gif(DecodingError),
+    PngEncode(PngEncodeError),
+    GifEncode(EncodingError),
     Format,
     /// The input was incorrect size, expected size is `IncorrectSize(width,
     /// height)`
@@ -24,7 +27,8 @@ pub enum AnimeError {
     DataBufferLength,
     PixelGifWidth(usize),
     PixelGifHeight(usize),
-    ParseError(String)
+    ParseError(String),
+    Font(String)
 }
 
 impl fmt::Display for AnimeError {
@@ -36,6 +40,8 @@ impl fmt::Display for AnimeError {
             AnimeError::Io(e) => write!(f, "Could not open: {}", e),
             AnimeError::Png(e) => write!(f, "PNG error: {}", e),
             AnimeError::Gif(e) => write!(f, "GIF error: {}", e),
+            AnimeError::PngEncode(e) => write!(f, "Could not write PNG: {}", e),
+            AnimeError::GifEncode(e) => write!(f, "Could not write GIF: {}", e),
             AnimeError::Format => write!(f, "PNG file is not 8bit greyscale"),
             AnimeError::IncorrectSize(width, height) => write!(
                 f,
@@ -61,7 +67,8 @@ impl fmt::Display for AnimeError {
             AnimeError::PixelGifHeight(n) => write!(
                 f,
                 "The gif used for pixel-perfect gif is is taller than {n}"
-            )
+            ),
+            AnimeError::Font(e) => write!(f, "Could not load font {e}")
         }
     }
 }
@@ -89,6 +96,20 @@ impl From<DecodingError> for AnimeError {
     }
 }
 
+impl From<PngEncodeError> for AnimeError {
+    #[inline]
+    fn from(err: PngEncodeError) -> Self {
+        AnimeError::PngEncode(err)
+    }
+}
+
+impl From<EncodingError> for AnimeError {
+    #[inline]
+    fn from(err: EncodingError) -> Self {
+        AnimeError::GifEncode(err)
+    }
+}
+
 impl From<AnimeError> for zbus::fdo::Error {
     #[inline]
     fn from(err: AnimeError) -> Self {
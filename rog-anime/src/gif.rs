@@ -20,6 +20,11 @@ pub struct AnimeFrame {
 }
 
 impl AnimeFrame {
+    #[inline]
+    pub fn new(data: AnimeDataBuffer, delay: Duration) -> Self {
+        Self { data, delay }
+    }
+
     /// Get the inner data buffer of the gif frame
     #[inline]
     pub fn frame(&self) -> &AnimeDataBuffer {
@@ -94,6 +99,12 @@ impl Fade {
 pub struct AnimeGif(Vec<AnimeFrame>, AnimTime);
 
 impl AnimeGif {
+    /// Create an animation from already converted frames
+    #[inline]
+    pub fn from_frames(frames: Vec<AnimeFrame>, duration: AnimTime) -> Self {
+        Self(frames, duration)
+    }
+
     /// Create an animation using the 74x36 ASUS gif format
     #[inline]
     pub fn from_diagonal_gif(
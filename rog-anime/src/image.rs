@@ -117,7 +117,7 @@ impl AnimeImage {
     /// For GA401 this is `26.8 / (33 + 0.5) = 0.8`
     /// For GA402 this is `27.4 / (35 + 0.5) = 0.77`
     /// For GA402 this is `30.9 / (39 + 0.5) = 0.77`
-    fn scale_x(anime_type: AnimeType) -> f32 {
+    pub(crate) fn scale_x(anime_type: AnimeType) -> f32 {
         match anime_type {
             AnimeType::GA401 => 0.8,
             AnimeType::GU604 => 0.78,
@@ -134,7 +134,7 @@ impl AnimeImage {
     /// For GA401 this is `16.5 / (54.0 + 1.0) = 0.3`
     /// For GA402 this is `17.3 / (61.0)       = 0.283`
     /// For GU604 this is `17.7 / (62.0 + 1)   = 0.28`
-    fn scale_y(anime_type: AnimeType) -> f32 {
+    pub(crate) fn scale_y(anime_type: AnimeType) -> f32 {
         match anime_type {
             AnimeType::GA401 => 0.3,
             AnimeType::GU604 => 0.28,
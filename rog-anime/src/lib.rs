@@ -26,6 +26,30 @@ pub use crate::gif::*;
 mod sequencer;
 pub use sequencer::*;
 
+/// Plays animations to a deadline, and blends between actions
+mod player;
+pub use player::*;
+
+/// Converted animation frames saved to disk
+mod cache;
+pub use cache::*;
+
+/// Spectrum analyser bars drawn from audio levels
+mod spectrum;
+pub use spectrum::*;
+
+/// Generated content: a clock, system info and digital rain
+mod widgets;
+pub use widgets::*;
+
+/// Text in the built-in font or a font file, still or scrolling
+mod text;
+pub use text::*;
+
+/// Draw frames as they look on the display, for previews and tests
+mod render;
+pub use render::*;
+
 /// Base errors that are possible
 pub mod error;
 
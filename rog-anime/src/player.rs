@@ -0,0 +1,334 @@
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use crate::error::Result;
+use crate::{AnimTime, AnimeDataBuffer, AnimeGif};
+
+/// Frames are shown for at least this long, some gifs have no delays
+const MIN_FRAME_DELAY: Duration = Duration::from_millis(10);
+/// Slower than this is as good as stopped
+const MIN_RATE: f32 = 0.01;
+/// Faster than this would show each frame for less than the display can
+const MAX_RATE: f32 = 100.0;
+/// Added to a fade so the last of the fade out is shown
+const FADE_GRACE: Duration = Duration::from_millis(250);
+
+/// How animations are played
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub struct Playback {
+    /// Playback speed, `2.0` is twice as fast
+    pub rate: f32,
+    /// Play animations this many times, in place of an `AnimTime::Count` or
+    /// `AnimTime::Infinite`
+    pub loops: Option<u32>,
+    /// Time taken to blend from the last frame of an action to the next
+    pub crossfade: Duration
+}
+
+impl Default for Playback {
+    fn default() -> Self {
+        Self {
+            rate: 1.0,
+            loops: None,
+            crossfade: Duration::ZERO
+        }
+    }
+}
+
+/// Plays an `AnimeGif` against a monotonic clock. Each frame is due at a
+/// fixed time from the start, so slow writes do not add up to drift, and
+/// frames that are already late are skipped.
+#[derive(Debug)]
+pub struct AnimePlayer<'a> {
+    gif: &'a AnimeGif,
+    /// When each frame is due within a loop, adjusted for the rate
+    starts: Vec<Duration>,
+    loop_time: Duration,
+    loops: Option<u32>,
+    run_time: Option<Duration>,
+    /// Fade in and fade out times
+    fade: Option<(Duration, Duration)>
+}
+
+impl<'a> AnimePlayer<'a> {
+    pub fn new(gif: &'a AnimeGif, playback: &Playback) -> Self {
+        let rate = playback.rate.clamp(MIN_RATE, MAX_RATE) as f64;
+        let mut starts = Vec::with_capacity(gif.frame_count());
+        let mut loop_time = Duration::ZERO;
+        for frame in gif.frames() {
+            starts.push(loop_time);
+            let delay = frame.delay().max(MIN_FRAME_DELAY).as_nanos() as f64;
+            loop_time += Duration::from_nanos(((delay / rate).round() as u64).max(1));
+        }
+
+        let (loops, run_time, fade) = match gif.duration() {
+            AnimTime::Count(count) => (Some(playback.loops.unwrap_or(count)), None, None),
+            AnimTime::Infinite => (playback.loops, None, None),
+            AnimTime::Time(time) => (playback.loops, Some(time), None),
+            AnimTime::Fade(fade) => {
+                let run_time = fade
+                    .show_for()
+                    .map_or(loop_time, |show| show + fade.total_fade_time());
+                let (fade_in, fade_out) = if fade.total_fade_time() > run_time {
+                    (run_time / 2, run_time / 2)
+                } else {
+                    (fade.fade_in(), fade.fade_out())
+                };
+                (
+                    playback.loops,
+                    Some(run_time + FADE_GRACE),
+                    Some((fade_in, fade_out + FADE_GRACE))
+                )
+            }
+        };
+
+        Self {
+            gif,
+            starts,
+            loop_time,
+            loops: loops.map(|l| l.max(1)),
+            run_time,
+            fade
+        }
+    }
+
+    /// The frame due at `elapsed`, numbered on from the first frame over all
+    /// loops. `None` once the animation has finished.
+    pub fn frame_at(&self, elapsed: Duration) -> Option<usize> {
+        if self.starts.is_empty() || self.run_time.is_some_and(|t| elapsed >= t) {
+            return None;
+        }
+        let count = (elapsed.as_nanos() / self.loop_time.as_nanos()) as usize;
+        if self.loops.is_some_and(|l| count >= l as usize) {
+            return None;
+        }
+        let within = elapsed - self.loop_time * count as u32;
+        let index = self.starts.partition_point(|s| *s <= within) - 1;
+        Some(count * self.starts.len() + index)
+    }
+
+    /// When frame `number` is due, counted from the start
+    pub fn due(&self, number: usize) -> Duration {
+        let count = number / self.starts.len();
+        self.loop_time * count as u32 + self.starts[number % self.starts.len()]
+    }
+
+    /// Frame `number` with the fade at `elapsed` applied
+    pub fn frame(&self, number: usize, elapsed: Duration) -> AnimeDataBuffer {
+        let mut output = self.gif.frames()[number % self.starts.len()]
+            .frame()
+            .clone();
+        if let (Some((fade_in, fade_out)), Some(run_time)) = (self.fade, self.run_time) {
+            let faded_in = elapsed.as_secs_f32() / fade_in.as_secs_f32();
+            let left = run_time.saturating_sub(elapsed).as_secs_f32() / fade_out.as_secs_f32();
+            let bright = faded_in.min(left);
+            if bright < 1.0 {
+                for pixel in output.data_mut() {
+                    *pixel = (*pixel as f32 * bright) as u8;
+                }
+            }
+        }
+        output
+    }
+
+    /// When to wake after showing frame `number`
+    pub fn next_due(&self, number: usize) -> Duration {
+        let next = self.due(number + 1);
+        self.run_time.map_or(next, |t| next.min(t))
+    }
+
+    /// Play in real time, blocking until done. If `write` returns `Ok(true)`
+    /// playing stops early and `false` is returned. A frame that fails to
+    /// write is skipped, only the first failure of a run is logged.
+    pub fn play(&self, mut write: impl FnMut(AnimeDataBuffer) -> Result<bool>) -> bool {
+        let start = Instant::now();
+        let mut shown = None;
+        let mut failed = false;
+        while let Some(number) = self.frame_at(start.elapsed()) {
+            if shown != Some(number) {
+                match write(self.frame(number, start.elapsed())) {
+                    Ok(true) => return false,
+                    Ok(false) => {}
+                    Err(e) if !failed => {
+                        warn!("Could not write AniMe frame {number}: {e}");
+                        failed = true;
+                    }
+                    Err(_) => {}
+                }
+                shown = Some(number);
+            }
+            sleep(self.next_due(number).saturating_sub(start.elapsed()));
+        }
+        true
+    }
+}
+
+/// Blends the start of each action with the last frame of the one before it
+#[derive(Debug, Clone, Default)]
+pub struct Crossfade {
+    length: Duration,
+    last: Option<AnimeDataBuffer>,
+    from: Option<AnimeDataBuffer>
+}
+
+impl Crossfade {
+    pub fn new(length: Duration) -> Self {
+        Self {
+            length,
+            last: None,
+            from: None
+        }
+    }
+
+    /// Start blending from the last frame shown. To be called as each action
+    /// starts.
+    pub fn next_action(&mut self) {
+        if let Some(last) = self.last.take() {
+            self.from = Some(last);
+        }
+    }
+
+    /// If frames of the current action still need blending at `elapsed`
+    pub fn blending(&self, elapsed: Duration) -> bool {
+        self.from.is_some() && elapsed < self.length
+    }
+
+    /// Blend `frame`, shown `elapsed` in to the action, with the last frame of
+    /// the action before
+    pub fn apply(&mut self, mut frame: AnimeDataBuffer, elapsed: Duration) -> AnimeDataBuffer {
+        if self.length.is_zero() {
+            return frame;
+        }
+        if self.blending(elapsed) {
+            if let Some(from) = &self.from {
+                let mix = elapsed.as_secs_f32() / self.length.as_secs_f32();
+                for (pixel, old) in frame.data_mut().iter_mut().zip(from.data()) {
+                    *pixel = (*old as f32 + (*pixel as f32 - *old as f32) * mix) as u8;
+                }
+            }
+        } else {
+            self.from = None;
+        }
+        self.last = Some(frame.clone());
+        frame
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{AnimePlayer, Crossfade, Playback};
+    use crate::{AnimTime, AnimeDataBuffer, AnimeFrame, AnimeGif, AnimeType, Fade};
+
+    fn gif(delays: &[u64], time: AnimTime) -> AnimeGif {
+        let frames = delays
+            .iter()
+            .enumerate()
+            .map(|(i, delay)| {
+                let mut data = AnimeDataBuffer::new(AnimeType::GA401);
+                data.data_mut().fill(i as u8 * 10 + 10);
+                AnimeFrame::new(data, Duration::from_millis(*delay))
+            })
+            .collect();
+        AnimeGif::from_frames(frames, time)
+    }
+
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    #[test]
+    fn frames_on_deadline() {
+        let gif = gif(
+            &[
+                100, 50, 100
+            ],
+            AnimTime::Count(2)
+        );
+        let player = AnimePlayer::new(&gif, &Playback::default());
+        assert_eq!(player.frame_at(ms(0)), Some(0));
+        assert_eq!(player.frame_at(ms(99)), Some(0));
+        assert_eq!(player.frame_at(ms(100)), Some(1));
+        // Running late skips straight to the frame that is due
+        assert_eq!(player.frame_at(ms(160)), Some(2));
+        assert_eq!(player.frame_at(ms(250)), Some(3));
+        assert_eq!(player.due(4), ms(350));
+        assert_eq!(player.frame_at(ms(499)), Some(5));
+        assert_eq!(player.frame_at(ms(500)), None);
+    }
+
+    #[test]
+    fn rate_and_loops() {
+        let gif = gif(&[100, 100], AnimTime::Infinite);
+        let playback = Playback {
+            rate: 2.0,
+            loops: Some(3),
+            ..Default::default()
+        };
+        let player = AnimePlayer::new(&gif, &playback);
+        assert_eq!(player.frame_at(ms(50)), Some(1));
+        assert_eq!(player.frame_at(ms(299)), Some(5));
+        assert_eq!(player.frame_at(ms(300)), None);
+
+        let player = AnimePlayer::new(&gif, &Playback::default());
+        assert_eq!(player.frame_at(ms(60_000)), Some(600));
+
+        // Huge rates are capped rather than making each frame take no time
+        for rate in [
+            1e9,
+            f32::INFINITY
+        ] {
+            let playback = Playback {
+                rate,
+                ..Default::default()
+            };
+            let player = AnimePlayer::new(&gif, &playback);
+            assert_eq!(player.frame_at(ms(0)), Some(0));
+            assert_eq!(player.frame_at(ms(1)), Some(1));
+            assert_eq!(player.frame_at(ms(2)), Some(2));
+        }
+
+        let gif = self::gif(&[100, 100], AnimTime::Time(ms(250)));
+        let player = AnimePlayer::new(&gif, &Playback::default());
+        assert_eq!(player.frame_at(ms(249)), Some(2));
+        assert_eq!(player.next_due(2), ms(250));
+        assert_eq!(player.frame_at(ms(250)), None);
+    }
+
+    #[test]
+    fn fade_in_out() {
+        let fade = Fade::new(ms(100), Some(ms(200)), ms(100));
+        let gif = gif(&[30], AnimTime::Fade(fade));
+        let player = AnimePlayer::new(&gif, &Playback::default());
+        assert_eq!(player.frame(0, ms(0)).data()[0], 0);
+        assert_eq!(player.frame(0, ms(50)).data()[0], 5);
+        assert_eq!(player.frame(0, ms(200)).data()[0], 10);
+        assert!(player.frame(0, ms(500)).data()[0] < 5);
+        assert_eq!(player.frame_at(ms(650)), None);
+    }
+
+    #[test]
+    fn crossfade_actions() {
+        let mut from = AnimeDataBuffer::new(AnimeType::GA401);
+        from.data_mut().fill(200);
+        let to = AnimeDataBuffer::new(AnimeType::GA401);
+
+        let mut fade = Crossfade::new(ms(100));
+        fade.next_action();
+        assert_eq!(fade.apply(from, ms(0)).data()[0], 200);
+        fade.next_action();
+        assert!(fade.blending(ms(0)));
+        assert_eq!(fade.apply(to.clone(), ms(0)).data()[0], 200);
+        assert_eq!(fade.apply(to.clone(), ms(50)).data()[0], 100);
+        assert_eq!(fade.apply(to.clone(), ms(100)).data()[0], 0);
+        assert!(!fade.blending(ms(0)));
+
+        let mut fade = Crossfade::default();
+        fade.next_action();
+        assert_eq!(fade.apply(to, ms(0)).data()[0], 0);
+    }
+}
@@ -0,0 +1,332 @@
+use std::io::Write;
+use std::time::Duration;
+
+use pix::gray::SGray8;
+use pix::Raster;
+
+use crate::error::{AnimeError, Result};
+use crate::{
+    ActionData, AnimTime, AnimeDataBuffer, AnimeImage, AnimePlayer, AnimeType, ClockTime,
+    Crossfade, Playback, Sequences, SystemMonitor
+};
+
+/// Size of a render, in pixels to a centimetre of display
+const PX_PER_CM: f32 = 16.0;
+/// Border around the outermost LEDs, in pixels
+const MARGIN: f32 = 8.0;
+/// How bright an LED that is off is drawn, so the shape of the display shows
+const LED_OFF: u8 = 24;
+/// The shortest time a GIF can show a frame for
+const GIF_MIN_DELAY: Duration = Duration::from_millis(10);
+/// Time between the frames drawn while an image fades in
+const FADE_STEP: Duration = Duration::from_millis(33);
+/// Actions that run until stopped, or for longer than this, are cut short
+pub const PREVIEW_MAX_TIME: Duration = Duration::from_secs(10);
+
+/// Draws `AnimeDataBuffer`s as the display shows them, with each LED at its
+/// place on the diagonal layout and spaced as on the laptop. Used for
+/// previews and for checking frames in tests without a device.
+#[derive(Debug, Clone)]
+pub struct AnimeRender {
+    anime_type: AnimeType,
+    width: u32,
+    height: u32,
+    radius: f32,
+    /// Index in to the data buffer and pixel centre of each LED
+    leds: Vec<(usize, f32, f32)>
+}
+
+impl AnimeRender {
+    pub fn new(anime_type: AnimeType) -> Self {
+        let scale_x = AnimeImage::scale_x(anime_type) * PX_PER_CM;
+        let scale_y = AnimeImage::scale_y(anime_type) * PX_PER_CM;
+        // The GA401 data starts one byte in
+        let offset = usize::from(anime_type == AnimeType::GA401);
+        let positions = AnimeImage::generate_image_positioning(anime_type);
+        let min_x = positions
+            .iter()
+            .flatten()
+            .fold(f32::MAX, |min, led| min.min(led.x()));
+        let leds: Vec<_> = positions
+            .iter()
+            .enumerate()
+            .filter_map(|(index, led)| {
+                led.map(|led| {
+                    (
+                        index + offset,
+                        MARGIN + (led.x() - min_x) * scale_x,
+                        MARGIN + led.y() * scale_y
+                    )
+                })
+            })
+            .collect();
+        let (max_x, max_y) = leds.iter().fold((0.0f32, 0.0f32), |(mx, my), (_, x, y)| {
+            (mx.max(*x), my.max(*y))
+        });
+        // Rows are half an LED apart, so the nearest LED is the one diagonally
+        // above or below
+        let radius = 0.4 * (0.5 * scale_x).hypot(scale_y).min(scale_x);
+
+        Self {
+            anime_type,
+            width: (max_x + MARGIN).ceil() as u32 + 1,
+            height: (max_y + MARGIN).ceil() as u32 + 1,
+            radius,
+            leds
+        }
+    }
+
+    #[inline]
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    #[inline]
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Draw `buffer` as 8bit greyscale, `width()` by `height()`
+    pub fn draw(&self, buffer: &AnimeDataBuffer) -> Result<Vec<u8>> {
+        if buffer.data().len() != self.anime_type.data_length() {
+            return Err(AnimeError::DataBufferLength);
+        }
+        let mut pixels = vec![0; (self.width * self.height) as usize];
+        let reach = self.radius.ceil() as i32;
+        for (index, cx, cy) in &self.leds {
+            let led = buffer.data()[*index] as u32;
+            let bright = LED_OFF + (led * (255 - LED_OFF) as u32 / 255) as u8;
+            let (px, py) = (cx.round() as i32, cy.round() as i32);
+            for y in py - reach..=py + reach {
+                for x in px - reach..=px + reach {
+                    if (x as f32 - cx).hypot(y as f32 - cy) <= self.radius {
+                        pixels[y as usize * self.width as usize + x as usize] = bright;
+                    }
+                }
+            }
+        }
+        Ok(pixels)
+    }
+
+    /// Write `buffer` as a greyscale PNG
+    pub fn write_png(&self, buffer: &AnimeDataBuffer, writer: impl Write) -> Result<()> {
+        let pixels: Vec<SGray8> = self.draw(buffer)?.into_iter().map(SGray8::new).collect();
+        let raster = Raster::with_pixels(self.width, self.height, pixels);
+        png_pong::Encoder::new(writer)
+            .into_step_enc()
+            .encode(&png_pong::Step {
+                raster: png_pong::PngRaster::Gray8(raster),
+                delay: 0
+            })?;
+        Ok(())
+    }
+
+    /// Write `frames`, each shown for its `Duration`, as a looping GIF
+    pub fn write_gif(
+        &self,
+        frames: &[(AnimeDataBuffer, Duration)],
+        writer: impl Write
+    ) -> Result<()> {
+        if frames.is_empty() {
+            return Err(AnimeError::NoFrames);
+        }
+        let palette: Vec<u8> = (0..=255u8)
+            .flat_map(|grey| {
+                [
+                    grey, grey, grey
+                ]
+            })
+            .collect();
+        let (width, height) = (self.width as u16, self.height as u16);
+        let mut encoder = gif::Encoder::new(writer, width, height, &palette)?;
+        encoder.set_repeat(gif::Repeat::Infinite)?;
+        for (buffer, delay) in frames {
+            let mut frame =
+                gif::Frame::from_indexed_pixels(width, height, &self.draw(buffer)?, None);
+            // GIF delays are in hundredths of a second
+            frame.delay = ((delay.as_millis() + 5) / 10).clamp(1, u16::MAX as u128) as u16;
+            encoder.write_frame(&frame)?;
+        }
+        Ok(())
+    }
+}
+
+/// Play `sequences` through once, as the daemon would with `playback`, and
+/// collect each frame with how long it is shown. Animations that repeat
+/// forever are played once, and anything longer than `PREVIEW_MAX_TIME` is cut
+/// short. Audio spectrums are skipped as there is no audio to draw.
+pub fn sequence_frames(
+    sequences: &Sequences,
+    playback: &Playback
+) -> Result<Vec<(AnimeDataBuffer, Duration)>> {
+    let anime_type = sequences.anime_type();
+    let mut frames = Vec::new();
+    let mut fade = Crossfade::new(playback.crossfade);
+    for action in sequences.iter() {
+        if !matches!(action, ActionData::Pause(_)) {
+            fade.next_action();
+        }
+        match action {
+            ActionData::Animation(gif) => {
+                let infinite = matches!(gif.duration(), AnimTime::Infinite);
+                let playback = Playback {
+                    loops: playback.loops.or(infinite.then_some(1)),
+                    ..*playback
+                };
+                let player = AnimePlayer::new(gif, &playback);
+                let mut elapsed = Duration::ZERO;
+                while let Some(number) = player
+                    .frame_at(elapsed)
+                    .filter(|_| elapsed < PREVIEW_MAX_TIME)
+                {
+                    let next = player.next_due(number);
+                    frames.push((
+                        fade.apply(player.frame(number, elapsed), elapsed),
+                        next - elapsed
+                    ));
+                    elapsed = next;
+                }
+            }
+            ActionData::Image(image) => {
+                let mut elapsed = Duration::ZERO;
+                while fade.blending(elapsed) {
+                    frames.push((fade.apply((**image).clone(), elapsed), FADE_STEP));
+                    elapsed += FADE_STEP;
+                }
+                frames.push((fade.apply((**image).clone(), elapsed), GIF_MIN_DELAY));
+            }
+            ActionData::Pause(duration) => match frames.last_mut() {
+                Some((_, delay)) => *delay += *duration,
+                None => frames.push((AnimeDataBuffer::new(anime_type), *duration))
+            },
+            ActionData::TimeDate(clock) => {
+                let now = ClockTime::now();
+                generated(
+                    &mut frames,
+                    &mut fade,
+                    clock.run_time(),
+                    clock.frame_time(),
+                    |_| clock.render(anime_type, &now)
+                )?;
+            }
+            ActionData::SystemInfo(info) => {
+                let mut monitor = SystemMonitor::new();
+                generated(
+                    &mut frames,
+                    &mut fade,
+                    info.run_time(),
+                    info.frame_time(),
+                    |_| info.render(anime_type, &monitor.sample())
+                )?;
+            }
+            ActionData::Matrix(rain) => {
+                // A fixed seed so a preview is the same each time
+                let mut rain_frames = rain.start(anime_type, 0);
+                generated(
+                    &mut frames,
+                    &mut fade,
+                    rain.run_time(),
+                    rain.frame_time(),
+                    |_| rain_frames.next_frame()
+                )?;
+            }
+            ActionData::Text(text) => {
+                generated(
+                    &mut frames,
+                    &mut fade,
+                    text.run_time(),
+                    text.frame_time(),
+                    |elapsed| text.frame(elapsed)
+                )?;
+            }
+            ActionData::AudioEq(_) => {}
+        }
+    }
+    Ok(frames)
+}
+
+fn generated(
+    frames: &mut Vec<(AnimeDataBuffer, Duration)>,
+    fade: &mut Crossfade,
+    run_time: Option<Duration>,
+    frame_time: Duration,
+    mut render: impl FnMut(Duration) -> Result<AnimeDataBuffer>
+) -> Result<()> {
+    let run_time = run_time.map_or(PREVIEW_MAX_TIME, |t| t.min(PREVIEW_MAX_TIME));
+    let frame_time = frame_time.max(GIF_MIN_DELAY);
+    let mut elapsed = Duration::ZERO;
+    while elapsed < run_time {
+        let shown = frame_time.min(run_time - elapsed);
+        frames.push((fade.apply(render(elapsed)?, elapsed), shown));
+        elapsed += shown;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{sequence_frames, AnimeRender, LED_OFF, PREVIEW_MAX_TIME};
+    use crate::{
+        ActionLoader, AnimTime, AnimeDataBuffer, AnimeImage, AnimeType, Playback, Sequences
+    };
+
+    #[test]
+    fn every_led_drawn() {
+        for anime_type in [
+            AnimeType::GA401,
+            AnimeType::GA402,
+            AnimeType::GU604
+        ] {
+            let render = AnimeRender::new(anime_type);
+            let count = AnimeImage::generate_image_positioning(anime_type)
+                .iter()
+                .flatten()
+                .count();
+            assert_eq!(render.leds.len(), count);
+
+            let mut buffer = AnimeDataBuffer::new(anime_type);
+            let off = render.draw(&buffer).unwrap();
+            assert!(off.iter().all(|p| *p == 0 || *p == LED_OFF));
+            buffer.data_mut().fill(255);
+            let on = render.draw(&buffer).unwrap();
+            // Each LED is a disc of its own, none are lost off the edge
+            let lit = on.iter().filter(|p| **p == 255).count();
+            assert_eq!(lit, off.iter().filter(|p| **p == LED_OFF).count());
+            assert!(lit > count * 9);
+        }
+    }
+
+    #[test]
+    fn frames_from_sequence() {
+        let mut seq = Sequences::new(AnimeType::GA402);
+        seq.insert(0, &ActionLoader::Pause(Duration::from_secs(1)))
+            .unwrap();
+        seq.insert(1, &ActionLoader::MatrixRain {
+            density: 0.5,
+            time: AnimTime::Infinite,
+            brightness: 1.0
+        })
+        .unwrap();
+        seq.insert(2, &ActionLoader::Pause(Duration::from_secs(2)))
+            .unwrap();
+
+        let frames = sequence_frames(&seq, &Playback::default()).unwrap();
+        let total: Duration = frames.iter().map(|(_, delay)| *delay).sum();
+        assert_eq!(total, Duration::from_secs(3) + PREVIEW_MAX_TIME);
+        assert!(frames[0].0.data().iter().all(|p| *p == 0));
+        // The same rain every time
+        let again = sequence_frames(&seq, &Playback::default()).unwrap();
+        assert!(frames
+            .iter()
+            .zip(&again)
+            .all(|(a, b)| a.0.data() == b.0.data()));
+
+        let mut gif = Vec::new();
+        AnimeRender::new(AnimeType::GA402)
+            .write_gif(&frames, &mut gif)
+            .unwrap();
+        assert!(gif.starts_with(b"GIF89a"));
+    }
+}
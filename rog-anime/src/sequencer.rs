@@ -6,7 +6,10 @@ use glam::Vec2;
 use serde::{Deserialize, Serialize};
 
 use crate::error::Result;
-use crate::{AnimTime, AnimeDataBuffer, AnimeDiagonal, AnimeGif, AnimeImage, AnimeType};
+use crate::{
+    AnimTime, AnimeDataBuffer, AnimeDiagonal, AnimeGif, AnimeImage, AnimeText, AnimeType,
+    AudioSpectrum, FrameCache, MatrixRain, Scroll, SystemInfo, TextFrames, TimeDate
+};
 
 /// All the possible `AniMe` actions that can be used. This enum is intended to
 /// be a helper for loading up `ActionData`.
@@ -43,7 +46,53 @@ pub enum ActionLoader {
         brightness: f32
     },
     /// A pause to be used between sequences
-    Pause(Duration)
+    Pause(Duration),
+    /// Spectrum analyser of the audio playing, `bars` across the display
+    AudioSpectrum {
+        bars: usize,
+        time: AnimTime,
+        brightness: f32
+    },
+    /// The local time, and the date under it if `show_date`
+    TimeDate {
+        twelve_hour: bool,
+        show_date: bool,
+        time: AnimTime,
+        brightness: f32
+    },
+    /// CPU load, CPU temperature and battery charge
+    SystemInfo { time: AnimTime, brightness: f32 },
+    /// Digital rain, `density` is how busy it is from `0.0` to `1.0`
+    MatrixRain {
+        density: f32,
+        time: AnimTime,
+        brightness: f32
+    },
+    /// Text in the built-in font, or a TTF/OTF `font` file. `size` is the
+    /// height in LED rows and `speed` is in LEDs a second.
+    Text {
+        text: String,
+        font: Option<PathBuf>,
+        size: f32,
+        scroll: Scroll,
+        speed: f32,
+        time: AnimTime,
+        brightness: f32
+    }
+}
+
+impl From<AnimeText> for ActionLoader {
+    fn from(text: AnimeText) -> Self {
+        Self::Text {
+            text: text.text,
+            font: text.font,
+            size: text.size,
+            scroll: text.scroll,
+            speed: text.speed,
+            time: text.time,
+            brightness: text.brightness
+        }
+    }
 }
 
 /// All the possible `AniMe` actions that can be used. The enum is intended to
@@ -57,14 +106,16 @@ pub enum ActionData {
     Image(Box<AnimeDataBuffer>),
     /// A pause to be used between sequences
     Pause(Duration),
-    /// Placeholder
-    AudioEq,
-    /// Placeholder
-    SystemInfo,
-    /// Placeholder
-    TimeDate,
-    /// Placeholder
-    Matrix
+    /// Drawn live from audio levels by the runner
+    AudioEq(AudioSpectrum),
+    /// Drawn live from system readings by the runner
+    SystemInfo(SystemInfo),
+    /// Drawn live from the local time by the runner
+    TimeDate(TimeDate),
+    /// Generated a frame at a time by the runner
+    Matrix(MatrixRain),
+    /// Rasterised text, the runner picks the frame for the scroll position
+    Text(TextFrames)
 }
 
 impl ActionData {
@@ -132,7 +183,60 @@ impl ActionData {
                     )?)
                 }
             }
-            ActionLoader::Pause(duration) => ActionData::Pause(*duration)
+            ActionLoader::Pause(duration) => ActionData::Pause(*duration),
+            ActionLoader::AudioSpectrum {
+                bars,
+                time,
+                brightness
+            } => ActionData::AudioEq(AudioSpectrum {
+                bars: *bars,
+                brightness: *brightness,
+                time: *time
+            }),
+            ActionLoader::TimeDate {
+                twelve_hour,
+                show_date,
+                time,
+                brightness
+            } => ActionData::TimeDate(TimeDate {
+                twelve_hour: *twelve_hour,
+                show_date: *show_date,
+                brightness: *brightness,
+                time: *time
+            }),
+            ActionLoader::SystemInfo { time, brightness } => ActionData::SystemInfo(SystemInfo {
+                brightness: *brightness,
+                time: *time
+            }),
+            ActionLoader::MatrixRain {
+                density,
+                time,
+                brightness
+            } => ActionData::Matrix(MatrixRain {
+                density: *density,
+                brightness: *brightness,
+                time: *time
+            }),
+            ActionLoader::Text {
+                text,
+                font,
+                size,
+                scroll,
+                speed,
+                time,
+                brightness
+            } => ActionData::Text(
+                AnimeText {
+                    text: text.clone(),
+                    font: font.clone(),
+                    size: *size,
+                    scroll: *scroll,
+                    speed: *speed,
+                    brightness: *brightness,
+                    time: *time
+                }
+                .rasterise(anime_type)?
+            )
         };
         Ok(a)
     }
@@ -140,20 +244,34 @@ impl ActionData {
 
 /// An optimised precomputed set of actions that the user can cycle through
 #[derive(Debug, Deserialize, Serialize)]
-pub struct Sequences(Vec<ActionData>, AnimeType);
+pub struct Sequences(
+    Vec<ActionData>,
+    AnimeType,
+    #[serde(skip)] Option<FrameCache>
+);
 
 impl Sequences {
     #[inline]
     pub fn new(anime_type: AnimeType) -> Self {
-        Self(Vec::new(), anime_type)
+        Self(Vec::new(), anime_type, None)
+    }
+
+    /// Load and save converted gif frames in `cache` when inserting
+    #[inline]
+    pub fn with_cache(mut self, cache: FrameCache) -> Self {
+        self.2 = Some(cache);
+        self
     }
 
     /// Use a base `AnimeAction` to generate the precomputed data and insert in
     /// to the run buffer
     #[inline]
     pub fn insert(&mut self, index: usize, action: &ActionLoader) -> Result<()> {
-        self.0
-            .insert(index, ActionData::from_anime_action(self.1, action)?);
+        let data = match &self.2 {
+            Some(cache) => cache.action_data(self.1, action)?,
+            None => ActionData::from_anime_action(self.1, action)?
+        };
+        self.0.insert(index, data);
         Ok(())
     }
 
@@ -168,6 +286,26 @@ impl Sequences {
         None
     }
 
+    #[inline]
+    pub fn anime_type(&self) -> AnimeType {
+        self.1
+    }
+
+    #[inline]
+    pub fn get(&self, index: usize) -> Option<&ActionData> {
+        self.0.get(index)
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
     pub fn iter(&self) -> ActionIterator<'_> {
         ActionIterator {
             actions: self,
@@ -0,0 +1,89 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::Result;
+use crate::widgets::{generated_run_time, Canvas};
+use crate::{AnimTime, AnimeDataBuffer, AnimeType};
+
+/// Spectrum analyser bars. The audio levels are supplied by the caller for
+/// each frame, so this only holds how to draw them.
+#[derive(Debug, Copy, Clone, Deserialize, Serialize)]
+pub struct AudioSpectrum {
+    /// How many bars to draw across the display
+    pub bars: usize,
+    pub brightness: f32,
+    pub time: AnimTime
+}
+
+impl AudioSpectrum {
+    /// How long the spectrum should be shown for, `None` if it should run
+    /// until stopped. `AnimTime::Count` is a count of seconds.
+    pub fn run_time(&self) -> Option<Duration> {
+        generated_run_time(self.time)
+    }
+
+    /// Draw one frame. `levels` are `0.0..=1.0` from lowest to highest
+    /// frequency and are grouped in to the bars. Bars rise from the
+    /// bottom of the display.
+    pub fn render(&self, anime_type: AnimeType, levels: &[f32]) -> Result<AnimeDataBuffer> {
+        let mut canvas = Canvas::new(anime_type);
+        let (width, height) = (canvas.width(), canvas.height());
+        let bars = self.bars.clamp(1, width);
+        let bright = (self.brightness.clamp(0.0, 1.0) * 255.0) as u8;
+
+        for bar in 0..bars {
+            // The loudest of the levels that fall in this bar
+            let start = bar * levels.len() / bars;
+            let end = ((bar + 1) * levels.len() / bars).max(start + 1);
+            let level = levels
+                .get(start..end)
+                .map_or(0.0, |l| l.iter().fold(0.0f32, |a, b| a.max(*b)))
+                .clamp(0.0, 1.0);
+            let bar_height = (level * height as f32).round() as usize;
+            let x0 = bar * width / bars;
+            // Leave a gap between bars if there is room for one
+            let x1 = ((bar + 1) * width / bars).max(x0 + 1);
+            let x1 = if x1 - x0 > 1 { x1 - 1 } else { x1 };
+            for y in height - bar_height..height {
+                for x in x0..x1 {
+                    canvas.set(x, y, bright);
+                }
+            }
+        }
+        canvas.to_buffer()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{AnimTime, AnimeDataBuffer, AnimeGrid, AnimeType, AudioSpectrum};
+
+    #[test]
+    fn bars_follow_levels() {
+        let spectrum = AudioSpectrum {
+            bars: 3,
+            brightness: 1.0,
+            time: AnimTime::Count(2)
+        };
+        assert_eq!(spectrum.run_time().unwrap().as_secs(), 2);
+
+        let silent = spectrum.render(AnimeType::GA401, &[]).unwrap();
+        assert!(silent.data().iter().all(|b| *b == 0));
+
+        // Only the right bar is lit, matches drawing the same on a grid
+        let frame = spectrum
+            .render(AnimeType::GA401, &[
+                0.0, 0.0, 0.0, 0.0, 0.0, 1.0
+            ])
+            .unwrap();
+        let mut grid = AnimeGrid::new(AnimeType::GA401);
+        for y in 0..55 {
+            for x in 22..32 {
+                grid.set(x, y, 255);
+            }
+        }
+        let expected = AnimeDataBuffer::try_from(grid).unwrap();
+        assert_eq!(frame.data(), expected.data());
+    }
+}
@@ -0,0 +1,419 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{AnimeError, Result};
+use crate::widgets::{generated_run_time, to_bright, Canvas, LINE_GAP, SMALL, TOP_MARGIN};
+use crate::{AnimTime, AnimeDataBuffer, AnimeType};
+
+/// Height in LED rows that the built-in font is drawn at by default
+pub const DEFAULT_TEXT_SIZE: f32 = 10.0;
+/// LEDs a second
+pub const DEFAULT_SCROLL_SPEED: f32 = 15.0;
+/// Slower than this and the text looks stuck
+const MIN_SCROLL_SPEED: f32 = 1.0;
+/// About 30 frames a second so scrolling is smooth
+const TEXT_FRAME_TIME: Duration = Duration::from_millis(33);
+
+/// Which way text moves across the display
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum Scroll {
+    /// Shown still against the right edge
+    #[default]
+    None,
+    /// Enter from the right and move left, like a marquee
+    Left,
+    Right,
+    /// Enter from the bottom and move up. The text is wrapped to fit the
+    /// display width.
+    Up,
+    Down
+}
+
+/// Text to rasterise for the display
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AnimeText {
+    pub text: String,
+    /// A TTF or OTF font file, the built-in font is used if `None`
+    pub font: Option<PathBuf>,
+    /// Height of the text in LED rows. The built-in font is scaled in whole
+    /// steps so is rounded to a multiple of its height.
+    pub size: f32,
+    pub scroll: Scroll,
+    /// LEDs a second
+    pub speed: f32,
+    pub brightness: f32,
+    /// `AnimTime::Count` is a count of times scrolled past, or of seconds if
+    /// the text does not scroll
+    pub time: AnimTime
+}
+
+impl AnimeText {
+    /// A message scrolling past `repeats` times in the built-in font
+    pub fn message(text: &str, repeats: u32) -> Self {
+        Self {
+            text: text.to_owned(),
+            font: None,
+            size: DEFAULT_TEXT_SIZE,
+            scroll: Scroll::Left,
+            speed: DEFAULT_SCROLL_SPEED,
+            brightness: 1.0,
+            time: AnimTime::Count(repeats.max(1))
+        }
+    }
+
+    /// Rasterise the text to the LED rows and columns of `anime_type`. The
+    /// rows are much closer together than the columns, so glyphs are
+    /// stretched over more rows to keep their shape.
+    pub fn rasterise(&self, anime_type: AnimeType) -> Result<TextFrames> {
+        let canvas = Canvas::new(anime_type);
+        let rasteriser = Rasteriser::new(self, anime_type)?;
+        // Newlines are respected when scrolling up or down, and become
+        // spaces otherwise
+        let bitmap = match self.scroll {
+            Scroll::Up | Scroll::Down => {
+                let max_width = canvas.right().saturating_sub(1);
+                let lines: Vec<TextBitmap> = self
+                    .text
+                    .lines()
+                    .flat_map(|line| rasteriser.wrap(line, max_width))
+                    .collect();
+                TextBitmap::stack(&lines)
+            }
+            _ => rasteriser.line(&self.text.lines().collect::<Vec<_>>().join(" "))
+        };
+
+        Ok(TextFrames {
+            anime_type,
+            bitmap,
+            display_width: canvas.width(),
+            display_height: canvas.height(),
+            right: canvas.right(),
+            scroll: self.scroll,
+            speed: self.speed.max(MIN_SCROLL_SPEED),
+            bright: to_bright(self.brightness),
+            time: self.time
+        })
+    }
+}
+
+/// Text rasterised to LED columns and rows, each a brightness
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+struct TextBitmap {
+    width: usize,
+    height: usize,
+    data: Vec<u8>
+}
+
+impl TextBitmap {
+    fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            data: vec![0; width * height]
+        }
+    }
+
+    /// Antialiased edges of glyphs can overlap, so the brightest wins
+    fn set_max(&mut self, x: usize, y: usize, b: u8) {
+        if x < self.width && y < self.height {
+            let px = &mut self.data[y * self.width + x];
+            *px = (*px).max(b);
+        }
+    }
+
+    fn get(&self, x: usize, y: usize) -> u8 {
+        self.data[y * self.width + x]
+    }
+
+    /// Lines one under the other and aligned to the right
+    fn stack(lines: &[TextBitmap]) -> Self {
+        let width = lines.iter().map(|l| l.width).max().unwrap_or_default();
+        let height = lines.iter().map(|l| l.height + LINE_GAP).sum::<usize>();
+        let mut stacked = Self::new(width, height.saturating_sub(LINE_GAP));
+        let mut top = 0;
+        for line in lines {
+            let left = width - line.width;
+            for y in 0..line.height {
+                for x in 0..line.width {
+                    stacked.set_max(left + x, top + y, line.get(x, y));
+                }
+            }
+            top += line.height + LINE_GAP;
+        }
+        stacked
+    }
+}
+
+enum Rasteriser {
+    /// The built-in font with each glyph row this many LED rows tall
+    Builtin(usize),
+    #[cfg(feature = "ttf")]
+    Ttf {
+        font: ab_glyph::FontVec,
+        scale: ab_glyph::PxScale
+    }
+}
+
+impl Rasteriser {
+    fn new(text: &AnimeText, anime_type: AnimeType) -> Result<Self> {
+        let size = if text.size > 0.0 {
+            text.size
+        } else {
+            DEFAULT_TEXT_SIZE
+        };
+        let Some(path) = &text.font else {
+            let scale = (size / SMALL.height as f32).round().max(1.0);
+            return Ok(Self::Builtin(scale as usize));
+        };
+
+        #[cfg(feature = "ttf")]
+        {
+            let data = std::fs::read(path)?;
+            let font = ab_glyph::FontVec::try_from_vec(data)
+                .map_err(|e| AnimeError::Font(format!("{}: {e}", path.display())))?;
+            // Columns are spaced further apart than rows, by this much
+            let ratio =
+                crate::AnimeImage::scale_x(anime_type) / crate::AnimeImage::scale_y(anime_type);
+            Ok(Self::Ttf {
+                font,
+                scale: ab_glyph::PxScale {
+                    x: size / ratio,
+                    y: size
+                }
+            })
+        }
+        #[cfg(not(feature = "ttf"))]
+        {
+            let _ = anime_type;
+            Err(AnimeError::Font(format!(
+                "{}: built without font file support",
+                path.display()
+            )))
+        }
+    }
+
+    fn line(&self, text: &str) -> TextBitmap {
+        match self {
+            Self::Builtin(scale) => {
+                let mut bitmap = TextBitmap::new(SMALL.text_width(text), SMALL.height * scale);
+                SMALL.draw(text, *scale, |x, y| bitmap.set_max(x, y, 255));
+                bitmap
+            }
+            #[cfg(feature = "ttf")]
+            Self::Ttf { font, scale } => {
+                use ab_glyph::{point, Font as _, ScaleFont as _};
+
+                let font = font.as_scaled(*scale);
+                let mut caret = 0.0;
+                let mut last = None;
+                let mut glyphs = Vec::new();
+                for c in text.chars() {
+                    let id = font.glyph_id(c);
+                    if let Some(last) = last {
+                        caret += font.kern(last, id);
+                    }
+                    glyphs.push(id.with_scale_and_position(*scale, point(caret, font.ascent())));
+                    caret += font.h_advance(id);
+                    last = Some(id);
+                }
+
+                let height = (font.ascent() - font.descent()).ceil() as usize;
+                let mut bitmap = TextBitmap::new(caret.ceil() as usize, height);
+                for glyph in glyphs {
+                    let Some(outline) = font.outline_glyph(glyph) else {
+                        continue;
+                    };
+                    let bounds = outline.px_bounds();
+                    outline.draw(|x, y, coverage| {
+                        let x = bounds.min.x as i32 + x as i32;
+                        let y = bounds.min.y as i32 + y as i32;
+                        if x >= 0 && y >= 0 {
+                            bitmap.set_max(x as usize, y as usize, (coverage * 255.0) as u8);
+                        }
+                    });
+                }
+                bitmap
+            }
+        }
+    }
+
+    /// Break `text` on spaces in to lines no wider than `max_width`. A word
+    /// that is wider on its own gets a line to itself.
+    fn wrap(&self, text: &str, max_width: usize) -> Vec<TextBitmap> {
+        let mut lines = Vec::new();
+        let mut current = String::new();
+        for word in text.split_whitespace() {
+            let joined = if current.is_empty() {
+                word.to_owned()
+            } else {
+                format!("{current} {word}")
+            };
+            if !current.is_empty() && self.line(&joined).width > max_width {
+                lines.push(self.line(&current));
+                current = word.to_owned();
+            } else {
+                current = joined;
+            }
+        }
+        lines.push(self.line(&current));
+        lines
+    }
+}
+
+/// Rasterised `AnimeText`, ready to draw at any point of its scroll
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TextFrames {
+    anime_type: AnimeType,
+    bitmap: TextBitmap,
+    display_width: usize,
+    display_height: usize,
+    /// Columns before this are lit on every row
+    right: usize,
+    scroll: Scroll,
+    speed: f32,
+    bright: u8,
+    time: AnimTime
+}
+
+impl TextFrames {
+    /// Distance in LEDs to scroll from fully off one side of the display to
+    /// fully off the other
+    fn distance(&self) -> usize {
+        match self.scroll {
+            Scroll::None => 0,
+            Scroll::Left | Scroll::Right => self.right + self.bitmap.width,
+            Scroll::Up | Scroll::Down => self.display_height + self.bitmap.height
+        }
+    }
+
+    /// How long the text takes to scroll past once, `None` if it is still
+    pub fn loop_time(&self) -> Option<Duration> {
+        (self.scroll != Scroll::None)
+            .then(|| Duration::from_secs_f32(self.distance() as f32 / self.speed))
+    }
+
+    /// How long the text should be shown for, `None` if it should run until
+    /// stopped
+    pub fn run_time(&self) -> Option<Duration> {
+        match (self.time, self.loop_time()) {
+            (AnimTime::Count(count), Some(loop_time)) => Some(loop_time * count),
+            (time, _) => generated_run_time(time)
+        }
+    }
+
+    /// How often a new frame should be drawn
+    pub fn frame_time(&self) -> Duration {
+        TEXT_FRAME_TIME
+    }
+
+    /// The frame shown `elapsed` after the text started. Scrolling repeats
+    /// once the text is fully off the display.
+    pub fn frame(&self, elapsed: Duration) -> Result<AnimeDataBuffer> {
+        let mut canvas = Canvas::new(self.anime_type);
+        let offset = match self.distance() {
+            0 => 0,
+            distance => (elapsed.as_secs_f32() * self.speed) as usize % distance
+        } as isize;
+        let (width, height) = (self.bitmap.width as isize, self.bitmap.height as isize);
+        // Still text against the right edge, clipping the end if too wide
+        let right = (self.right as isize - 1 - width).max(0);
+        let (x0, y0) = match self.scroll {
+            Scroll::None => (right, TOP_MARGIN as isize),
+            Scroll::Left => (self.right as isize - offset, TOP_MARGIN as isize),
+            Scroll::Right => (offset - width, TOP_MARGIN as isize),
+            Scroll::Up => (right, self.display_height as isize - offset),
+            Scroll::Down => (right, offset - height)
+        };
+
+        for y in 0..self.bitmap.height {
+            for x in 0..self.bitmap.width {
+                let (cx, cy) = (x0 + x as isize, y0 + y as isize);
+                if cx < 0 || cy < 0 || cx as usize >= self.display_width {
+                    continue;
+                }
+                let b = self.bitmap.get(x, y) as u32 * self.bright as u32 / 255;
+                canvas.set(cx as usize, cy as usize, b as u8);
+            }
+        }
+        canvas.to_buffer()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::TextBitmap;
+    use crate::error::AnimeError;
+    use crate::{AnimTime, AnimeText, AnimeType, Scroll};
+
+    #[test]
+    fn marquee_scrolls_past() {
+        let text = AnimeText {
+            speed: 10.0,
+            ..AnimeText::message("Hi", 2)
+        };
+        let frames = text.rasterise(AnimeType::GA401).unwrap();
+        // 2 glyphs and a gap, drawn on double height rows
+        assert_eq!((frames.bitmap.width, frames.bitmap.height), (7, 10));
+        // Across the 33 columns plus the text, twice at 10 a second
+        assert_eq!(frames.loop_time(), Some(Duration::from_secs_f32(4.0)));
+        assert_eq!(frames.run_time(), Some(Duration::from_secs_f32(8.0)));
+
+        let lit = |elapsed: f32| {
+            let frame = frames.frame(Duration::from_secs_f32(elapsed)).unwrap();
+            frame.data().iter().filter(|b| **b != 0).count()
+        };
+        // Starts off the display, and is off again when the scroll repeats
+        assert_eq!(lit(0.0), 0);
+        assert!(lit(1.0) > 0);
+        assert_eq!(lit(4.0), 0);
+        assert_eq!(lit(1.0), lit(5.0));
+    }
+
+    #[test]
+    fn wrapped_when_vertical() {
+        let text = AnimeText {
+            text: "one two three four five six".to_owned(),
+            scroll: Scroll::Up,
+            size: 5.0,
+            ..AnimeText::message("", 1)
+        };
+        let frames = text.rasterise(AnimeType::GA402).unwrap();
+        assert!(frames.bitmap.width <= frames.right);
+        assert!(frames.bitmap.height > 5);
+
+        let still = AnimeText {
+            scroll: Scroll::None,
+            time: AnimTime::Count(3),
+            ..text
+        };
+        let frames = still.rasterise(AnimeType::GA402).unwrap();
+        assert_eq!(frames.bitmap.height, 5);
+        assert!(frames.loop_time().is_none());
+        assert_eq!(frames.run_time(), Some(Duration::from_secs(3)));
+        let frame = frames.frame(Duration::ZERO).unwrap();
+        assert!(frame.data().contains(&255));
+
+        let stacked = TextBitmap::stack(&[
+            TextBitmap::new(2, 1),
+            TextBitmap::new(3, 2)
+        ]);
+        assert_eq!((stacked.width, stacked.height), (3, 5));
+    }
+
+    #[cfg(feature = "ttf")]
+    #[test]
+    fn missing_font() {
+        let text = AnimeText {
+            font: Some("/does/not/exist.ttf".into()),
+            ..AnimeText::message("Hi", 1)
+        };
+        assert!(matches!(
+            text.rasterise(AnimeType::GA401),
+            Err(AnimeError::Io(_))
+        ));
+    }
+}
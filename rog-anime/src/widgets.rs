@@ -0,0 +1,800 @@
+use std::fs;
+use std::ops::Range;
+use std::path::Path;
+use std::time::Duration;
+
+use chrono::{Datelike, Timelike};
+use serde::{Deserialize, Serialize};
+
+use crate::error::Result;
+use crate::{AnimTime, AnimeDataBuffer, AnimeImage, AnimeType};
+
+/// The LED rows are about a third as far apart as the columns, so each row of
+/// a glyph is drawn up to this many LED rows tall to keep its shape
+const MAX_ROW_SCALE: usize = 3;
+/// Rows left clear above the first line of text
+pub(crate) const TOP_MARGIN: usize = 1;
+/// Rows left clear between lines of text
+pub(crate) const LINE_GAP: usize = 2;
+/// Neither the clock or system info change faster than this
+const PANEL_FRAME_TIME: Duration = Duration::from_secs(1);
+/// About 20 frames a second for the rain
+const RAIN_FRAME_TIME: Duration = Duration::from_millis(50);
+/// Chance of an empty column starting a drop each frame at full density
+const RAIN_SPAWN_CHANCE: f32 = 0.1;
+/// hwmon names of CPU temperature sensors
+const CPU_SENSORS: [&str; 3] = [
+    "k10temp", "coretemp", "zenpower"
+];
+const MONTHS: [&str; 12] = [
+    "JAN", "FEB", "MAR", "APR", "MAY", "JUN", "JUL", "AUG", "SEP", "OCT", "NOV", "DEC"
+];
+
+/// How long generated content should be shown for, `None` if it should run
+/// until stopped. `AnimTime::Count` is a count of seconds.
+pub(crate) fn generated_run_time(time: AnimTime) -> Option<Duration> {
+    match time {
+        AnimTime::Time(time) => Some(time),
+        AnimTime::Count(secs) => Some(Duration::from_secs(secs as u64)),
+        AnimTime::Fade(fade) => Some(fade.show_for().unwrap_or_default() + fade.total_fade_time()),
+        AnimTime::Infinite => None
+    }
+}
+
+pub(crate) fn to_bright(brightness: f32) -> u8 {
+    (brightness.clamp(0.0, 1.0) * 255.0) as u8
+}
+
+/// A fixed height bitmap font. The bits of each glyph are its rows from the
+/// top, each `width` bits long with the lowest bit the rightmost column.
+pub(crate) struct Font {
+    pub(crate) height: usize,
+    /// The character, its width in columns, and its bits
+    glyphs: &'static [(char, usize, u64)]
+}
+
+impl Font {
+    /// Lowercase is drawn as uppercase, anything else missing as `?`
+    fn glyph(&self, c: char) -> (usize, u64) {
+        let c = c.to_ascii_uppercase();
+        self.glyphs
+            .iter()
+            .find(|g| g.0 == c)
+            .or_else(|| self.glyphs.iter().find(|g| g.0 == '?'))
+            .map_or((0, 0), |g| (g.1, g.2))
+    }
+
+    fn is_set(&self, (width, bits): (usize, u64), col: usize, row: usize) -> bool {
+        let bit = (self.height - 1 - row) * width + width - 1 - col;
+        bits >> bit & 1 == 1
+    }
+
+    /// Call `set` with the position of every lit pixel of `text`, with each
+    /// glyph row `scale` rows tall
+    pub(crate) fn draw(&self, text: &str, scale: usize, mut set: impl FnMut(usize, usize)) {
+        let mut x = 0;
+        for c in text.chars() {
+            let glyph = self.glyph(c);
+            for row in 0..self.height {
+                for col in 0..glyph.0 {
+                    if self.is_set(glyph, col, row) {
+                        for r in 0..scale {
+                            set(x + col, row * scale + r);
+                        }
+                    }
+                }
+            }
+            x += glyph.0 + 1;
+        }
+    }
+
+    /// Width in columns, with a column of space between glyphs
+    pub(crate) fn text_width(&self, text: &str) -> usize {
+        let width: usize = text.chars().map(|c| self.glyph(c).0 + 1).sum();
+        width.saturating_sub(1)
+    }
+}
+
+/// 3x5 font for labels and short text
+#[allow(clippy::unusual_byte_groupings)]
+pub(crate) const SMALL: Font = Font {
+    height: 5,
+    glyphs: &[
+        ('0', 3, 0b111_101_101_101_111),
+        ('1', 3, 0b010_110_010_010_111),
+        ('2', 3, 0b111_001_111_100_111),
+        ('3', 3, 0b111_001_111_001_111),
+        ('4', 3, 0b101_101_111_001_001),
+        ('5', 3, 0b111_100_111_001_111),
+        ('6', 3, 0b111_100_111_101_111),
+        ('7', 3, 0b111_001_001_001_001),
+        ('8', 3, 0b111_101_111_101_111),
+        ('9', 3, 0b111_101_111_001_111),
+        ('A', 3, 0b010_101_111_101_101),
+        ('B', 3, 0b110_101_110_101_110),
+        ('C', 3, 0b011_100_100_100_011),
+        ('D', 3, 0b110_101_101_101_110),
+        ('E', 3, 0b111_100_110_100_111),
+        ('F', 3, 0b111_100_110_100_100),
+        ('G', 3, 0b011_100_101_101_011),
+        ('H', 3, 0b101_101_111_101_101),
+        ('I', 3, 0b111_010_010_010_111),
+        ('J', 3, 0b001_001_001_101_010),
+        ('K', 3, 0b101_101_110_101_101),
+        ('L', 3, 0b100_100_100_100_111),
+        ('M', 3, 0b101_111_111_101_101),
+        ('N', 3, 0b110_101_101_101_101),
+        ('O', 3, 0b010_101_101_101_010),
+        ('P', 3, 0b110_101_110_100_100),
+        ('Q', 3, 0b010_101_101_110_011),
+        ('R', 3, 0b110_101_110_101_101),
+        ('S', 3, 0b011_100_010_001_110),
+        ('T', 3, 0b111_010_010_010_010),
+        ('U', 3, 0b101_101_101_101_111),
+        ('V', 3, 0b101_101_101_101_010),
+        ('W', 3, 0b101_101_111_111_101),
+        ('X', 3, 0b101_101_010_101_101),
+        ('Y', 3, 0b101_101_010_010_010),
+        ('Z', 3, 0b111_001_010_100_111),
+        (' ', 1, 0b0_0_0_0_0),
+        (':', 1, 0b0_1_0_1_0),
+        ('.', 1, 0b0_0_0_0_1),
+        ('!', 1, 0b1_1_1_0_1),
+        ('-', 3, 0b000_000_111_000_000),
+        ('/', 3, 0b001_001_010_100_100),
+        ('%', 3, 0b101_001_010_100_101),
+        ('?', 3, 0b111_001_010_000_010)
+    ]
+};
+
+/// 5x7 font for the clock
+#[allow(clippy::unusual_byte_groupings)]
+const LARGE: Font = Font {
+    height: 7,
+    glyphs: &[
+        ('0', 5, 0b01110_10001_10011_10101_11001_10001_01110),
+        ('1', 5, 0b00100_01100_00100_00100_00100_00100_01110),
+        ('2', 5, 0b01110_10001_00001_00010_00100_01000_11111),
+        ('3', 5, 0b11111_00010_00100_00010_00001_10001_01110),
+        ('4', 5, 0b00010_00110_01010_10010_11111_00010_00010),
+        ('5', 5, 0b11111_10000_11110_00001_00001_10001_01110),
+        ('6', 5, 0b00110_01000_10000_11110_10001_10001_01110),
+        ('7', 5, 0b11111_00001_00010_00100_01000_01000_01000),
+        ('8', 5, 0b01110_10001_10001_01110_10001_10001_01110),
+        ('9', 5, 0b01110_10001_10001_01111_00001_00010_01100),
+        (':', 1, 0b0_0_1_0_1_0_0),
+        ('?', 5, 0b01110_10001_00001_00010_00100_00000_00100)
+    ]
+};
+
+/// A frame covering every LED of one `AnimeType`. Unlike `AnimeGrid` this is
+/// sized to the display, so also covers the larger GA402 and GU604.
+#[derive(Debug, Clone)]
+pub(crate) struct Canvas {
+    anime_type: AnimeType,
+    /// Canvas position of each LED in the order they are written out
+    leds: Vec<Option<(usize, usize)>>,
+    /// Columns of each row that have an LED
+    rows: Vec<Range<usize>>,
+    width: usize,
+    data: Vec<u8>
+}
+
+impl Canvas {
+    pub(crate) fn new(anime_type: AnimeType) -> Self {
+        let leds: Vec<Option<(usize, usize)>> = AnimeImage::generate_image_positioning(anime_type)
+            .iter()
+            .map(|led| led.map(|led| (led.x().ceil() as usize, led.y().ceil() as usize)))
+            .collect();
+
+        let mut rows: Vec<Range<usize>> = Vec::new();
+        for (x, y) in leds.iter().flatten() {
+            if rows.len() <= *y {
+                // Empty until the LEDs of the row are found
+                rows.resize(y + 1, Range {
+                    start: usize::MAX,
+                    end: 0
+                });
+            }
+            let row = &mut rows[*y];
+            row.start = row.start.min(*x);
+            row.end = row.end.max(x + 1);
+        }
+        let width = rows.iter().map(|r| r.end).max().unwrap_or_default();
+
+        Self {
+            anime_type,
+            leds,
+            data: vec![0; width * rows.len()],
+            rows,
+            width
+        }
+    }
+
+    pub(crate) fn width(&self) -> usize {
+        self.width
+    }
+
+    pub(crate) fn height(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// Set a position with a brightness value, positions outside the canvas
+    /// are ignored
+    pub(crate) fn set(&mut self, x: usize, y: usize, b: u8) {
+        if x < self.width && y < self.height() {
+            self.data[y * self.width + x] = b;
+        }
+    }
+
+    fn clear(&mut self) {
+        self.data.fill(0);
+    }
+
+    /// The column after the last one that every row has an LED in
+    pub(crate) fn right(&self) -> usize {
+        self.rows.iter().map(|r| r.end).min().unwrap_or_default()
+    }
+
+    /// Text is drawn against the right edge as the rows get shorter from the
+    /// left further down the display
+    fn text_x(&self, font: &Font, text: &str) -> Option<usize> {
+        self.right().checked_sub(font.text_width(text) + 1)
+    }
+
+    fn fits(&self, font: &Font, text: &str, y: usize, scale: usize) -> bool {
+        let bottom = y + font.height * scale;
+        self.text_x(font, text).is_some_and(|x| {
+            bottom <= self.height() && self.rows[y..bottom].iter().all(|r| r.start <= x)
+        })
+    }
+
+    /// The largest row scale that all `lines` fit at when drawn from `y`
+    fn line_scale(&self, lines: &[(&Font, &str)], y: usize) -> usize {
+        (1..=MAX_ROW_SCALE)
+            .rev()
+            .find(|scale| {
+                let mut y = y;
+                lines.iter().all(|(font, text)| {
+                    let fits = self.fits(font, text, y, *scale);
+                    y += font.height * scale + LINE_GAP;
+                    fits
+                })
+            })
+            .unwrap_or(1)
+    }
+
+    fn draw_text(&mut self, font: &Font, text: &str, y: usize, scale: usize, b: u8) {
+        let x = self.text_x(font, text).unwrap_or_default();
+        font.draw(text, scale, |col, row| self.set(x + col, y + row, b));
+    }
+
+    /// Draw `lines` from `y` down at the largest scale they all fit, and
+    /// return the row after the last line
+    fn draw_lines(&mut self, lines: &[(&Font, &str)], mut y: usize, b: u8) -> usize {
+        let scale = self.line_scale(lines, y);
+        for (font, text) in lines {
+            self.draw_text(font, text, y, scale, b);
+            y += font.height * scale + LINE_GAP;
+        }
+        y
+    }
+
+    pub(crate) fn to_buffer(&self) -> Result<AnimeDataBuffer> {
+        let mut buf = vec![0u8; self.anime_type.data_length()];
+        // Only the GA401 data starts with a blank byte
+        let offset = usize::from(self.anime_type == AnimeType::GA401);
+        for (idx, led) in self.leds.iter().enumerate() {
+            if let Some((x, y)) = led {
+                buf[idx + offset] = self.data[y * self.width + x];
+            }
+        }
+        AnimeDataBuffer::from_vec(self.anime_type, buf)
+    }
+}
+
+/// A local time for `TimeDate` to draw
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct ClockTime {
+    pub hour: u32,
+    pub minute: u32,
+    pub day: u32,
+    /// `1..=12`
+    pub month: u32
+}
+
+impl ClockTime {
+    pub fn now() -> Self {
+        let now = chrono::Local::now();
+        Self {
+            hour: now.hour(),
+            minute: now.minute(),
+            day: now.day(),
+            month: now.month()
+        }
+    }
+}
+
+/// A clock with the date under it. The time is drawn as large as it fits on
+/// each display.
+#[derive(Debug, Copy, Clone, Deserialize, Serialize)]
+pub struct TimeDate {
+    /// Show the time as 12 hour instead of 24 hour
+    pub twelve_hour: bool,
+    /// Show the day and month under the time
+    pub show_date: bool,
+    pub brightness: f32,
+    pub time: AnimTime
+}
+
+impl TimeDate {
+    /// How long the clock should be shown for, `None` if it should run until
+    /// stopped. `AnimTime::Count` is a count of seconds.
+    pub fn run_time(&self) -> Option<Duration> {
+        generated_run_time(self.time)
+    }
+
+    /// How often a new frame should be drawn
+    pub fn frame_time(&self) -> Duration {
+        PANEL_FRAME_TIME
+    }
+
+    pub fn render(&self, anime_type: AnimeType, now: &ClockTime) -> Result<AnimeDataBuffer> {
+        let mut canvas = Canvas::new(anime_type);
+        let bright = to_bright(self.brightness);
+
+        let time = if self.twelve_hour {
+            let hour = match now.hour % 12 {
+                0 => 12,
+                hour => hour
+            };
+            format!("{hour}:{:02}", now.minute)
+        } else {
+            format!("{:02}:{:02}", now.hour, now.minute)
+        };
+        // Each line is sized on its own so the time can be larger than the date
+        let y = canvas.draw_lines(&[(&LARGE, time.as_str())], TOP_MARGIN, bright);
+        if self.show_date {
+            let month = MONTHS[(now.month as usize).clamp(1, 12) - 1];
+            let date = format!("{} {month}", now.day);
+            canvas.draw_lines(&[(&SMALL, date.as_str())], y, bright);
+        }
+        canvas.to_buffer()
+    }
+}
+
+/// Readings for `SystemInfo` to draw. Anything that could not be read is
+/// `None`.
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct SystemStats {
+    /// `0.0..=1.0` across all cores
+    pub cpu_load: Option<f32>,
+    /// Hottest CPU sensor in celsius
+    pub cpu_temp: Option<f32>,
+    /// Battery charge in percent
+    pub battery: Option<u8>
+}
+
+/// CPU load, CPU temperature and battery charge, one per line
+#[derive(Debug, Copy, Clone, Deserialize, Serialize)]
+pub struct SystemInfo {
+    pub brightness: f32,
+    pub time: AnimTime
+}
+
+impl SystemInfo {
+    /// How long the panel should be shown for, `None` if it should run until
+    /// stopped. `AnimTime::Count` is a count of seconds.
+    pub fn run_time(&self) -> Option<Duration> {
+        generated_run_time(self.time)
+    }
+
+    /// How often a new frame should be drawn
+    pub fn frame_time(&self) -> Duration {
+        PANEL_FRAME_TIME
+    }
+
+    pub fn render(&self, anime_type: AnimeType, stats: &SystemStats) -> Result<AnimeDataBuffer> {
+        let mut canvas = Canvas::new(anime_type);
+        let value = |v: Option<String>| v.unwrap_or_else(|| "--".to_owned());
+        let cpu = format!(
+            "C {}",
+            value(
+                stats
+                    .cpu_load
+                    .map(|l| format!("{:.0}%", l.clamp(0.0, 1.0) * 100.0))
+            )
+        );
+        let temp = format!("T {}", value(stats.cpu_temp.map(|t| format!("{t:.0}C"))));
+        let battery = format!("B {}", value(stats.battery.map(|b| format!("{b}%"))));
+
+        canvas.draw_lines(
+            &[
+                (&SMALL, cpu.as_str()),
+                (&SMALL, temp.as_str()),
+                (&SMALL, battery.as_str())
+            ],
+            TOP_MARGIN,
+            to_bright(self.brightness)
+        );
+        canvas.to_buffer()
+    }
+}
+
+/// The summed times of all CPUs from `/proc/stat`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct CpuTimes {
+    idle: u64,
+    total: u64
+}
+
+impl CpuTimes {
+    fn parse(stat: &str) -> Option<Self> {
+        let line = stat.lines().find(|l| l.starts_with("cpu "))?;
+        // user nice system idle iowait irq softirq steal, guest time is
+        // already counted in user
+        let times: Vec<u64> = line
+            .split_whitespace()
+            .skip(1)
+            .take(8)
+            .filter_map(|t| t.parse().ok())
+            .collect();
+        Some(Self {
+            idle: times.get(3)? + times.get(4).unwrap_or(&0),
+            total: times.iter().sum()
+        })
+    }
+
+    fn load_since(&self, earlier: &Self) -> Option<f32> {
+        let total = self.total.checked_sub(earlier.total).filter(|t| *t > 0)?;
+        let idle = self.idle.saturating_sub(earlier.idle);
+        Some((1.0 - idle as f32 / total as f32).clamp(0.0, 1.0))
+    }
+}
+
+/// Reads `SystemStats` from `/proc` and `/sys`. The CPU load is the average
+/// since the previous sample.
+#[derive(Debug, Default)]
+pub struct SystemMonitor {
+    last_cpu: Option<CpuTimes>
+}
+
+impl SystemMonitor {
+    pub fn new() -> Self {
+        Self {
+            last_cpu: Self::read_cpu()
+        }
+    }
+
+    fn read_cpu() -> Option<CpuTimes> {
+        CpuTimes::parse(&fs::read_to_string("/proc/stat").ok()?)
+    }
+
+    fn read_cpu_temp(hwmon: &Path) -> Option<f32> {
+        fs::read_dir(hwmon)
+            .ok()?
+            .flatten()
+            .map(|e| e.path())
+            .filter(|dir| {
+                fs::read_to_string(dir.join("name"))
+                    .is_ok_and(|name| CPU_SENSORS.contains(&name.trim()))
+            })
+            .flat_map(|dir| fs::read_dir(dir).into_iter().flatten().flatten())
+            .filter(|e| {
+                let name = e.file_name();
+                let name = name.to_string_lossy();
+                name.starts_with("temp") && name.ends_with("_input")
+            })
+            .filter_map(|e| {
+                fs::read_to_string(e.path())
+                    .ok()?
+                    .trim()
+                    .parse::<f32>()
+                    .ok()
+            })
+            .map(|milli| milli / 1000.0)
+            .reduce(f32::max)
+    }
+
+    fn read_battery(power_supply: &Path) -> Option<u8> {
+        fs::read_dir(power_supply)
+            .ok()?
+            .flatten()
+            .map(|e| e.path())
+            .find(|dir| fs::read_to_string(dir.join("type")).is_ok_and(|t| t.trim() == "Battery"))
+            .and_then(|dir| fs::read_to_string(dir.join("capacity")).ok())
+            .and_then(|capacity| capacity.trim().parse().ok())
+    }
+
+    pub fn sample(&mut self) -> SystemStats {
+        let cpu = Self::read_cpu();
+        let cpu_load = self
+            .last_cpu
+            .zip(cpu)
+            .and_then(|(last, now)| now.load_since(&last));
+        self.last_cpu = cpu;
+        SystemStats {
+            cpu_load,
+            cpu_temp: Self::read_cpu_temp(Path::new("/sys/class/hwmon")),
+            battery: Self::read_battery(Path::new("/sys/class/power_supply"))
+        }
+    }
+}
+
+/// Digital rain falling down the display
+#[derive(Debug, Copy, Clone, Deserialize, Serialize)]
+pub struct MatrixRain {
+    /// How busy the rain is, `0.0..=1.0`
+    pub density: f32,
+    pub brightness: f32,
+    pub time: AnimTime
+}
+
+impl MatrixRain {
+    /// How long the rain should be shown for, `None` if it should run until
+    /// stopped. `AnimTime::Count` is a count of seconds.
+    pub fn run_time(&self) -> Option<Duration> {
+        generated_run_time(self.time)
+    }
+
+    /// How often a new frame should be drawn
+    pub fn frame_time(&self) -> Duration {
+        RAIN_FRAME_TIME
+    }
+
+    /// Start the rain on an empty display. The same `seed` always gives the
+    /// same frames.
+    pub fn start(&self, anime_type: AnimeType, seed: u64) -> Rain {
+        let canvas = Canvas::new(anime_type);
+        Rain {
+            drops: vec![None; canvas.width()],
+            canvas,
+            // xorshift gets stuck on zero
+            rng: seed.max(1),
+            density: self.density.clamp(0.0, 1.0),
+            bright: to_bright(self.brightness)
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+struct Drop {
+    /// Row of the brightest end
+    head: usize,
+    /// Rows in the fading tail
+    length: usize,
+    /// Rows moved each frame
+    speed: usize
+}
+
+/// A running `MatrixRain`, each call to `next_frame()` moves it on one frame
+#[derive(Debug, Clone)]
+pub struct Rain {
+    canvas: Canvas,
+    /// One for each column
+    drops: Vec<Option<Drop>>,
+    rng: u64,
+    density: f32,
+    bright: u8
+}
+
+impl Rain {
+    fn random(&mut self) -> u64 {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 7;
+        self.rng ^= self.rng << 17;
+        self.rng
+    }
+
+    /// `0.0..1.0`
+    fn chance(&mut self) -> f32 {
+        (self.random() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    pub fn next_frame(&mut self) -> Result<AnimeDataBuffer> {
+        let height = self.canvas.height();
+        for x in 0..self.drops.len() {
+            if let Some(drop) = &mut self.drops[x] {
+                drop.head += drop.speed;
+                if drop.head >= height + drop.length {
+                    self.drops[x] = None;
+                }
+            } else if self.chance() < self.density * RAIN_SPAWN_CHANCE {
+                let length = 6 + (self.random() % 19) as usize;
+                let speed = 1 + (self.random() % 2) as usize;
+                self.drops[x] = Some(Drop {
+                    head: 0,
+                    length,
+                    speed
+                });
+            }
+        }
+
+        self.canvas.clear();
+        for (x, drop) in self.drops.iter().enumerate() {
+            let Some(drop) = drop else {
+                continue;
+            };
+            for i in 0..drop.length.min(drop.head + 1) {
+                let fade = (drop.length - i) as f32 / drop.length as f32;
+                self.canvas
+                    .set(x, drop.head - i, (self.bright as f32 * fade) as u8);
+            }
+        }
+        self.canvas.to_buffer()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Canvas, ClockTime, CpuTimes, LARGE, SMALL};
+    use crate::{
+        AnimTime, AnimeDataBuffer, AnimeGrid, AnimeImage, AnimeType, MatrixRain, Pixel, SystemInfo,
+        SystemStats, TimeDate, Vec2
+    };
+
+    #[test]
+    fn canvas_covers_display() {
+        for (anime_type, width, height) in [
+            (AnimeType::GA401, 33, 55),
+            (AnimeType::GA402, 35, 61),
+            (AnimeType::GU604, 39, 62)
+        ] {
+            let mut canvas = Canvas::new(anime_type);
+            assert_eq!((canvas.width(), canvas.height()), (width, height));
+            for y in 0..height {
+                for x in 0..width {
+                    canvas.set(x, y, 255);
+                }
+            }
+            let frame = canvas.to_buffer().unwrap();
+            let lit = frame.data().iter().filter(|b| **b == 255).count();
+            let leds = crate::AnimeImage::generate_image_positioning(anime_type)
+                .iter()
+                .flatten()
+                .count();
+            assert_eq!(lit, leds);
+        }
+
+        // Same mapping as the grid where the two overlap
+        let mut canvas = Canvas::new(AnimeType::GA401);
+        let mut grid = AnimeGrid::new(AnimeType::GA401);
+        for y in 0..55 {
+            canvas.set(32, y, 255);
+            grid.set(32, y, 255);
+        }
+        let expected = AnimeDataBuffer::try_from(grid).unwrap();
+        assert_eq!(canvas.to_buffer().unwrap().data(), expected.data());
+    }
+
+    #[test]
+    fn canvas_matches_image() {
+        // A white image scaled past the edges lights every LED
+        for anime_type in [
+            AnimeType::GA401,
+            AnimeType::GA402,
+            AnimeType::GU604
+        ] {
+            let pixel = Pixel {
+                color: 255,
+                alpha: 1.0
+            };
+            let mut image = AnimeImage::new(
+                Vec2::new(4.0, 4.0),
+                0.0,
+                Vec2::default(),
+                1.0,
+                vec![pixel; 100 * 100],
+                100,
+                anime_type
+            )
+            .unwrap();
+            image.update();
+            let expected = AnimeDataBuffer::try_from(&image).unwrap();
+
+            let mut canvas = Canvas::new(anime_type);
+            for y in 0..canvas.height() {
+                for x in 0..canvas.width() {
+                    canvas.set(x, y, 255);
+                }
+            }
+            assert_eq!(canvas.to_buffer().unwrap().data(), expected.data());
+        }
+    }
+
+    #[test]
+    fn text_sized_to_display() {
+        // The clock is drawn taller on the displays with more rows
+        let ga401 = Canvas::new(AnimeType::GA401);
+        let ga402 = Canvas::new(AnimeType::GA402);
+        let gu604 = Canvas::new(AnimeType::GU604);
+        assert_eq!(ga401.line_scale(&[(&LARGE, "12:34")], 1), 2);
+        assert_eq!(ga402.line_scale(&[(&LARGE, "12:34")], 1), 3);
+        assert_eq!(gu604.line_scale(&[(&LARGE, "12:34")], 1), 3);
+        // Lines further down are narrower
+        assert!(ga401.fits(&SMALL, "28 OCT", 1, 3));
+        assert!(!ga401.fits(&SMALL, "28 OCT", 40, 1));
+        assert_eq!(SMALL.text_width("B 100%"), 21);
+        // Missing glyphs are drawn as a question mark
+        assert_eq!(LARGE.glyph('x'), LARGE.glyph('?'));
+
+        let clock = TimeDate {
+            twelve_hour: true,
+            show_date: true,
+            brightness: 1.0,
+            time: AnimTime::Count(5)
+        };
+        assert_eq!(clock.run_time().unwrap().as_secs(), 5);
+        let now = ClockTime {
+            hour: 13,
+            minute: 5,
+            day: 28,
+            month: 10
+        };
+        for anime_type in [
+            AnimeType::GA401,
+            AnimeType::GA402,
+            AnimeType::GU604
+        ] {
+            let frame = clock.render(anime_type, &now).unwrap();
+            assert!(frame.data().contains(&255));
+        }
+    }
+
+    #[test]
+    fn system_info() {
+        let stat = "cpu  100 0 100 700 100 0 0 0 50 0\ncpu0 1 2 3 4\n";
+        let earlier = CpuTimes::parse(stat).unwrap();
+        assert_eq!(earlier, CpuTimes {
+            idle: 800,
+            total: 1000
+        });
+        let later = CpuTimes {
+            idle: 900,
+            total: 1400
+        };
+        assert_eq!(later.load_since(&earlier), Some(0.75));
+        assert_eq!(earlier.load_since(&earlier), None);
+
+        let info = SystemInfo {
+            brightness: 0.5,
+            time: AnimTime::Infinite
+        };
+        assert!(info.run_time().is_none());
+        let stats = SystemStats {
+            cpu_load: Some(0.42),
+            cpu_temp: Some(61.2),
+            battery: None
+        };
+        let frame = info.render(AnimeType::GA401, &stats).unwrap();
+        assert!(frame.data().iter().all(|b| *b == 0 || *b == 127));
+        assert!(frame.data().contains(&127));
+    }
+
+    #[test]
+    fn rain_repeats_for_seed() {
+        let rain = MatrixRain {
+            density: 1.0,
+            brightness: 1.0,
+            time: AnimTime::Infinite
+        };
+        let mut a = rain.start(AnimeType::GA402, 42);
+        let mut b = rain.start(AnimeType::GA402, 42);
+        let mut lit = false;
+        for _ in 0..30 {
+            let frame = a.next_frame().unwrap();
+            assert_eq!(frame.data(), b.next_frame().unwrap().data());
+            lit |= frame.data().iter().any(|b| *b != 0);
+        }
+        assert!(lit);
+
+        let calm = MatrixRain {
+            density: 0.0,
+            ..rain
+        };
+        let mut calm = calm.start(AnimeType::GA402, 42);
+        for _ in 0..30 {
+            assert!(calm.next_frame().unwrap().data().iter().all(|b| *b == 0));
+        }
+    }
+}
@@ -0,0 +1,110 @@
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use pix::el::Pixel as _;
+    use rog_anime::*;
+
+    fn data_path(name: &str) -> PathBuf {
+        let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        path.push("tests/data");
+        path.push(name);
+        path
+    }
+
+    fn edge(anime_type: AnimeType) -> AnimeDataBuffer {
+        let mut matrix = AnimeImage::new(
+            Vec2::new(1.0, 1.0),
+            0.0,
+            Vec2::default(),
+            0.0,
+            vec![Pixel::default(); 1000],
+            100,
+            anime_type
+        )
+        .unwrap();
+        matrix.edge_outline();
+        AnimeDataBuffer::try_from(&matrix).unwrap()
+    }
+
+    fn diagonal(name: &str, anime_type: AnimeType) -> AnimeDataBuffer {
+        AnimeDiagonal::from_png(&data_path(name), None, 255.0, anime_type)
+            .unwrap()
+            .into_data_buffer(anime_type)
+            .unwrap()
+    }
+
+    fn read_golden(path: &Path) -> (u32, u32, Vec<u8>) {
+        let file = std::fs::File::open(path).unwrap();
+        let step = png_pong::Decoder::new(file)
+            .unwrap()
+            .into_steps()
+            .next()
+            .unwrap()
+            .unwrap();
+        let png_pong::PngRaster::Gray8(raster) = step.raster else {
+            panic!("{path:?} is not 8bit greyscale");
+        };
+        let pixels = raster.pixels().iter().map(|p| u8::from(p.one())).collect();
+        (raster.width(), raster.height(), pixels)
+    }
+
+    /// Compare a render against the golden image `name`. Run with
+    /// `UPDATE_GOLDEN=1` to write the golden images after a deliberate change.
+    fn check_golden(name: &str, anime_type: AnimeType, buffer: &AnimeDataBuffer) {
+        let render = AnimeRender::new(anime_type);
+        let path = data_path(name);
+        if std::env::var_os("UPDATE_GOLDEN").is_some() {
+            let file = std::fs::File::create(&path).unwrap();
+            render.write_png(buffer, file).unwrap();
+        }
+
+        let (width, height, golden) = read_golden(&path);
+        assert_eq!((width, height), (render.width(), render.height()));
+        let drawn = render.draw(buffer).unwrap();
+        let wrong = drawn.iter().zip(&golden).filter(|(a, b)| a != b).count();
+        assert_eq!(wrong, 0, "{wrong} pixels differ from {name}");
+    }
+
+    #[test]
+    fn ga401_render_golden() {
+        check_golden(
+            "ga401-edge-render.png",
+            AnimeType::GA401,
+            &edge(AnimeType::GA401)
+        );
+        check_golden(
+            "ga401-diagonal-render.png",
+            AnimeType::GA401,
+            &diagonal("ga401-diagonal.png", AnimeType::GA401)
+        );
+    }
+
+    #[test]
+    fn ga402_render_golden() {
+        check_golden(
+            "ga402-edge-render.png",
+            AnimeType::GA402,
+            &edge(AnimeType::GA402)
+        );
+        check_golden(
+            "ga402-fullbright-render.png",
+            AnimeType::GA402,
+            &diagonal("ga402-diagonal-fullbright.png", AnimeType::GA402)
+        );
+    }
+
+    #[test]
+    fn gu604_render_golden() {
+        check_golden(
+            "gu604-edge-render.png",
+            AnimeType::GU604,
+            &edge(AnimeType::GU604)
+        );
+        check_golden(
+            "gu604-diagonal-render.png",
+            AnimeType::GU604,
+            &diagonal("gu604-diagonal.png", AnimeType::GU604)
+        );
+    }
+}
@@ -1,14 +1,18 @@
+use std::collections::HashMap;
 use std::env;
+use std::path::{Path, PathBuf};
 
 use dmi_id::DMIID;
 use log::{error, info, warn};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 
 use crate::keyboard::AdvancedAuraType;
-use crate::{AuraModeNum, AuraZone, PowerZones};
+use crate::{AuraModeNum, AuraZone, DeviceCorrection, PowerZones};
 
 pub const ASUS_LED_MODE_CONF: &str = "/usr/share/asusd/aura_support.ron";
 pub const ASUS_LED_MODE_USER_CONF: &str = "/etc/asusd/asusd_user_ledmodes.ron";
+pub const ASUS_LED_MODE_DROPIN_DIR: &str = "/etc/asusd/aura_support.d";
 
 #[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
 pub struct LedSupportData {
@@ -54,7 +58,11 @@ pub struct LedSupportData {
     #[serde(default)]
     pub advanced_type: AdvancedAuraType,
     /// If empty will default to `Keyboard` power zone
-    pub power_zones: Vec<PowerZones>
+    pub power_zones: Vec<PowerZones>,
+    /// Gamma and white balance so colours match other devices. No change if
+    /// not set.
+    #[serde(default, skip_serializing_if = "DeviceCorrection::is_identity")]
+    pub colour_correction: DeviceCorrection
 }
 
 impl LedSupportData {
@@ -69,14 +77,43 @@ impl LedSupportData {
         // let prod_family = dmi.product_family().expect("Could not get
         // product_family");
 
-        if let Some(data) = LedSupportFile::load_from_supoprt_db() {
-            return data.match_device(&dmi.board_name, product_id);
+        let db = LedSupportDb::load_from_supoprt_db();
+        for issue in db.issues() {
+            if issue.is_error() {
+                error!("{issue}");
+            } else {
+                warn!("{issue}");
+            }
+        }
+        if db.is_loaded() {
+            let found = db.match_device(&dmi.board_name, product_id);
+            match found.reason {
+                MatchReason::Default => warn!(
+                    "the aura_support.ron file has no entry for this model: {}, {product_id}. \
+                     Using a default",
+                    dmi.board_name
+                ),
+                _ => info!(
+                    "Matched to {} ({}) from {:?}",
+                    found.data.device_name, found.reason, found.sources
+                )
+            }
+            return found.data;
         }
         info!("Using generic LED control for keyboard brightness only. No aura_support file found");
         let mut data = LedSupportData::default();
         data.power_zones.push(PowerZones::Keyboard);
         data
     }
+
+    /// The name and product ID, as used in messages
+    fn label(&self) -> String {
+        if self.product_id.is_empty() {
+            self.device_name.clone()
+        } else {
+            format!("{}:{}", self.device_name, self.product_id)
+        }
+    }
 }
 
 #[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize, Serialize)]
@@ -86,86 +123,451 @@ impl LedSupportFile {
     pub fn get(&self) -> &[LedSupportData] {
         &self.0
     }
+}
 
-    /// The list is stored in ordered format, so the iterator must be reversed
-    /// to ensure we match to *whole names* first before doing a glob match
-    fn match_device(&self, device_name: &str, product_id: &str) -> LedSupportData {
-        for config in self.0.iter().rev() {
-            if device_name.contains(&config.device_name) {
-                info!("Matched to {}", config.device_name);
-                if !config.product_id.is_empty() {
-                    info!("Checking product ID");
-                    if config.product_id == product_id {
-                        info!("Matched to {}", config.product_id);
-                        return config.clone();
-                    } else {
-                        continue;
+/// A change to one of the lists in an existing entry
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub enum ListChange<T> {
+    /// Replace the list
+    Set(Vec<T>),
+    /// Add these if not already in the list
+    Add(Vec<T>),
+    /// Remove these from the list
+    Remove(Vec<T>)
+}
+
+impl<T: PartialEq + Clone> ListChange<T> {
+    fn apply(&self, list: &mut Vec<T>) {
+        match self {
+            ListChange::Set(items) => *list = items.clone(),
+            ListChange::Add(items) => {
+                for item in items {
+                    if !list.contains(item) {
+                        list.push(item.clone());
                     }
                 }
-                return config.clone();
             }
+            ListChange::Remove(items) => list.retain(|i| !items.contains(i))
         }
-        warn!(
-            "the aura_support.ron file has no entry for this model: {device_name}, {product_id}. \
-             Using a default"
-        );
-        LedSupportData {
-            device_name: device_name.to_owned(),
-            product_id: product_id.to_owned(),
-            layout_name: "Default".to_owned(),
-            basic_modes: vec![AuraModeNum::Static],
-            basic_zones: vec![],
-            advanced_type: AdvancedAuraType::None,
-            power_zones: vec![PowerZones::Keyboard]
+    }
+}
+
+/// Changes only the fields that are `Some` in the entry with the same
+/// `device_name` and `product_id`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct LedSupportMerge {
+    pub device_name: String,
+    #[serde(default)]
+    pub product_id: String,
+    #[serde(default)]
+    pub layout_name: Option<String>,
+    #[serde(default)]
+    pub basic_modes: Option<ListChange<AuraModeNum>>,
+    #[serde(default)]
+    pub basic_zones: Option<ListChange<AuraZone>>,
+    #[serde(default)]
+    pub advanced_type: Option<AdvancedAuraType>,
+    #[serde(default)]
+    pub power_zones: Option<ListChange<PowerZones>>,
+    #[serde(default)]
+    pub colour_correction: Option<DeviceCorrection>
+}
+
+impl LedSupportMerge {
+    /// The names of the fields this changes
+    fn fields(&self) -> Vec<&'static str> {
+        let mut fields = Vec::new();
+        if self.layout_name.is_some() {
+            fields.push("layout_name");
+        }
+        if self.basic_modes.is_some() {
+            fields.push("basic_modes");
+        }
+        if self.basic_zones.is_some() {
+            fields.push("basic_zones");
+        }
+        if self.advanced_type.is_some() {
+            fields.push("advanced_type");
+        }
+        if self.power_zones.is_some() {
+            fields.push("power_zones");
+        }
+        if self.colour_correction.is_some() {
+            fields.push("colour_correction");
         }
+        fields
     }
 
-    /// Load `LedSupportFile` from the `aura_support.ron` file at
-    /// `/usr/share/asusd/aura_support.ron` and append with data from
-    /// `/etc/asusd/asusd_user_ledmodes.ron` if that file is available.
-    ///
-    /// Returns `None` if neither file exists or does not parse correctly.
-    pub fn load_from_supoprt_db() -> Option<Self> {
-        let mut loaded = false;
-        let mut data = LedSupportFile::default();
-        // Load user configs first so they are first to be checked
-        if let Ok(file) = std::fs::read_to_string(ASUS_LED_MODE_USER_CONF) {
-            if file.is_empty() {
-                warn!("{} is empty", ASUS_LED_MODE_USER_CONF);
-            } else {
-                if let Ok(mut tmp) = ron::from_str::<LedSupportFile>(&file) {
-                    data.0.append(&mut tmp.0);
+    fn apply(&self, data: &mut LedSupportData) {
+        if let Some(layout_name) = &self.layout_name {
+            data.layout_name = layout_name.clone();
+        }
+        if let Some(change) = &self.basic_modes {
+            change.apply(&mut data.basic_modes);
+        }
+        if let Some(change) = &self.basic_zones {
+            change.apply(&mut data.basic_zones);
+        }
+        if let Some(advanced_type) = &self.advanced_type {
+            data.advanced_type = advanced_type.clone();
+        }
+        if let Some(change) = &self.power_zones {
+            change.apply(&mut data.power_zones);
+        }
+        if let Some(correction) = self.colour_correction {
+            data.colour_correction = correction;
+        }
+    }
+}
+
+/// An entry in a file in `/etc/asusd/aura_support.d/`. Each file is a list of
+/// these, e.g:
+/// ```ignore
+/// [
+///     Merge((device_name: "GA401I", basic_modes: Some(Add([Pulse])))),
+///     Replace((device_name: "GX550L", basic_modes: [Static], power_zones: [Keyboard])),
+/// ]
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub enum LedSupportDropIn {
+    /// Add an entry, or replace all of the entry with the same `device_name`
+    /// and `product_id`
+    Replace(LedSupportData),
+    /// Change some fields of an existing entry
+    Merge(LedSupportMerge)
+}
+
+/// A problem found while loading the support data. Files with a read or parse
+/// error are skipped, everything else is still loaded.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub enum SupportIssue {
+    Read {
+        path: PathBuf,
+        error: String
+    },
+    Parse {
+        path: PathBuf,
+        line: usize,
+        col: usize,
+        error: String
+    },
+    /// Two files changed the same field of an entry, the `later` one is used
+    Conflict {
+        entry: String,
+        field: String,
+        earlier: PathBuf,
+        later: PathBuf
+    },
+    /// A merge names an entry that doesn't exist
+    MissingEntry {
+        path: PathBuf,
+        entry: String
+    }
+}
+
+impl SupportIssue {
+    pub fn is_error(&self) -> bool {
+        matches!(self, SupportIssue::Read { .. } | SupportIssue::Parse { .. })
+    }
+}
+
+impl std::fmt::Display for SupportIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SupportIssue::Read { path, error } => {
+                write!(f, "{}: could not read: {error}", path.display())
+            }
+            SupportIssue::Parse {
+                path,
+                line,
+                col,
+                error
+            } => write!(f, "{}:{line}:{col}: {error}, file skipped", path.display()),
+            SupportIssue::Conflict {
+                entry,
+                field,
+                earlier,
+                later
+            } => write!(
+                f,
+                "{entry}: {field} is changed by both {} and {}, using {}",
+                earlier.display(),
+                later.display(),
+                later.display()
+            ),
+            SupportIssue::MissingEntry { path, entry } => write!(
+                f,
+                "{}: merge for {entry} has no entry to change, ignored",
+                path.display()
+            )
+        }
+    }
+}
+
+/// An entry of the loaded support data, and the files it came from
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LedSupportEntry {
+    pub data: LedSupportData,
+    /// The file the entry was first defined in, then every file that changed
+    /// it in load order
+    pub sources: Vec<PathBuf>
+}
+
+/// Why an entry was chosen for a device
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum MatchReason {
+    /// The `device_name` is in the board name and the `product_id` is the same
+    ProductId,
+    /// The `device_name` is in the board name and the entry is for any product
+    BoardName,
+    /// No entry matched so a keyboard-only default is used
+    Default
+}
+
+impl std::fmt::Display for MatchReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MatchReason::ProductId => write!(f, "board name and product ID match"),
+            MatchReason::BoardName => write!(f, "board name matches, entry is for any product"),
+            MatchReason::Default => write!(f, "no entry matches")
+        }
+    }
+}
+
+/// The entry chosen for a device, and why
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SupportMatch {
+    pub data: LedSupportData,
+    /// Empty if the default is used
+    pub sources: Vec<PathBuf>,
+    pub reason: MatchReason,
+    /// Other entries with a `device_name` in the board name and why they
+    /// weren't used
+    pub passed_over: Vec<String>
+}
+
+/// The support data loaded in layers, each able to change the ones before:
+/// 1. The packaged `/usr/share/asusd/aura_support.ron`
+/// 2. `/etc/asusd/asusd_user_ledmodes.ron`, each entry in it replaces or adds a
+///    whole entry
+/// 3. Each `.ron` file in `/etc/asusd/aura_support.d/` in name order, as a list
+///    of `LedSupportDropIn`
+#[derive(Debug, Clone, Default)]
+pub struct LedSupportDb {
+    entries: Vec<LedSupportEntry>,
+    issues: Vec<SupportIssue>,
+    /// The last file to change each field of an entry, used to find conflicts
+    changed: HashMap<(String, &'static str), PathBuf>
+}
+
+impl LedSupportDb {
+    /// Load from the installed locations
+    pub fn load_from_supoprt_db() -> Self {
+        Self::load(
+            Path::new(ASUS_LED_MODE_CONF),
+            Path::new(ASUS_LED_MODE_USER_CONF),
+            Path::new(ASUS_LED_MODE_DROPIN_DIR)
+        )
+    }
+
+    /// Load from the given locations, any of which may be missing
+    pub fn load(support_file: &Path, user_file: &Path, dropin_dir: &Path) -> Self {
+        let mut db = Self::default();
+        if let Some(file) = db.read::<LedSupportFile>(support_file) {
+            info!("Loaded LED support data from {}", support_file.display());
+            db.entries = file
+                .0
+                .into_iter()
+                .map(|data| LedSupportEntry {
+                    data,
+                    sources: vec![support_file.to_owned()]
+                })
+                .collect();
+        }
+        if let Some(file) = db.read::<LedSupportFile>(user_file) {
+            info!("Loaded user LED support data from {}", user_file.display());
+            for data in file.0 {
+                db.apply(user_file, LedSupportDropIn::Replace(data));
+            }
+        }
+
+        let mut dropins: Vec<PathBuf> = std::fs::read_dir(dropin_dir)
+            .map(|dir| {
+                dir.filter_map(|e| e.ok().map(|e| e.path()))
+                    .filter(|p| p.extension().is_some_and(|e| e == "ron"))
+                    .collect()
+            })
+            .unwrap_or_default();
+        dropins.sort();
+        for path in dropins {
+            if let Some(changes) = db.read::<Vec<LedSupportDropIn>>(&path) {
+                info!("Loaded LED support drop-in {}", path.display());
+                for change in changes {
+                    db.apply(&path, change);
                 }
-                info!(
-                    "Loaded user-defined LED support data from {}",
-                    ASUS_LED_MODE_USER_CONF
-                );
             }
         }
-        // Load and append the default LED support data
-        if let Ok(file) = std::fs::read_to_string(ASUS_LED_MODE_CONF) {
-            if file.is_empty() {
-                warn!("{} is empty", ASUS_LED_MODE_CONF);
-            } else {
-                let mut tmp: LedSupportFile = ron::from_str(&file)
-                    .map_err(|e| error!("{e}"))
-                    .unwrap_or_else(|_| panic!("Could not deserialise {}", ASUS_LED_MODE_CONF));
-                data.0.append(&mut tmp.0);
-                loaded = true;
-                info!(
-                    "Loaded default LED support data from {}",
-                    ASUS_LED_MODE_CONF
-                );
+        db
+    }
+
+    /// `false` if no file had any entries
+    pub fn is_loaded(&self) -> bool {
+        !self.entries.is_empty()
+    }
+
+    pub fn entries(&self) -> &[LedSupportEntry] {
+        &self.entries
+    }
+
+    pub fn issues(&self) -> &[SupportIssue] {
+        &self.issues
+    }
+
+    /// A missing file is not an issue, the layers are all optional
+    fn read<T: DeserializeOwned>(&mut self, path: &Path) -> Option<T> {
+        let file = match std::fs::read_to_string(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return None,
+            Err(e) => {
+                self.issues.push(SupportIssue::Read {
+                    path: path.to_owned(),
+                    error: e.to_string()
+                });
+                return None;
+            }
+        };
+        if file.trim().is_empty() {
+            warn!("{} is empty", path.display());
+            return None;
+        }
+        ron::from_str(&file)
+            .map_err(|e| {
+                self.issues.push(SupportIssue::Parse {
+                    path: path.to_owned(),
+                    line: e.span.start.line,
+                    col: e.span.start.col,
+                    error: e.code.to_string()
+                });
+            })
+            .ok()
+    }
+
+    fn apply(&mut self, path: &Path, change: LedSupportDropIn) {
+        let (entry, fields) = match &change {
+            LedSupportDropIn::Replace(data) => (data.label(), vec![
+                "layout_name", "basic_modes", "basic_zones", "advanced_type", "power_zones",
+            ]),
+            LedSupportDropIn::Merge(merge) => (
+                LedSupportData {
+                    device_name: merge.device_name.clone(),
+                    product_id: merge.product_id.clone(),
+                    ..Default::default()
+                }
+                .label(),
+                merge.fields()
+            )
+        };
+
+        let existing = self.entries.iter_mut().find(|e| e.data.label() == entry);
+        match (change, existing) {
+            (LedSupportDropIn::Replace(data), Some(existing)) => {
+                existing.data = data;
+                existing.sources.push(path.to_owned());
+            }
+            (LedSupportDropIn::Replace(data), None) => self.entries.push(LedSupportEntry {
+                data,
+                sources: vec![path.to_owned()]
+            }),
+            (LedSupportDropIn::Merge(merge), Some(existing)) => {
+                merge.apply(&mut existing.data);
+                existing.sources.push(path.to_owned());
+            }
+            (LedSupportDropIn::Merge(_), None) => {
+                self.issues.push(SupportIssue::MissingEntry {
+                    path: path.to_owned(),
+                    entry
+                });
+                return;
+            }
+        }
+
+        for field in fields {
+            if let Some(earlier) = self.changed.insert((entry.clone(), field), path.to_owned()) {
+                if earlier != path {
+                    self.issues.push(SupportIssue::Conflict {
+                        entry: entry.clone(),
+                        field: field.to_owned(),
+                        earlier,
+                        later: path.to_owned()
+                    });
+                }
             }
         }
-        data.0.sort_by(|a, b| a.device_name.cmp(&b.device_name));
+    }
 
-        if loaded {
-            return Some(data);
+    /// Find the entry for the board name and USB product ID. Names are
+    /// partial and case insensitive, so several may match, in which case the
+    /// longest `device_name` is used, then one for this product over one for
+    /// any, then the one loaded last.
+    pub fn match_device(&self, board_name: &str, product_id: &str) -> SupportMatch {
+        let board_name_lower = board_name.to_lowercase();
+        let mut passed_over = Vec::new();
+        let mut candidates = Vec::new();
+        for entry in &self.entries {
+            if !board_name_lower.contains(&entry.data.device_name.to_lowercase()) {
+                continue;
+            }
+            if !entry.data.product_id.is_empty()
+                && !entry.data.product_id.eq_ignore_ascii_case(product_id)
+            {
+                passed_over.push(format!(
+                    "{}: for product ID {}",
+                    entry.data.label(),
+                    entry.data.product_id
+                ));
+                continue;
+            }
+            candidates.push(entry);
         }
 
-        warn!("Does {} exist?", ASUS_LED_MODE_USER_CONF);
-        None
+        let best = candidates
+            .iter()
+            .enumerate()
+            .max_by_key(|(i, e)| (e.data.device_name.len(), !e.data.product_id.is_empty(), *i))
+            .map(|(i, _)| i);
+        let Some(best) = best else {
+            return SupportMatch {
+                data: LedSupportData {
+                    device_name: board_name.to_owned(),
+                    product_id: product_id.to_owned(),
+                    layout_name: "Default".to_owned(),
+                    basic_modes: vec![AuraModeNum::Static],
+                    basic_zones: vec![],
+                    advanced_type: AdvancedAuraType::None,
+                    power_zones: vec![PowerZones::Keyboard],
+                    colour_correction: DeviceCorrection::default()
+                },
+                sources: Vec::new(),
+                reason: MatchReason::Default,
+                passed_over
+            };
+        };
+        for (i, entry) in candidates.iter().enumerate() {
+            if i != best {
+                passed_over.push(format!("{}: less specific", entry.data.label()));
+            }
+        }
+        let entry = candidates[best];
+        SupportMatch {
+            data: entry.data.clone(),
+            sources: entry.sources.clone(),
+            reason: if entry.data.product_id.is_empty() {
+                MatchReason::BoardName
+            } else {
+                MatchReason::ProductId
+            },
+            passed_over
+        }
     }
 }
 
@@ -179,7 +581,9 @@ mod tests {
     use ron::ser::PrettyConfig;
 
     use super::LedSupportData;
-    use crate::aura_detection::{LedSupportFile, PowerZones};
+    use crate::aura_detection::{
+        LedSupportDb, LedSupportFile, MatchReason, PowerZones, SupportIssue
+    };
     use crate::keyboard::{AdvancedAuraType, LedCode};
     // use crate::zoned::Zone;
     use crate::{AuraModeNum, AuraZone};
@@ -200,7 +604,8 @@ mod tests {
             power_zones: vec![
                 PowerZones::Keyboard,
                 PowerZones::RearGlow,
-            ]
+            ],
+            ..Default::default()
         };
 
         assert!(ron::to_string(&led).is_ok());
@@ -272,4 +677,131 @@ mod tests {
         //     ron::ser::to_string_pretty(&tmp, my_config).unwrap()
         // );
     }
+
+    #[test]
+    fn layered_support_data() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        let dropins = root.join("aura_support.d");
+        std::fs::create_dir_all(&dropins).unwrap();
+        let support = root.join("aura_support.ron");
+        std::fs::write(
+            &support,
+            r#"([
+                (device_name: "GA401", basic_modes: [Static], power_zones: [Keyboard]),
+                (device_name: "GA401I", basic_modes: [Static, Breathe], power_zones: [Keyboard]),
+                (device_name: "GA401I", product_id: "19b6", basic_modes: [Static], power_zones: [Keyboard]),
+            ])"#
+        )
+        .unwrap();
+        let user = root.join("asusd_user_ledmodes.ron");
+        std::fs::write(
+            &user,
+            r#"([(device_name: "GA401", layout_name: "ga401q", basic_modes: [Pulse], power_zones: [Keyboard])])"#
+        )
+        .unwrap();
+        std::fs::write(
+            dropins.join("10-modes.ron"),
+            r#"[
+                Merge((device_name: "GA401I", basic_modes: Some(Add([Pulse, Static])))),
+                Merge((device_name: "GA401", layout_name: Some("ga401"))),
+                Merge((device_name: "G14", power_zones: Some(Set([Logo])))),
+            ]"#
+        )
+        .unwrap();
+        std::fs::write(
+            dropins.join("20-modes.ron"),
+            r#"[Merge((device_name: "GA401I", basic_modes: Some(Remove([Breathe]))))]"#
+        )
+        .unwrap();
+        std::fs::write(
+            dropins.join("30-broken.ron"),
+            "[\n    Merge((device_name: 4)),\n]"
+        )
+        .unwrap();
+        std::fs::write(dropins.join("40-ignored.txt"), "not ron").unwrap();
+
+        let db = LedSupportDb::load(&support, &user, &dropins);
+
+        let found = db.match_device("GA401IV", "1866");
+        assert_eq!(found.reason, MatchReason::BoardName);
+        assert_eq!(found.data.device_name, "GA401I");
+        assert_eq!(found.data.basic_modes, vec![
+            AuraModeNum::Static,
+            AuraModeNum::Pulse
+        ]);
+        assert_eq!(found.sources, vec![
+            support.clone(),
+            dropins.join("10-modes.ron"),
+            dropins.join("20-modes.ron")
+        ]);
+        assert_eq!(found.passed_over, vec![
+            "GA401I:19b6: for product ID 19b6",
+            "GA401: less specific"
+        ]);
+
+        let found = db.match_device("ga401iv", "19B6");
+        assert_eq!(found.reason, MatchReason::ProductId);
+        assert_eq!(found.data.basic_modes, vec![AuraModeNum::Static]);
+
+        // The user file replaced the whole entry, then a drop-in changed it
+        let found = db.match_device("GA401QM", "19b6");
+        assert_eq!(found.data.layout_name, "ga401");
+        assert_eq!(found.data.basic_modes, vec![AuraModeNum::Pulse]);
+        assert_eq!(found.sources.len(), 3);
+
+        assert_eq!(
+            db.match_device("GX550L", "19b6").reason,
+            MatchReason::Default
+        );
+
+        let issues = db.issues();
+        assert_eq!(issues.len(), 4, "{issues:?}");
+        assert!(
+            matches!(&issues[0], SupportIssue::Conflict { entry, field, .. }
+            if entry == "GA401" && field == "layout_name")
+        );
+        assert!(matches!(&issues[1], SupportIssue::MissingEntry { entry, .. } if entry == "G14"));
+        assert!(
+            matches!(&issues[2], SupportIssue::Conflict { entry, field, .. }
+            if entry == "GA401I" && field == "basic_modes")
+        );
+        assert!(matches!(&issues[3], SupportIssue::Parse { line: 2, .. }));
+        assert!(issues[3].is_error());
+    }
+
+    #[test]
+    fn match_most_specific_entry() {
+        let tmp = tempfile::tempdir().unwrap();
+        let support = tmp.path().join("aura_support.ron");
+        // Not in name order, which used to be what decided between matches
+        std::fs::write(
+            &support,
+            r#"([
+                (device_name: "A401IV", layout_name: "longest", basic_modes: [Static], power_zones: [Keyboard]),
+                (device_name: "ga40", layout_name: "lower", basic_modes: [Static], power_zones: [Keyboard]),
+                (device_name: "GA40", product_id: "19B6", layout_name: "product", basic_modes: [Static], power_zones: [Keyboard]),
+            ])"#
+        )
+        .unwrap();
+        let missing = tmp.path().join("missing");
+        let db = LedSupportDb::load(&support, &missing, &missing);
+
+        // The longest name wins even though a shorter one sorts after it
+        let found = db.match_device("GA401IV", "19b6");
+        assert_eq!(found.data.layout_name, "longest");
+        assert_eq!(found.passed_over, vec![
+            "ga40: less specific",
+            "GA40:19B6: less specific"
+        ]);
+
+        // Case is ignored in both the name and the product ID, and of two
+        // names the same length the one for this product wins
+        let found = db.match_device("GA402RJ", "19b6");
+        assert_eq!(found.data.layout_name, "product");
+        assert_eq!(found.reason, MatchReason::ProductId);
+        let found = db.match_device("GA402RJ", "1866");
+        assert_eq!(found.data.layout_name, "lower");
+        assert_eq!(found.reason, MatchReason::BoardName);
+    }
 }
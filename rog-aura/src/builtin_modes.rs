@@ -6,7 +6,7 @@ use serde::{Deserialize, Serialize};
 use zbus::zvariant::{OwnedValue, Type, Value};
 
 use crate::error::Error;
-use crate::AURA_LAPTOP_LED_MSG_LEN;
+use crate::{Colour, AURA_LAPTOP_LED_MSG_LEN};
 
 #[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Deserialize, Serialize)]
 #[cfg_attr(
@@ -77,72 +77,6 @@ impl From<i32> for LedBrightness {
     }
 }
 
-#[cfg_attr(feature = "dbus", derive(Type, Value, OwnedValue))]
-#[derive(Debug, Clone, PartialEq, Eq, Copy, Deserialize, Serialize)]
-pub struct Colour {
-    pub r: u8,
-    pub g: u8,
-    pub b: u8
-}
-
-impl Default for Colour {
-    fn default() -> Self {
-        Colour { r: 166, g: 0, b: 0 }
-    }
-}
-
-impl FromStr for Colour {
-    type Err = Error;
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        if s.len() < 6 {
-            return Err(Error::ParseColour);
-        }
-        let r = u8::from_str_radix(&s[0..2], 16).or(Err(Error::ParseColour))?;
-        let g = u8::from_str_radix(&s[2..4], 16).or(Err(Error::ParseColour))?;
-        let b = u8::from_str_radix(&s[4..6], 16).or(Err(Error::ParseColour))?;
-        Ok(Colour { r, g, b })
-    }
-}
-
-impl From<&[f32; 3]> for Colour {
-    fn from(c: &[f32; 3]) -> Self {
-        Self {
-            r: (255.0 * c[0]) as u8,
-            g: (255.0 * c[1]) as u8,
-            b: (255.0 * c[2]) as u8
-        }
-    }
-}
-
-impl From<Colour> for [f32; 3] {
-    fn from(c: Colour) -> Self {
-        [
-            c.r as f32 / 255.0,
-            c.g as f32 / 255.0,
-            c.b as f32 / 255.0
-        ]
-    }
-}
-
-impl From<&[u8; 3]> for Colour {
-    fn from(c: &[u8; 3]) -> Self {
-        Self {
-            r: c[0],
-            g: c[1],
-            b: c[2]
-        }
-    }
-}
-
-impl From<Colour> for [u8; 3] {
-    fn from(c: Colour) -> Self {
-        [
-            c.r, c.g, c.b
-        ]
-    }
-}
-
 #[cfg_attr(
     feature = "dbus",
     derive(Type, Value, OwnedValue),
@@ -0,0 +1,1022 @@
+use std::fmt::Display;
+use std::str::FromStr;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "dbus")]
+use zbus::zvariant::{OwnedValue, Type, Value};
+
+use crate::error::Error;
+use crate::keyboard::LedCode;
+use crate::AuraZone;
+
+#[cfg_attr(feature = "dbus", derive(Type, Value, OwnedValue))]
+#[derive(Debug, Clone, PartialEq, Eq, Copy, Deserialize, Serialize)]
+pub struct Colour {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8
+}
+
+impl Default for Colour {
+    fn default() -> Self {
+        Colour { r: 166, g: 0, b: 0 }
+    }
+}
+
+impl Colour {
+    pub const fn new(r: u8, g: u8, b: u8) -> Self {
+        Self { r, g, b }
+    }
+
+    /// Find a CSS colour by name, case insensitive
+    pub fn from_name(name: &str) -> Option<Self> {
+        NAMED_COLOURS
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, c)| *c)
+    }
+
+    pub fn to_hsv(self) -> Hsv {
+        self.into()
+    }
+
+    pub fn to_hsl(self) -> Hsl {
+        self.into()
+    }
+
+    /// Change the brightness by `amount` (`0.0..=1.0`) without changing the
+    /// hue
+    pub fn scale(self, amount: f32) -> Self {
+        let amount = amount.clamp(0.0, 1.0);
+        let scale = |c: u8| (c as f32 * amount).round() as u8;
+        Self {
+            r: scale(self.r),
+            g: scale(self.g),
+            b: scale(self.b)
+        }
+    }
+
+    /// Linear blend to `other`, `amount` is clamped to `0.0..=1.0`
+    pub fn lerp(self, other: Self, amount: f32) -> Self {
+        let amount = amount.clamp(0.0, 1.0);
+        let mix = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * amount).round() as u8;
+        Self {
+            r: mix(self.r, other.r),
+            g: mix(self.g, other.g),
+            b: mix(self.b, other.b)
+        }
+    }
+
+    /// Blend to `other` through the hues between them rather than through
+    /// grey, taking the shortest way around the colour wheel
+    pub fn lerp_hsv(self, other: Self, amount: f32) -> Self {
+        let amount = amount.clamp(0.0, 1.0);
+        let (a, mut b) = (self.to_hsv(), other.to_hsv());
+        // Greys have no hue, so take it from the other end to avoid a sweep
+        // through red
+        let a_h = if a.s == 0.0 { b.h } else { a.h };
+        if b.s == 0.0 {
+            b.h = a_h;
+        }
+        let mut dh = b.h - a_h;
+        if dh > 180.0 {
+            dh -= 360.0;
+        } else if dh < -180.0 {
+            dh += 360.0;
+        }
+        Hsv {
+            h: (a_h + dh * amount).rem_euclid(360.0),
+            s: a.s + (b.s - a.s) * amount,
+            v: a.v + (b.v - a.v) * amount
+        }
+        .into()
+    }
+}
+
+/// Accepts `#rrggbb`, `#rgb` (the `#` is optional), `rgb(r, g, b)` with values
+/// `0-255` or percentages, `hsl(h, s%, l%)`, `hsv(h, s%, v%)`, or a CSS colour
+/// name. As before these were added, anything after the first six hex digits
+/// is ignored.
+impl FromStr for Colour {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if let Some(args) = function_args(s, "rgb") {
+            let [r, g, b] = args?;
+            return Ok(Colour {
+                r: parse_channel(r)?,
+                g: parse_channel(g)?,
+                b: parse_channel(b)?
+            });
+        }
+        if let Some(args) = function_args(s, "hsl") {
+            let [h, s, l] = parse_hue_args(args?)?;
+            return Ok(Hsl { h, s, l }.into());
+        }
+        if let Some(args) = function_args(s, "hsv") {
+            let [h, s, v] = parse_hue_args(args?)?;
+            return Ok(Hsv { h, s, v }.into());
+        }
+
+        let hex = s.strip_prefix('#').unwrap_or(s);
+        if hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            let digit =
+                |i: usize| u8::from_str_radix(&hex[i..i + 1], 16).or(Err(Error::ParseColour));
+            let byte =
+                |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).or(Err(Error::ParseColour));
+            match hex.len() {
+                3 => {
+                    return Ok(Colour {
+                        r: digit(0)? * 17,
+                        g: digit(1)? * 17,
+                        b: digit(2)? * 17
+                    })
+                }
+                6 => {
+                    return Ok(Colour {
+                        r: byte(0)?,
+                        g: byte(2)?,
+                        b: byte(4)?
+                    })
+                }
+                _ => {}
+            }
+        }
+        if let Some(colour) = Colour::from_name(s) {
+            return Ok(colour);
+        }
+        match hex.get(..6) {
+            Some(hex) if hex.chars().all(|c| c.is_ascii_hexdigit()) => hex.parse(),
+            _ => Err(Error::ParseColour)
+        }
+    }
+}
+
+/// The three arguments of `name(a, b, c)`, separated by commas or spaces
+fn function_args<'a>(s: &'a str, name: &str) -> Option<Result<[&'a str; 3], Error>> {
+    let prefix = s.get(..name.len())?;
+    if !prefix.eq_ignore_ascii_case(name) {
+        return None;
+    }
+    let args = s[name.len()..]
+        .trim_start()
+        .strip_prefix('(')
+        .and_then(|s| s.strip_suffix(')'));
+    let Some(args) = args else {
+        return Some(Err(Error::ParseColour));
+    };
+    let args: Vec<&str> = args
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|a| !a.is_empty())
+        .collect();
+    Some(args.try_into().or(Err(Error::ParseColour)))
+}
+
+/// A `0-255` value or a percentage
+fn parse_channel(arg: &str) -> Result<u8, Error> {
+    if let Some(percent) = arg.strip_suffix('%') {
+        let percent: f32 = percent.parse().or(Err(Error::ParseColour))?;
+        if !(0.0..=100.0).contains(&percent) {
+            return Err(Error::ParseColour);
+        }
+        return Ok((percent * 2.55).round() as u8);
+    }
+    arg.parse().or(Err(Error::ParseColour))
+}
+
+/// The hue in degrees for the first argument, then percentages as
+/// `0.0..=1.0`
+fn parse_hue_args([h, a, b]: [&str; 3]) -> Result<[f32; 3], Error> {
+    let h: f32 = h
+        .strip_suffix("deg")
+        .unwrap_or(h)
+        .parse()
+        .or(Err(Error::ParseColour))?;
+    let percent = |arg: &str| -> Result<f32, Error> {
+        let p: f32 = arg
+            .strip_suffix('%')
+            .ok_or(Error::ParseColour)?
+            .parse()
+            .or(Err(Error::ParseColour))?;
+        if !(0.0..=100.0).contains(&p) {
+            return Err(Error::ParseColour);
+        }
+        Ok(p / 100.0)
+    };
+    Ok([
+        h.rem_euclid(360.0),
+        percent(a)?,
+        percent(b)?
+    ])
+}
+
+impl Display for Colour {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}", self.r, self.g, self.b)
+    }
+}
+
+impl From<&[f32; 3]> for Colour {
+    fn from(c: &[f32; 3]) -> Self {
+        Self {
+            r: (255.0 * c[0]) as u8,
+            g: (255.0 * c[1]) as u8,
+            b: (255.0 * c[2]) as u8
+        }
+    }
+}
+
+impl From<Colour> for [f32; 3] {
+    fn from(c: Colour) -> Self {
+        [
+            c.r as f32 / 255.0,
+            c.g as f32 / 255.0,
+            c.b as f32 / 255.0
+        ]
+    }
+}
+
+impl From<&[u8; 3]> for Colour {
+    fn from(c: &[u8; 3]) -> Self {
+        Self {
+            r: c[0],
+            g: c[1],
+            b: c[2]
+        }
+    }
+}
+
+impl From<Colour> for [u8; 3] {
+    fn from(c: Colour) -> Self {
+        [
+            c.r, c.g, c.b
+        ]
+    }
+}
+
+/// Hue in degrees `0.0..360.0`, saturation and value in `0.0..=1.0`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+pub struct Hsv {
+    pub h: f32,
+    pub s: f32,
+    pub v: f32
+}
+
+/// Hue in degrees `0.0..360.0`, saturation and lightness in `0.0..=1.0`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+pub struct Hsl {
+    pub h: f32,
+    pub s: f32,
+    pub l: f32
+}
+
+/// The hue in degrees, and the max and min of the channels as `0.0..=1.0`
+fn hue_max_min(c: Colour) -> (f32, f32, f32) {
+    let [r, g, b]: [f32; 3] = c.into();
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let delta = max - min;
+    let h = if delta == 0.0 {
+        0.0
+    } else if max == r {
+        60.0 * ((g - b) / delta).rem_euclid(6.0)
+    } else if max == g {
+        60.0 * ((b - r) / delta + 2.0)
+    } else {
+        60.0 * ((r - g) / delta + 4.0)
+    };
+    (h, max, min)
+}
+
+/// Build from the hue, chroma, and the amount to add to every channel
+fn from_hue_chroma(h: f32, chroma: f32, m: f32) -> Colour {
+    let h = h.rem_euclid(360.0) / 60.0;
+    let x = chroma * (1.0 - (h % 2.0 - 1.0).abs());
+    let (r, g, b) = match h as u8 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x)
+    };
+    let channel = |c: f32| ((c + m).clamp(0.0, 1.0) * 255.0).round() as u8;
+    Colour {
+        r: channel(r),
+        g: channel(g),
+        b: channel(b)
+    }
+}
+
+impl From<Colour> for Hsv {
+    fn from(c: Colour) -> Self {
+        let (h, max, min) = hue_max_min(c);
+        Self {
+            h,
+            s: if max == 0.0 { 0.0 } else { (max - min) / max },
+            v: max
+        }
+    }
+}
+
+impl From<Hsv> for Colour {
+    fn from(c: Hsv) -> Self {
+        let s = c.s.clamp(0.0, 1.0);
+        let v = c.v.clamp(0.0, 1.0);
+        let chroma = v * s;
+        from_hue_chroma(c.h, chroma, v - chroma)
+    }
+}
+
+impl From<Colour> for Hsl {
+    fn from(c: Colour) -> Self {
+        let (h, max, min) = hue_max_min(c);
+        let l = (max + min) / 2.0;
+        let s = if max == min {
+            0.0
+        } else {
+            (max - min) / (1.0 - (2.0 * l - 1.0).abs())
+        };
+        Self { h, s, l }
+    }
+}
+
+impl From<Hsl> for Colour {
+    fn from(c: Hsl) -> Self {
+        let s = c.s.clamp(0.0, 1.0);
+        let l = c.l.clamp(0.0, 1.0);
+        let chroma = (1.0 - (2.0 * l - 1.0).abs()) * s;
+        from_hue_chroma(c.h, chroma, l - chroma / 2.0)
+    }
+}
+
+/// How a `Gradient` blends between stops
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum GradientBlend {
+    /// Straight from one colour to the next, may pass through grey
+    #[default]
+    Rgb,
+    /// Through the hues between the colours
+    Hsv
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub struct GradientStop {
+    /// Where the colour is in the gradient, `0.0..=1.0`
+    pub position: f32,
+    pub colour: Colour
+}
+
+/// A gradient of any number of colour stops, which can be sampled by
+/// position, or by time to cycle through it
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Gradient {
+    stops: Vec<GradientStop>,
+    #[serde(default)]
+    blend: GradientBlend
+}
+
+impl Default for Gradient {
+    fn default() -> Self {
+        Self::rainbow()
+    }
+}
+
+impl Gradient {
+    pub fn new(stops: Vec<GradientStop>, blend: GradientBlend) -> Self {
+        Self { stops, blend }
+    }
+
+    /// Spread the colours evenly from `0.0` to `1.0`
+    pub fn even(colours: &[Colour], blend: GradientBlend) -> Self {
+        let last = colours.len().saturating_sub(1).max(1) as f32;
+        Self {
+            stops: colours
+                .iter()
+                .enumerate()
+                .map(|(i, colour)| GradientStop {
+                    position: i as f32 / last,
+                    colour: *colour
+                })
+                .collect(),
+            blend
+        }
+    }
+
+    /// The default colours used for the zones of multizone keyboards
+    pub fn rainbow() -> Self {
+        Self::even(
+            &[
+                crate::RED,
+                crate::VIOLET,
+                crate::BLUE,
+                crate::TEAL,
+                crate::GREEN,
+                crate::YELLOW,
+                crate::ORANGE
+            ],
+            GradientBlend::Rgb
+        )
+    }
+
+    pub fn stops(&self) -> &[GradientStop] {
+        &self.stops
+    }
+
+    /// The colour at `position`, which is clamped to the first and last stops
+    pub fn sample(&self, position: f32) -> Colour {
+        self.sample_inner(position, false)
+    }
+
+    /// The colour at `position`, which wraps around so that past the last
+    /// stop the gradient blends back to the first
+    pub fn sample_wrapped(&self, position: f32) -> Colour {
+        self.sample_inner(position.rem_euclid(1.0), true)
+    }
+
+    /// Cycle through the gradient once every `period`
+    pub fn at_time(&self, elapsed: Duration, period: Duration) -> Colour {
+        if period.is_zero() {
+            return self.sample(0.0);
+        }
+        self.sample_wrapped(elapsed.as_secs_f32() / period.as_secs_f32())
+    }
+
+    /// Stops need not be in order, the stops either side of `position` are
+    /// found directly
+    fn sample_inner(&self, position: f32, wrap: bool) -> Colour {
+        let mut below: Option<GradientStop> = None;
+        let mut above: Option<GradientStop> = None;
+        for stop in &self.stops {
+            if stop.position <= position && below.is_none_or(|b| stop.position >= b.position) {
+                below = Some(*stop);
+            }
+            if stop.position >= position && above.is_none_or(|a| stop.position < a.position) {
+                above = Some(*stop);
+            }
+        }
+
+        let by_position = |a: &&GradientStop, b: &&GradientStop| a.position.total_cmp(&b.position);
+        if wrap {
+            below = below.or_else(|| {
+                self.stops.iter().max_by(by_position).map(|s| GradientStop {
+                    position: s.position - 1.0,
+                    ..*s
+                })
+            });
+            above = above.or_else(|| {
+                self.stops.iter().min_by(by_position).map(|s| GradientStop {
+                    position: s.position + 1.0,
+                    ..*s
+                })
+            });
+        }
+
+        match (below, above) {
+            (Some(below), Some(above)) => {
+                let span = above.position - below.position;
+                if span <= f32::EPSILON {
+                    return below.colour;
+                }
+                let amount = (position - below.position) / span;
+                match self.blend {
+                    GradientBlend::Rgb => below.colour.lerp(above.colour, amount),
+                    GradientBlend::Hsv => below.colour.lerp_hsv(above.colour, amount)
+                }
+            }
+            (Some(stop), None) | (None, Some(stop)) => stop.colour,
+            (None, None) => Colour::new(0, 0, 0)
+        }
+    }
+}
+
+/// Gamma and white balance for one set of LEDs, used so that a colour looks
+/// the same on each device
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(try_from = "UncheckedCorrection")]
+pub struct ColourCorrection {
+    /// Applied to each channel as `0.0..=1.0`, `1.0` is no change and higher
+    /// darkens the mid tones
+    pub gamma: f32,
+    /// How much of each channel is used at full, lower the channels that tint
+    /// white
+    pub white: [f32; 3]
+}
+
+// NaN is rejected when loading from config
+impl Eq for ColourCorrection {}
+
+/// A `ColourCorrection` as written in the config, before it is checked
+#[derive(Deserialize)]
+struct UncheckedCorrection {
+    gamma: f32,
+    white: [f32; 3]
+}
+
+impl TryFrom<UncheckedCorrection> for ColourCorrection {
+    type Error = String;
+
+    fn try_from(c: UncheckedCorrection) -> Result<Self, Self::Error> {
+        if c.gamma.is_nan() || c.white.iter().any(|w| w.is_nan()) {
+            return Err("colour correction values can't be NaN".to_owned());
+        }
+        Ok(Self {
+            gamma: c.gamma,
+            white: c.white
+        })
+    }
+}
+
+impl Default for ColourCorrection {
+    fn default() -> Self {
+        Self {
+            gamma: 1.0,
+            white: [
+                1.0, 1.0, 1.0
+            ]
+        }
+    }
+}
+
+impl ColourCorrection {
+    pub fn is_identity(&self) -> bool {
+        *self == Self::default()
+    }
+
+    pub fn apply(&self, colour: Colour) -> Colour {
+        if self.is_identity() {
+            return colour;
+        }
+        let correct = |c: u8, white: f32| {
+            ((c as f32 / 255.0).powf(self.gamma) * white.clamp(0.0, 1.0) * 255.0).round() as u8
+        };
+        Colour {
+            r: correct(colour.r, self.white[0]),
+            g: correct(colour.g, self.white[1]),
+            b: correct(colour.b, self.white[2])
+        }
+    }
+}
+
+/// The corrections for a laptop, kept with the model in `aura_support.ron`.
+/// The lightbar and logo often use different LEDs to the keyboard.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct DeviceCorrection {
+    #[serde(default)]
+    pub keyboard: ColourCorrection,
+    #[serde(default)]
+    pub lightbar: ColourCorrection
+}
+
+impl DeviceCorrection {
+    pub fn is_identity(&self) -> bool {
+        self.keyboard.is_identity() && self.lightbar.is_identity()
+    }
+
+    pub fn for_led(&self, led: LedCode) -> &ColourCorrection {
+        if led.is_lightbar_zone() {
+            &self.lightbar
+        } else {
+            &self.keyboard
+        }
+    }
+
+    pub fn for_zone(&self, zone: AuraZone) -> &ColourCorrection {
+        match zone {
+            AuraZone::Logo | AuraZone::BarLeft | AuraZone::BarRight => &self.lightbar,
+            _ => &self.keyboard
+        }
+    }
+}
+
+/// The CSS named colours
+pub const NAMED_COLOURS: [(&str, Colour); 148] = [
+    ("aliceblue", Colour::new(240, 248, 255)),
+    ("antiquewhite", Colour::new(250, 235, 215)),
+    ("aqua", Colour::new(0, 255, 255)),
+    ("aquamarine", Colour::new(127, 255, 212)),
+    ("azure", Colour::new(240, 255, 255)),
+    ("beige", Colour::new(245, 245, 220)),
+    ("bisque", Colour::new(255, 228, 196)),
+    ("black", Colour::new(0, 0, 0)),
+    ("blanchedalmond", Colour::new(255, 235, 205)),
+    ("blue", Colour::new(0, 0, 255)),
+    ("blueviolet", Colour::new(138, 43, 226)),
+    ("brown", Colour::new(165, 42, 42)),
+    ("burlywood", Colour::new(222, 184, 135)),
+    ("cadetblue", Colour::new(95, 158, 160)),
+    ("chartreuse", Colour::new(127, 255, 0)),
+    ("chocolate", Colour::new(210, 105, 30)),
+    ("coral", Colour::new(255, 127, 80)),
+    ("cornflowerblue", Colour::new(100, 149, 237)),
+    ("cornsilk", Colour::new(255, 248, 220)),
+    ("crimson", Colour::new(220, 20, 60)),
+    ("cyan", Colour::new(0, 255, 255)),
+    ("darkblue", Colour::new(0, 0, 139)),
+    ("darkcyan", Colour::new(0, 139, 139)),
+    ("darkgoldenrod", Colour::new(184, 134, 11)),
+    ("darkgray", Colour::new(169, 169, 169)),
+    ("darkgreen", Colour::new(0, 100, 0)),
+    ("darkgrey", Colour::new(169, 169, 169)),
+    ("darkkhaki", Colour::new(189, 183, 107)),
+    ("darkmagenta", Colour::new(139, 0, 139)),
+    ("darkolivegreen", Colour::new(85, 107, 47)),
+    ("darkorange", Colour::new(255, 140, 0)),
+    ("darkorchid", Colour::new(153, 50, 204)),
+    ("darkred", Colour::new(139, 0, 0)),
+    ("darksalmon", Colour::new(233, 150, 122)),
+    ("darkseagreen", Colour::new(143, 188, 143)),
+    ("darkslateblue", Colour::new(72, 61, 139)),
+    ("darkslategray", Colour::new(47, 79, 79)),
+    ("darkslategrey", Colour::new(47, 79, 79)),
+    ("darkturquoise", Colour::new(0, 206, 209)),
+    ("darkviolet", Colour::new(148, 0, 211)),
+    ("deeppink", Colour::new(255, 20, 147)),
+    ("deepskyblue", Colour::new(0, 191, 255)),
+    ("dimgray", Colour::new(105, 105, 105)),
+    ("dimgrey", Colour::new(105, 105, 105)),
+    ("dodgerblue", Colour::new(30, 144, 255)),
+    ("firebrick", Colour::new(178, 34, 34)),
+    ("floralwhite", Colour::new(255, 250, 240)),
+    ("forestgreen", Colour::new(34, 139, 34)),
+    ("fuchsia", Colour::new(255, 0, 255)),
+    ("gainsboro", Colour::new(220, 220, 220)),
+    ("ghostwhite", Colour::new(248, 248, 255)),
+    ("gold", Colour::new(255, 215, 0)),
+    ("goldenrod", Colour::new(218, 165, 32)),
+    ("gray", Colour::new(128, 128, 128)),
+    ("green", Colour::new(0, 128, 0)),
+    ("greenyellow", Colour::new(173, 255, 47)),
+    ("grey", Colour::new(128, 128, 128)),
+    ("honeydew", Colour::new(240, 255, 240)),
+    ("hotpink", Colour::new(255, 105, 180)),
+    ("indianred", Colour::new(205, 92, 92)),
+    ("indigo", Colour::new(75, 0, 130)),
+    ("ivory", Colour::new(255, 255, 240)),
+    ("khaki", Colour::new(240, 230, 140)),
+    ("lavender", Colour::new(230, 230, 250)),
+    ("lavenderblush", Colour::new(255, 240, 245)),
+    ("lawngreen", Colour::new(124, 252, 0)),
+    ("lemonchiffon", Colour::new(255, 250, 205)),
+    ("lightblue", Colour::new(173, 216, 230)),
+    ("lightcoral", Colour::new(240, 128, 128)),
+    ("lightcyan", Colour::new(224, 255, 255)),
+    ("lightgoldenrodyellow", Colour::new(250, 250, 210)),
+    ("lightgray", Colour::new(211, 211, 211)),
+    ("lightgreen", Colour::new(144, 238, 144)),
+    ("lightgrey", Colour::new(211, 211, 211)),
+    ("lightpink", Colour::new(255, 182, 193)),
+    ("lightsalmon", Colour::new(255, 160, 122)),
+    ("lightseagreen", Colour::new(32, 178, 170)),
+    ("lightskyblue", Colour::new(135, 206, 250)),
+    ("lightslategray", Colour::new(119, 136, 153)),
+    ("lightslategrey", Colour::new(119, 136, 153)),
+    ("lightsteelblue", Colour::new(176, 196, 222)),
+    ("lightyellow", Colour::new(255, 255, 224)),
+    ("lime", Colour::new(0, 255, 0)),
+    ("limegreen", Colour::new(50, 205, 50)),
+    ("linen", Colour::new(250, 240, 230)),
+    ("magenta", Colour::new(255, 0, 255)),
+    ("maroon", Colour::new(128, 0, 0)),
+    ("mediumaquamarine", Colour::new(102, 205, 170)),
+    ("mediumblue", Colour::new(0, 0, 205)),
+    ("mediumorchid", Colour::new(186, 85, 211)),
+    ("mediumpurple", Colour::new(147, 112, 219)),
+    ("mediumseagreen", Colour::new(60, 179, 113)),
+    ("mediumslateblue", Colour::new(123, 104, 238)),
+    ("mediumspringgreen", Colour::new(0, 250, 154)),
+    ("mediumturquoise", Colour::new(72, 209, 204)),
+    ("mediumvioletred", Colour::new(199, 21, 133)),
+    ("midnightblue", Colour::new(25, 25, 112)),
+    ("mintcream", Colour::new(245, 255, 250)),
+    ("mistyrose", Colour::new(255, 228, 225)),
+    ("moccasin", Colour::new(255, 228, 181)),
+    ("navajowhite", Colour::new(255, 222, 173)),
+    ("navy", Colour::new(0, 0, 128)),
+    ("oldlace", Colour::new(253, 245, 230)),
+    ("olive", Colour::new(128, 128, 0)),
+    ("olivedrab", Colour::new(107, 142, 35)),
+    ("orange", Colour::new(255, 165, 0)),
+    ("orangered", Colour::new(255, 69, 0)),
+    ("orchid", Colour::new(218, 112, 214)),
+    ("palegoldenrod", Colour::new(238, 232, 170)),
+    ("palegreen", Colour::new(152, 251, 152)),
+    ("paleturquoise", Colour::new(175, 238, 238)),
+    ("palevioletred", Colour::new(219, 112, 147)),
+    ("papayawhip", Colour::new(255, 239, 213)),
+    ("peachpuff", Colour::new(255, 218, 185)),
+    ("peru", Colour::new(205, 133, 63)),
+    ("pink", Colour::new(255, 192, 203)),
+    ("plum", Colour::new(221, 160, 221)),
+    ("powderblue", Colour::new(176, 224, 230)),
+    ("purple", Colour::new(128, 0, 128)),
+    ("rebeccapurple", Colour::new(102, 51, 153)),
+    ("red", Colour::new(255, 0, 0)),
+    ("rosybrown", Colour::new(188, 143, 143)),
+    ("royalblue", Colour::new(65, 105, 225)),
+    ("saddlebrown", Colour::new(139, 69, 19)),
+    ("salmon", Colour::new(250, 128, 114)),
+    ("sandybrown", Colour::new(244, 164, 96)),
+    ("seagreen", Colour::new(46, 139, 87)),
+    ("seashell", Colour::new(255, 245, 238)),
+    ("sienna", Colour::new(160, 82, 45)),
+    ("silver", Colour::new(192, 192, 192)),
+    ("skyblue", Colour::new(135, 206, 235)),
+    ("slateblue", Colour::new(106, 90, 205)),
+    ("slategray", Colour::new(112, 128, 144)),
+    ("slategrey", Colour::new(112, 128, 144)),
+    ("snow", Colour::new(255, 250, 250)),
+    ("springgreen", Colour::new(0, 255, 127)),
+    ("steelblue", Colour::new(70, 130, 180)),
+    ("tan", Colour::new(210, 180, 140)),
+    ("teal", Colour::new(0, 128, 128)),
+    ("thistle", Colour::new(216, 191, 216)),
+    ("tomato", Colour::new(255, 99, 71)),
+    ("turquoise", Colour::new(64, 224, 208)),
+    ("violet", Colour::new(238, 130, 238)),
+    ("wheat", Colour::new(245, 222, 179)),
+    ("white", Colour::new(255, 255, 255)),
+    ("whitesmoke", Colour::new(245, 245, 245)),
+    ("yellow", Colour::new(255, 255, 0)),
+    ("yellowgreen", Colour::new(154, 205, 50))
+];
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{
+        Colour, ColourCorrection, DeviceCorrection, Gradient, GradientBlend, GradientStop, Hsl, Hsv
+    };
+    use crate::keyboard::LedCode;
+    use crate::AuraZone;
+
+    #[test]
+    fn parse_colours() {
+        let red = Colour::new(255, 0, 0);
+        for s in [
+            "ff0000",
+            "#FF0000",
+            "#f00",
+            "f00",
+            "red",
+            "Red",
+            "rgb(255, 0, 0)",
+            "rgb(100% 0% 0%)",
+            "hsl(0, 100%, 50%)",
+            "hsv(360deg, 100%, 100%)",
+            " #ff0000 "
+        ] {
+            assert_eq!(s.parse::<Colour>().unwrap(), red, "{s}");
+        }
+        assert_eq!(
+            "rebeccapurple".parse::<Colour>().unwrap(),
+            Colour::new(102, 51, 153)
+        );
+        assert_eq!(
+            "#123".parse::<Colour>().unwrap(),
+            Colour::new(0x11, 0x22, 0x33)
+        );
+        for s in [
+            "", "#ff00", "gg0000", "#gg00000", "rgb(256, 0, 0)", "rgb(1, 2)", "rgb(1, 2, 3",
+            "hsl(0, 100, 50%)", "notacolour"
+        ] {
+            assert!(s.parse::<Colour>().is_err(), "{s}");
+        }
+        // Trailing text after a full colour is ignored, as it always was
+        for s in [
+            "ff00000", "#ff0000ff", "ff0000 and more"
+        ] {
+            assert_eq!(s.parse::<Colour>().unwrap(), red, "{s}");
+        }
+        assert_eq!(Colour::new(1, 0xab, 255).to_string(), "#01abff");
+    }
+
+    #[test]
+    fn hsv_hsl_round_trip() {
+        for c in [
+            Colour::new(255, 127, 0),
+            Colour::new(127, 0, 255),
+            Colour::new(0, 0, 0),
+            Colour::new(255, 255, 255),
+            Colour::new(12, 200, 99),
+            Colour::new(128, 128, 128)
+        ] {
+            assert_eq!(Colour::from(c.to_hsv()), c);
+            assert_eq!(Colour::from(c.to_hsl()), c);
+        }
+        let hsv = Colour::new(0, 255, 255).to_hsv();
+        assert_eq!(hsv, Hsv {
+            h: 180.0,
+            s: 1.0,
+            v: 1.0
+        });
+        let hsl = Colour::new(0, 0, 255).to_hsl();
+        assert_eq!(hsl, Hsl {
+            h: 240.0,
+            s: 1.0,
+            l: 0.5
+        });
+    }
+
+    #[test]
+    fn blends() {
+        let red = Colour::new(255, 0, 0);
+        let blue = Colour::new(0, 0, 255);
+        assert_eq!(red.lerp(blue, 0.5), Colour::new(128, 0, 128));
+        // Red to blue is shortest through magenta, not green
+        assert_eq!(red.lerp_hsv(blue, 0.5), Colour::new(255, 0, 255));
+        assert_eq!(Colour::new(255, 127, 0).scale(0.5), Colour::new(128, 64, 0));
+    }
+
+    #[test]
+    fn gradient_sampling() {
+        let red = Colour::new(255, 0, 0);
+        let green = Colour::new(0, 255, 0);
+        let blue = Colour::new(0, 0, 255);
+        let gradient = Gradient::new(
+            vec![
+                GradientStop {
+                    position: 1.0,
+                    colour: blue
+                },
+                GradientStop {
+                    position: 0.0,
+                    colour: red
+                },
+                GradientStop {
+                    position: 0.5,
+                    colour: green
+                },
+            ],
+            GradientBlend::Rgb
+        );
+        assert_eq!(gradient.sample(-1.0), red);
+        assert_eq!(gradient.sample(0.25), Colour::new(128, 128, 0));
+        assert_eq!(gradient.sample(0.5), green);
+        assert_eq!(gradient.sample(2.0), blue);
+        assert_eq!(gradient.sample_wrapped(1.25), Colour::new(128, 128, 0));
+
+        // Wrapping blends from the last stop back to the first
+        let gradient = Gradient::new(
+            vec![
+                GradientStop {
+                    position: 0.25,
+                    colour: red
+                },
+                GradientStop {
+                    position: 0.75,
+                    colour: blue
+                },
+            ],
+            GradientBlend::Rgb
+        );
+        assert_eq!(gradient.sample(0.0), red);
+        assert_eq!(gradient.sample_wrapped(0.0), Colour::new(128, 0, 128));
+        assert_eq!(
+            gradient.at_time(Duration::from_millis(2750), Duration::from_secs(1)),
+            blue
+        );
+
+        let rainbow = Gradient::rainbow();
+        assert_eq!(rainbow.stops().len(), 7);
+        assert_eq!(rainbow.sample(0.0), crate::RED);
+        assert_eq!(rainbow.sample(1.0), crate::ORANGE);
+        assert_eq!(
+            Gradient::new(Vec::new(), GradientBlend::Hsv).sample(0.5),
+            Colour::new(0, 0, 0)
+        );
+    }
+
+    #[test]
+    fn gradient_blends_and_spacing() {
+        let red = Colour::new(255, 0, 0);
+        let blue = Colour::new(0, 0, 255);
+        let gradient = Gradient::even(
+            &[
+                red,
+                Colour::new(0, 255, 0),
+                blue
+            ],
+            GradientBlend::Hsv
+        );
+        let positions: Vec<f32> = gradient.stops().iter().map(|s| s.position).collect();
+        assert_eq!(positions, vec![0.0, 0.5, 1.0]);
+        // Through yellow rather than the grey-brown of an RGB blend
+        assert_eq!(gradient.sample(0.25), Colour::new(255, 255, 0));
+        assert_eq!(
+            Gradient::even(
+                &[
+                    red,
+                    Colour::new(0, 255, 0)
+                ],
+                GradientBlend::Rgb
+            )
+            .sample(0.5),
+            Colour::new(128, 128, 0)
+        );
+
+        // Wrapping an HSV gradient goes the short way from blue back to red
+        let gradient = Gradient::even(
+            &[
+                red, blue
+            ],
+            GradientBlend::Hsv
+        );
+        assert_eq!(gradient.sample_wrapped(1.0), red);
+        assert_eq!(
+            Gradient::new(
+                vec![
+                    GradientStop {
+                        position: 0.0,
+                        colour: red
+                    },
+                    GradientStop {
+                        position: 0.5,
+                        colour: blue
+                    },
+                ],
+                GradientBlend::Hsv
+            )
+            .sample_wrapped(0.75),
+            Colour::new(255, 0, 255)
+        );
+
+        // One colour is the whole gradient
+        let single = Gradient::even(&[blue], GradientBlend::Rgb);
+        assert_eq!(single.stops()[0].position, 0.0);
+        for position in [
+            -0.5, 0.0, 0.3, 1.0, 7.2
+        ] {
+            assert_eq!(single.sample(position), blue);
+            assert_eq!(single.sample_wrapped(position), blue);
+        }
+        assert_eq!(
+            gradient.at_time(Duration::from_secs(3), Duration::ZERO),
+            red
+        );
+    }
+
+    #[test]
+    fn correction() {
+        let c = Colour::new(255, 128, 0);
+        assert_eq!(ColourCorrection::default().apply(c), c);
+        let correction = ColourCorrection {
+            gamma: 2.0,
+            white: [
+                1.0, 0.5, 1.0
+            ]
+        };
+        assert_eq!(correction.apply(c), Colour::new(255, 32, 0));
+    }
+
+    #[test]
+    fn correction_clamps_and_picks_leds() {
+        let white = Colour::new(255, 255, 255);
+        let correction = ColourCorrection {
+            gamma: 1.0,
+            white: [
+                2.0, -1.0, 0.5
+            ]
+        };
+        assert_eq!(correction.apply(white), Colour::new(255, 0, 128));
+        assert!(!correction.is_identity());
+
+        let device = DeviceCorrection {
+            keyboard: ColourCorrection::default(),
+            lightbar: correction
+        };
+        assert!(!device.is_identity());
+        assert!(DeviceCorrection::default().is_identity());
+        assert_eq!(device.for_led(LedCode::A).apply(white), white);
+        assert_eq!(device.for_led(LedCode::LightbarLeft), &correction);
+        assert_eq!(device.for_zone(AuraZone::Key1), &device.keyboard);
+        assert_eq!(device.for_zone(AuraZone::Logo), &correction);
+        assert_eq!(device.for_zone(AuraZone::BarRight), &correction);
+    }
+
+    #[test]
+    fn correction_config() {
+        let correction: ColourCorrection =
+            ron::from_str("(gamma: 2.2, white: (1.0, 0.9, 0.8))").unwrap();
+        assert_eq!(correction, ColourCorrection {
+            gamma: 2.2,
+            white: [1.0, 0.9, 0.8]
+        });
+        let text = ron::to_string(&correction).unwrap();
+        assert_eq!(
+            ron::from_str::<ColourCorrection>(&text).unwrap(),
+            correction
+        );
+
+        let err = ron::from_str::<ColourCorrection>("(gamma: NaN, white: (1.0, 1.0, 1.0))");
+        assert!(err.unwrap_err().to_string().contains("can't be NaN"));
+        assert!(ron::from_str::<ColourCorrection>("(gamma: 1.0, white: (1.0, NaN, 1.0))").is_err());
+        let device: DeviceCorrection =
+            ron::from_str("(lightbar: (gamma: 1.0, white: (1.0, 1.0, 0.5)))").unwrap();
+        assert!(device.keyboard.is_identity());
+        assert_eq!(device.lightbar.white[2], 0.5);
+    }
+}
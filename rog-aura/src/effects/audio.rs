@@ -0,0 +1,117 @@
+//! Effects that follow the audio playing. The levels are fed in with
+//! `AdvancedEffects::audio_levels()`, from lowest to highest frequency in the
+//! range `0.0..=1.0`.
+
+use serde::{Deserialize, Serialize};
+
+use super::{mix_colour, EffectState};
+use crate::keyboard::{KeyLayout, LedCode};
+use crate::{effect_state_impl, Colour};
+
+/// The key pulses to the bass then fades back to the background
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AudioPulse {
+    led: LedCode,
+    pulse_colour: Colour,
+    background: Colour,
+    /// How many steps a full pulse takes to fade out
+    fade_steps: u8,
+    #[serde(skip)]
+    level: f32,
+    #[serde(skip)]
+    colour: Colour
+}
+
+impl AudioPulse {
+    pub fn new(address: LedCode, pulse_colour: Colour, background: Colour, fade_steps: u8) -> Self {
+        Self {
+            led: address,
+            pulse_colour,
+            background,
+            fade_steps,
+            level: 0.0,
+            colour: background
+        }
+    }
+}
+
+impl EffectState for AudioPulse {
+    effect_state_impl!();
+
+    fn next_colour_state(&mut self, _layout: &KeyLayout) {
+        self.colour = mix_colour(&self.background, &self.pulse_colour, self.level);
+        self.level = (self.level - 1.0 / self.fade_steps.max(1) as f32).max(0.0);
+    }
+
+    fn audio_levels(&mut self, levels: &[f32]) {
+        // The lowest quarter of the bands is the bass
+        let bass = &levels[..levels.len().div_ceil(4)];
+        if !bass.is_empty() {
+            let level = bass.iter().sum::<f32>() / bass.len() as f32;
+            self.level = self.level.max(level.clamp(0.0, 1.0));
+        }
+    }
+}
+
+/// Spectrum analyser across the keyboard. Each key column shows a band, lit
+/// from the bottom row up to the level of the band.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AudioBars {
+    led: LedCode,
+    /// Colour of the bottom of a bar
+    low_colour: Colour,
+    /// Colour of the top of a full bar
+    high_colour: Colour,
+    background: Colour,
+    #[serde(skip)]
+    levels: Vec<f32>,
+    #[serde(skip)]
+    colour: Colour
+}
+
+impl AudioBars {
+    pub fn new(
+        address: LedCode,
+        low_colour: Colour,
+        high_colour: Colour,
+        background: Colour
+    ) -> Self {
+        Self {
+            led: address,
+            low_colour,
+            high_colour,
+            background,
+            levels: Vec::new(),
+            colour: background
+        }
+    }
+}
+
+impl EffectState for AudioBars {
+    effect_state_impl!();
+
+    fn next_colour_state(&mut self, layout: &KeyLayout) {
+        let Some(pos) = layout.key_position(self.led) else {
+            return;
+        };
+        if self.levels.is_empty() {
+            self.colour = self.background;
+            return;
+        }
+        let width = layout.max_width().max(1.0);
+        let height = layout.keyboard_height().max(1.0);
+        let band = ((pos.x / width * self.levels.len() as f32) as usize).min(self.levels.len() - 1);
+        // 0.0 at the bottom of the keyboard, 1.0 at the top
+        let key_height = (1.0 - pos.y / height).clamp(0.0, 1.0);
+        self.colour = if self.levels[band] >= key_height {
+            mix_colour(&self.low_colour, &self.high_colour, key_height)
+        } else {
+            self.background
+        };
+    }
+
+    fn audio_levels(&mut self, levels: &[f32]) {
+        self.levels.clear();
+        self.levels.extend_from_slice(levels);
+    }
+}
@@ -16,8 +16,11 @@ pub struct Breathe {
     /// Temporary data to help keep state
     #[serde(skip)]
     colour: Colour,
+    /// How far through the fade the colour is, `0.0` is off
     #[serde(skip)]
-    count_flipped: bool,
+    level: f32,
+    #[serde(skip)]
+    rising: bool,
     #[serde(skip)]
     use_colour1: bool
 }
@@ -30,7 +33,8 @@ impl Breathe {
             start_colour2: colour2,
             speed,
             colour: colour1,
-            count_flipped: false,
+            level: 1.0,
+            rising: false,
             use_colour1: true
         }
     }
@@ -40,45 +44,27 @@ impl EffectState for Breathe {
     effect_state_impl!();
 
     fn next_colour_state(&mut self, _layout: &KeyLayout) {
-        let Self {
-            start_colour1: colour1,
-            start_colour2: colour2,
-            speed,
-            colour: colour_actual,
-            count_flipped: flipped,
-            use_colour1,
-            ..
-        } = self;
-
-        let speed = 4 - <u8>::from(*speed);
-
-        if *colour_actual == (Colour { r: 0, g: 0, b: 0 }) {
-            *use_colour1 = !*use_colour1;
-        }
-
-        let colour = if !*use_colour1 { colour2 } else { colour1 };
-
-        let r1_scale = colour.r / speed / 2;
-        let g1_scale = colour.g / speed / 2;
-        let b1_scale = colour.b / speed / 2;
-
-        if *colour_actual == (Colour { r: 0, g: 0, b: 0 }) {
-            *flipped = true;
-        } else if colour_actual.r >= colour.r
-            && colour_actual.g >= colour.g
-            && colour_actual.b >= colour.b
-        {
-            *flipped = false;
+        // Fade the brightness only, so the hue doesn't drift as each channel
+        // reaches zero at a different time
+        let steps = 2.0 * (4 - <u8>::from(self.speed)) as f32;
+        if self.rising {
+            self.level = (self.level + 1.0 / steps).min(1.0);
+            self.rising = self.level < 1.0;
+        } else {
+            self.level = (self.level - 1.0 / steps).max(0.0);
+            if self.level == 0.0 {
+                self.rising = true;
+                self.use_colour1 = !self.use_colour1;
+            }
         }
 
-        if !*flipped {
-            colour_actual.r = colour_actual.r.saturating_sub(r1_scale);
-            colour_actual.g = colour_actual.g.saturating_sub(g1_scale);
-            colour_actual.b = colour_actual.b.saturating_sub(b1_scale);
+        let colour = if self.use_colour1 {
+            self.start_colour1
         } else {
-            colour_actual.r = colour_actual.r.saturating_add(r1_scale);
-            colour_actual.g = colour_actual.g.saturating_add(g1_scale);
-            colour_actual.b = colour_actual.b.saturating_add(b1_scale);
-        }
+            self.start_colour2
+        };
+        let mut hsv = colour.to_hsv();
+        hsv.v *= self.level;
+        self.colour = hsv.into();
     }
 }
@@ -0,0 +1,54 @@
+use serde::{Deserialize, Serialize};
+
+use super::{mix_colour, p_random, EffectState};
+use crate::keyboard::{KeyLayout, LedCode};
+use crate::{effect_state_impl, Colour};
+
+const BLACK: Colour = Colour { r: 0, g: 0, b: 0 };
+
+/// Flames rising from the bottom row, hottest (and brightest) at the bottom
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Fire {
+    led: LedCode,
+    /// Colour at the base of the flames
+    hot: Colour,
+    /// Colour at the tips of the flames
+    cold: Colour,
+    /// Percentage of heat the random flicker can take away
+    flicker: u8,
+    #[serde(skip)]
+    heat: f32,
+    #[serde(skip)]
+    colour: Colour
+}
+
+impl Fire {
+    pub fn new(address: LedCode, hot: Colour, cold: Colour, flicker: u8) -> Self {
+        Self {
+            led: address,
+            hot,
+            cold,
+            flicker,
+            heat: 0.0,
+            colour: BLACK
+        }
+    }
+}
+
+impl EffectState for Fire {
+    effect_state_impl!();
+
+    fn next_colour_state(&mut self, layout: &KeyLayout) {
+        let Some(pos) = layout.key_position(self.led) else {
+            return;
+        };
+        let base = pos.y / layout.keyboard_height().max(f32::EPSILON);
+        let flicker = p_random() as f32 / 255.0 * self.flicker.min(100) as f32 / 100.0;
+        let target = (base - flicker).clamp(0.0, 1.0);
+        // Smooth so the flames lick rather than strobe
+        self.heat = (self.heat + target) / 2.0;
+
+        let colour = mix_colour(&self.cold, &self.hot, self.heat);
+        self.colour = mix_colour(&BLACK, &colour, self.heat);
+    }
+}
@@ -0,0 +1,53 @@
+use serde::{Deserialize, Serialize};
+
+use super::{mix_colour, EffectState};
+use crate::keyboard::{KeyLayout, LedCode};
+use crate::{effect_state_impl, Colour};
+
+/// A gradient from `inner` at the `centre` key to `outer` at `radius` key
+/// widths away
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RadialGradient {
+    led: LedCode,
+    centre: LedCode,
+    inner: Colour,
+    outer: Colour,
+    radius: f32,
+    #[serde(skip)]
+    colour: Colour
+}
+
+impl RadialGradient {
+    pub fn new(
+        address: LedCode,
+        centre: LedCode,
+        inner: Colour,
+        outer: Colour,
+        radius: f32
+    ) -> Self {
+        Self {
+            led: address,
+            centre,
+            inner,
+            outer,
+            radius,
+            colour: outer
+        }
+    }
+}
+
+impl EffectState for RadialGradient {
+    effect_state_impl!();
+
+    fn next_colour_state(&mut self, layout: &KeyLayout) {
+        let (Some(pos), Some(centre)) = (
+            layout.key_position(self.led),
+            layout.key_position(self.centre)
+        ) else {
+            self.colour = self.outer;
+            return;
+        };
+        let amount = pos.distance(&centre) / self.radius.max(f32::EPSILON);
+        self.colour = mix_colour(&self.inner, &self.outer, amount);
+    }
+}
@@ -0,0 +1,178 @@
+use serde::{Deserialize, Serialize};
+
+use super::{mix_colour, Effect};
+use crate::keyboard::{KeyLayout, LedCode};
+use crate::{AuraZone, Colour};
+
+const BLACK: Colour = Colour { r: 0, g: 0, b: 0 };
+
+/// How a layer is combined with the layers below it
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum BlendMode {
+    /// The layer colour replaces the colour below
+    #[default]
+    Normal,
+    /// Channels are added, saturating at full
+    Add,
+    /// Channels are multiplied, useful to darken or tint the layers below
+    Multiply,
+    /// The brightest of each channel is kept
+    Max
+}
+
+impl BlendMode {
+    pub fn blend(self, below: Colour, above: Colour) -> Colour {
+        let apply = |f: fn(u8, u8) -> u8| Colour {
+            r: f(below.r, above.r),
+            g: f(below.g, above.g),
+            b: f(below.b, above.b)
+        };
+        match self {
+            BlendMode::Normal => above,
+            BlendMode::Add => apply(u8::saturating_add),
+            BlendMode::Multiply => apply(|a, b| ((a as u16 * b as u16 + 127) / 255) as u8),
+            BlendMode::Max => apply(u8::max)
+        }
+    }
+}
+
+/// The LEDs a layer is allowed to change
+#[derive(Debug, Default, Clone, PartialEq, Deserialize, Serialize)]
+pub enum LedMask {
+    #[default]
+    All,
+    Leds(Vec<LedCode>),
+    /// The zones map to the `Zoned*`, `LidLogo` and `Lightbar*` LEDs, so this
+    /// is for zoned keyboards and lightbars. Per-key keyboards should use
+    /// `Leds`.
+    Zones(Vec<AuraZone>)
+}
+
+impl LedMask {
+    pub fn contains(&self, led: LedCode) -> bool {
+        match self {
+            LedMask::All => true,
+            LedMask::Leds(leds) => leds.contains(&led),
+            LedMask::Zones(zones) => zones.iter().any(|zone| zone_contains(*zone, led))
+        }
+    }
+}
+
+fn zone_contains(zone: AuraZone, led: LedCode) -> bool {
+    match zone {
+        AuraZone::None => true,
+        AuraZone::Key1 => matches!(led, LedCode::ZonedKbLeft | LedCode::SingleZone),
+        AuraZone::Key2 => led == LedCode::ZonedKbLeftMid,
+        AuraZone::Key3 => led == LedCode::ZonedKbRightMid,
+        AuraZone::Key4 => led == LedCode::ZonedKbRight,
+        AuraZone::Logo => led == LedCode::LidLogo,
+        AuraZone::BarLeft => matches!(
+            led,
+            LedCode::LightbarLeft | LedCode::LightbarLeftCorner | LedCode::LightbarLeftBottom
+        ),
+        AuraZone::BarRight => matches!(
+            led,
+            LedCode::LightbarRight | LedCode::LightbarRightCorner | LedCode::LightbarRightBottom
+        )
+    }
+}
+
+fn full() -> f32 {
+    1.0
+}
+
+/// A named group of effects composited over the layers below it
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct EffectLayer {
+    pub name: String,
+    /// `0.0` hides the layer, `1.0` applies the blended colour fully
+    #[serde(default = "full")]
+    pub opacity: f32,
+    #[serde(default)]
+    pub blend: BlendMode,
+    #[serde(default)]
+    pub mask: LedMask,
+    pub effects: Vec<Effect>
+}
+
+impl EffectLayer {
+    pub fn new(name: &str, blend: BlendMode, opacity: f32) -> Self {
+        Self {
+            name: name.to_owned(),
+            opacity,
+            blend,
+            mask: LedMask::All,
+            effects: Vec::new()
+        }
+    }
+
+    #[inline]
+    pub fn push(&mut self, effect: Effect) {
+        self.effects.push(effect);
+    }
+
+    /// Push an effect for every LED in the layout
+    pub fn push_all_keys(&mut self, layout: &KeyLayout, effect: impl Fn(LedCode) -> Effect) {
+        for (led, _) in layout.key_positions() {
+            self.effects.push(effect(led));
+        }
+    }
+
+    /// Blend the layer in to `frame`. LEDs that no effect in this layer sets
+    /// are left as they are, LEDs not yet in `frame` are blended over black.
+    pub(crate) fn compose(&self, frame: &mut Vec<(LedCode, Colour)>) {
+        if self.opacity <= 0.0 {
+            return;
+        }
+        for effect in &self.effects {
+            let led = effect.led();
+            if !self.mask.contains(led) {
+                continue;
+            }
+            let idx = frame
+                .iter()
+                .position(|(l, _)| *l == led)
+                .unwrap_or_else(|| {
+                    frame.push((led, BLACK));
+                    frame.len() - 1
+                });
+            let below = frame[idx].1;
+            let blended = self.blend.blend(below, effect.colour());
+            frame[idx].1 = mix_colour(&below, &blended, self.opacity);
+        }
+    }
+}
+
+/// Applied to every LED after all layers are composited
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub struct OutputStage {
+    /// Scales the final colour, `0.0..=1.0`
+    pub brightness: f32,
+    /// `1.0` leaves colours untouched, higher values darken the mid tones
+    pub gamma: f32
+}
+
+impl Default for OutputStage {
+    fn default() -> Self {
+        Self {
+            brightness: 1.0,
+            gamma: 1.0
+        }
+    }
+}
+
+impl OutputStage {
+    pub fn apply(&self, colour: Colour) -> Colour {
+        if *self == Self::default() {
+            return colour;
+        }
+        let brightness = self.brightness.clamp(0.0, 1.0);
+        let gamma = self.gamma.max(0.01);
+        let adjust = |c: u8| ((c as f32 / 255.0).powf(gamma) * brightness * 255.0).round() as u8;
+        Colour {
+            r: adjust(colour.r),
+            g: adjust(colour.g),
+            b: adjust(colour.b)
+        }
+    }
+}
@@ -12,8 +12,32 @@ pub use breathe::*;
 mod static_;
 pub use static_::*;
 
+mod ripple;
+pub use ripple::*;
+
+mod gradient;
+pub use gradient::*;
+
+mod sweep;
+pub use sweep::*;
+
+mod sparkle;
+pub use sparkle::*;
+
+mod fire;
+pub use fire::*;
+
+mod reactive;
+pub use reactive::*;
+
+mod audio;
+pub use audio::*;
+
+mod layers;
+pub use layers::*;
+
 use crate::keyboard::{AuraLaptopUsbPackets, KeyLayout, LedCode, LedUsbPackets};
-use crate::Colour;
+use crate::{Colour, DeviceCorrection, Speed};
 
 // static mut RNDINDEX: usize = 0;
 static mut PRNDINDEX: usize = 0;
@@ -42,6 +66,20 @@ pub fn p_random() -> i32 {
     }
 }
 
+/// Linear blend from `a` to `b`, `amount` is clamped to `0.0..=1.0`
+pub(crate) fn mix_colour(a: &Colour, b: &Colour, amount: f32) -> Colour {
+    a.lerp(*b, amount)
+}
+
+/// How far a moving effect travels each step, in key widths
+pub(crate) fn speed_step(speed: Speed) -> f32 {
+    match speed {
+        Speed::Low => 0.1,
+        Speed::Med => 0.2,
+        Speed::High => 0.4
+    }
+}
+
 pub trait InputForEffect {
     /// Calculate the next colour state
     fn next_colour_state(&mut self);
@@ -62,12 +100,24 @@ pub(crate) trait EffectState {
     fn get_led(&self) -> LedCode;
 
     fn set_led(&mut self, address: LedCode);
+
+    /// A key was pressed, only effects that react to typing need this
+    fn key_pressed(&mut self, _led: LedCode) {}
+
+    /// New audio levels, only effects that follow audio need this
+    fn audio_levels(&mut self, _levels: &[f32]) {}
 }
 
+/// The effects in `effects` are the base layer, where the last effect set for
+/// an LED wins. `layers` are then blended over it in order.
 #[derive(Debug, Deserialize, Serialize, Default)]
 pub struct AdvancedEffects {
     effects: Vec<Effect>,
-    zoned: bool
+    zoned: bool,
+    #[serde(default)]
+    layers: Vec<EffectLayer>,
+    #[serde(default)]
+    output: OutputStage
 }
 
 impl AdvancedEffects {
@@ -75,7 +125,9 @@ impl AdvancedEffects {
     pub fn new(zoned: bool) -> Self {
         Self {
             effects: Default::default(),
-            zoned
+            zoned,
+            layers: Default::default(),
+            output: Default::default()
         }
     }
 
@@ -84,6 +136,20 @@ impl AdvancedEffects {
         self.effects.push(action);
     }
 
+    /// Push an effect for every LED in the layout, for effects that are
+    /// spread across the keyboard such as `Ripple` or `Wave`
+    pub fn push_all_keys(&mut self, layout: &KeyLayout, effect: impl Fn(LedCode) -> Effect) {
+        for (led, _) in layout.key_positions() {
+            self.effects.push(effect(led));
+        }
+    }
+
+    /// The effects of the base layer
+    #[inline]
+    pub fn effects(&self) -> &[Effect] {
+        &self.effects
+    }
+
     #[inline]
     pub fn insert(&mut self, index: usize, action: Effect) {
         self.effects.insert(index, action);
@@ -100,13 +166,106 @@ impl AdvancedEffects {
         None
     }
 
+    /// Add a layer on top of the existing layers, replacing any layer with the
+    /// same name
+    pub fn push_layer(&mut self, layer: EffectLayer) {
+        self.layers.retain(|l| l.name != layer.name);
+        self.layers.push(layer);
+    }
+
+    pub fn layer_mut(&mut self, name: &str) -> Option<&mut EffectLayer> {
+        self.layers.iter_mut().find(|l| l.name == name)
+    }
+
+    pub fn remove_layer(&mut self, name: &str) -> Option<EffectLayer> {
+        let index = self.layers.iter().position(|l| l.name == name)?;
+        Some(self.layers.remove(index))
+    }
+
+    #[inline]
+    pub fn layers(&self) -> &[EffectLayer] {
+        &self.layers
+    }
+
+    #[inline]
+    pub fn output(&self) -> OutputStage {
+        self.output
+    }
+
+    #[inline]
+    pub fn set_output(&mut self, output: OutputStage) {
+        self.output = output;
+    }
+
+    fn all_effects(&self) -> impl Iterator<Item = &Effect> {
+        self.effects
+            .iter()
+            .chain(self.layers.iter().flat_map(|l| l.effects.iter()))
+    }
+
+    fn all_effects_mut(&mut self) -> impl Iterator<Item = &mut Effect> {
+        self.effects
+            .iter_mut()
+            .chain(self.layers.iter_mut().flat_map(|l| l.effects.iter_mut()))
+    }
+
     pub fn next_state(&mut self, layout: &KeyLayout) {
-        for effect in &mut self.effects {
+        for effect in self.all_effects_mut() {
             effect.next_state(layout);
         }
     }
 
+    /// Pass a key press to every effect, call before `next_state()`
+    pub fn key_pressed(&mut self, led: LedCode) {
+        for effect in self.all_effects_mut() {
+            effect.key_pressed(led);
+        }
+    }
+
+    /// True if any effect reacts to key presses, so the caller knows to listen
+    /// for input
+    pub fn wants_key_presses(&self) -> bool {
+        self.all_effects().any(Effect::is_reactive)
+    }
+
+    /// Pass the latest audio levels to every effect, call before
+    /// `next_state()`
+    pub fn audio_levels(&mut self, levels: &[f32]) {
+        for effect in self.all_effects_mut() {
+            effect.audio_levels(levels);
+        }
+    }
+
+    /// True if any effect follows audio, so the caller knows to capture it
+    pub fn wants_audio(&self) -> bool {
+        self.all_effects().any(Effect::is_audio)
+    }
+
+    /// The final colour of each LED after the layers and output stage
+    pub fn compose(&self) -> Vec<(LedCode, Colour)> {
+        let mut frame: Vec<(LedCode, Colour)> = Vec::with_capacity(self.effects.len());
+        for effect in &self.effects {
+            let led = effect.led();
+            match frame.iter_mut().find(|(l, _)| *l == led) {
+                Some((_, c)) => *c = effect.colour(),
+                None => frame.push((led, effect.colour()))
+            }
+        }
+        for layer in &self.layers {
+            layer.compose(&mut frame);
+        }
+        for (_, c) in &mut frame {
+            *c = self.output.apply(*c);
+        }
+        frame
+    }
+
     pub fn create_packets(&self) -> AuraLaptopUsbPackets {
+        self.create_corrected_packets(&DeviceCorrection::default())
+    }
+
+    /// As `create_packets`, with each colour corrected for the device
+    pub fn create_corrected_packets(&self, correction: &DeviceCorrection) -> AuraLaptopUsbPackets {
         let mut usb_packets = if self.zoned {
             // TODO: figure out if that single byte difference for multizone actually
             // matters
@@ -115,9 +274,9 @@ impl AdvancedEffects {
             LedUsbPackets::new_per_key()
         };
 
-        for effect in &self.effects {
-            let c = effect.colour();
-            usb_packets.set(effect.led(), c.r, c.g, c.b);
+        for (led, c) in self.compose() {
+            let c = correction.for_led(led).apply(c);
+            usb_packets.set(led, c.r, c.g, c.b);
         }
         usb_packets.into()
     }
@@ -173,6 +332,20 @@ macro_rules! effect_impl {
                     $(Effect::$effect(c) => c.get_colour(),)*
                 }
             }
+
+            /// Tell the effect a key was pressed
+            pub fn key_pressed(&mut self, led: $crate::keyboard::LedCode) {
+                match self {
+                    $(Effect::$effect(c) => c.key_pressed(led),)*
+                }
+            }
+
+            /// Give the effect the latest audio levels
+            pub fn audio_levels(&mut self, levels: &[f32]) {
+                match self {
+                    $(Effect::$effect(c) => c.audio_levels(levels),)*
+                }
+            }
         }
     };
 }
@@ -186,7 +359,33 @@ pub enum Effect {
     Static(Static),
     Breathe(Breathe),
     DoomFlicker(DoomFlicker),
-    DoomLightFlash(DoomLightFlash)
+    DoomLightFlash(DoomLightFlash),
+    Ripple(Ripple),
+    Wave(Wave),
+    RadialGradient(RadialGradient),
+    Comet(Comet),
+    Starfield(Starfield),
+    Fire(Fire),
+    KeyPress(KeyPress),
+    PressRipple(PressRipple),
+    Heatmap(Heatmap),
+    AudioPulse(AudioPulse),
+    AudioBars(AudioBars)
+}
+
+impl Effect {
+    /// Effects that change only in response to key presses
+    pub fn is_reactive(&self) -> bool {
+        matches!(
+            self,
+            Effect::KeyPress(_) | Effect::PressRipple(_) | Effect::Heatmap(_)
+        )
+    }
+
+    /// Effects that change only in response to audio
+    pub fn is_audio(&self) -> bool {
+        matches!(self, Effect::AudioPulse(_) | Effect::AudioBars(_))
+    }
 }
 
 impl Default for Effect {
@@ -195,13 +394,27 @@ impl Default for Effect {
     }
 }
 
-effect_impl!(Static, Breathe, DoomFlicker, DoomLightFlash);
+effect_impl!(
+    Static, Breathe, DoomFlicker, DoomLightFlash, Ripple, Wave, RadialGradient, Comet, Starfield,
+    Fire, KeyPress, PressRipple, Heatmap, AudioPulse, AudioBars
+);
 
 #[cfg(test)]
 mod tests {
-    use crate::effects::{AdvancedEffects, Breathe, DoomFlicker, Effect, Static};
+    use crate::effects::{
+        AdvancedEffects, AudioBars, AudioPulse, BlendMode, Breathe, Comet, DoomFlicker, Effect,
+        EffectLayer, Fire, Heatmap, KeyPress, LedMask, OutputStage, PressRipple, RadialGradient,
+        Ripple, Starfield, Static, Wave
+    };
     use crate::keyboard::{KeyLayout, LedCode};
-    use crate::{Colour, Speed};
+    use crate::{AuraZone, Colour, Direction, Speed};
+
+    const BLACK: Colour = Colour { r: 0, g: 0, b: 0 };
+    const WHITE: Colour = Colour {
+        r: 255,
+        g: 255,
+        b: 255
+    };
 
     #[test]
     fn single_key_next_state_then_create() {
@@ -260,7 +473,10 @@ mod tests {
         let packets = seq.create_packets();
 
         assert_eq!(packets[0][0], 0x5d);
-        assert_eq!(packets[5][33], 171);
+        // Each step at this speed is a sixth of the brightness, so this is two
+        // thirds of 255. The old per-channel fade stepped by a rounded down 42
+        // and landed on 171.
+        assert_eq!(packets[5][33], 170);
         assert_eq!(packets[5][34], 85);
         assert_eq!(packets[5][35], 0);
     }
@@ -302,4 +518,381 @@ mod tests {
         assert_eq!(packets[5][34], 87);
         assert_eq!(packets[5][35], 40);
     }
+
+    #[test]
+    fn radial_gradient() {
+        let layout = KeyLayout::default_layout();
+        let mut seq = AdvancedEffects::new(false);
+        seq.push_all_keys(&layout, |led| {
+            Effect::RadialGradient(RadialGradient::new(
+                led,
+                LedCode::F,
+                Colour { r: 255, g: 0, b: 0 },
+                Colour { r: 0, g: 0, b: 255 },
+                2.4
+            ))
+        });
+
+        seq.next_state(&layout);
+        let packets = seq.create_packets();
+        // Centre
+        assert_eq!(packets[5][33..=35], [255, 0, 0]);
+        // G is one key (1.2 wide with padding) away, half way to the radius
+        assert_eq!(packets[5][36..=38], [127, 0, 128]);
+        // Escape is far outside the radius
+        assert_eq!(packets[1][24..=26], [0, 0, 255]);
+    }
+
+    #[test]
+    fn ripple_reaches_neighbour() {
+        let layout = KeyLayout::default_layout();
+        let mut seq = AdvancedEffects::new(false);
+        seq.push_all_keys(&layout, |led| {
+            Effect::Ripple(Ripple::new(led, LedCode::D, WHITE, BLACK, Speed::High, 1.0))
+        });
+
+        seq.next_state(&layout);
+        let packets = seq.create_packets();
+        assert_eq!(packets[5][33..=35], [51, 51, 51]);
+
+        seq.next_state(&layout);
+        seq.next_state(&layout);
+        let packets = seq.create_packets();
+        assert_eq!(packets[5][33..=35], [255, 255, 255]);
+        // Origin is now inside the ring
+        assert_eq!(packets[5][30..=32], [0, 0, 0]);
+    }
+
+    #[test]
+    fn wave_moves_right() {
+        let layout = KeyLayout::default_layout();
+        let mut seq = AdvancedEffects::new(false);
+        seq.push(Effect::Wave(Wave::new(
+            LedCode::F,
+            Direction::Right,
+            BLACK,
+            WHITE,
+            Speed::Low,
+            4.8
+        )));
+
+        seq.next_state(&layout);
+        let packets = seq.create_packets();
+        assert_eq!(packets[5][33..=35], [218, 218, 218]);
+    }
+
+    #[test]
+    fn comet_sweeps_across() {
+        let layout = KeyLayout::default_layout();
+        let mut seq = AdvancedEffects::new(false);
+        seq.push(Effect::Comet(Comet::new(
+            LedCode::F,
+            Direction::Right,
+            WHITE,
+            BLACK,
+            Speed::High,
+            2.0
+        )));
+
+        for _ in 0..13 {
+            seq.next_state(&layout);
+        }
+        let packets = seq.create_packets();
+        // The head hasn't reached F yet
+        assert_eq!(packets[5][33..=35], [0, 0, 0]);
+
+        seq.next_state(&layout);
+        let packets = seq.create_packets();
+        assert_eq!(packets[5][33..=35], [242, 242, 242]);
+
+        // The tail fades out behind the head
+        for _ in 0..3 {
+            seq.next_state(&layout);
+        }
+        let packets = seq.create_packets();
+        assert_eq!(packets[5][33..=35], [89, 89, 89]);
+    }
+
+    #[test]
+    fn fire_without_flicker() {
+        let layout = KeyLayout::default_layout();
+        let mut seq = AdvancedEffects::new(false);
+        seq.push(Effect::Fire(Fire::new(
+            LedCode::F,
+            Colour {
+                r: 255,
+                g: 255,
+                b: 0
+            },
+            Colour { r: 255, g: 0, b: 0 },
+            0
+        )));
+
+        seq.next_state(&layout);
+        let packets = seq.create_packets();
+        assert_eq!(packets[5][33..=35], [81, 26, 0]);
+    }
+
+    #[test]
+    fn starfield_serialise() {
+        let layout = KeyLayout::default_layout();
+        let mut seq = AdvancedEffects::new(false);
+        seq.push(Effect::Starfield(Starfield::new(
+            LedCode::F,
+            WHITE,
+            Colour { r: 0, g: 0, b: 40 },
+            0,
+            8
+        )));
+        seq.push(Effect::Ripple(Ripple::new(
+            LedCode::G,
+            LedCode::F,
+            WHITE,
+            BLACK,
+            Speed::Med,
+            1.5
+        )));
+
+        let s = ron::to_string(&seq).unwrap();
+        let mut seq: AdvancedEffects = ron::from_str(&s).unwrap();
+
+        // A density of 0 never lights up
+        seq.next_state(&layout);
+        let packets = seq.create_packets();
+        assert_eq!(packets[5][33..=35], [0, 0, 40]);
+    }
+
+    #[test]
+    fn key_press_fades() {
+        let layout = KeyLayout::default_layout();
+        let mut seq = AdvancedEffects::new(false);
+        seq.push_all_keys(&layout, |led| {
+            Effect::KeyPress(KeyPress::new(led, WHITE, BLACK, 4))
+        });
+        assert!(seq.wants_key_presses());
+
+        seq.key_pressed(LedCode::F);
+        seq.next_state(&layout);
+        let packets = seq.create_packets();
+        assert_eq!(packets[5][33..=35], [255, 255, 255]);
+        assert_eq!(packets[5][36..=38], [0, 0, 0]);
+
+        seq.next_state(&layout);
+        let packets = seq.create_packets();
+        assert_eq!(packets[5][33..=35], [191, 191, 191]);
+
+        for _ in 0..3 {
+            seq.next_state(&layout);
+        }
+        let packets = seq.create_packets();
+        assert_eq!(packets[5][33..=35], [0, 0, 0]);
+    }
+
+    #[test]
+    fn press_ripple_spreads() {
+        let layout = KeyLayout::default_layout();
+        let mut seq = AdvancedEffects::new(false);
+        seq.push_all_keys(&layout, |led| {
+            Effect::PressRipple(PressRipple::new(led, WHITE, BLACK, Speed::High, 1.0))
+        });
+
+        seq.key_pressed(LedCode::D);
+        for _ in 0..3 {
+            seq.next_state(&layout);
+        }
+        let packets = seq.create_packets();
+        // Same as the `Ripple` test, F is one key from D
+        assert_eq!(packets[5][33..=35], [255, 255, 255]);
+        assert_eq!(packets[5][30..=32], [0, 0, 0]);
+    }
+
+    #[test]
+    fn heatmap_warms_and_cools() {
+        let layout = KeyLayout::default_layout();
+        let mut seq = AdvancedEffects::new(false);
+        seq.push(Effect::Heatmap(Heatmap::new(
+            LedCode::F,
+            BLACK,
+            Colour { r: 255, g: 0, b: 0 },
+            4,
+            10
+        )));
+        seq.push(Effect::Static(Static::new(LedCode::G, WHITE)));
+        assert!(seq.wants_key_presses());
+
+        for _ in 0..2 {
+            seq.key_pressed(LedCode::F);
+            seq.key_pressed(LedCode::G);
+        }
+        seq.next_state(&layout);
+        let packets = seq.create_packets();
+        // Half hot, less one cooling step
+        assert_eq!(packets[5][33..=35], [102, 0, 0]);
+        assert_eq!(packets[5][36..=38], [255, 255, 255]);
+
+        for _ in 0..4 {
+            seq.next_state(&layout);
+        }
+        let packets = seq.create_packets();
+        assert_eq!(packets[5][33..=35], [0, 0, 0]);
+    }
+
+    #[test]
+    fn layers_blend_over_base() {
+        let layout = KeyLayout::default_layout();
+        let mut seq = AdvancedEffects::new(false);
+        seq.push(Effect::Static(Static::new(LedCode::F, Colour {
+            r: 200,
+            g: 100,
+            b: 0
+        })));
+        seq.push(Effect::Static(Static::new(LedCode::G, WHITE)));
+
+        let mut highlight = EffectLayer::new("highlight", BlendMode::Add, 1.0);
+        highlight.push(Effect::Static(Static::new(LedCode::F, Colour {
+            r: 100,
+            g: 100,
+            b: 100
+        })));
+        // Not in the base, so blended over black
+        highlight.push(Effect::Static(Static::new(LedCode::Esc, Colour {
+            r: 0,
+            g: 0,
+            b: 100
+        })));
+        seq.push_layer(highlight);
+
+        let mut tint = EffectLayer::new("tint", BlendMode::Multiply, 0.5);
+        tint.mask = LedMask::Leds(vec![LedCode::G]);
+        tint.push_all_keys(&layout, |led| {
+            Effect::Static(Static::new(led, Colour { r: 0, g: 0, b: 255 }))
+        });
+        seq.push_layer(tint);
+
+        seq.next_state(&layout);
+        let packets = seq.create_packets();
+        assert_eq!(packets[5][33..=35], [255, 200, 100]);
+        assert_eq!(packets[1][24..=26], [0, 0, 100]);
+        // Half way between white and the blue multiply
+        assert_eq!(packets[5][36..=38], [128, 128, 255]);
+
+        seq.layer_mut("tint").unwrap().blend = BlendMode::Max;
+        seq.layer_mut("tint").unwrap().opacity = 1.0;
+        seq.remove_layer("highlight").unwrap();
+        seq.set_output(OutputStage {
+            brightness: 0.5,
+            gamma: 1.0
+        });
+        let packets = seq.create_packets();
+        assert_eq!(packets[5][33..=35], [100, 50, 0]);
+        assert_eq!(packets[5][36..=38], [128, 128, 128]);
+        assert_eq!(packets[1][24..=26], [0, 0, 0]);
+    }
+
+    #[test]
+    fn zone_mask_and_gamma() {
+        let mut seq = AdvancedEffects::new(true);
+        let mut layer = EffectLayer::new("logo", BlendMode::Normal, 1.0);
+        layer.mask = LedMask::Zones(vec![AuraZone::Key2]);
+        layer.push(Effect::Static(Static::new(LedCode::ZonedKbLeft, WHITE)));
+        layer.push(Effect::Static(Static::new(LedCode::ZonedKbLeftMid, WHITE)));
+        seq.push_layer(layer);
+        seq.set_output(OutputStage {
+            brightness: 1.0,
+            gamma: 2.0
+        });
+
+        let frame = seq.compose();
+        assert_eq!(frame, vec![(LedCode::ZonedKbLeftMid, WHITE)]);
+
+        seq.layer_mut("logo").unwrap().effects[1] =
+            Effect::Static(Static::new(LedCode::ZonedKbLeftMid, Colour {
+                r: 128,
+                g: 0,
+                b: 255
+            }));
+        assert_eq!(seq.compose(), vec![
+            (LedCode::ZonedKbLeftMid, Colour {
+                r: 64,
+                g: 0,
+                b: 255
+            })
+        ]);
+    }
+
+    #[test]
+    fn load_unlayered_config() {
+        let layout = KeyLayout::default_layout();
+        let mut seq: AdvancedEffects = ron::from_str(
+            "(effects: [Static((led: F, colour: (r: 255, g: 127, b: 0)))], zoned: false)"
+        )
+        .unwrap();
+        assert!(seq.layers().is_empty());
+        assert_eq!(seq.output(), OutputStage::default());
+
+        seq.next_state(&layout);
+        let packets = seq.create_packets();
+        assert_eq!(packets[5][33..=35], [255, 127, 0]);
+
+        let layer: EffectLayer = ron::from_str(
+            "(name: \"top\", effects: [Static((led: F, colour: (r: 0, g: 0, b: 0)))])"
+        )
+        .unwrap();
+        assert_eq!(layer.opacity, 1.0);
+        assert_eq!(layer.blend, BlendMode::Normal);
+        assert_eq!(layer.mask, LedMask::All);
+    }
+
+    #[test]
+    fn audio_pulse_and_bars() {
+        let layout = KeyLayout::default_layout();
+        let mut seq = AdvancedEffects::new(false);
+        seq.push(Effect::AudioPulse(AudioPulse::new(
+            LedCode::F,
+            WHITE,
+            BLACK,
+            2
+        )));
+        let mut bars = EffectLayer::new("bars", BlendMode::Normal, 1.0);
+        bars.mask = LedMask::Leds(vec![
+            LedCode::Esc,
+            LedCode::LCtrl,
+            LedCode::Backspace,
+        ]);
+        bars.push_all_keys(&layout, |led| {
+            Effect::AudioBars(AudioBars::new(
+                led,
+                Colour { r: 0, g: 255, b: 0 },
+                Colour { r: 255, g: 0, b: 0 },
+                BLACK
+            ))
+        });
+        seq.push_layer(bars);
+        assert!(seq.wants_audio());
+        assert!(!seq.wants_key_presses());
+
+        // Loud bass, silent treble
+        seq.audio_levels(&[
+            1.0, 0.0, 0.0, 0.0
+        ]);
+        seq.next_state(&layout);
+        let packets = seq.create_packets();
+        assert_eq!(packets[5][33..=35], [255, 255, 255]);
+        let frame = seq.compose();
+        let colour = |led| frame.iter().find(|(l, _)| *l == led).unwrap().1;
+        // The left column is full, red at the top fading to green at the bottom
+        assert_eq!(colour(LedCode::Esc), Colour {
+            r: 230,
+            g: 25,
+            b: 0
+        });
+        assert_eq!(colour(LedCode::LCtrl), Colour { r: 0, g: 255, b: 0 });
+        // Right column is empty
+        assert_eq!(colour(LedCode::Backspace), BLACK);
+
+        seq.audio_levels(&[0.0; 4]);
+        seq.next_state(&layout);
+        let packets = seq.create_packets();
+        assert_eq!(packets[5][33..=35], [128, 128, 128]);
+    }
 }
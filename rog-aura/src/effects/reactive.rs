@@ -0,0 +1,174 @@
+//! Effects that respond to key presses fed in with
+//! `AdvancedEffects::key_pressed()`
+
+use serde::{Deserialize, Serialize};
+
+use super::{mix_colour, speed_step, EffectState};
+use crate::keyboard::{KeyLayout, LedCode};
+use crate::{effect_state_impl, Colour, Speed};
+
+/// Most ripples tracked at once, older ripples are dropped when fast typing
+/// would go over this
+const MAX_RIPPLES: usize = 8;
+
+/// The key lights up when pressed then fades back to the background
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct KeyPress {
+    led: LedCode,
+    press_colour: Colour,
+    background: Colour,
+    /// How many steps the key takes to fade out
+    fade_steps: u8,
+    #[serde(skip)]
+    brightness: f32,
+    #[serde(skip)]
+    colour: Colour
+}
+
+impl KeyPress {
+    pub fn new(address: LedCode, press_colour: Colour, background: Colour, fade_steps: u8) -> Self {
+        Self {
+            led: address,
+            press_colour,
+            background,
+            fade_steps,
+            brightness: 0.0,
+            colour: background
+        }
+    }
+}
+
+impl EffectState for KeyPress {
+    effect_state_impl!();
+
+    fn next_colour_state(&mut self, _layout: &KeyLayout) {
+        self.colour = mix_colour(&self.background, &self.press_colour, self.brightness);
+        self.brightness = (self.brightness - 1.0 / self.fade_steps.max(1) as f32).max(0.0);
+    }
+
+    fn key_pressed(&mut self, led: LedCode) {
+        if led == self.led {
+            self.brightness = 1.0;
+        }
+    }
+}
+
+/// A ring expands out from every pressed key
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct PressRipple {
+    led: LedCode,
+    ring_colour: Colour,
+    background: Colour,
+    speed: Speed,
+    /// Thickness of the ring in key widths
+    width: f32,
+    /// Origin and radius of each ripple
+    #[serde(skip)]
+    ripples: Vec<(LedCode, f32)>,
+    #[serde(skip)]
+    colour: Colour
+}
+
+impl PressRipple {
+    pub fn new(
+        address: LedCode,
+        ring_colour: Colour,
+        background: Colour,
+        speed: Speed,
+        width: f32
+    ) -> Self {
+        Self {
+            led: address,
+            ring_colour,
+            background,
+            speed,
+            width,
+            ripples: Vec::new(),
+            colour: background
+        }
+    }
+}
+
+impl EffectState for PressRipple {
+    effect_state_impl!();
+
+    fn next_colour_state(&mut self, layout: &KeyLayout) {
+        let step = speed_step(self.speed);
+        let max_radius = layout.max_width() + self.width;
+        for (_, radius) in &mut self.ripples {
+            *radius += step;
+        }
+        self.ripples.retain(|(_, radius)| *radius <= max_radius);
+
+        let mut amount: f32 = 0.0;
+        if let Some(pos) = layout.key_position(self.led) {
+            for (origin, radius) in &self.ripples {
+                if let Some(origin) = layout.key_position(*origin) {
+                    let offset = (pos.distance(&origin) - radius).abs();
+                    amount = amount.max(1.0 - offset / self.width.max(f32::EPSILON));
+                }
+            }
+        }
+        self.colour = mix_colour(&self.background, &self.ring_colour, amount);
+    }
+
+    fn key_pressed(&mut self, led: LedCode) {
+        if self.ripples.len() >= MAX_RIPPLES {
+            self.ripples.remove(0);
+        }
+        self.ripples.push((led, 0.0));
+    }
+}
+
+/// Keys warm up the more they are pressed, and slowly cool down
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Heatmap {
+    led: LedCode,
+    cold: Colour,
+    hot: Colour,
+    /// Number of presses to go from cold to fully hot
+    presses_to_max: u16,
+    /// Steps to cool from fully hot to cold, `0` to never cool
+    cool_steps: u32,
+    #[serde(skip)]
+    heat: f32,
+    #[serde(skip)]
+    colour: Colour
+}
+
+impl Heatmap {
+    pub fn new(
+        address: LedCode,
+        cold: Colour,
+        hot: Colour,
+        presses_to_max: u16,
+        cool_steps: u32
+    ) -> Self {
+        Self {
+            led: address,
+            cold,
+            hot,
+            presses_to_max,
+            cool_steps,
+            heat: 0.0,
+            colour: cold
+        }
+    }
+}
+
+impl EffectState for Heatmap {
+    effect_state_impl!();
+
+    fn next_colour_state(&mut self, _layout: &KeyLayout) {
+        if self.cool_steps != 0 {
+            self.heat = (self.heat - 1.0 / self.cool_steps as f32).max(0.0);
+        }
+        self.colour = mix_colour(&self.cold, &self.hot, self.heat);
+    }
+
+    fn key_pressed(&mut self, led: LedCode) {
+        if led == self.led {
+            self.heat = (self.heat + 1.0 / self.presses_to_max.max(1) as f32).min(1.0);
+        }
+    }
+}
@@ -0,0 +1,68 @@
+use serde::{Deserialize, Serialize};
+
+use super::{mix_colour, speed_step, EffectState};
+use crate::keyboard::{KeyLayout, LedCode};
+use crate::{effect_state_impl, Colour, Speed};
+
+/// A ring expanding out from the `origin` key, restarting once it has passed
+/// the furthest key
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Ripple {
+    led: LedCode,
+    /// The key the ripple starts from
+    origin: LedCode,
+    /// Colour of the ring
+    ring_colour: Colour,
+    background: Colour,
+    speed: Speed,
+    /// Thickness of the ring in key widths
+    width: f32,
+    #[serde(skip)]
+    radius: f32,
+    #[serde(skip)]
+    colour: Colour
+}
+
+impl Ripple {
+    pub fn new(
+        address: LedCode,
+        origin: LedCode,
+        ring_colour: Colour,
+        background: Colour,
+        speed: Speed,
+        width: f32
+    ) -> Self {
+        Self {
+            led: address,
+            origin,
+            ring_colour,
+            background,
+            speed,
+            width,
+            radius: 0.0,
+            colour: background
+        }
+    }
+}
+
+impl EffectState for Ripple {
+    effect_state_impl!();
+
+    fn next_colour_state(&mut self, layout: &KeyLayout) {
+        self.radius += speed_step(self.speed);
+        if self.radius > layout.max_width() + self.width {
+            self.radius = 0.0;
+        }
+
+        let (Some(pos), Some(origin)) = (
+            layout.key_position(self.led),
+            layout.key_position(self.origin)
+        ) else {
+            self.colour = self.background;
+            return;
+        };
+        let offset = (pos.distance(&origin) - self.radius).abs();
+        let amount = 1.0 - offset / self.width.max(f32::EPSILON);
+        self.colour = mix_colour(&self.background, &self.ring_colour, amount);
+    }
+}
@@ -0,0 +1,54 @@
+use serde::{Deserialize, Serialize};
+
+use super::{mix_colour, p_random, EffectState};
+use crate::keyboard::{KeyLayout, LedCode};
+use crate::{effect_state_impl, Colour};
+
+/// Keys randomly light up then fade out
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Starfield {
+    led: LedCode,
+    star_colour: Colour,
+    background: Colour,
+    /// Chance out of 255 of a key lighting up on each step
+    density: u8,
+    /// How many steps a star takes to fade out
+    fade_steps: u8,
+    #[serde(skip)]
+    brightness: f32,
+    #[serde(skip)]
+    colour: Colour
+}
+
+impl Starfield {
+    pub fn new(
+        address: LedCode,
+        star_colour: Colour,
+        background: Colour,
+        density: u8,
+        fade_steps: u8
+    ) -> Self {
+        Self {
+            led: address,
+            star_colour,
+            background,
+            density,
+            fade_steps,
+            brightness: 0.0,
+            colour: background
+        }
+    }
+}
+
+impl EffectState for Starfield {
+    effect_state_impl!();
+
+    fn next_colour_state(&mut self, _layout: &KeyLayout) {
+        self.brightness -= 1.0 / self.fade_steps.max(1) as f32;
+        if p_random() < self.density as i32 {
+            self.brightness = 1.0;
+        }
+        self.brightness = self.brightness.max(0.0);
+        self.colour = mix_colour(&self.background, &self.star_colour, self.brightness);
+    }
+}
@@ -0,0 +1,133 @@
+use std::f32::consts::PI;
+
+use serde::{Deserialize, Serialize};
+
+use super::{mix_colour, speed_step, EffectState};
+use crate::keyboard::{KeyLayout, KeyPosition, LedCode};
+use crate::{effect_state_impl, Colour, Direction, Speed};
+
+/// Distance along the direction of travel, and the total length of that axis
+fn along(direction: Direction, pos: &KeyPosition, layout: &KeyLayout) -> (f32, f32) {
+    match direction {
+        Direction::Right => (pos.x, layout.max_width()),
+        Direction::Left => (layout.max_width() - pos.x, layout.max_width()),
+        Direction::Down => (pos.y, layout.max_height()),
+        Direction::Up => (layout.max_height() - pos.y, layout.max_height())
+    }
+}
+
+/// A sine wave blending between two colours, moving across the keyboard
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Wave {
+    led: LedCode,
+    direction: Direction,
+    colour1: Colour,
+    colour2: Colour,
+    speed: Speed,
+    /// Length of one full cycle of the wave in key widths
+    wavelength: f32,
+    #[serde(skip)]
+    phase: f32,
+    #[serde(skip)]
+    colour: Colour
+}
+
+impl Wave {
+    pub fn new(
+        address: LedCode,
+        direction: Direction,
+        colour1: Colour,
+        colour2: Colour,
+        speed: Speed,
+        wavelength: f32
+    ) -> Self {
+        Self {
+            led: address,
+            direction,
+            colour1,
+            colour2,
+            speed,
+            wavelength,
+            phase: 0.0,
+            colour: colour1
+        }
+    }
+}
+
+impl EffectState for Wave {
+    effect_state_impl!();
+
+    fn next_colour_state(&mut self, layout: &KeyLayout) {
+        let wavelength = self.wavelength.max(f32::EPSILON);
+        self.phase = (self.phase + speed_step(self.speed)) % wavelength;
+        let Some(pos) = layout.key_position(self.led) else {
+            return;
+        };
+        let (distance, _) = along(self.direction, &pos, layout);
+        let angle = 2.0 * PI * (distance - self.phase) / wavelength;
+        self.colour = mix_colour(&self.colour1, &self.colour2, (angle.sin() + 1.0) / 2.0);
+    }
+}
+
+/// A bright head with a fading tail sweeping across the keyboard, a short tail
+/// makes a laser
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Comet {
+    led: LedCode,
+    direction: Direction,
+    head_colour: Colour,
+    background: Colour,
+    speed: Speed,
+    /// Length of the tail in key widths
+    tail: f32,
+    #[serde(skip)]
+    head: f32,
+    #[serde(skip)]
+    colour: Colour
+}
+
+impl Comet {
+    pub fn new(
+        address: LedCode,
+        direction: Direction,
+        head_colour: Colour,
+        background: Colour,
+        speed: Speed,
+        tail: f32
+    ) -> Self {
+        Self {
+            led: address,
+            direction,
+            head_colour,
+            background,
+            speed,
+            tail,
+            head: 0.0,
+            colour: background
+        }
+    }
+}
+
+impl EffectState for Comet {
+    effect_state_impl!();
+
+    fn next_colour_state(&mut self, layout: &KeyLayout) {
+        self.head += speed_step(self.speed);
+        let Some(pos) = layout.key_position(self.led) else {
+            return;
+        };
+        let (distance, length) = along(self.direction, &pos, layout);
+        // Let the tail leave the keyboard before starting again
+        if self.head > length + self.tail {
+            self.head = 0.0;
+        }
+
+        let behind = self.head - distance;
+        self.colour = if (0.0..=self.tail).contains(&behind) {
+            let amount = 1.0 - behind / self.tail.max(f32::EPSILON);
+            mix_colour(&self.background, &self.head_colour, amount)
+        } else {
+            self.background
+        };
+    }
+}
@@ -7,6 +7,7 @@ pub enum Error {
     ParseDirection,
     ParseBrightness,
     IoPath(String, std::io::Error),
+    Layout(String),
     Ron(ron::Error),
     RonParse(ron::error::SpannedError)
 }
@@ -20,6 +21,7 @@ impl fmt::Display for Error {
             Error::ParseDirection => write!(f, "Could not parse direction"),
             Error::ParseBrightness => write!(f, "Could not parse brightness"),
             Error::IoPath(path, io) => write!(f, "IO Error: {path}, {io}"),
+            Error::Layout(e) => write!(f, "Layout Error: {e}"),
             Error::Ron(e) => write!(f, "RON Parse Error: {e}"),
             Error::RonParse(e) => write!(f, "RON Parse Error: {e}")
         }
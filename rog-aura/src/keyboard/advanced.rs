@@ -9,7 +9,7 @@ use zbus::zvariant::Type;
 /// The `LedCode` used in setting up keyboard layouts is important because it
 /// determines the idexing for an RGB value in the final USB packets (for
 /// per-key addressable keyboards).
-#[derive(Debug, Default, PartialEq, Eq, Copy, Clone, Serialize, Deserialize)]
+#[derive(Debug, Default, PartialEq, Eq, Hash, Copy, Clone, Serialize, Deserialize)]
 pub enum LedCode {
     VolUp,
     VolDown,
@@ -88,6 +88,8 @@ pub enum LedCode {
     LShift3_1,
     LShift3_2,
     LShift3_3,
+    /// The extra key between left shift and Z on ISO keyboards
+    Iso102,
     Z,
     X,
     C,
@@ -386,6 +388,9 @@ impl LedUsbPackets {
             LedCode::LShift3_1 => (6, 36),
             LedCode::LShift3_2 => (6, 36),
             LedCode::LShift3_3 => (6, 36),
+            // TODO: confirm on an ISO keyboard, this is the gap in the matrix
+            // between LShift and Z
+            LedCode::Iso102 => (6, 39),
             LedCode::Z => (6, 42),
             LedCode::X => (6, 45),
             LedCode::C => (6, 48),
@@ -573,6 +578,7 @@ impl From<&LedCode> for &str {
             LedCode::LShift3_1 => "Left Shift LED 1",
             LedCode::LShift3_2 => "Left Shift LED 2",
             LedCode::LShift3_3 => "Left Shift LED 3",
+            LedCode::Iso102 => "ISO Extra",
             LedCode::Z => "Z",
             LedCode::X => "X",
             LedCode::C => "C",
@@ -0,0 +1,513 @@
+//! Checks and previews for the layout files in `data/layouts`, and skeletons
+//! to start a layout for a new model from.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::Path;
+
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::aura_detection::LedSupportData;
+use crate::error::Error;
+use crate::keyboard::{AdvancedAuraType, KeyLayout, KeyRect, KeyRow, KeyShape, LedCode};
+use crate::{AuraDeviceType, PowerZones};
+
+/// The widest a row can be before it is reported by `validate_layout()`, in
+/// key units. The lightbar of the G733 with a numpad is 24.3 wide.
+pub const MAX_CHASSIS_WIDTH: f32 = 26.0;
+/// Preview characters per key unit across
+const ASCII_X_SCALE: f32 = 5.0;
+/// Preview lines per key unit down
+const ASCII_Y_SCALE: f32 = 2.0;
+/// SVG pixels per key unit
+const SVG_SCALE: f32 = 40.0;
+/// The numpads have no LEDs of their own for these, so their keys light the
+/// LED of the matching key in the number row
+const NUMPAD_SHARED_LEDS: [LedCode; 12] = [
+    LedCode::N0,
+    LedCode::N1,
+    LedCode::N2,
+    LedCode::N3,
+    LedCode::N4,
+    LedCode::N5,
+    LedCode::N6,
+    LedCode::N7,
+    LedCode::N8,
+    LedCode::N9,
+    LedCode::Hyphen,
+    LedCode::FwdSlash
+];
+
+/// A problem found in a layout file
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub enum LayoutIssue {
+    /// The key name is not a `LedCode`
+    UnknownLed { row: usize, name: String },
+    /// More than one key uses the LED, only one of them can be lit
+    DuplicateLed { row: usize, led: LedCode },
+    /// The key uses a shape that isn't in `key_shapes`
+    MissingShape { row: usize, shape: String },
+    /// Nothing uses the shape
+    UnusedShape(String),
+    /// The row is wider than the chassis, in key units
+    RowTooWide { row: usize, width: f32 },
+    /// An `aura_support.ron` entry names a layout that has no file
+    MissingLayout {
+        device_name: String,
+        layout_name: String
+    }
+}
+
+impl LayoutIssue {
+    /// Errors break the layout, the rest are only untidy
+    pub fn is_error(&self) -> bool {
+        !matches!(self, Self::UnusedShape(_))
+    }
+}
+
+impl fmt::Display for LayoutIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownLed { row, name } => write!(f, "row {row}: unknown key {name}"),
+            Self::DuplicateLed { row, led } => write!(f, "row {row}: {led:?} is already used"),
+            Self::MissingShape { row, shape } => write!(f, "row {row}: no shape named {shape}"),
+            Self::UnusedShape(shape) => write!(f, "shape {shape} is not used"),
+            Self::RowTooWide { row, width } => write!(
+                f,
+                "row {row}: {width:.1} keys wide is wider than the chassis"
+            ),
+            Self::MissingLayout {
+                device_name,
+                layout_name
+            } => write!(f, "{device_name}: no layout file for {layout_name}")
+        }
+    }
+}
+
+/// The width and height covered by the keys
+fn extent(rects: &[(LedCode, KeyRect)]) -> (f32, f32) {
+    rects.iter().fold((0.0, 0.0), |(w, h), (_, r)| {
+        (w.max(r.x + r.width), h.max(r.y + r.height))
+    })
+}
+
+/// A key name as written in the file, which may not be a valid `LedCode`
+struct RawLed(String);
+
+impl<'de> Deserialize<'de> for RawLed {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Visitor;
+        impl serde::de::Visitor<'_> for Visitor {
+            type Value = RawLed;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "a key name")
+            }
+
+            fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<RawLed, E> {
+                Ok(RawLed(v.to_owned()))
+            }
+        }
+        deserializer.deserialize_identifier(Visitor)
+    }
+}
+
+#[derive(Deserialize)]
+struct RawRow {
+    pad_left: f32,
+    row: Vec<(RawLed, String)>
+}
+
+/// Only what is needed to check a layout, so that one bad key name doesn't
+/// stop the rest of the file being checked
+#[derive(Deserialize)]
+struct RawLayout {
+    key_shapes: HashMap<String, KeyShape>,
+    key_rows: Vec<RawRow>
+}
+
+/// Whether a second key lighting `led` is how the hardware is, rather than a
+/// mistake in the file. Some keys are split across a row to cover the gaps
+/// between LEDs, such as the wide right Alt of the GL503 or the two PrtSc
+/// positions of the G513, so repeats within the row the LED was first used in
+/// are expected, as are numpad keys reusing the number row.
+fn is_expected_repeat(led: LedCode, first_row: usize, row: usize) -> bool {
+    first_row == row || NUMPAD_SHARED_LEDS.contains(&led)
+}
+
+/// Check the text of a layout file. Rows are counted from 0. Returns an error
+/// only if the file can't be parsed at all.
+pub fn validate_layout(ron_text: &str, max_width: f32) -> Result<Vec<LayoutIssue>, Error> {
+    let layout: RawLayout = ron::from_str(ron_text)?;
+    let mut issues = Vec::new();
+    // The row each LED is first used in
+    let mut used_leds = HashMap::new();
+    let mut used_shapes = HashSet::new();
+
+    for (row_num, row) in layout.key_rows.iter().enumerate() {
+        let mut width = row.pad_left;
+        for (led, shape_name) in &row.row {
+            let led = match ron::from_str::<LedCode>(&led.0) {
+                Ok(led) => Some(led),
+                Err(_) => {
+                    issues.push(LayoutIssue::UnknownLed {
+                        row: row_num,
+                        name: led.0.clone()
+                    });
+                    None
+                }
+            };
+            let Some(shape) = layout.key_shapes.get(shape_name) else {
+                issues.push(LayoutIssue::MissingShape {
+                    row: row_num,
+                    shape: shape_name.clone()
+                });
+                continue;
+            };
+            used_shapes.insert(shape_name);
+
+            match shape {
+                KeyShape::Led {
+                    width: w,
+                    pad_left,
+                    pad_right,
+                    ..
+                } => {
+                    width += w + pad_left + pad_right;
+                    // Blanks and placeholders are spacing so can repeat
+                    if let Some(led) = led.filter(|l| !l.is_placeholder()) {
+                        match used_leds.get(&led) {
+                            Some(&first_row) if !is_expected_repeat(led, first_row, row_num) => {
+                                issues.push(LayoutIssue::DuplicateLed { row: row_num, led });
+                            }
+                            Some(_) => {}
+                            None => {
+                                used_leds.insert(led, row_num);
+                            }
+                        }
+                    }
+                }
+                KeyShape::Blank { width: w, .. } => width += w
+            }
+        }
+        if width > max_width {
+            issues.push(LayoutIssue::RowTooWide {
+                row: row_num,
+                width
+            });
+        }
+    }
+
+    let mut unused: Vec<_> = layout
+        .key_shapes
+        .keys()
+        .filter(|s| !used_shapes.contains(s))
+        .cloned()
+        .collect();
+    unused.sort();
+    issues.extend(unused.into_iter().map(LayoutIssue::UnusedShape));
+    Ok(issues)
+}
+
+/// Check that every layout named in `aura_support.ron` has a file in
+/// `layouts_dir`
+pub fn validate_support_links(support: &[LedSupportData], layouts_dir: &Path) -> Vec<LayoutIssue> {
+    support
+        .iter()
+        .filter(|d| !d.layout_name.is_empty())
+        .filter(|d| {
+            !layouts_dir
+                .join(format!("{}_US.ron", d.layout_name))
+                .exists()
+        })
+        .map(|d| LayoutIssue::MissingLayout {
+            device_name: d.device_name.clone(),
+            layout_name: d.layout_name.clone()
+        })
+        .collect()
+}
+
+impl KeyLayout {
+    /// A text drawing of the layout with each LED labelled. Labels are cut
+    /// short to fit the key.
+    pub fn preview_ascii(&self) -> String {
+        let rects = self.key_rects();
+        let (width, height) = extent(&rects);
+        let width = (width * ASCII_X_SCALE).ceil() as usize + 1;
+        let height = (height * ASCII_Y_SCALE).ceil() as usize + 1;
+        let mut canvas = vec![vec![' '; width]; height];
+
+        for (led, rect) in rects.iter().filter(|(l, _)| !l.is_placeholder()) {
+            let x0 = (rect.x * ASCII_X_SCALE).round() as usize;
+            let x1 = ((rect.x + rect.width) * ASCII_X_SCALE).round() as usize;
+            let x1 = x1.saturating_sub(1).max(x0 + 1).min(width - 1);
+            let y0 = (rect.y * ASCII_Y_SCALE).round() as usize;
+            let y1 = ((rect.y + rect.height) * ASCII_Y_SCALE).round() as usize;
+            let y1 = y1.saturating_sub(1).max(y0).min(height - 1);
+
+            for line in canvas.iter_mut().take(y1 + 1).skip(y0) {
+                line[x0] = '[';
+                line[x1] = ']';
+            }
+            let label = format!("{led:?}");
+            for (i, c) in label.chars().take(x1 - x0 - 1).enumerate() {
+                canvas[y0][x0 + 1 + i] = c;
+            }
+        }
+
+        let mut out = String::new();
+        for line in canvas {
+            let line: String = line.into_iter().collect();
+            out.push_str(line.trim_end());
+            out.push('\n');
+        }
+        out.trim_end_matches('\n').to_owned() + "\n"
+    }
+
+    /// An SVG drawing of the layout with each LED labelled
+    pub fn preview_svg(&self) -> String {
+        let rects = self.key_rects();
+        let (width, height) = extent(&rects);
+        // Leave a margin the same as the top left
+        let width = (width + 0.2) * SVG_SCALE;
+        let height = (height + 0.2) * SVG_SCALE;
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width:.0}\" \
+             height=\"{height:.0}\" viewBox=\"0 0 {width:.0} {height:.0}\">\n"
+        );
+        svg.push_str(&format!(
+            "  <rect width=\"{width:.0}\" height=\"{height:.0}\" fill=\"#202020\"/>\n"
+        ));
+        for (led, rect) in rects.iter().filter(|(l, _)| !l.is_placeholder()) {
+            let centre = rect.centre();
+            svg.push_str(&format!(
+                "  <rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\" rx=\"4\" \
+                 fill=\"#404040\" stroke=\"#a0a0a0\"/>\n",
+                rect.x * SVG_SCALE,
+                rect.y * SVG_SCALE,
+                rect.width * SVG_SCALE,
+                rect.height * SVG_SCALE
+            ));
+            svg.push_str(&format!(
+                "  <text x=\"{:.1}\" y=\"{:.1}\" font-size=\"10\" font-family=\"sans-serif\" \
+                 fill=\"#ffffff\" text-anchor=\"middle\" \
+                 dominant-baseline=\"middle\">{led:?}</text>\n",
+                centre.x * SVG_SCALE,
+                centre.y * SVG_SCALE
+            ));
+        }
+        svg.push_str("</svg>\n");
+        svg
+    }
+
+    /// A layout to start from for a new model. The LED type comes from the
+    /// `aura_support.ron` entry with this product ID if there is one, or else
+    /// from the product ID alone. Per-key keyboards get a plain US layout to
+    /// be moved and resized to match the laptop.
+    pub fn skeleton(support: &[LedSupportData], product_id: &str) -> Result<Self, Error> {
+        let id = product_id.to_lowercase();
+        let id = id.trim_start_matches("0x");
+        let entry = support.iter().find(|d| d.product_id.to_lowercase() == id);
+
+        let advanced_type = match entry {
+            Some(entry) => entry.advanced_type.clone(),
+            None => match AuraDeviceType::from(id) {
+                AuraDeviceType::LaptopKeyboard2021 | AuraDeviceType::LaptopKeyboardPre2021 => {
+                    AdvancedAuraType::PerKey
+                }
+                AuraDeviceType::LaptopKeyboardTuf => AdvancedAuraType::None,
+                _ => {
+                    return Err(Error::Layout(format!(
+                        "{product_id} is not a known keyboard"
+                    )))
+                }
+            }
+        };
+        let lightbar = entry.is_some_and(|d| {
+            d.power_zones
+                .iter()
+                .any(|z| matches!(z, PowerZones::Lightbar | PowerZones::KeyboardAndLightbar))
+        });
+
+        let mut key_shapes = HashMap::new();
+        let mut key_rows = if let AdvancedAuraType::Zoned(zones) = &advanced_type {
+            key_shapes.insert(
+                "zone".to_owned(),
+                KeyShape::new_led(4.0, 5.0, 0.1, 0.1, 0.1, 0.1)
+            );
+            vec![
+                KeyRow::new(
+                    0.1,
+                    0.1,
+                    zones.iter().map(|z| (*z, "zone".to_owned())).collect()
+                ),
+            ]
+        } else {
+            key_shapes.insert(
+                "regular".to_owned(),
+                KeyShape::new_led(1.0, 1.0, 0.1, 0.1, 0.1, 0.1)
+            );
+            KeyLayout::default_layout().rows_ref().to_vec()
+        };
+        if lightbar {
+            key_shapes.insert(
+                "lightbar".to_owned(),
+                KeyShape::new_led(7.0, 0.3, 0.1, 0.1, 0.5, 0.1)
+            );
+            key_rows.push(KeyRow::new(0.1, 0.1, vec![
+                (LedCode::LightbarLeft, "lightbar".to_owned()),
+                (LedCode::LightbarRight, "lightbar".to_owned()),
+            ]));
+        }
+
+        Ok(KeyLayout::new("US", key_shapes, key_rows))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::{validate_layout, validate_support_links, LayoutIssue, MAX_CHASSIS_WIDTH};
+    use crate::aura_detection::{LedSupportData, LedSupportFile};
+    use crate::keyboard::{AdvancedAuraType, KeyLayout, LedCode};
+
+    #[test]
+    fn shipped_layouts_are_valid() {
+        let mut data = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        data.push("data");
+        let support: LedSupportFile =
+            ron::from_str(&std::fs::read_to_string(data.join("aura_support.ron")).unwrap())
+                .unwrap();
+        let layouts = data.join("layouts");
+        assert!(validate_support_links(support.get(), &layouts).is_empty());
+
+        for file in std::fs::read_dir(&layouts).unwrap() {
+            let path = file.unwrap().path();
+            let issues =
+                validate_layout(&std::fs::read_to_string(&path).unwrap(), MAX_CHASSIS_WIDTH)
+                    .unwrap();
+            let errors: Vec<_> = issues.iter().filter(|i| i.is_error()).collect();
+            assert!(errors.is_empty(), "{path:?}: {errors:?}");
+        }
+    }
+
+    #[test]
+    fn find_layout_issues() {
+        let issues = validate_layout(
+            r#"(
+                locale: "US",
+                key_shapes: {
+                    "regular": Led(width: 1.0, height: 1.0, pad_left: 0.1, pad_right: 0.1, pad_top: 0.1, pad_bottom: 0.1),
+                    "wide": Led(width: 6.0, height: 1.0, pad_left: 0.1, pad_right: 0.1, pad_top: 0.1, pad_bottom: 0.1),
+                    "gap": Blank(width: 1.0, height: 1.0),
+                    "unused": Blank(width: 1.0, height: 1.0),
+                },
+                key_rows: [
+                    (pad_left: 0.1, pad_top: 0.1, row: [(Esc, "regular"), (Esc, "gap"), (Spacing, "regular"), (Spacing, "regular")]),
+                    (pad_left: 0.1, pad_top: 0.1, row: [(Esc, "regular"), (Escape, "regular"), (F1, "round")]),
+                    (pad_left: 0.1, pad_top: 0.1, row: [(F2, "wide"), (F3, "wide")]),
+                ],
+            )"#,
+            10.0
+        )
+        .unwrap();
+        assert_eq!(issues, vec![
+            LayoutIssue::DuplicateLed {
+                row: 1,
+                led: LedCode::Esc
+            },
+            LayoutIssue::UnknownLed {
+                row: 1,
+                name: "Escape".to_owned()
+            },
+            LayoutIssue::MissingShape {
+                row: 1,
+                shape: "round".to_owned()
+            },
+            LayoutIssue::RowTooWide {
+                row: 2,
+                width: 12.5
+            },
+            LayoutIssue::UnusedShape("unused".to_owned()),
+        ]);
+        assert!(!issues[4].is_error());
+
+        // Split keys and numpads reusing the number row are how the hardware
+        // is, but other keys repeated in a later row are not
+        let issues = validate_layout(
+            r#"(
+                locale: "US",
+                key_shapes: {
+                    "regular": Led(width: 1.0, height: 1.0, pad_left: 0.1, pad_right: 0.1, pad_top: 0.1, pad_bottom: 0.1),
+                },
+                key_rows: [
+                    (pad_left: 0.1, pad_top: 0.1, row: [(N1, "regular"), (Hyphen, "regular"), (PrtSc, "regular")]),
+                    (pad_left: 0.1, pad_top: 0.1, row: [(RAlt, "regular"), (RAlt, "regular"), (N1, "regular"), (Hyphen, "regular")]),
+                    (pad_left: 0.1, pad_top: 0.1, row: [(PrtSc, "regular")]),
+                ],
+            )"#,
+            10.0
+        )
+        .unwrap();
+        assert_eq!(issues, vec![
+            LayoutIssue::DuplicateLed {
+                row: 2,
+                led: LedCode::PrtSc
+            }
+        ]);
+
+        assert!(validate_layout("(key_rows: [", 10.0).is_err());
+
+        let support = vec![
+            LedSupportData {
+                device_name: "GX000".to_owned(),
+                layout_name: "gx000".to_owned(),
+                ..Default::default()
+            },
+        ];
+        let issues = validate_support_links(&support, &PathBuf::from("/nonexistent"));
+        assert_eq!(issues.len(), 1);
+    }
+
+    #[test]
+    fn skeleton_and_preview() {
+        let layout = KeyLayout::skeleton(&[], "0x19B6").unwrap();
+        assert_eq!(layout.key_positions().len(), 74);
+        // The skeleton is a valid layout file
+        let text = ron::to_string(&layout).unwrap();
+        let issues = validate_layout(&text, MAX_CHASSIS_WIDTH).unwrap();
+        assert!(issues.is_empty(), "{issues:?}");
+
+        let zoned = [
+            LedSupportData {
+                product_id: "1a30".to_owned(),
+                advanced_type: AdvancedAuraType::Zoned(vec![
+                    LedCode::ZonedKbLeft,
+                    LedCode::ZonedKbRight,
+                ]),
+                power_zones: vec![crate::PowerZones::Lightbar],
+                ..Default::default()
+            }
+        ];
+        let layout = KeyLayout::skeleton(&zoned, "1A30").unwrap();
+        let leds: Vec<_> = layout.key_rects().into_iter().map(|(l, _)| l).collect();
+        assert_eq!(leds, vec![
+            LedCode::ZonedKbLeft,
+            LedCode::ZonedKbRight,
+            LedCode::LightbarLeft,
+            LedCode::LightbarRight
+        ]);
+        assert!(KeyLayout::skeleton(&[], "1abe").is_err());
+
+        let preview = KeyLayout::default_layout().preview_ascii();
+        let lines: Vec<_> = preview.lines().collect();
+        assert_eq!(lines.len(), 15);
+        assert!(lines[0].starts_with(" [Esc] [F1 ] [F2 ] [F3 ] "));
+        assert!(lines[1].starts_with(" [   ] [   ] [   ] [   ] "));
+
+        let svg = KeyLayout::default_layout().preview_svg();
+        assert!(svg.starts_with("<svg"));
+        assert_eq!(svg.matches(">Esc</text>").count(), 1);
+    }
+}
@@ -13,6 +13,146 @@ use crate::error::Error;
 use crate::keyboard::{AdvancedAuraType, LedCode};
 use crate::{AuraModeNum, AuraZone};
 
+/// Files that may hold the system XKB layout, and the setting in each
+const XKB_CONFIGS: [(&str, &str); 3] = [
+    ("/etc/X11/xorg.conf.d/00-keyboard.conf", "\"XkbLayout\""),
+    ("/etc/default/keyboard", "XKBLAYOUT="),
+    ("/etc/vconsole.conf", "XKBLAYOUT=")
+];
+
+/// The locale of a keyboard layout. All but US are ISO layouts, with an extra
+/// key beside left shift and a tall return.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum LayoutLocale {
+    #[default]
+    US,
+    UK,
+    DE,
+    FR,
+    /// Swedish, Finnish, Norwegian and Danish
+    Nordic
+}
+
+impl LayoutLocale {
+    pub const ALL: [Self; 5] = [
+        Self::US,
+        Self::UK,
+        Self::DE,
+        Self::FR,
+        Self::Nordic
+    ];
+
+    pub fn is_iso(self) -> bool {
+        self != Self::US
+    }
+
+    /// Match an XKB layout such as `gb` or `de(nodeadkeys)`. Only the first
+    /// of a list is used as it is the default. Unknown layouts are US.
+    pub fn from_xkb(layout: &str) -> Self {
+        let name = layout
+            .split([',', '('])
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_lowercase();
+        match name.as_str() {
+            "gb" | "uk" | "ie" => Self::UK,
+            "de" | "at" | "ch" => Self::DE,
+            "fr" | "be" => Self::FR,
+            "se" | "fi" | "no" | "dk" => Self::Nordic,
+            _ => Self::US
+        }
+    }
+
+    /// The system keyboard layout from `XKB_DEFAULT_LAYOUT` or the config
+    /// files written by `localectl`, or US if none are set
+    pub fn detect() -> Self {
+        if let Ok(layout) = std::env::var("XKB_DEFAULT_LAYOUT") {
+            if !layout.is_empty() {
+                return Self::from_xkb(&layout);
+            }
+        }
+        for (path, setting) in XKB_CONFIGS {
+            if let Some(layout) = std::fs::read_to_string(path)
+                .ok()
+                .and_then(|text| xkb_layout_setting(&text, setting))
+            {
+                return Self::from_xkb(&layout);
+            }
+        }
+        Self::US
+    }
+
+    /// Keys with a different legend to US
+    fn legends(self) -> &'static [(LedCode, &'static str)] {
+        match self {
+            Self::US => &[],
+            Self::UK => &[
+                (LedCode::Tilde, "`"),
+                (LedCode::N2, "2 \""),
+                (LedCode::N3, "3 £"),
+                (LedCode::Quote, "' @"),
+                (LedCode::BackSlash, "# ~"),
+                (LedCode::Iso102, "\\ |")
+            ],
+            Self::DE => &[
+                (LedCode::Tilde, "^"),
+                (LedCode::Hyphen, "ß"),
+                (LedCode::Equals, "´"),
+                (LedCode::Y, "Z"),
+                (LedCode::LBracket, "Ü"),
+                (LedCode::RBracket, "+"),
+                (LedCode::SemiColon, "Ö"),
+                (LedCode::Quote, "Ä"),
+                (LedCode::BackSlash, "#"),
+                (LedCode::Iso102, "<"),
+                (LedCode::Z, "Y"),
+                (LedCode::FwdSlash, "-")
+            ],
+            Self::FR => &[
+                (LedCode::Tilde, "²"),
+                (LedCode::Hyphen, ")"),
+                (LedCode::Q, "A"),
+                (LedCode::W, "Z"),
+                (LedCode::LBracket, "^"),
+                (LedCode::RBracket, "$"),
+                (LedCode::A, "Q"),
+                (LedCode::SemiColon, "M"),
+                (LedCode::Quote, "ù"),
+                (LedCode::BackSlash, "*"),
+                (LedCode::Iso102, "<"),
+                (LedCode::Z, "W"),
+                (LedCode::M, ","),
+                (LedCode::Comma, ";"),
+                (LedCode::Period, ":"),
+                (LedCode::FwdSlash, "!")
+            ],
+            Self::Nordic => &[
+                (LedCode::Tilde, "§"),
+                (LedCode::Hyphen, "+"),
+                (LedCode::Equals, "´"),
+                (LedCode::LBracket, "Å"),
+                (LedCode::RBracket, "¨"),
+                (LedCode::SemiColon, "Ö"),
+                (LedCode::Quote, "Ä"),
+                (LedCode::BackSlash, "'"),
+                (LedCode::Iso102, "<"),
+                (LedCode::FwdSlash, "-")
+            ]
+        }
+    }
+}
+
+/// The layout from a line such as `XKBLAYOUT="gb"` or
+/// `Option "XkbLayout" "gb"`
+fn xkb_layout_setting(text: &str, setting: &str) -> Option<String> {
+    text.lines()
+        .filter(|l| !l.trim_start().starts_with('#'))
+        .find_map(|l| l.split_once(setting))
+        .map(|(_, value)| value.trim().trim_matches('"').to_owned())
+        .filter(|v| !v.is_empty())
+}
+
 /// The `key_type` plays a role in effects (eventually). You could for example
 /// add a `ShapeType::Spacing` to pad out an effect, such as a laserbeam across
 /// a row so that it doesn't appear to *jump* across a gap
@@ -83,6 +223,37 @@ impl KeyShape {
     }
 }
 
+/// Centre of a key in key units, see `KeyLayout::key_positions()`
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct KeyPosition {
+    pub x: f32,
+    pub y: f32
+}
+
+impl KeyPosition {
+    pub fn distance(&self, other: &KeyPosition) -> f32 {
+        ((self.x - other.x).powi(2) + (self.y - other.y).powi(2)).sqrt()
+    }
+}
+
+/// The area of a key in key units, see `KeyLayout::key_rects()`
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct KeyRect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32
+}
+
+impl KeyRect {
+    pub fn centre(&self) -> KeyPosition {
+        KeyPosition {
+            x: self.x + self.width / 2.0,
+            y: self.y + self.height / 2.0
+        }
+    }
+}
+
 /// The first `Key` will determine the row height.
 ///
 /// Every row is considered to start a x=0, with the first row being y=0,
@@ -110,6 +281,11 @@ impl KeyRow {
         }
     }
 
+    /// True if the LED is in this row
+    pub fn has(&self, led: LedCode) -> bool {
+        self.row.iter().any(|k| k.0 == led)
+    }
+
     pub fn row(&self) -> Iter<'_, (LedCode, KeyShape)> {
         self.built_row.iter()
     }
@@ -167,6 +343,9 @@ impl KeyRow {
 pub struct KeyLayout {
     /// Localization of this keyboard layout
     locale: String,
+    /// Labels of keys that differ from the US legend, see `key_label()`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    legends: Vec<(LedCode, String)>,
     /// The shapes of keys used
     key_shapes: HashMap<String, KeyShape>,
     /// The rows of keys of this layout
@@ -185,11 +364,34 @@ pub struct KeyLayout {
     /// Should be copied from the `LaptopLedData` as laptops may have the same
     /// layout, but different EC features.
     #[serde(skip)]
-    advanced_type: AdvancedAuraType
+    advanced_type: AdvancedAuraType,
+    /// The centre of each LED, built with the rows for effects to look up
+    /// every frame
+    #[serde(skip)]
+    positions: HashMap<LedCode, KeyPosition>
 }
 
 impl KeyLayout {
-    fn from_file(path: &Path) -> Result<Self, Error> {
+    /// Create a layout from shapes and rows, as in a layout file
+    pub fn new(locale: &str, key_shapes: HashMap<String, KeyShape>, key_rows: Vec<KeyRow>) -> Self {
+        let mut layout = Self {
+            locale: locale.to_owned(),
+            legends: Vec::new(),
+            key_shapes,
+            key_rows,
+            basic_modes: Vec::new(),
+            basic_zones: Vec::new(),
+            advanced_type: AdvancedAuraType::None,
+            positions: HashMap::new()
+        };
+        let unused = layout.build_rows();
+        if !unused.is_empty() {
+            warn!("The new layout had unused shapes {unused:?}");
+        }
+        layout
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self, Error> {
         let buf: String = std::fs::read_to_string(path)
             .map_err(|e| Error::IoPath(path.to_string_lossy().to_string(), e))?;
         if buf.is_empty() {
@@ -199,30 +401,88 @@ impl KeyLayout {
             ))
         } else {
             let mut data = ron::from_str::<Self>(&buf)?;
+            let unused = data.build_rows();
+            if !unused.is_empty() {
+                warn!("The layout {path:?} had unused shapes {unused:?}",);
+            }
 
-            let mut unused = HashSet::new();
-            for k in data.key_shapes.keys() {
-                unused.insert(k);
+            Ok(data)
+        }
+    }
+
+    /// Merge the `key_shapes` in to each row and find the key positions.
+    /// Returns the names of any shapes that were not used.
+    fn build_rows(&mut self) -> HashSet<String> {
+        let mut unused: HashSet<String> = self.key_shapes.keys().cloned().collect();
+        for row in &mut self.key_rows {
+            row.built_row.clear();
+            for k in &row.row {
+                if let Some(shape) = self.key_shapes.get(&k.1) {
+                    row.built_row.push((k.0, shape.clone()));
+                    unused.remove(&k.1);
+                } else {
+                    warn!("Key {:?} was missing matching shape {}", k.0, k.1);
+                }
             }
+        }
+        self.positions.clear();
+        for (led, pos) in self.key_positions() {
+            self.positions.entry(led).or_insert(pos);
+        }
+        unused
+    }
 
-            let rows = &mut data.key_rows;
-            for row in rows {
-                for k in &mut row.row {
-                    if let Some(shape) = data.key_shapes.get(&k.1) {
-                        row.built_row.push((k.0, shape.clone()));
-                        unused.remove(&k.1);
-                    } else {
-                        warn!("Key {:?} was missing matching shape {}", k.0, k.1);
+    /// The area of every LED in the layout, in key units with x=0, y=0 at the
+    /// top left. The area excludes the key padding.
+    pub fn key_rects(&self) -> Vec<(LedCode, KeyRect)> {
+        let mut rects = Vec::new();
+        let mut y = 0.0;
+        for row in &self.key_rows {
+            y += row.pad_top;
+            let mut x = row.pad_left;
+            for (led, shape) in row.row() {
+                match shape {
+                    KeyShape::Led {
+                        width,
+                        height,
+                        pad_left,
+                        pad_right,
+                        pad_top,
+                        ..
+                    } => {
+                        rects.push((*led, KeyRect {
+                            x: x + pad_left,
+                            y: y + pad_top,
+                            width: *width,
+                            height: *height
+                        }));
+                        x += pad_left + width + pad_right;
                     }
+                    KeyShape::Blank { width, .. } => x += width
                 }
             }
+            y += row.height();
+        }
+        rects
+    }
 
-            if !unused.is_empty() {
-                warn!("The layout {path:?} had unused shapes {unused:?}",);
-            }
+    /// The centre of every LED in the layout, in key units with x=0, y=0 at the
+    /// top left. `KeyShape::Blank` and padding take up space, so an effect
+    /// moving across a row crosses gaps at the same speed as keys.
+    pub fn key_positions(&self) -> Vec<(LedCode, KeyPosition)> {
+        self.key_rects()
+            .into_iter()
+            .map(|(led, rect)| (led, rect.centre()))
+            .collect()
+    }
 
-            Ok(data)
-        }
+    /// The centre of the LED, or `None` if it isn't in this layout
+    pub fn key_position(&self, led: LedCode) -> Option<KeyPosition> {
+        self.positions.get(&led).copied()
+    }
+
+    pub fn locale(&self) -> &str {
+        &self.locale
     }
 
     pub fn rows(&self) -> Iter<'_, KeyRow> {
@@ -277,21 +537,28 @@ impl KeyLayout {
         width
     }
 
-    /// Find a layout matching the name in `LaptopLedData` in the provided dir
-    pub fn find_layout(led_data: LedSupportData, mut data_path: PathBuf) -> Result<Self, Error> {
-        // TODO: locales
+    /// Find a layout matching the name in `LaptopLedData` in the provided dir.
+    /// A layout file for the locale is used if there is one, otherwise the US
+    /// layout is localised with `localised()`.
+    pub fn find_layout(
+        led_data: LedSupportData,
+        mut data_path: PathBuf,
+        locale: LayoutLocale
+    ) -> Result<Self, Error> {
         let layout_name = if led_data.layout_name.is_empty() {
             "ga401q".to_owned() // Need some sort of default here due to ROGCC
                                 // expecting it
         } else {
             led_data.layout_name
         };
-        let layout_file = format!("{layout_name}_US.ron");
         data_path.push("layouts");
-        data_path.push(layout_file);
-        let path = data_path.as_path();
-
-        let mut tmp = KeyLayout::from_file(path)?;
+        let locale_file = data_path.join(format!("{layout_name}_{locale:?}.ron"));
+        let mut tmp = if locale != LayoutLocale::US && locale_file.exists() {
+            KeyLayout::from_file(&locale_file)?
+        } else {
+            data_path.push(format!("{layout_name}_US.ron"));
+            KeyLayout::from_file(&data_path)?.localised(locale)
+        };
         tmp.basic_modes = led_data.basic_modes;
         tmp.basic_zones = led_data.basic_zones;
         tmp.advanced_type = led_data.advanced_type;
@@ -299,6 +566,142 @@ impl KeyLayout {
         Ok(tmp)
     }
 
+    /// The label on the key, which depends on the locale
+    pub fn key_label(&self, led: LedCode) -> &str {
+        self.legends
+            .iter()
+            .find(|(l, _)| *l == led)
+            .map_or_else(|| <&str>::from(led), |(_, s)| s.as_str())
+    }
+
+    /// Derive the layout for another locale from a US layout. ISO locales get
+    /// the extra key beside left shift, taking space from left shift, and a
+    /// tall return with backslash moved beside it. Layouts without these keys,
+    /// such as zoned keyboards, are left as they are.
+    pub fn localised(&self, locale: LayoutLocale) -> Self {
+        let mut layout = self.clone();
+        if locale == LayoutLocale::US {
+            return layout;
+        }
+        layout.locale = format!("{locale:?}");
+        if locale.is_iso() && !layout.key_rows.iter().any(|r| r.has(LedCode::Iso102)) {
+            layout.add_iso_key();
+            layout.add_iso_return();
+        }
+        layout.legends = locale
+            .legends()
+            .iter()
+            .map(|(led, s)| (*led, (*s).to_owned()))
+            .collect();
+        layout.build_rows();
+        layout
+    }
+
+    /// The total width of the named shape including padding
+    fn shape_width(&self, name: &str) -> Option<f32> {
+        self.key_shapes.get(name).map(|shape| match shape {
+            KeyShape::Led {
+                width,
+                pad_left,
+                pad_right,
+                ..
+            } => width + pad_left + pad_right,
+            KeyShape::Blank { width, .. } => *width
+        })
+    }
+
+    /// Add a copy of the named `KeyShape::Led` with `width` changed
+    fn add_resized_shape(&mut self, name: &str, new_name: &str, change: f32) -> bool {
+        let Some(KeyShape::Led {
+            width,
+            height,
+            pad_left,
+            pad_right,
+            pad_top,
+            pad_bottom
+        }) = self.key_shapes.get(name).cloned()
+        else {
+            return false;
+        };
+        if width + change < 0.5 {
+            return false;
+        }
+        self.key_shapes.insert(
+            new_name.to_owned(),
+            KeyShape::new_led(
+                width + change,
+                height,
+                pad_left,
+                pad_right,
+                pad_top,
+                pad_bottom
+            )
+        );
+        true
+    }
+
+    fn add_iso_key(&mut self) {
+        let Some(row) = self.key_rows.iter().position(|r| r.has(LedCode::LShift)) else {
+            return;
+        };
+        let keys = &self.key_rows[row].row;
+        let shift = keys
+            .iter()
+            .position(|k| k.0 == LedCode::LShift)
+            .unwrap_or_default();
+        // Same size as Z
+        let Some(extra) = keys.iter().find(|k| k.0 == LedCode::Z).map(|k| k.1.clone()) else {
+            return;
+        };
+        let shift_shape = keys[shift].1.clone();
+        let change = self.shape_width(&extra).unwrap_or_default();
+        // Left shift may be too narrow to take space from on simple layouts
+        let resized = self.add_resized_shape(&shift_shape, "iso_lshift", -change);
+        let keys = &mut self.key_rows[row].row;
+        if resized {
+            keys[shift].1 = "iso_lshift".to_owned();
+        }
+        keys.insert(shift + 1, (LedCode::Iso102, extra));
+    }
+
+    fn add_iso_return(&mut self) {
+        let Some(top) = self.key_rows.iter().position(|r| r.has(LedCode::BackSlash)) else {
+            return;
+        };
+        let bottom = top + 1;
+        if !self
+            .key_rows
+            .get(bottom)
+            .is_some_and(|r| r.has(LedCode::Return))
+        {
+            return;
+        }
+        // Backslash is the same size as the key before it on the ISO row
+        let bottom_keys = &self.key_rows[bottom].row;
+        let ret = bottom_keys
+            .iter()
+            .position(|k| k.0 == LedCode::Return)
+            .unwrap_or_default();
+        let Some(hash) = ret.checked_sub(1).map(|i| bottom_keys[i].1.clone()) else {
+            return;
+        };
+        let ret_shape = bottom_keys[ret].1.clone();
+        let change = self.shape_width(&hash).unwrap_or_default();
+        let resized = self.add_resized_shape(&ret_shape, "iso_return", -change);
+
+        // The top of the return takes the place of backslash
+        for key in self.key_rows[top].row.iter_mut() {
+            if key.0 == LedCode::BackSlash {
+                key.0 = LedCode::Return;
+            }
+        }
+        let bottom_keys = &mut self.key_rows[bottom].row;
+        if resized {
+            bottom_keys[ret].1 = "iso_return".to_owned();
+        }
+        bottom_keys.insert(ret, (LedCode::BackSlash, hash));
+    }
+
     pub fn layout_files(mut data_path: PathBuf) -> Result<Vec<PathBuf>, Error> {
         data_path.push("layouts");
         let path = data_path.as_path();
@@ -321,8 +724,9 @@ impl KeyLayout {
 
 impl KeyLayout {
     pub fn default_layout() -> Self {
-        Self {
+        let mut layout = Self {
             locale: "US".to_owned(),
+            legends: Vec::new(),
             basic_modes: vec![
                 AuraModeNum::Static,
                 AuraModeNum::Breathe,
@@ -330,6 +734,7 @@ impl KeyLayout {
             ],
             basic_zones: vec![AuraZone::None],
             advanced_type: AdvancedAuraType::None,
+            positions: HashMap::new(),
             key_shapes: HashMap::from([(
                 "regular".to_owned(),
                 KeyShape::new_led(1.0, 1.0, 0.1, 0.1, 0.1, 0.1)
@@ -423,19 +828,22 @@ impl KeyLayout {
                     (LedCode::RCtrl, "regular".to_owned()),
                 ]),
             ]
-        }
+        };
+        layout.build_rows();
+        layout
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use std::collections::HashSet;
+    use std::collections::{HashMap, HashSet};
     use std::fs::{self, OpenOptions};
     use std::io::Read;
     use std::path::PathBuf;
 
-    use crate::aura_detection::LedSupportFile;
-    use crate::keyboard::KeyLayout;
+    use super::xkb_layout_setting;
+    use crate::aura_detection::{LedSupportData, LedSupportFile};
+    use crate::keyboard::{KeyLayout, KeyRow, KeyShape, LayoutLocale, LedCode};
 
     #[test]
     fn check_parse_all() {
@@ -492,6 +900,34 @@ mod tests {
         // data).unwrap(); file.write_all(json.as_bytes()).unwrap();
     }
 
+    #[test]
+    fn key_positions_include_blanks() {
+        let mut layout: KeyLayout = ron::from_str(
+            r#"(
+                locale: "US",
+                key_shapes: {
+                    "regular": Led(width: 1.0, height: 1.0, pad_left: 0.1, pad_right: 0.1, pad_top: 0.1, pad_bottom: 0.1),
+                    "gap": Blank(width: 1.0, height: 1.2),
+                },
+                key_rows: [
+                    (pad_left: 0.1, pad_top: 0.1, row: [(Esc, "regular"), (Esc, "gap"), (F1, "regular")]),
+                    (pad_left: 0.5, pad_top: 0.1, row: [(Tilde, "regular")]),
+                ],
+            )"#
+        )
+        .unwrap();
+        assert!(layout.build_rows().is_empty());
+
+        let f1 = layout.key_position(LedCode::F1).unwrap();
+        assert!((f1.x - 2.9).abs() < 0.001);
+        assert!((f1.y - 0.7).abs() < 0.001);
+        let tilde = layout.key_position(LedCode::Tilde).unwrap();
+        assert!((tilde.x - 1.1).abs() < 0.001);
+        assert!((tilde.y - 2.0).abs() < 0.001);
+        // Blanks are spacing only
+        assert_eq!(layout.key_positions().len(), 3);
+    }
+
     #[test]
     fn check_layout_file_links() {
         const DATA_DIR: &str = env!("CARGO_MANIFEST_DIR");
@@ -539,4 +975,111 @@ mod tests {
             }
         }
     }
+
+    /// The set of LEDs, `LedCode` isn't `Ord` so this is sorted by name
+    fn led_set(layout: &KeyLayout) -> Vec<String> {
+        let mut leds: Vec<String> = layout
+            .key_positions()
+            .iter()
+            .map(|(l, _)| format!("{l:?}"))
+            .collect();
+        leds.sort();
+        leds.dedup();
+        leds
+    }
+
+    #[test]
+    fn localised_variants_keep_leds() {
+        let mut data_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        data_path.push("data");
+        let bases = [
+            KeyLayout::default_layout(),
+            KeyLayout::from_file(&data_path.join("layouts/ga401q_US.ron")).unwrap(),
+            KeyLayout::from_file(&data_path.join("layouts/g733pz-per-key_US.ron")).unwrap()
+        ];
+
+        for base in &bases {
+            let base_leds = led_set(base);
+            for locale in LayoutLocale::ALL {
+                let layout = base.localised(locale);
+                assert_eq!(layout.locale(), format!("{locale:?}"));
+                let mut expected = base_leds.clone();
+                if locale.is_iso() {
+                    expected.push("Iso102".to_owned());
+                    expected.sort();
+                }
+                assert_eq!(led_set(&layout), expected, "{locale:?}");
+                // Nothing moves past the edge of the US layout
+                assert!((layout.max_width() - base.max_width()).abs() < 0.001);
+
+                if locale.is_iso() {
+                    // Backslash moved down beside the bottom of return
+                    let backslash = layout.key_position(LedCode::BackSlash).unwrap();
+                    let quote = layout.key_position(LedCode::Quote).unwrap();
+                    assert!((backslash.y - quote.y).abs() < 0.001);
+                    let shift = layout.key_position(LedCode::LShift).unwrap();
+                    let extra = layout.key_position(LedCode::Iso102).unwrap();
+                    let z = layout.key_position(LedCode::Z).unwrap();
+                    assert!(shift.x < extra.x && extra.x < z.x);
+                }
+            }
+        }
+
+        // Left shift is wide enough to make space, so Z doesn't move
+        let uk = bases[1].localised(LayoutLocale::UK);
+        let z = uk.key_position(LedCode::Z).unwrap();
+        assert!(z.distance(&bases[1].key_position(LedCode::Z).unwrap()) < 0.001);
+
+        let de = KeyLayout::default_layout().localised(LayoutLocale::DE);
+        assert_eq!(de.key_label(LedCode::Y), "Z");
+        assert_eq!(de.key_label(LedCode::Esc), "Escape");
+
+        // Zoned layouts have nothing to change
+        let zoned = KeyLayout::new(
+            "US",
+            HashMap::from([(
+                "zone".to_owned(),
+                KeyShape::new_led(4.0, 5.0, 0.1, 0.1, 0.1, 0.1)
+            )]),
+            vec![
+                KeyRow::new(0.1, 0.1, vec![
+                    (LedCode::ZonedKbLeft, "zone".to_owned()),
+                    (LedCode::ZonedKbRight, "zone".to_owned()),
+                ]),
+            ]
+        );
+        let uk = zoned.localised(LayoutLocale::UK);
+        assert_eq!(led_set(&uk), led_set(&zoned));
+
+        // No UK file is shipped so the US one is localised
+        let led_data = LedSupportData {
+            layout_name: "ga401q".to_owned(),
+            ..Default::default()
+        };
+        let layout = KeyLayout::find_layout(led_data, data_path, LayoutLocale::UK).unwrap();
+        assert_eq!(layout.locale(), "UK");
+        assert!(layout.key_position(LedCode::Iso102).is_some());
+    }
+
+    #[test]
+    fn xkb_layouts() {
+        assert_eq!(LayoutLocale::from_xkb("gb"), LayoutLocale::UK);
+        assert_eq!(LayoutLocale::from_xkb("de(nodeadkeys)"), LayoutLocale::DE);
+        assert_eq!(LayoutLocale::from_xkb("fi,us"), LayoutLocale::Nordic);
+        assert_eq!(LayoutLocale::from_xkb("us,de"), LayoutLocale::US);
+        assert_eq!(LayoutLocale::from_xkb("jp"), LayoutLocale::US);
+        assert_eq!(LayoutLocale::from_xkb(""), LayoutLocale::US);
+
+        let xorg = "Section \"InputClass\"\n    Option \"XkbLayout\" \"fr\"\nEndSection\n";
+        assert_eq!(
+            xkb_layout_setting(xorg, "\"XkbLayout\"").as_deref(),
+            Some("fr")
+        );
+        let debian = "# XKBLAYOUT=\"us\"\nXKBMODEL=\"pc105\"\nXKBLAYOUT=\"se\"\n";
+        assert_eq!(
+            xkb_layout_setting(debian, "XKBLAYOUT=").as_deref(),
+            Some("se")
+        );
+        assert_eq!(xkb_layout_setting("XKBLAYOUT=\n", "XKBLAYOUT="), None);
+    }
 }
@@ -6,6 +6,10 @@ pub use advanced::*;
 mod layouts;
 pub use layouts::*;
 
+/// Validation, previews, and skeletons for layout files
+mod layout_tools;
+pub use layout_tools::*;
+
 mod power;
 pub use power::*;
 
@@ -16,6 +16,10 @@ pub mod effects;
 mod builtin_modes;
 pub use builtin_modes::*;
 
+/// Colour spaces, parsing, gradients, and per-device correction
+mod colour;
+pub use colour::*;
+
 /// Helper for detecting what is available
 pub mod aura_detection;
 pub mod error;
@@ -61,9 +65,6 @@ pub const ORANGE: Colour = Colour {
     g: 0xa4,
     b: 0x00
 };
-pub const GRADIENT: [Colour; 7] = [
-    RED, VIOLET, BLUE, TEAL, GREEN, YELLOW, ORANGE
-];
 
 #[cfg_attr(feature = "dbus", derive(Type, Value, OwnedValue))]
 #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
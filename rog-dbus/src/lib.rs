@@ -4,10 +4,15 @@ use zbus::proxy::ProxyImpl;
 pub mod asus_armoury;
 pub mod scsi_aura;
 pub mod zbus_anime;
+pub mod zbus_app_profiles;
 pub mod zbus_aura;
+pub mod zbus_config_bundle;
 pub mod zbus_fan_curves;
 pub mod zbus_platform;
+pub mod zbus_rules;
+pub mod zbus_scenes;
 pub mod zbus_slash;
+pub mod zbus_telemetry;
 
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 
@@ -0,0 +1,22 @@
+//! # `DBus` interface proxy for: `xyz.ljones.AppProfiles`
+//!
+//! The profiles themselves are configured in `/etc/asusd/app_profiles.ron`.
+
+use zbus::proxy;
+
+#[proxy(
+    interface = "xyz.ljones.AppProfiles",
+    default_service = "xyz.ljones.Asusd",
+    default_path = "/xyz/ljones"
+)]
+pub trait AppProfiles {
+    /// Name of the currently applied profile, empty if none
+    #[zbus(property)]
+    fn active_profile(&self) -> zbus::Result<String>;
+
+    /// Enabled property
+    #[zbus(property)]
+    fn enabled(&self) -> zbus::Result<bool>;
+    #[zbus(property)]
+    fn set_enabled(&self, value: bool) -> zbus::Result<()>;
+}
@@ -41,6 +41,9 @@ pub trait Aura {
     /// DirectAddressingRaw method
     fn direct_addressing_raw(&self, data: AuraLaptopUsbPackets) -> zbus::Result<()>;
 
+    /// ApplyEffect method
+    fn apply_effect(&self, effect: AuraEffect) -> zbus::Result<()>;
+
     /// Brightness property
     #[zbus(property)]
     fn brightness(&self) -> zbus::Result<LedBrightness>;
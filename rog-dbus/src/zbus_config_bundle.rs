@@ -0,0 +1,20 @@
+//! # `DBus` interface proxy for: `xyz.ljones.ConfigBundle`
+//!
+//! Bundles are RON strings, see `asusd::ctrl_config_bundle::ConfigBundle`
+//! for the format.
+
+use zbus::proxy;
+
+#[proxy(
+    interface = "xyz.ljones.ConfigBundle",
+    default_service = "xyz.ljones.Asusd",
+    default_path = "/xyz/ljones"
+)]
+pub trait ConfigBundle {
+    /// Export all stored configs as a single versioned RON document
+    fn export_config(&self) -> zbus::Result<String>;
+
+    /// Import a bundle created by `export_config`. Returns a description of
+    /// each section, or part of a section, that was skipped.
+    fn import_config(&self, bundle: &str) -> zbus::Result<Vec<String>>;
+}
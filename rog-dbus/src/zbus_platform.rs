@@ -48,6 +48,27 @@ pub trait Platform {
     // Toggle one-shot charge to 100%
     fn one_shot_full_charge(&self) -> zbus::Result<()>;
 
+    /// The charge schedule as a RON string, see
+    /// `asusd::charge_schedule::ChargeSchedule`
+    #[zbus(property)]
+    fn charge_schedule(&self) -> zbus::Result<String>;
+    #[zbus(property)]
+    fn set_charge_schedule(&self, value: &str) -> zbus::Result<()>;
+
+    /// ChargeScheduleEnabled property
+    #[zbus(property)]
+    fn charge_schedule_enabled(&self) -> zbus::Result<bool>;
+    #[zbus(property)]
+    fn set_charge_schedule_enabled(&self, value: bool) -> zbus::Result<()>;
+
+    /// The limit the charge schedule is currently applying, 0 if none
+    #[zbus(property)]
+    fn scheduled_charge_limit(&self) -> zbus::Result<u8>;
+
+    /// The observed charge rate in percent per hour
+    #[zbus(property)]
+    fn charge_rate(&self) -> zbus::Result<f64>;
+
     /// ThrottleBalancedEpp property
     #[zbus(property)]
     fn profile_balanced_epp(&self) -> zbus::Result<CPUEPP>;
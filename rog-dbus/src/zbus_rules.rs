@@ -0,0 +1,35 @@
+//! # `DBus` interface proxy for: `xyz.ljones.Rules`
+//!
+//! Rules are passed as RON strings, see `asusd::ctrl_rules::Rule` for the
+//! format.
+
+use zbus::proxy;
+
+#[proxy(
+    interface = "xyz.ljones.Rules",
+    default_service = "xyz.ljones.Asusd",
+    default_path = "/xyz/ljones"
+)]
+pub trait Rules {
+    /// Add a rule from a RON string. A rule with the same name is replaced.
+    fn add_rule(&self, rule: &str) -> zbus::Result<()>;
+
+    /// Check all rules against the current system state without running any
+    /// actions. Returns the results as a RON string.
+    fn dry_run(&self) -> zbus::Result<String>;
+
+    /// Return all rules as a RON string
+    fn list_rules(&self) -> zbus::Result<String>;
+
+    /// RemoveRule method
+    fn remove_rule(&self, name: &str) -> zbus::Result<()>;
+
+    /// SetRuleEnabled method
+    fn set_rule_enabled(&self, name: &str, enabled: bool) -> zbus::Result<()>;
+
+    /// Enable or disable the rules engine as a whole
+    #[zbus(property)]
+    fn enabled(&self) -> zbus::Result<bool>;
+    #[zbus(property)]
+    fn set_enabled(&self, value: bool) -> zbus::Result<()>;
+}
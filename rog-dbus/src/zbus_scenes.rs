@@ -0,0 +1,25 @@
+//! # `DBus` interface proxy for: `xyz.ljones.Scenes`
+
+use zbus::proxy;
+
+#[proxy(
+    interface = "xyz.ljones.Scenes",
+    default_service = "xyz.ljones.Asusd",
+    default_path = "/xyz/ljones"
+)]
+pub trait Scenes {
+    /// Apply a saved scene. If any part fails the previous settings are
+    /// restored and an error returned. Returns the parts of the scene that
+    /// were skipped as the device is no longer present.
+    fn apply_scene(&self, name: &str) -> zbus::Result<Vec<String>>;
+
+    /// DeleteScene method
+    fn delete_scene(&self, name: &str) -> zbus::Result<()>;
+
+    /// Return the names of all saved scenes
+    fn list_scenes(&self) -> zbus::Result<Vec<String>>;
+
+    /// Save the current settings under the name, replacing any existing
+    /// scene of that name. Returns the parts that could not be saved.
+    fn save_scene(&self, name: &str) -> zbus::Result<Vec<String>>;
+}
@@ -0,0 +1,30 @@
+//! # `DBus` interface proxy for: `xyz.ljones.Telemetry`
+
+pub use asusd::ctrl_telemetry::{FanReading, TelemetrySample, TempReading};
+use zbus::proxy;
+
+#[proxy(
+    interface = "xyz.ljones.Telemetry",
+    default_service = "xyz.ljones.Asusd",
+    default_path = "/xyz/ljones"
+)]
+pub trait Telemetry {
+    /// All samples kept, oldest first
+    fn history(&self) -> zbus::Result<Vec<TelemetrySample>>;
+
+    /// The most recent sample
+    #[zbus(property)]
+    fn latest(&self) -> zbus::Result<TelemetrySample>;
+
+    /// Number of samples kept in the history
+    #[zbus(property)]
+    fn history_length(&self) -> zbus::Result<u32>;
+    #[zbus(property)]
+    fn set_history_length(&self, value: u32) -> zbus::Result<()>;
+
+    /// Time between samples in milliseconds
+    #[zbus(property)]
+    fn interval(&self) -> zbus::Result<u32>;
+    #[zbus(property)]
+    fn set_interval(&self, value: u32) -> zbus::Result<()>;
+}
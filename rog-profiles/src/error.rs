@@ -16,7 +16,18 @@ pub enum ProfileError {
     /// (pwm/temp, prev, next)
     ParseFanCurvePrevHigher(&'static str, u8, u8),
     ParseFanCurvePercentOver100(u8),
-    NotEnoughPoints // Zbus(zbus::Error),
+    NotEnoughPoints, // Zbus(zbus::Error),
+    /// Resampling needs at least 2 points, (given)
+    CurveTooFewPoints(usize),
+    /// Resampling needs a span of at least 7 degrees, (first, last)
+    CurveTempRange(u8, u8),
+    /// (prev, next)
+    CurveTempNotIncreasing(u8, u8),
+    /// (prev, next)
+    CurvePwmDecreasing(u8, u8),
+    /// (temp, pwm)
+    CurveUnsafe(u8, u8),
+    ParseCurvePreset
 }
 
 impl fmt::Display for ProfileError {
@@ -42,6 +53,37 @@ impl fmt::Display for ProfileError {
             ProfileError::ParseFanCurvePercentOver100(value) => {
                 write!(f, "Invalid percentage, {} is higher than 100", value)
             } // Error::Zbus(detail) => write!(f, "Zbus error: {}", detail),
+            ProfileError::CurveTooFewPoints(count) => {
+                write!(
+                    f,
+                    "At least 2 curve points are required, {} supplied",
+                    count
+                )
+            }
+            ProfileError::CurveTempRange(first, last) => write!(
+                f,
+                "Curve temperatures {}c to {}c are too close together to make 8 points",
+                first, last
+            ),
+            ProfileError::CurveTempNotIncreasing(prev, next) => write!(
+                f,
+                "Invalid curve, temperatures must be increasing but {}c is followed by {}c",
+                prev, next
+            ),
+            ProfileError::CurvePwmDecreasing(prev, next) => write!(
+                f,
+                "Invalid curve, fan power must not decrease but {} is followed by {}",
+                prev, next
+            ),
+            ProfileError::CurveUnsafe(temp, pwm) => write!(
+                f,
+                "Unsafe curve, fan power at {}c is {}% but must be at least {}% from {}c",
+                temp,
+                (*pwm as u32) * 100 / 255,
+                (crate::fan_curve_set::SAFE_MIN_PWM as u32) * 100 / 255,
+                crate::fan_curve_set::SAFE_TEMP
+            ),
+            ProfileError::ParseCurvePreset => write!(f, "Invalid curve preset name")
         }
     }
 }
@@ -60,6 +102,11 @@ impl From<ProfileError> for FdoErr {
         error!("ProfileError: got: {error}");
         match error {
             ProfileError::NotSupported => FdoErr::NotSupported("".to_owned()),
+            ProfileError::CurveTooFewPoints(_)
+            | ProfileError::CurveTempRange(..)
+            | ProfileError::CurveTempNotIncreasing(..)
+            | ProfileError::CurvePwmDecreasing(..)
+            | ProfileError::CurveUnsafe(..) => FdoErr::InvalidArgs(format!("{error}")),
             _ => FdoErr::Failed(format!("Failed with {error}"))
         }
     }
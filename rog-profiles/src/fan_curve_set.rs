@@ -12,6 +12,13 @@ fn set_sysfs_name(string: &mut [u8], fan: char, index: usize) {
     string[15] = char::from_digit(index as u32 + 1, 10).unwrap() as u8;
 }
 
+/// Number of points in a curve as used by the kernel driver
+pub const CURVE_POINTS: usize = 8;
+/// Temperature from which the fan must run at `SAFE_MIN_PWM` or higher
+pub const SAFE_TEMP: u8 = 70;
+/// Minimum fan power (0-255) from `SAFE_TEMP` upwards, this is 20%
+pub const SAFE_MIN_PWM: u8 = 51;
+
 pub(crate) fn pwm_str(fan: char, index: usize) -> String {
     // The char 'X' is replaced via indexing
     let mut string = "pwmX_auto_pointX_pwm".to_owned();
@@ -131,11 +138,192 @@ impl std::str::FromStr for CurveData {
     }
 }
 
+/// Named curves which can be generated for any fan
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CurvePreset {
+    /// Fan stays off until warm and stays low for as long as is safe
+    Silent,
+    /// Fan power rises steadily with temperature
+    Linear,
+    /// Fan starts early and reaches full power well before the limit
+    Aggressive
+}
+
+impl CurvePreset {
+    pub const fn list() -> [Self; 3] {
+        [
+            Self::Silent,
+            Self::Linear,
+            Self::Aggressive
+        ]
+    }
+
+    /// Fan power in percent for each curve point
+    const fn percentages(self) -> [u8; CURVE_POINTS] {
+        match self {
+            Self::Silent => [
+                0, 0, 5, 10, 25, 40, 60, 80
+            ],
+            Self::Linear => [
+                10, 23, 36, 49, 61, 74, 87, 100
+            ],
+            Self::Aggressive => [
+                20, 30, 45, 60, 75, 90, 100, 100
+            ]
+        }
+    }
+}
+
+impl std::str::FromStr for CurvePreset {
+    type Err = ProfileError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().trim() {
+            "silent" => Ok(Self::Silent),
+            "linear" => Ok(Self::Linear),
+            "aggressive" => Ok(Self::Aggressive),
+            _ => Err(ProfileError::ParseCurvePreset)
+        }
+    }
+}
+
+/// Linearly interpolate the fan power at `temp`. Points must be sorted by
+/// temperature. Outside of the curve the first or last point is used.
+fn interpolate(points: &[(u8, u8)], temp: u8) -> u8 {
+    let (Some(first), Some(last)) = (points.first(), points.last()) else {
+        return 0;
+    };
+    if temp <= first.0 {
+        return first.1;
+    }
+    for pair in points.windows(2) {
+        let ((t0, p0), (t1, p1)) = (pair[0], pair[1]);
+        if temp <= t1 {
+            if t1 == t0 {
+                return p1;
+            }
+            let frac = (temp - t0) as f32 / (t1 - t0) as f32;
+            return (p0 as f32 + (p1 as f32 - p0 as f32) * frac).round() as u8;
+        }
+    }
+    last.1
+}
+
+fn check_increasing(points: &[(u8, u8)]) -> Result<(), ProfileError> {
+    for pair in points.windows(2) {
+        let ((t0, p0), (t1, p1)) = (pair[0], pair[1]);
+        if t1 <= t0 {
+            return Err(ProfileError::CurveTempNotIncreasing(t0, t1));
+        }
+        if p1 < p0 {
+            return Err(ProfileError::CurvePwmDecreasing(p0, p1));
+        }
+    }
+    Ok(())
+}
+
 impl CurveData {
     pub fn set_fan(&mut self, fan: FanCurvePU) {
         self.fan = fan;
     }
 
+    /// The curve as `(temp, pwm)` pairs
+    pub fn points(&self) -> [(u8, u8); CURVE_POINTS] {
+        let mut points = [(0, 0); CURVE_POINTS];
+        for (i, point) in points.iter_mut().enumerate() {
+            *point = (self.temp[i], self.pwm[i]);
+        }
+        points
+    }
+
+    /// Generate a preset curve for the fan. The GPU curve tops out at a lower
+    /// temperature as GPUs typically throttle earlier. The returned
+    /// `CurveData` is not enabled.
+    pub fn from_preset(fan: FanCurvePU, preset: CurvePreset) -> Self {
+        let temp = match fan {
+            FanCurvePU::CPU | FanCurvePU::MID => [
+                30, 40, 50, 60, 70, 80, 90, 100
+            ],
+            FanCurvePU::GPU => [
+                30, 40, 50, 60, 70, 76, 82, 88
+            ]
+        };
+        let pwm = preset
+            .percentages()
+            .map(|p| (p as f32 * 2.55).round() as u8);
+        Self {
+            fan,
+            pwm,
+            temp,
+            enabled: false
+        }
+    }
+
+    /// Create a curve from any number of `(temp, pwm)` points, at least 2.
+    /// The new points are evenly spaced between the first and last
+    /// temperature, and the fan power is interpolated from the given curve.
+    /// The returned `CurveData` is not enabled.
+    pub fn resample(fan: FanCurvePU, points: &[(u8, u8)]) -> Result<Self, ProfileError> {
+        if points.len() < 2 {
+            return Err(ProfileError::CurveTooFewPoints(points.len()));
+        }
+        check_increasing(points)?;
+        let first = points[0].0;
+        let last = points[points.len() - 1].0;
+        if ((last - first) as usize) < CURVE_POINTS - 1 {
+            return Err(ProfileError::CurveTempRange(first, last));
+        }
+
+        let mut temp = [0u8; CURVE_POINTS];
+        let mut pwm = [0u8; CURVE_POINTS];
+        let step = (last - first) as f32 / (CURVE_POINTS - 1) as f32;
+        for i in 0..CURVE_POINTS {
+            temp[i] = (first as f32 + step * i as f32).round() as u8;
+            pwm[i] = interpolate(points, temp[i]);
+        }
+        Ok(Self {
+            fan,
+            pwm,
+            temp,
+            enabled: false
+        })
+    }
+
+    /// The fan power at `temp`, linearly interpolated between curve points
+    pub fn pwm_at(&self, temp: u8) -> u8 {
+        interpolate(&self.points(), temp)
+    }
+
+    /// Average each inner point's fan power with its neighbours to remove
+    /// sharp steps. The first and last points are not changed. The result
+    /// may need to be validated again as power near `SAFE_TEMP` can drop.
+    pub fn smooth(&mut self) {
+        let pwm = self.pwm;
+        for i in 1..CURVE_POINTS - 1 {
+            let sum = pwm[i - 1] as u32 + pwm[i] as u32 + pwm[i + 1] as u32;
+            self.pwm[i] = ((sum + 1) / 3) as u8;
+        }
+    }
+
+    /// Check the curve is safe to write to the device: temperatures must
+    /// strictly increase, fan power must never decrease, and the fan must run
+    /// at `SAFE_MIN_PWM` or more from `SAFE_TEMP` upwards.
+    pub fn validate(&self) -> Result<(), ProfileError> {
+        let points = self.points();
+        check_increasing(&points)?;
+        for (temp, pwm) in points {
+            if temp >= SAFE_TEMP && pwm < SAFE_MIN_PWM {
+                return Err(ProfileError::CurveUnsafe(temp, pwm));
+            }
+        }
+        // The last point is held for all higher temperatures
+        let (temp, pwm) = points[CURVE_POINTS - 1];
+        if temp < SAFE_TEMP && pwm < SAFE_MIN_PWM {
+            return Err(ProfileError::CurveUnsafe(SAFE_TEMP, pwm));
+        }
+        Ok(())
+    }
+
     fn set_val_from_attr(tmp: &str, device: &Device, buf: &mut [u8; 8]) {
         if let Some(n) = tmp.chars().nth(15) {
             let i = n.to_digit(10).unwrap() as usize;
@@ -232,6 +420,122 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn curve_validate() {
+        let curve =
+            CurveData::from_str("30c:1%,49c:2%,59c:3%,69c:4%,79c:31%,89c:49%,99c:56%,109c:58%")
+                .unwrap();
+        assert!(curve.validate().is_ok());
+
+        let curve = CurveData::from_str("30:1,49:2,49:3,69:4,79:80,89:90,99:100,109:110").unwrap();
+        assert!(matches!(
+            curve.validate(),
+            Err(ProfileError::CurveTempNotIncreasing(49, 49))
+        ));
+
+        let curve = CurveData::from_str("30:1,40:2,50:3,60:4,70:10,80:90,90:100,100:110").unwrap();
+        assert!(matches!(
+            curve.validate(),
+            Err(ProfileError::CurveUnsafe(70, 10))
+        ));
+
+        let curve = CurveData::from_str("20:1,25:2,30:3,35:4,40:10,45:20,50:30,55:40").unwrap();
+        assert!(matches!(
+            curve.validate(),
+            Err(ProfileError::CurveUnsafe(SAFE_TEMP, 40))
+        ));
+
+        let mut curve = CurveData::from_preset(FanCurvePU::CPU, CurvePreset::Linear);
+        curve.pwm.swap(2, 3);
+        assert!(matches!(
+            curve.validate(),
+            Err(ProfileError::CurvePwmDecreasing(..))
+        ));
+    }
+
+    #[test]
+    fn curve_presets_are_safe() {
+        for fan in [
+            FanCurvePU::CPU,
+            FanCurvePU::GPU,
+            FanCurvePU::MID
+        ] {
+            for preset in CurvePreset::list() {
+                let curve = CurveData::from_preset(fan, preset);
+                assert!(curve.validate().is_ok(), "{fan:?} {preset:?}");
+                assert_eq!(curve.fan, fan);
+            }
+        }
+        let curve = CurveData::from_preset(FanCurvePU::CPU, CurvePreset::Aggressive);
+        assert_eq!(curve.pwm[CURVE_POINTS - 1], 255);
+    }
+
+    #[test]
+    fn curve_pwm_at() {
+        let curve =
+            CurveData::from_str("30:0,40:10,50:20,60:30,70:100,80:150,90:200,100:255").unwrap();
+        assert_eq!(curve.pwm_at(0), 0);
+        assert_eq!(curve.pwm_at(35), 5);
+        assert_eq!(curve.pwm_at(60), 30);
+        assert_eq!(curve.pwm_at(65), 65);
+        assert_eq!(curve.pwm_at(255), 255);
+    }
+
+    #[test]
+    fn curve_resample() {
+        let curve = CurveData::resample(FanCurvePU::GPU, &[
+            (30, 0),
+            (65, 100),
+            (100, 255)
+        ])
+        .unwrap();
+        assert_eq!(curve.fan, FanCurvePU::GPU);
+        assert!(!curve.enabled);
+        assert_eq!(curve.temp, [30, 40, 50, 60, 70, 80, 90, 100]);
+        assert_eq!(curve.pwm, [0, 29, 57, 86, 122, 166, 211, 255]);
+
+        let many: Vec<(u8, u8)> = (0..=70).map(|i| (30 + i, i * 3)).collect();
+        let curve = CurveData::resample(FanCurvePU::CPU, &many).unwrap();
+        assert_eq!(curve.temp, [30, 40, 50, 60, 70, 80, 90, 100]);
+        assert_eq!(curve.pwm, [0, 30, 60, 90, 120, 150, 180, 210]);
+
+        assert!(matches!(
+            CurveData::resample(FanCurvePU::CPU, &[(30, 0)]),
+            Err(ProfileError::CurveTooFewPoints(1))
+        ));
+        assert!(matches!(
+            CurveData::resample(FanCurvePU::CPU, &[
+                (30, 0),
+                (35, 100)
+            ]),
+            Err(ProfileError::CurveTempRange(30, 35))
+        ));
+        assert!(matches!(
+            CurveData::resample(FanCurvePU::CPU, &[
+                (30, 0),
+                (30, 100)
+            ]),
+            Err(ProfileError::CurveTempNotIncreasing(30, 30))
+        ));
+        // Rejected curves are the caller's mistake
+        for err in [
+            ProfileError::CurveTooFewPoints(1),
+            ProfileError::CurveTempRange(30, 35)
+        ] {
+            let err: zbus::fdo::Error = err.into();
+            assert!(matches!(err, zbus::fdo::Error::InvalidArgs(_)));
+        }
+    }
+
+    #[test]
+    fn curve_smooth() {
+        let mut curve =
+            CurveData::from_str("30:0,40:0,50:0,60:90,70:90,80:90,90:255,100:255").unwrap();
+        curve.smooth();
+        assert_eq!(curve.pwm, [0, 0, 30, 60, 90, 145, 200, 255]);
+        assert!(curve.validate().is_ok());
+    }
+
     #[test]
     fn check_pwm_str() {
         assert_eq!(pwm_str('1', 0), "pwm1_auto_point1_pwm");
@@ -1,6 +1,8 @@
 use std::fmt::Display;
 use std::str::FromStr;
 
+/// The disk shares the colour handling of the laptop keyboards
+pub use rog_aura::Colour;
 use serde::{Deserialize, Serialize};
 #[cfg(feature = "dbus")]
 use zbus::zvariant::{OwnedValue, Type, Value};
@@ -8,52 +10,6 @@ use zbus::zvariant::{OwnedValue, Type, Value};
 use crate::error::Error;
 use crate::scsi::{apply_task, dir_task, mode_task, rgb_task, save_task, speed_task};
 
-#[cfg_attr(feature = "dbus", derive(Type, Value, OwnedValue))]
-#[derive(Debug, Clone, PartialEq, Eq, Copy, Deserialize, Serialize)]
-pub struct Colour {
-    pub r: u8,
-    pub g: u8,
-    pub b: u8
-}
-
-impl Default for Colour {
-    fn default() -> Self {
-        Colour { r: 166, g: 0, b: 0 }
-    }
-}
-
-impl FromStr for Colour {
-    type Err = Error;
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        if s.len() < 6 {
-            return Err(Error::ParseColour);
-        }
-        let r = u8::from_str_radix(&s[0..2], 16).or(Err(Error::ParseColour))?;
-        let g = u8::from_str_radix(&s[2..4], 16).or(Err(Error::ParseColour))?;
-        let b = u8::from_str_radix(&s[4..6], 16).or(Err(Error::ParseColour))?;
-        Ok(Colour { r, g, b })
-    }
-}
-
-impl From<&[u8; 3]> for Colour {
-    fn from(c: &[u8; 3]) -> Self {
-        Self {
-            r: c[0],
-            g: c[1],
-            b: c[2]
-        }
-    }
-}
-
-impl From<Colour> for [u8; 3] {
-    fn from(c: Colour) -> Self {
-        [
-            c.r, c.b, c.g
-        ]
-    }
-}
-
 #[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Deserialize, Serialize)]
 #[cfg_attr(
     feature = "dbus",
@@ -366,10 +322,10 @@ impl From<&AuraEffect> for Vec<sg::Task> {
 
         tasks.append(&mut vec![
             mode_task(effect.mode as u8),
-            rgb_task(0, &effect.colour1.into()),
-            rgb_task(1, &effect.colour2.into()),
-            rgb_task(2, &effect.colour3.into()),
-            rgb_task(3, &effect.colour4.into()),
+            rgb_task(0, &effect.colour1),
+            rgb_task(1, &effect.colour2),
+            rgb_task(2, &effect.colour3),
+            rgb_task(3, &effect.colour4),
         ]);
 
         if !matches!(effect.mode, AuraMode::Static | AuraMode::Off) {
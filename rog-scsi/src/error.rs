@@ -3,7 +3,6 @@ use std::{error, fmt};
 #[derive(Debug)]
 pub enum Error {
     ParseMode,
-    ParseColour,
     ParseSpeed,
     ParseDirection,
     IoPath(String, std::io::Error),
@@ -15,7 +14,6 @@ impl fmt::Display for Error {
     // This trait requires `fmt` with this exact signature.
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Error::ParseColour => write!(f, "Could not parse colour"),
             Error::ParseSpeed => write!(f, "Could not parse speed"),
             Error::ParseDirection => write!(f, "Could not parse direction"),
             Error::ParseMode => write!(f, "Could not parse mode"),
@@ -2,6 +2,8 @@ extern crate sg;
 
 pub use sg::Task;
 
+use crate::Colour;
+
 static ENE_APPLY_VAL: u8 = 0x01; // Value for Apply Changes Register
 static ENE_SAVE_VAL: u8 = 0xaa;
 
@@ -34,10 +36,16 @@ fn data(reg: u32, arg_count: u8) -> [u8; 16] {
     cdb
 }
 
-pub(crate) fn rgb_task(led: u32, rgb: &[u8; 3]) -> Task {
+/// The disk takes the colour in RBG order
+pub(crate) fn rgb_task(led: u32, colour: &Colour) -> Task {
     let mut task = Task::new();
     task.set_cdb(data(led * 3 + ENE_REG_COLORS_EFFECT_V2, 3).as_slice());
-    task.set_data(rgb, sg::Direction::ToDevice);
+    task.set_data(
+        &[
+            colour.r, colour.b, colour.g
+        ],
+        sg::Direction::ToDevice
+    );
     task
 }
 
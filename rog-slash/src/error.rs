@@ -1,5 +1,8 @@
 use std::error::Error;
 use std::fmt;
+
+use gif::EncodingError;
+use png_pong::encode::Error as PngEncodeError;
 pub type Result<T> = std::result::Result<T, SlashError>;
 
 #[derive(Debug)]
@@ -9,7 +12,10 @@ pub enum SlashError {
     NoDevice,
     UnsupportedDevice,
     DataBufferLength,
-    ParseError(String)
+    ParseError(String),
+    PngEncode(PngEncodeError),
+    GifEncode(EncodingError),
+    NoFrames
 }
 
 impl fmt::Display for SlashError {
@@ -24,13 +30,30 @@ impl fmt::Display for SlashError {
                 f,
                 "The data buffer was incorrect length for generating USB packets"
             ),
-            SlashError::UnsupportedDevice => write!(f, "Unsupported Slash device found")
+            SlashError::UnsupportedDevice => write!(f, "Unsupported Slash device found"),
+            SlashError::PngEncode(e) => write!(f, "Could not write PNG: {}", e),
+            SlashError::GifEncode(e) => write!(f, "Could not write GIF: {}", e),
+            SlashError::NoFrames => write!(f, "There are no frames to write")
         }
     }
 }
 
 impl Error for SlashError {}
 
+impl From<PngEncodeError> for SlashError {
+    #[inline]
+    fn from(err: PngEncodeError) -> Self {
+        SlashError::PngEncode(err)
+    }
+}
+
+impl From<EncodingError> for SlashError {
+    #[inline]
+    fn from(err: EncodingError) -> Self {
+        SlashError::GifEncode(err)
+    }
+}
+
 impl From<SlashError> for zbus::fdo::Error {
     #[inline]
     fn from(err: SlashError) -> Self {
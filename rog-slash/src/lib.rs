@@ -3,6 +3,10 @@
 mod data;
 pub use data::*;
 
+/// Draw frames as they look on the lid, for previews and tests
+mod render;
+pub use render::*;
+
 /// Base errors that are possible
 pub mod error;
 
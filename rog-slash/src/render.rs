@@ -0,0 +1,314 @@
+use std::io::Write;
+use std::time::Duration;
+
+use pix::gray::SGray8;
+use pix::Raster;
+
+use crate::error::{Result, SlashError};
+use crate::SlashMode;
+
+/// The Slash is a row of LED segments along the diagonal of the lid
+pub const SLASH_SEGMENTS: usize = 7;
+/// Length of a segment along the diagonal, in pixels
+const SEGMENT_LENGTH: f32 = 40.0;
+/// Space between segments, in pixels
+const SEGMENT_GAP: f32 = 8.0;
+/// Width of a segment across the diagonal, in pixels
+const SEGMENT_WIDTH: f32 = 14.0;
+/// Angle of the diagonal from horizontal, rising to the right like a `/`
+const SLASH_ANGLE: f32 = 60.0;
+/// Border around the segments, in pixels
+const MARGIN: f32 = 8.0;
+/// How bright a segment that is off is drawn, so the shape of the Slash shows
+const LED_OFF: u8 = 24;
+/// Frame time of a mode drawn at interval 0, each step of interval adds
+/// another
+const MODE_FRAME_TIME: Duration = Duration::from_millis(80);
+
+/// The brightness of each segment, from the lower left end of the Slash
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SlashFrame(pub [u8; SLASH_SEGMENTS]);
+
+/// Draws `SlashFrame`s as the segments sit on the lid. Used for previews and
+/// for checking frames in tests without a device.
+#[derive(Debug, Clone)]
+pub struct SlashRender {
+    width: u32,
+    height: u32,
+    /// Pixel centre of each segment
+    centres: [(f32, f32); SLASH_SEGMENTS]
+}
+
+impl Default for SlashRender {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SlashRender {
+    pub fn new() -> Self {
+        let (dx, dy) = Self::axis();
+        let step = SEGMENT_LENGTH + SEGMENT_GAP;
+        // Half the extent of a segment on each axis, for the bounds
+        let half_x = (SEGMENT_LENGTH * dx.abs() + SEGMENT_WIDTH * dy.abs()) / 2.0;
+        let half_y = (SEGMENT_LENGTH * dy.abs() + SEGMENT_WIDTH * dx.abs()) / 2.0;
+        let span = step * (SLASH_SEGMENTS - 1) as f32;
+
+        let mut centres = [(0.0, 0.0); SLASH_SEGMENTS];
+        for (i, centre) in centres.iter_mut().enumerate() {
+            let along = step * i as f32;
+            *centre = (
+                MARGIN + half_x + along * dx,
+                // Pixel rows count down, so the first segment is at the bottom
+                MARGIN + half_y + (span - along) * -dy
+            );
+        }
+        Self {
+            width: (2.0 * (MARGIN + half_x) + span * dx).ceil() as u32,
+            height: (2.0 * (MARGIN + half_y) + span * -dy).ceil() as u32,
+            centres
+        }
+    }
+
+    /// Unit vector along the Slash in pixel coordinates, up and to the right
+    fn axis() -> (f32, f32) {
+        let angle = SLASH_ANGLE.to_radians();
+        (angle.cos(), -angle.sin())
+    }
+
+    #[inline]
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    #[inline]
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Draw `frame` as 8bit greyscale, `width()` by `height()`
+    pub fn draw(&self, frame: &SlashFrame) -> Vec<u8> {
+        let (dx, dy) = Self::axis();
+        let mut pixels = vec![0; (self.width * self.height) as usize];
+        for (y, row) in pixels.chunks_mut(self.width as usize).enumerate() {
+            for (x, pixel) in row.iter_mut().enumerate() {
+                let (px, py) = (x as f32 + 0.5, y as f32 + 0.5);
+                let segment = self.centres.iter().position(|(cx, cy)| {
+                    let along = (px - cx) * dx + (py - cy) * dy;
+                    let across = (px - cx) * -dy + (py - cy) * dx;
+                    along.abs() <= SEGMENT_LENGTH / 2.0 && across.abs() <= SEGMENT_WIDTH / 2.0
+                });
+                if let Some(segment) = segment {
+                    let led = frame.0[segment] as u32;
+                    *pixel = LED_OFF + (led * (255 - LED_OFF) as u32 / 255) as u8;
+                }
+            }
+        }
+        pixels
+    }
+
+    /// Write `frame` as a greyscale PNG
+    pub fn write_png(&self, frame: &SlashFrame, writer: impl Write) -> Result<()> {
+        let pixels: Vec<SGray8> = self.draw(frame).into_iter().map(SGray8::new).collect();
+        let raster = Raster::with_pixels(self.width, self.height, pixels);
+        png_pong::Encoder::new(writer)
+            .into_step_enc()
+            .encode(&png_pong::Step {
+                raster: png_pong::PngRaster::Gray8(raster),
+                delay: 0
+            })?;
+        Ok(())
+    }
+
+    /// Write `frames`, each shown for its `Duration`, as a looping GIF
+    pub fn write_gif(&self, frames: &[(SlashFrame, Duration)], writer: impl Write) -> Result<()> {
+        if frames.is_empty() {
+            return Err(SlashError::NoFrames);
+        }
+        let palette: Vec<u8> = (0..=255u8)
+            .flat_map(|grey| {
+                [
+                    grey, grey, grey
+                ]
+            })
+            .collect();
+        let (width, height) = (self.width as u16, self.height as u16);
+        let mut encoder = gif::Encoder::new(writer, width, height, &palette)?;
+        encoder.set_repeat(gif::Repeat::Infinite)?;
+        for (slash_frame, delay) in frames {
+            let mut frame =
+                gif::Frame::from_indexed_pixels(width, height, &self.draw(slash_frame), None);
+            // GIF delays are in hundredths of a second
+            frame.delay = ((delay.as_millis() + 5) / 10).clamp(1, u16::MAX as u128) as u16;
+            encoder.write_frame(&frame)?;
+        }
+        Ok(())
+    }
+}
+
+/// A small repeatable noise, so a preview is the same each time
+fn noise(step: usize, segment: usize) -> f32 {
+    let mut x = (step as u32)
+        .wrapping_mul(0x9e37_79b9)
+        .wrapping_add((segment as u32).wrapping_mul(0x85eb_ca6b));
+    x ^= x >> 15;
+    x = x.wrapping_mul(0x2c1b_3c6d);
+    x ^= x >> 12;
+    (x & 0xff) as f32 / 255.0
+}
+
+/// How lit `segment` is at `step` of `mode`, `0.0..=1.0`
+fn mode_level(mode: SlashMode, step: usize, segment: usize) -> f32 {
+    const LAST: usize = SLASH_SEGMENTS - 1;
+    const MIDDLE: usize = SLASH_SEGMENTS / 2;
+    let lit = |on: bool| if on { 1.0 } else { 0.0 };
+    match mode {
+        // One segment back and forth
+        SlashMode::Bounce => {
+            let at = if step <= LAST { step } else { 2 * LAST - step };
+            lit(segment == at)
+        }
+        // A bright head with a fading tail along the Slash
+        SlashMode::Slash => match step.checked_sub(segment) {
+            Some(behind) if behind < 3 => 1.0 - behind as f32 / 3.0,
+            _ => 0.0
+        },
+        SlashMode::Loading => lit(segment < step),
+        SlashMode::BitStream => lit(noise(step, segment) > 0.5),
+        // Every third segment, moving along
+        SlashMode::Transmission => lit(segment % 3 == step),
+        // A wave moving along
+        SlashMode::Flow => {
+            let phase = (segment as f32 - step as f32) / SLASH_SEGMENTS as f32;
+            0.5 + 0.5 * (phase * std::f32::consts::TAU).cos()
+        }
+        SlashMode::Flux => lit(segment % 2 == step % 2),
+        // All fading in and out together
+        SlashMode::Phantom => {
+            let half = SLASH_SEGMENTS as f32;
+            1.0 - (step as f32 - half).abs() / half
+        }
+        SlashMode::Spectrum => noise(step, segment),
+        SlashMode::Hazard | SlashMode::Buzzer => lit(step & 1 == 0),
+        // From both ends to the middle
+        SlashMode::Interfacing => lit(segment.min(LAST - segment) < step),
+        SlashMode::Ramp => ((segment + step) % SLASH_SEGMENTS) as f32 / LAST as f32,
+        SlashMode::GameOver => lit(segment + step < SLASH_SEGMENTS),
+        // From the middle out to both ends
+        SlashMode::Start => lit(segment.abs_diff(MIDDLE) < step)
+    }
+}
+
+/// The steps in one cycle of `mode`
+fn mode_steps(mode: SlashMode) -> usize {
+    match mode {
+        SlashMode::Bounce => 2 * (SLASH_SEGMENTS - 1),
+        SlashMode::Slash => SLASH_SEGMENTS + 3,
+        SlashMode::Loading | SlashMode::GameOver => SLASH_SEGMENTS + 1,
+        SlashMode::BitStream | SlashMode::Spectrum => 16,
+        SlashMode::Transmission => 3,
+        SlashMode::Flow | SlashMode::Ramp => SLASH_SEGMENTS,
+        SlashMode::Flux | SlashMode::Hazard => 2,
+        SlashMode::Phantom => 2 * SLASH_SEGMENTS,
+        SlashMode::Interfacing | SlashMode::Start => SLASH_SEGMENTS / 2 + 2,
+        SlashMode::Buzzer => 6
+    }
+}
+
+/// One cycle of `mode` as frames, each with how long it is shown. The modes
+/// run in the firmware so these are a likeness of each for previews, not what
+/// the Slash shows frame for frame. A higher `interval` is drawn slower.
+pub fn mode_frames(mode: SlashMode, interval: u8, brightness: u8) -> Vec<(SlashFrame, Duration)> {
+    let frame_time = if mode == SlashMode::Buzzer {
+        MODE_FRAME_TIME / 2
+    } else {
+        MODE_FRAME_TIME * (interval as u32 + 1)
+    };
+    (0..mode_steps(mode))
+        .map(|step| {
+            let mut frame = SlashFrame::default();
+            for (segment, led) in frame.0.iter_mut().enumerate() {
+                *led = (mode_level(mode, step, segment) * brightness as f32).round() as u8;
+            }
+            (frame, frame_time)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{mode_frames, SlashFrame, SlashRender, LED_OFF, SLASH_SEGMENTS};
+    use crate::SlashMode;
+
+    #[test]
+    fn every_segment_drawn() {
+        let render = SlashRender::new();
+        let off = render.draw(&SlashFrame::default());
+        assert_eq!(off.len(), (render.width() * render.height()) as usize);
+        assert!(off.iter().all(|p| *p == 0 || *p == LED_OFF));
+        let segment_px = off.iter().filter(|p| **p == LED_OFF).count();
+
+        // Each segment on its own lights its share of the pixels, so none
+        // overlap or are lost off the edge
+        for segment in 0..SLASH_SEGMENTS {
+            let mut frame = SlashFrame::default();
+            frame.0[segment] = 255;
+            let lit = render.draw(&frame).iter().filter(|p| **p == 255).count();
+            assert!(lit > 400, "{segment}: {lit}");
+            assert!(
+                lit.abs_diff(segment_px / SLASH_SEGMENTS) < 40,
+                "{segment}: {lit}"
+            );
+        }
+
+        // The first segment is at the lower left
+        let mut frame = SlashFrame::default();
+        frame.0[0] = 255;
+        let pixels = render.draw(&frame);
+        let first = pixels.iter().position(|p| *p == 255).unwrap() as u32;
+        assert!(first / render.width() > render.height() / 2);
+        assert!(first % render.width() < render.width() / 2);
+    }
+
+    #[test]
+    fn modes_to_frames() {
+        for mode in SlashMode::list() {
+            let mode: SlashMode = mode.parse().unwrap();
+            let frames = mode_frames(mode, 2, 128);
+            assert!(frames.len() > 1, "{mode}");
+            assert!(
+                frames.iter().all(|(f, _)| f.0.iter().all(|l| *l <= 128)),
+                "{mode}"
+            );
+            // Something changes, or it would not be an animation
+            assert!(frames.iter().any(|(f, _)| *f != frames[0].0), "{mode}");
+        }
+
+        let bounce = mode_frames(SlashMode::Bounce, 0, 255);
+        assert_eq!(bounce[0].0 .0, [255, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(bounce[6].0 .0, [0, 0, 0, 0, 0, 0, 255]);
+        assert_eq!(bounce[11].0 .0, [0, 255, 0, 0, 0, 0, 0]);
+        assert_eq!(bounce[0].1, Duration::from_millis(80));
+        assert_eq!(
+            mode_frames(SlashMode::Bounce, 4, 255)[0].1,
+            Duration::from_millis(400)
+        );
+        // The same every time
+        assert_eq!(
+            mode_frames(SlashMode::BitStream, 0, 255),
+            mode_frames(SlashMode::BitStream, 0, 255)
+        );
+
+        let mut gif = Vec::new();
+        SlashRender::new().write_gif(&bounce, &mut gif).unwrap();
+        assert!(gif.starts_with(b"GIF89a"));
+        let mut png = Vec::new();
+        SlashRender::new()
+            .write_png(&bounce[0].0, &mut png)
+            .unwrap();
+        assert!(png.starts_with(b"\x89PNG"));
+        assert!(SlashRender::new().write_gif(&[], Vec::new()).is_err());
+    }
+}